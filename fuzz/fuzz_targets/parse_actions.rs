@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Run with `cargo +nightly fuzz run parse_actions`
+fuzz_target!(|data: &[u8]| {
+    if let Ok(response) = std::str::from_utf8(data) {
+        let _ = mermaid::agents::parse_actions(response);
+    }
+});
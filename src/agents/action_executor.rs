@@ -1,49 +1,397 @@
 use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tokio::time::timeout;
 
+use super::ast_search;
 use super::executor;
 use super::filesystem;
+use super::format;
 use super::git;
+use super::http_request;
+use super::lint;
+use super::search_replace;
+use super::snapshot::SnapshotManager;
 use super::types::{ActionResult, AgentAction};
+use super::web_search;
+use crate::app::Config;
+use crate::tui::OperationMode;
 
-/// Execute an agent action
-pub async fn execute_action(action: &AgentAction) -> Result<ActionResult> {
+/// Execute an agent action in the project at `root`, running commands with the session's
+/// environment variables and the loaded `config` (timeouts, URL allowlist, search provider)
+pub async fn execute_action(
+    action: &AgentAction,
+    root: &Path,
+    session_env: &HashMap<String, String>,
+    config: &Config,
+) -> Result<ActionResult> {
+    // Function calls run the built-in action they name
+    let action = match action.resolve() {
+        Ok(action) => action,
+        Err(e) => return Ok(ActionResult::error(e.to_string())),
+    };
+
+    // Timed-out commands are killed when their future is dropped (`kill_on_drop`)
+    let limit = config.mode.action_timeout(action.type_name());
+    match timeout(limit, run_action(&action, root, session_env, config)).await {
+        Ok(result) => result,
+        Err(_) => Ok(ActionResult::error(format!(
+            "Timed out after {}s",
+            limit.as_secs()
+        ))),
+    }
+}
+
+/// Run a resolved action, turning failures into `ActionResult::Error`
+async fn run_action(
+    action: &AgentAction,
+    root: &Path,
+    session_env: &HashMap<String, String>,
+    config: &Config,
+) -> Result<ActionResult> {
     match action {
-        AgentAction::ReadFile { path } => {
-            filesystem::read_file(path).map(|content| ActionResult::Success { output: content })
+        AgentAction::ReadFile { path } => filesystem::read_file(path).map(ActionResult::success),
+        AgentAction::WriteFile { path, content } => filesystem::write_file(path, content)
+            .map(|_| ActionResult::success(format!("File written: {}", path))),
+        AgentAction::DeleteFile { path } => filesystem::delete_file(path)
+            .map(|_| ActionResult::success(format!("File deleted: {}", path))),
+        AgentAction::CreateDirectory { path } => filesystem::create_directory(path)
+            .map(|_| ActionResult::success(format!("Directory created: {}", path))),
+        AgentAction::ExecuteCommand {
+            command,
+            working_dir,
+        } => executor::execute_command(command, working_dir.as_deref(), session_env).await,
+        AgentAction::GitDiff { path } => git::get_diff(path.as_deref()).map(ActionResult::success),
+        AgentAction::GitStatus => git::get_status().map(ActionResult::success),
+        AgentAction::GitStashList => git::list_stashes()
+            .and_then(|stashes| Ok(serde_json::to_string_pretty(&stashes)?))
+            .map(ActionResult::success),
+        AgentAction::GitStashShow { stash_ref } => {
+            git::show_stash(stash_ref).map(ActionResult::success)
         },
-        AgentAction::WriteFile { path, content } => {
-            filesystem::write_file(path, content).map(|_| ActionResult::Success {
-                output: format!("File written: {}", path),
-            })
+        AgentAction::GitCommit {
+            message,
+            files,
+            amend: false,
+        } => git::commit(message, files)
+            .map(|_| ActionResult::success(format!("Committed with message: {}", message))),
+        AgentAction::GitCommit {
+            message,
+            files,
+            amend: true,
+        } => {
+            let message = (!message.is_empty()).then_some(message.as_str());
+            git::amend(message, files)
+                .map(|hash| ActionResult::success(format!("Amended last commit, now {}", hash)))
         },
-        AgentAction::DeleteFile { path } => {
-            filesystem::delete_file(path).map(|_| ActionResult::Success {
-                output: format!("File deleted: {}", path),
-            })
+        AgentAction::GitPush {
+            remote,
+            branch,
+            force,
+        } => git::push(remote, branch, *force).map(ActionResult::success),
+        AgentAction::SearchReplace {
+            pattern,
+            replacement,
+            path_filter,
+            regex,
+        } => search_replace::search_replace(
+            root,
+            pattern,
+            replacement,
+            path_filter.as_deref(),
+            *regex,
+        )
+        .await
+        .map(ActionResult::success),
+        AgentAction::AstSearch { pattern, language } => {
+            ast_search::ast_search(root, pattern, language.as_deref())
+                .await
+                .map(ActionResult::success)
         },
-        AgentAction::CreateDirectory { path } => {
-            filesystem::create_directory(path).map(|_| ActionResult::Success {
-                output: format!("Directory created: {}", path),
-            })
+        // Generating needs the chat model, which the TUI drives
+        AgentAction::GenerateTests { source_file, .. } => Err(anyhow::anyhow!(
+            "Test generation for {} needs the interactive chat (:gen-tests)",
+            source_file
+        )),
+        AgentAction::LintFile { path, fix } => {
+            lint::lint_file(path, *fix, project_type(root).as_deref())
+                .await
+                .and_then(|report| Ok(serde_json::to_string_pretty(&report)?))
+                .map(ActionResult::success)
         },
-        AgentAction::ExecuteCommand {
-            command,
-            working_dir,
-        } => executor::execute_command(command, working_dir.as_deref()).await,
-        AgentAction::GitDiff { path } => {
-            git::get_diff(path.as_deref()).map(|diff| ActionResult::Success { output: diff })
+        AgentAction::FormatFiles { paths } => {
+            format::format_files(paths).await.map(ActionResult::success)
+        },
+        AgentAction::HttpRequest {
+            method,
+            url,
+            headers,
+            body,
+        } => {
+            let allowed = &config.mode.allowed_url_patterns;
+            http_request::http_request(method, url, headers, body.as_deref(), allowed)
+                .await
+                .map(ActionResult::success)
         },
-        AgentAction::GitStatus => {
-            git::get_status().map(|status| ActionResult::Success { output: status })
+        AgentAction::WebSearch { query, num_results } => match &config.search_provider {
+            Some(provider) => web_search::web_search(provider, query, *num_results)
+                .await
+                .map(|results| ActionResult::success(web_search::format_results(query, &results))),
+            None => Err(anyhow::anyhow!(
+                "Web search is not configured (add [search_provider] to config.toml)"
+            )),
         },
-        AgentAction::GitCommit { message, files } => {
-            git::commit(message, files).map(|_| ActionResult::Success {
-                output: format!("Committed with message: {}", message),
-            })
+        AgentAction::SetEnvironmentVariable {
+            key,
+            value,
+            persist,
+        } => set_environment_variable(root, key, value, *persist).map(ActionResult::success),
+        AgentAction::Rollback { to_snapshot_id } => {
+            rollback(root, to_snapshot_id).map(ActionResult::success)
+        },
+        AgentAction::GenerateDocs { source_file, .. } => Err(anyhow::anyhow!(
+            "Doc generation for {} needs the interactive chat (:gen-docs)",
+            source_file
+        )),
+        AgentAction::CallFunction { name, .. } => {
+            Err(anyhow::anyhow!("Unknown function: {}", name))
         },
     }
-    .map_err(|e| ActionResult::Error {
-        error: e.to_string(),
-    })
+    .map_err(|e| ActionResult::error(e.to_string()))
     .or_else(|e| Ok(e))
 }
+
+/// Project type of the project at `root`, used to pick a linter
+fn project_type(root: &Path) -> Option<String> {
+    crate::context::detect_project_type(root)
+}
+
+/// Write every file in a snapshot of the project at `root` back to disk and delete the
+/// ones created since
+fn rollback(root: &Path, snapshot_id: &str) -> Result<String> {
+    let manager = SnapshotManager::new(root)?;
+    let snapshot = manager.load(snapshot_id)?;
+    let (restored, removed) = manager.restore(&snapshot)?;
+    Ok(format!(
+        "Restored {} files and removed {} created files from snapshot {}",
+        restored, removed, snapshot.id
+    ))
+}
+
+/// Check a variable name, appending it to the project's `.env` when `persist` is set
+/// Session variables are kept by the caller, which passes them to later commands
+fn set_environment_variable(root: &Path, key: &str, value: &str, persist: bool) -> Result<String> {
+    anyhow::ensure!(
+        key.chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        "Invalid environment variable name: {}",
+        key
+    );
+    filesystem::reject_redacted(value)?;
+    if !persist {
+        return Ok(format!("Set {} for this session", key));
+    }
+
+    let path = root.join(".env");
+    let existing = fs::read_to_string(&path).unwrap_or_default();
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    if !existing.is_empty() && !existing.ends_with('\n') {
+        writeln!(file)?;
+    }
+    writeln!(file, "{}={}", key, dotenv_value(value))?;
+    Ok(format!("Added {} to .env", key))
+}
+
+/// Quote a `.env` value when it contains spaces, quotes or comment characters
+fn dotenv_value(value: &str) -> String {
+    if value
+        .chars()
+        .any(|c| c.is_whitespace() || matches!(c, '#' | '"' | '\'' | '\\' | '$'))
+    {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Whether a variable's value should be hidden, e.g. `API_KEY` or `DB_PASSWORD`
+pub fn is_secret_env_key(key: &str) -> bool {
+    let upper = key.to_uppercase();
+    ["KEY", "SECRET", "TOKEN", "PASSWORD", "CREDENTIAL"]
+        .iter()
+        .any(|word| upper.contains(word))
+}
+
+/// Whether a variable decides which programs or libraries commands load, like `PATH`,
+/// `LD_PRELOAD` or `NODE_OPTIONS`
+pub fn is_loader_env_key(key: &str) -> bool {
+    let upper = key.to_uppercase();
+    upper.starts_with("LD_")
+        || upper.starts_with("DYLD_")
+        || [
+            "PATH",
+            "BASH_ENV",
+            "ENV",
+            "SHELL",
+            "NODE_OPTIONS",
+            "NODE_PATH",
+            "PYTHONPATH",
+            "PYTHONSTARTUP",
+            "PERL5LIB",
+            "PERL5OPT",
+            "RUBYLIB",
+            "RUBYOPT",
+            "GIT_SSH_COMMAND",
+        ]
+        .contains(&upper.as_str())
+}
+
+/// Copy of an environment with secret values replaced by `****`
+pub fn mask_env(env: &HashMap<String, String>) -> HashMap<String, String> {
+    env.iter()
+        .map(|(key, value)| {
+            let value = if is_secret_env_key(key) {
+                "****".to_string()
+            } else {
+                value.clone()
+            };
+            (key.clone(), value)
+        })
+        .collect()
+}
+
+/// One executed action, as recorded in `.mermaid/audit.log`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Local>,
+    pub action_type: String,
+    pub target: String,
+    /// Operation mode the action ran under
+    pub mode: OperationMode,
+    pub success: bool,
+    pub duration_ms: u64,
+    /// Whether the user approved the action in a confirmation prompt
+    pub user_confirmed: bool,
+    /// Environment variables the action set or ran with, secrets masked
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+}
+
+/// Appends executed actions to the project's audit log as JSON Lines
+pub struct AuditLogger {
+    path: PathBuf,
+}
+
+impl AuditLogger {
+    /// Audit log for the project rooted at `project_dir`
+    pub fn new(project_dir: impl AsRef<Path>) -> Self {
+        Self {
+            path: project_dir.as_ref().join(".mermaid").join("audit.log"),
+        }
+    }
+
+    /// Append an entry, creating `.mermaid/` if needed
+    pub fn log(&self, entry: &AuditEntry) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+
+    /// The last `count` entries, oldest first; unreadable lines are skipped
+    pub fn tail(&self, count: usize) -> Result<Vec<AuditEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let entries: Vec<AuditEntry> = fs::read_to_string(&self.path)?
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        let skip = entries.len().saturating_sub(count);
+        Ok(entries.into_iter().skip(skip).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_audit_log_tail() {
+        let temp_dir = TempDir::new().unwrap();
+        let logger = AuditLogger::new(temp_dir.path());
+        assert!(logger.tail(5).unwrap().is_empty());
+
+        for target in ["a.rs", "b.rs", "c.rs"] {
+            let entry = AuditEntry {
+                timestamp: Local::now(),
+                action_type: "write_file".to_string(),
+                target: target.to_string(),
+                mode: OperationMode::BypassAll,
+                success: true,
+                duration_ms: 3,
+                user_confirmed: false,
+                env: HashMap::new(),
+            };
+            logger.log(&entry).unwrap();
+        }
+
+        let tail = logger.tail(2).unwrap();
+        assert_eq!(tail.len(), 2);
+        assert_eq!(tail[0].target, "b.rs");
+        assert_eq!(tail[1].mode, OperationMode::BypassAll);
+    }
+
+    #[tokio::test]
+    async fn test_rollback_in_the_project_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::write(root.join("a.rs"), "before").unwrap();
+        let id = SnapshotManager::new(&root)
+            .unwrap()
+            .take([root.join("a.rs")])
+            .unwrap();
+        fs::write(root.join("a.rs"), "after").unwrap();
+
+        let action = AgentAction::Rollback { to_snapshot_id: id };
+        let result = execute_action(&action, &root, &HashMap::new(), &Config::default())
+            .await
+            .unwrap();
+        assert!(matches!(result, ActionResult::Success { .. }));
+        assert_eq!(fs::read_to_string(root.join("a.rs")).unwrap(), "before");
+    }
+
+    #[test]
+    fn test_session_env_masking() {
+        let env = HashMap::from([
+            (
+                "DATABASE_URL".to_string(),
+                "postgres://localhost".to_string(),
+            ),
+            ("openai_api_key".to_string(), "sk-abc".to_string()),
+            ("GITHUB_TOKEN".to_string(), "ghp".to_string()),
+        ]);
+        let masked = mask_env(&env);
+        assert_eq!(masked["DATABASE_URL"], "postgres://localhost");
+        assert_eq!(masked["openai_api_key"], "****");
+        assert_eq!(masked["GITHUB_TOKEN"], "****");
+
+        assert_eq!(dotenv_value("plain"), "plain");
+        assert_eq!(dotenv_value("two words"), "\"two words\"");
+        let root = Path::new(".");
+        assert!(set_environment_variable(root, "1BAD", "x", false).is_err());
+        assert!(set_environment_variable(root, "GOOD_NAME", "x", false).is_ok());
+    }
+}
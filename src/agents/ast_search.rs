@@ -0,0 +1,123 @@
+use anyhow::Result;
+use ignore::WalkBuilder;
+use std::collections::HashMap;
+use std::path::Path;
+use tree_sitter::Query;
+
+use crate::context::{QueryMatch, TreeParser};
+
+/// Most matches returned by one search
+const MAX_AST_RESULTS: usize = 100;
+
+/// Run a tree-sitter query over the project files under `root`
+/// Returns the matches and whether the result limit cut the search short
+pub fn search(
+    root: &Path,
+    pattern: &str,
+    language: Option<&str>,
+) -> Result<(Vec<QueryMatch>, bool)> {
+    let mut parser = TreeParser::new()?;
+    // Compiled per language on first use; None when the pattern doesn't fit the grammar
+    let mut queries: HashMap<String, Option<Query>> = HashMap::new();
+    let mut query_errors = Vec::new();
+    let mut results = Vec::new();
+
+    for entry in WalkBuilder::new(root).build().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let Some(file_language) = parser.language_of(path) else {
+            continue;
+        };
+        if language.is_some_and(|l| !l.eq_ignore_ascii_case(&file_language)) {
+            continue;
+        }
+
+        let query = queries.entry(file_language.clone()).or_insert_with(|| {
+            parser
+                .compile_query(&file_language, pattern)
+                .map_err(|e| query_errors.push(e.to_string()))
+                .ok()
+        });
+        let Some(query) = query.as_ref() else {
+            continue;
+        };
+        let Ok(content) = std::fs::read_to_string(path) else {
+            continue;
+        };
+
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        if let Ok(matches) = parser.query_file(relative, &content, query) {
+            results.extend(matches);
+        }
+        if results.len() >= MAX_AST_RESULTS {
+            results.truncate(MAX_AST_RESULTS);
+            return Ok((results, true));
+        }
+    }
+
+    // A pattern that fits none of the project's languages is an error, not "no matches"
+    if !queries.is_empty() && queries.values().all(|q| q.is_none()) {
+        anyhow::bail!(query_errors.join("\n"));
+    }
+    Ok((results, false))
+}
+
+/// Run a structural search in the project at `root` and format the matches
+pub async fn ast_search(root: &Path, pattern: &str, language: Option<&str>) -> Result<String> {
+    let root = root.to_path_buf();
+    let (pattern, language) = (pattern.to_string(), language.map(str::to_string));
+    // The walk reads the whole tree, so it runs on a blocking thread
+    let (matches, truncated) =
+        tokio::task::spawn_blocking(move || search(&root, &pattern, language.as_deref())).await??;
+    if matches.is_empty() {
+        return Ok("No matches".to_string());
+    }
+
+    let mut output = matches
+        .iter()
+        .map(|m| {
+            format!(
+                "{}:{} @{}: {}",
+                m.file_path.display(),
+                m.line,
+                m.capture,
+                m.text
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if truncated {
+        output.push_str(&format!("\n(stopped after {} matches)", MAX_AST_RESULTS));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_search_matches_predicates() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("lib.rs"),
+            "fn handle_click() {}\nfn render() {}\nfn handle_key() {}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            temp_dir.path().join("app.py"),
+            "def handle_x():\n    pass\n",
+        )
+        .unwrap();
+
+        let pattern = r#"(function_item name: (identifier) @name (#match? @name "^handle_"))"#;
+        let (matches, truncated) = search(temp_dir.path(), pattern, None).unwrap();
+
+        assert!(!truncated);
+        let found: Vec<(usize, &str)> = matches.iter().map(|m| (m.line, m.text.as_str())).collect();
+        assert_eq!(found, vec![(1, "handle_click"), (3, "handle_key")]);
+
+        // Rust node names don't exist in the Python grammar
+        assert!(search(temp_dir.path(), pattern, Some("python")).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use super::types::AgentAction;
+
+/// Programs whose commands only inspect the project
+const READ_ONLY_PROGRAMS: &[&str] = &[
+    "ls", "cat", "head", "tail", "pwd", "echo", "grep", "rg", "find", "wc", "tree", "du", "df",
+    "which", "file", "stat", "date", "whoami",
+];
+
+/// Git subcommands that don't change the repository
+const READ_ONLY_GIT: &[&str] = &["status", "log", "diff", "show", "blame", "ls-files"];
+
+/// Shell syntax that could hide a write (redirection, chaining, substitution)
+const SHELL_OPERATORS: &[&str] = &[">", "<", "|", ";", "&", "`", "$(", "\n", "\r"];
+
+/// Flags that make an otherwise read-only program write files or run commands
+const WRITING_FLAGS: &[(&str, &[&str])] = &[
+    ("git", &["--output", "--ext-diff", "--textconv"]),
+    ("find", &["-delete", "-exec", "-ok", "-fprint", "-fls"]),
+    ("rg", &["--pre"]),
+];
+
+/// Whether a shell command only reads, such as `git status` or `ls src`
+pub fn is_read_only_command(command: &str) -> bool {
+    if SHELL_OPERATORS.iter().any(|op| command.contains(op)) {
+        return false;
+    }
+    let mut words = command.split_whitespace();
+    let Some(program) = words.next() else {
+        return false;
+    };
+    let writes = WRITING_FLAGS
+        .iter()
+        .filter(|(name, _)| *name == program)
+        .any(|(_, flags)| {
+            words
+                .clone()
+                .any(|word| flags.iter().any(|f| word.starts_with(f)))
+        });
+    if writes {
+        return false;
+    }
+    match program {
+        "git" => words.next().is_some_and(|sub| READ_ONLY_GIT.contains(&sub)),
+        _ => READ_ONLY_PROGRAMS.contains(&program),
+    }
+}
+
+/// Whether an action may run concurrently with its neighbours
+fn is_parallelizable(action: &AgentAction) -> bool {
+    match action.resolve() {
+        Ok(AgentAction::ReadFile { .. }) => true,
+        Ok(AgentAction::ExecuteCommand { command, .. }) => is_read_only_command(&command),
+        _ => false,
+    }
+}
+
+/// Partition actions into waves of indices that can run together, in order
+/// Adjacent file reads and read-only commands share a wave; every other
+/// action gets a wave of its own, so no wave reads a file that another
+/// action in the same wave writes, and later actions still see earlier writes
+pub fn plan_waves(actions: &[AgentAction]) -> Vec<Vec<usize>> {
+    let mut waves: Vec<Vec<usize>> = Vec::new();
+    let mut open_wave = false;
+    for (index, action) in actions.iter().enumerate() {
+        let parallel = is_parallelizable(action);
+        match waves.last_mut() {
+            Some(wave) if parallel && open_wave => wave.push(index),
+            _ => waves.push(vec![index]),
+        }
+        open_wave = parallel;
+    }
+    waves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_waves() {
+        let read = |path: &str| AgentAction::ReadFile {
+            path: path.to_string(),
+        };
+        let command = |command: &str| AgentAction::ExecuteCommand {
+            command: command.to_string(),
+            working_dir: None,
+        };
+        let actions = vec![
+            read("a.rs"),
+            command("git status"),
+            AgentAction::WriteFile {
+                path: "a.rs".to_string(),
+                content: String::new(),
+            },
+            read("a.rs"),
+            read("b.rs"),
+            command("cargo build"),
+            command("ls > out.txt"),
+            command("ls\nrm -rf src"),
+            command("cat < secrets"),
+            command("git diff --output=out.txt"),
+            command("find . -fprint out.txt"),
+            command("find . -fprintf out.txt %p"),
+            command("rg --pre ./script pattern"),
+        ];
+        assert_eq!(
+            plan_waves(&actions),
+            vec![
+                vec![0, 1],
+                vec![2],
+                vec![3, 4],
+                vec![5],
+                vec![6],
+                vec![7],
+                vec![8],
+                vec![9],
+                vec![10],
+                vec![11],
+                vec![12],
+            ]
+        );
+    }
+}
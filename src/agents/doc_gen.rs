@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use super::test_gen::{public_signatures, strip_code_fence};
+use super::types::{AgentAction, DocFormat};
+use crate::models::{ChatMessage, MessageRole, Model, ModelConfig, ProjectContext};
+
+/// Unchanged lines kept around each change in a diff
+const DIFF_CONTEXT: usize = 1;
+
+/// Ask the model to document a source file's public API
+/// Returns the WriteFile action for the documented file, ready to confirm
+pub async fn generate_docs(
+    model: &mut dyn Model,
+    context: &ProjectContext,
+    source_file: &str,
+    format: DocFormat,
+) -> Result<AgentAction> {
+    let content = std::fs::read_to_string(source_file)
+        .with_context(|| format!("Failed to read {}", source_file))?;
+    let signatures = public_signatures(Path::new(source_file), &content)?;
+    anyhow::ensure!(
+        !signatures.is_empty(),
+        "No public functions found in {}",
+        source_file
+    );
+
+    let prompt = format!(
+        "Write doc comments for the public API of {} using {}.\n\
+         Document these items:\n{}\n\n\
+         Reply with the complete file, changing nothing but the added comments.\n\n{}",
+        source_file,
+        format.instructions(),
+        signatures.join("\n"),
+        content
+    );
+    let messages = vec![ChatMessage {
+        role: MessageRole::User,
+        content: prompt,
+        timestamp: chrono::Local::now(),
+    }];
+    // The reply repeats the whole file, so leave room for it
+    let config = ModelConfig {
+        max_tokens: Some(content.len() / 2 + 2000),
+        ..ModelConfig::default()
+    };
+
+    let response = model.chat(&messages, context, &config, None).await?;
+    Ok(AgentAction::WriteFile {
+        path: source_file.to_string(),
+        content: strip_code_fence(&response.content),
+    })
+}
+
+/// Changed lines between two versions of a file, as `+`/`-` lines under `@@` headers
+pub fn line_diff(old: &str, new: &str) -> Vec<String> {
+    let changes = diff::lines(old, new);
+    let is_change = |i: usize| !matches!(changes[i], diff::Result::Both(..));
+
+    let mut output = Vec::new();
+    let mut old_line = 0;
+    let mut last_shown = None;
+    for (i, change) in changes.iter().enumerate() {
+        if let diff::Result::Left(_) | diff::Result::Both(..) = change {
+            old_line += 1;
+        }
+        let near_change = (i.saturating_sub(DIFF_CONTEXT)..=i + DIFF_CONTEXT)
+            .any(|j| j < changes.len() && is_change(j));
+        if !near_change {
+            continue;
+        }
+        if last_shown.map(|last| last + 1) != Some(i) {
+            output.push(format!("@@ line {} @@", old_line.max(1)));
+        }
+        last_shown = Some(i);
+
+        output.push(match change {
+            diff::Result::Left(line) => format!("-{}", line),
+            diff::Result::Right(line) => format!("+{}", line),
+            diff::Result::Both(line, _) => format!(" {}", line),
+        });
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_diff_shows_added_comments() {
+        let old = "use std::fs;\n\npub fn open() {}\n\nfn helper() {}\n";
+        let new = "use std::fs;\n\n/// Open the file\npub fn open() {}\n\nfn helper() {}\n";
+        assert_eq!(
+            line_diff(old, new),
+            vec![
+                "@@ line 2 @@",
+                " ",
+                "+/// Open the file",
+                " pub fn open() {}"
+            ]
+        );
+        assert!(line_diff(old, old).is_empty());
+    }
+}
@@ -1,19 +1,24 @@
 use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::time::Duration;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
-use tokio::time::timeout;
 
 use crate::agents::ActionResult;
 
 /// Execute a shell command and capture output
-pub async fn execute_command(command: &str, working_dir: Option<&str>) -> Result<ActionResult> {
+pub async fn execute_command(
+    command: &str,
+    working_dir: Option<&str>,
+    env: &HashMap<String, String>,
+) -> Result<ActionResult> {
     // Security checks
     if contains_dangerous_command(command) {
-        return Ok(ActionResult::Error {
-            error: format!("Dangerous command blocked: {}", command),
-        });
+        return Ok(ActionResult::error(format!(
+            "Dangerous command blocked: {}",
+            command
+        )));
     }
 
     // Parse the command
@@ -35,32 +40,26 @@ pub async fn execute_command(command: &str, working_dir: Option<&str>) -> Result
         .arg(command)
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped());
+        .stderr(Stdio::piped())
+        .envs(env)
+        // The caller's timeout drops this future; make sure the process dies with it
+        .kill_on_drop(true);
 
     // Set working directory if specified
     if let Some(dir) = working_dir {
         cmd.current_dir(dir);
     }
 
-    // Execute with timeout
-    let timeout_duration = Duration::from_secs(30);
-
-    match timeout(timeout_duration, run_command(cmd)).await {
-        Ok(Ok(output)) => Ok(ActionResult::Success { output }),
-        Ok(Err(e)) => Ok(ActionResult::Error {
-            error: format!("Command failed: {}", e),
-        }),
-        Err(_) => Ok(ActionResult::Error {
-            error: format!(
-                "Command timed out after {} seconds",
-                timeout_duration.as_secs()
-            ),
-        }),
+    // The timeout is applied per action type by execute_action
+    match run_command(cmd).await {
+        Ok(result) => Ok(result),
+        Err(e) => Ok(ActionResult::error(format!("Command failed: {}", e))),
     }
 }
 
 /// Run the command and stream output
-async fn run_command(mut cmd: Command) -> Result<String> {
+/// Failing with something on stderr is an error; warnings from a successful run stay in `stderr`
+async fn run_command(mut cmd: Command) -> Result<ActionResult> {
     let mut child = cmd
         .spawn()
         .context("Failed to execute command. Is the shell available?")?;
@@ -103,21 +102,49 @@ async fn run_command(mut cmd: Command) -> Result<String> {
         .await
         .context("Failed to wait for command to complete. Process may have crashed.")?;
 
-    // Combine output and errors
-    let mut full_output = output;
-    if !errors.is_empty() {
-        full_output.push_str("\n--- stderr ---\n");
-        full_output.push_str(&errors);
+    let exit_code = status.code().unwrap_or(-1);
+    if exit_code != 0 && !errors.is_empty() {
+        return Ok(ActionResult::Error {
+            error: errors,
+            exit_code,
+        });
     }
+    Ok(ActionResult::Success {
+        stdout: output,
+        stderr: errors,
+        exit_code,
+    })
+}
 
-    if !status.success() {
-        full_output.push_str(&format!(
-            "\n--- Command exited with status: {} ---",
-            status.code().unwrap_or(-1)
-        ));
+/// The directory a standalone `cd` command moves to, resolved against `base`
+/// A bare `cd` goes back to `root`; None for other commands (including `cd dir && ...`),
+/// when the directory doesn't exist, or when it's outside `root`
+pub fn resolve_cd(command: &str, base: &Path, root: &Path) -> Option<PathBuf> {
+    let command = command.trim();
+    let root = std::fs::canonicalize(root).ok()?;
+    let target = match command.strip_prefix("cd") {
+        Some("") => return Some(root),
+        Some(rest) if rest.starts_with(char::is_whitespace) => rest.trim(),
+        _ => return None,
+    };
+    if target.contains(['&', '|', ';', '\n']) {
+        return None;
     }
+    let target = target
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .or_else(|| target.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')))
+        .unwrap_or(target);
 
-    Ok(full_output)
+    let path = match target.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            PathBuf::from(std::env::var("HOME").ok()?).join(rest.trim_start_matches('/'))
+        },
+        _ => base.join(target),
+    };
+    std::fs::canonicalize(path)
+        .ok()
+        .filter(|dir| dir.is_dir() && dir.starts_with(&root))
 }
 
 /// Check if a command contains dangerous operations
@@ -172,30 +199,103 @@ mod tests {
 
     #[tokio::test]
     async fn test_safe_command() {
-        let result = execute_command("echo 'Hello, Mermaid!'", None)
+        let result = execute_command("echo 'Hello, Mermaid!'", None, &HashMap::new())
             .await
             .unwrap();
 
         match result {
-            ActionResult::Success { output } => {
-                assert!(output.contains("Hello, Mermaid!"));
+            ActionResult::Success { stdout, .. } => {
+                assert!(stdout.contains("Hello, Mermaid!"));
+            },
+            _ => panic!("Expected success"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stderr_captured_separately() {
+        let env = HashMap::new();
+        match execute_command("echo built; echo 'warning: unused' >&2", None, &env)
+            .await
+            .unwrap()
+        {
+            ActionResult::Success {
+                stdout,
+                stderr,
+                exit_code,
+            } => {
+                assert_eq!(stdout, "built\n");
+                assert_eq!(stderr, "warning: unused\n");
+                assert_eq!(exit_code, 0);
             },
             _ => panic!("Expected success"),
         }
+        match execute_command("echo partial; echo 'error: failed' >&2; exit 3", None, &env)
+            .await
+            .unwrap()
+        {
+            ActionResult::Error { error, exit_code } => {
+                assert_eq!(error, "error: failed\n");
+                assert_eq!(exit_code, 3);
+            },
+            _ => panic!("Expected error"),
+        }
+        // Failing silently is still a result the model should see
+        let result = execute_command("exit 2", None, &env).await.unwrap();
+        assert!(matches!(result, ActionResult::Success { exit_code: 2, .. }));
+        assert!(result.output().contains("exited with status: 2"));
+
+        let legacy: ActionResult = serde_json::from_str(r#"{"Success":{"output":"ok"}}"#).unwrap();
+        assert_eq!(legacy.output(), "ok");
     }
 
     #[tokio::test]
     async fn test_dangerous_command_blocked() {
-        let result = execute_command("rm -rf /", None).await.unwrap();
+        let result = execute_command("rm -rf /", None, &HashMap::new())
+            .await
+            .unwrap();
 
         match result {
-            ActionResult::Error { error } => {
+            ActionResult::Error { error, .. } => {
                 assert!(error.contains("Dangerous command blocked"));
             },
             _ => panic!("Expected error"),
         }
     }
 
+    #[test]
+    fn test_resolve_cd() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("my app")).unwrap();
+        let base = std::fs::canonicalize(dir.path()).unwrap();
+
+        assert_eq!(
+            resolve_cd("cd \"my app\"", &base, &base),
+            Some(base.join("my app"))
+        );
+        assert_eq!(
+            resolve_cd(
+                &format!("cd {}", base.join("my app").display()),
+                &base,
+                &base
+            ),
+            Some(base.join("my app"))
+        );
+        assert_eq!(
+            resolve_cd("  cd ..", &base.join("my app"), &base),
+            Some(base.clone())
+        );
+        assert_eq!(resolve_cd("cd missing", &base, &base), None);
+        assert_eq!(resolve_cd("cd 'my app' && make", &base, &base), None);
+        assert_eq!(resolve_cd("cdrecord image.iso", &base, &base), None);
+
+        // Moves stay inside the project
+        let app = base.join("my app");
+        assert_eq!(resolve_cd("cd", &app, &app), Some(app.clone()));
+        assert_eq!(resolve_cd("cd ..", &app, &app), None);
+        assert_eq!(resolve_cd("cd /", &app, &app), None);
+        assert_eq!(resolve_cd("cd ~", &app, &app), None);
+    }
+
     #[test]
     fn test_dangerous_command_detection() {
         assert!(contains_dangerous_command("rm -rf /"));
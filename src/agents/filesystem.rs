@@ -1,15 +1,53 @@
 use anyhow::{Context, Result};
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::context::{is_env_file, redact_env_file, REDACTED};
+use crate::utils::MermaidError;
+
+/// Directories outside the project that file actions may use, from `--root`
+static WORKSPACE_ROOTS: RwLock<Vec<PathBuf>> = RwLock::new(Vec::new());
+
+/// Let file actions reach `root` (canonical) as well as the project directory
+pub fn allow_workspace_root(root: PathBuf) {
+    let mut roots = WORKSPACE_ROOTS.write().unwrap_or_else(|e| e.into_inner());
+    if !roots.contains(&root) {
+        roots.push(root);
+    }
+}
+
+/// Whether `path` is in the project directory or an allowed workspace root
+fn in_workspace(path: &Path) -> Result<bool> {
+    // A removed current directory shouldn't lock out the workspace roots
+    if std::env::current_dir().is_ok_and(|cwd| path.starts_with(cwd)) {
+        return Ok(true);
+    }
+    let roots = WORKSPACE_ROOTS.read().unwrap_or_else(|e| e.into_inner());
+    Ok(roots.iter().any(|root| path.starts_with(root)))
+}
 
 /// Read a file from the filesystem
+/// Dotenv files come back with their secrets redacted, as they are in the context
 pub fn read_file(path: &str) -> Result<String> {
     let path = normalize_path(path)?;
 
     // Security check: ensure path is within current directory
-    validate_path(&path)?;
+    if is_env_file(&path) {
+        validate_location(&path)?;
+    } else {
+        validate_path(&path)?;
+    }
 
-    fs::read_to_string(&path).with_context(|| format!("Failed to read file: {}", path.display()))
+    let content = fs::read_to_string(&path)
+        .map_err(|e| access_error(e, &path))
+        .with_context(|| format!("Failed to read file: {}", path.display()))?;
+    Ok(if is_env_file(&path) {
+        redact_env_file(&content)
+    } else {
+        content
+    })
 }
 
 /// Write content to a file
@@ -18,6 +56,7 @@ pub fn write_file(path: &str, content: &str) -> Result<()> {
 
     // Security check
     validate_path(&path)?;
+    reject_redacted(content)?;
 
     // Create parent directories if they don't exist
     if let Some(parent) = path.parent() {
@@ -36,7 +75,75 @@ pub fn write_file(path: &str, content: &str) -> Result<()> {
             .with_context(|| format!("Failed to create backup of: {}", path.display()))?;
     }
 
-    fs::write(&path, content).with_context(|| format!("Failed to write file: {}", path.display()))
+    fs::write(&path, content)
+        .map_err(|e| access_error(e, &path))
+        .with_context(|| format!("Failed to write file: {}", path.display()))
+}
+
+/// Read `path` relative to `root` instead of the current directory, refusing
+/// anything that resolves outside `root` or looks sensitive
+pub fn read_project_file(root: &Path, path: &str) -> Result<String> {
+    let root = root.canonicalize()?;
+    let path = root
+        .join(path)
+        .canonicalize()
+        .with_context(|| format!("Failed to read file: {}", path))?;
+    if !path.starts_with(&root) {
+        anyhow::bail!(
+            "Security error: attempted to access path outside of project directory: {}",
+            path.display()
+        );
+    }
+    reject_sensitive(&path)?;
+
+    fs::read_to_string(&path)
+        .map_err(|e| access_error(e, &path))
+        .with_context(|| format!("Failed to read file: {}", path.display()))
+}
+
+/// Refuse content copied from a redacted file, which would replace real secrets
+/// with the placeholder
+pub fn reject_redacted(content: &str) -> Result<()> {
+    if content.contains(REDACTED) {
+        anyhow::bail!(
+            "Refusing to write the {} placeholder; redacted values must be filled in by hand",
+            REDACTED
+        );
+    }
+    Ok(())
+}
+
+/// Surface permission failures as `FilePermissionDenied` so the UI can suggest a fix
+fn access_error(error: io::Error, path: &Path) -> anyhow::Error {
+    if error.kind() == io::ErrorKind::PermissionDenied {
+        MermaidError::FilePermissionDenied {
+            path: path.to_path_buf(),
+        }
+        .into()
+    } else {
+        error.into()
+    }
+}
+
+/// Replace a file's content by writing a temp file next to it and renaming it over the original
+/// Checked like `write_file` (see `check_write`)
+pub fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    let path = &check_write(path, content)?;
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("file");
+    let temp_path = path.with_file_name(format!(".{}.mermaid-tmp", file_name));
+
+    fs::write(&temp_path, content)
+        .with_context(|| format!("Failed to write {}", temp_path.display()))?;
+    fs::rename(&temp_path, path).with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+/// Resolve a path to write, refusing ones outside the project or sensitive, and
+/// content holding redacted placeholders
+pub fn check_write(path: &Path, content: &str) -> Result<PathBuf> {
+    let path = normalize_path(&path.to_string_lossy())?;
+    validate_path(&path)?;
+    reject_redacted(content)?;
+    Ok(path)
 }
 
 /// Delete a file
@@ -86,9 +193,8 @@ fn normalize_path(path: &str) -> Result<PathBuf> {
     let path = Path::new(path);
 
     if path.is_absolute() {
-        // For absolute paths, ensure they're within the current directory
-        let current_dir = std::env::current_dir()?;
-        if !path.starts_with(&current_dir) {
+        // For absolute paths, ensure they're within the current directory or a workspace root
+        if !in_workspace(path)? {
             anyhow::bail!("Access denied: path outside of project directory");
         }
         Ok(path.to_path_buf())
@@ -101,8 +207,38 @@ fn normalize_path(path: &str) -> Result<PathBuf> {
 
 /// Validate that a path is safe to access
 fn validate_path(path: &Path) -> Result<()> {
-    let current_dir = std::env::current_dir()?;
+    validate_location(path)?;
+    reject_sensitive(path)
+}
 
+/// Refuse paths to credentials and other sensitive files
+fn reject_sensitive(path: &Path) -> Result<()> {
+    let sensitive_patterns = [
+        ".ssh",
+        ".aws",
+        ".env",
+        "id_rsa",
+        "id_ed25519",
+        ".git/config",
+        ".npmrc",
+        ".pypirc",
+    ];
+
+    let path_str = path.to_string_lossy();
+    for pattern in &sensitive_patterns {
+        if path_str.contains(pattern) {
+            anyhow::bail!(
+                "Security error: attempted to access potentially sensitive file: {}",
+                path.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate that a path is inside the project directory or a workspace root
+fn validate_location(path: &Path) -> Result<()> {
     // Resolve the path to handle .. and .
     let canonical = if path.exists() {
         path.canonicalize()?
@@ -120,36 +256,14 @@ fn validate_path(path: &Path) -> Result<()> {
         }
     };
 
-    // Ensure the path is within the current directory
-    if !canonical.starts_with(&current_dir) {
+    // Ensure the path is within the current directory or a workspace root
+    if !in_workspace(&canonical)? {
         anyhow::bail!(
             "Security error: attempted to access path outside of project directory: {}",
             path.display()
         );
     }
 
-    // Check for sensitive files
-    let sensitive_patterns = [
-        ".ssh",
-        ".aws",
-        ".env",
-        "id_rsa",
-        "id_ed25519",
-        ".git/config",
-        ".npmrc",
-        ".pypirc",
-    ];
-
-    let path_str = path.to_string_lossy();
-    for pattern in &sensitive_patterns {
-        if path_str.contains(pattern) {
-            anyhow::bail!(
-                "Security error: attempted to access potentially sensitive file: {}",
-                path.display()
-            );
-        }
-    }
-
     Ok(())
 }
 
@@ -193,5 +307,21 @@ mod tests {
 
         // This should fail - trying to access absolute path outside project
         assert!(read_file("/etc/passwd").is_err());
+
+        // Atomic writes get the same checks as `write_file`
+        assert!(write_atomic(Path::new("../outside.txt"), "x").is_err());
+        assert!(write_atomic(Path::new(".ssh/config"), "x").is_err());
+        assert!(write_atomic(Path::new("notes.txt"), "key: [REDACTED]").is_err());
+        assert!(!temp_dir.path().join("notes.txt").exists());
+    }
+
+    #[test]
+    fn test_env_files_redacted() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_current_dir(&temp_dir).unwrap();
+        fs::write(temp_dir.path().join(".env"), "API_KEY=sk-live\nPORT=8080\n").unwrap();
+
+        assert_eq!(read_file(".env").unwrap(), "API_KEY=[REDACTED]\nPORT=8080");
+        assert!(write_file("config.yaml", "api_key: [REDACTED]\n").is_err());
     }
 }
@@ -0,0 +1,171 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use super::filesystem::{read_file, write_atomic};
+
+/// Formatters that read a file on stdin and print the formatted version
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Formatter {
+    Rustfmt,
+    Prettier,
+    Black,
+    Gofmt,
+}
+
+impl Formatter {
+    fn for_path(path: &str) -> Option<Self> {
+        match Path::new(path).extension().and_then(|e| e.to_str())? {
+            "rs" => Some(Formatter::Rustfmt),
+            "js" | "jsx" | "ts" | "tsx" | "json" | "css" | "html" | "md" => {
+                Some(Formatter::Prettier)
+            },
+            "py" => Some(Formatter::Black),
+            "go" => Some(Formatter::Gofmt),
+            _ => None,
+        }
+    }
+
+    fn command(&self, path: &str, check: bool) -> Command {
+        let mut cmd = match self {
+            Formatter::Rustfmt => {
+                let mut cmd = Command::new("rustfmt");
+                cmd.args(["--edition", "2021"]);
+                if check {
+                    cmd.arg("--check");
+                }
+                cmd
+            },
+            Formatter::Prettier => {
+                let mut cmd = Command::new("npx");
+                cmd.args(["prettier", "--stdin-filepath", path]);
+                cmd
+            },
+            Formatter::Black => {
+                let mut cmd = Command::new("black");
+                cmd.args(["--quiet", "-"]);
+                cmd
+            },
+            Formatter::Gofmt => Command::new("gofmt"),
+        };
+        cmd.stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        cmd
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Formatter::Rustfmt => "rustfmt",
+            Formatter::Prettier => "prettier",
+            Formatter::Black => "black",
+            Formatter::Gofmt => "gofmt",
+        }
+    }
+}
+
+/// Whether a path has a supported formatter
+pub fn can_format(path: &str) -> bool {
+    Formatter::for_path(path).is_some()
+}
+
+/// Format each file with the formatter for its extension, reporting lines changed per file
+pub async fn format_files(paths: &[String]) -> Result<String> {
+    anyhow::ensure!(!paths.is_empty(), "No files to format");
+
+    let mut formatted = 0;
+    let mut report = Vec::new();
+    for path in paths {
+        match format_file(path).await {
+            Ok(0) => report.push(format!("  {}: already formatted", path)),
+            Ok(lines) => {
+                formatted += 1;
+                report.push(format!("  {}: {} lines changed", path, lines));
+            },
+            Err(e) => report.push(format!("  {}: {}", path, e)),
+        }
+    }
+    Ok(format!(
+        "Formatted {} of {} files\n{}",
+        formatted,
+        paths.len(),
+        report.join("\n")
+    ))
+}
+
+/// Format one file in place, returning how many lines changed
+async fn format_file(path: &str) -> Result<usize> {
+    let formatter =
+        Formatter::for_path(path).with_context(|| format!("No formatter for {}", path))?;
+    let content = read_file(path)?;
+
+    // rustfmt can say up front whether anything needs formatting
+    if formatter == Formatter::Rustfmt
+        && run_formatter(formatter, path, &content, true).await.is_ok()
+    {
+        return Ok(0);
+    }
+
+    let formatted = run_formatter(formatter, path, &content, false).await?;
+    if formatted == content {
+        return Ok(0);
+    }
+    write_atomic(Path::new(path), &formatted)?;
+    Ok(lines_changed(&content, &formatted))
+}
+
+/// Pipe `content` through a formatter, returning its stdout
+async fn run_formatter(
+    formatter: Formatter,
+    path: &str,
+    content: &str,
+    check: bool,
+) -> Result<String> {
+    let mut child = formatter
+        .command(path, check)
+        .spawn()
+        .with_context(|| format!("Failed to run {}. Is it installed?", formatter.name()))?;
+    let mut stdin = child
+        .stdin
+        .take()
+        .context("Formatter process has no stdin")?;
+    stdin.write_all(content.as_bytes()).await?;
+    drop(stdin);
+
+    let output = child.wait_with_output().await?;
+    anyhow::ensure!(
+        output.status.success(),
+        "{} failed: {}",
+        formatter.name(),
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Lines that differ between two versions, counting a rewritten line once
+fn lines_changed(old: &str, new: &str) -> usize {
+    let (mut removed, mut added) = (0, 0);
+    for change in diff::lines(old, new) {
+        match change {
+            diff::Result::Left(_) => removed += 1,
+            diff::Result::Right(_) => added += 1,
+            diff::Result::Both(..) => {},
+        }
+    }
+    removed.max(added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_changed() {
+        assert_eq!(lines_changed("a\nb\nc\n", "a\nb\nc\n"), 0);
+        assert_eq!(lines_changed("fn a(){}\nb\n", "fn a() {}\nb\n"), 1);
+        assert_eq!(lines_changed("x\n", "x\ny\nz\n"), 2);
+    }
+}
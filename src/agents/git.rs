@@ -1,6 +1,17 @@
 use anyhow::{Context, Result};
 use git2::{DiffOptions, Repository, StatusOptions};
+use serde::Serialize;
 use std::path::Path;
+use std::process::Command;
+
+/// One entry of `git stash list`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StashEntry {
+    /// e.g. `stash@{0}`
+    #[serde(rename = "ref")]
+    pub stash_ref: String,
+    pub message: String,
+}
 
 /// Get git diff for the current repository
 pub fn get_diff(path: Option<&str>) -> Result<String> {
@@ -95,25 +106,7 @@ pub fn commit(message: &str, files: &[String]) -> Result<()> {
     let repo = Repository::open_from_env()
         .context("Failed to open git repository. Is this a git repo?")?;
 
-    let mut index = repo.index()?;
-
-    // Add specified files to the index
-    if !files.is_empty() {
-        for file in files {
-            index
-                .add_path(Path::new(file))
-                .with_context(|| format!("Failed to add file to index: {}", file))?;
-        }
-    } else {
-        // Add all modified files
-        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
-    }
-
-    index.write()?;
-
-    // Get the tree for the index
-    let tree_id = index.write_tree()?;
-    let tree = repo.find_tree(tree_id)?;
+    let tree = stage(&repo, files)?;
 
     // Get parent commit
     let parent_commit = match repo.head() {
@@ -144,6 +137,115 @@ pub fn commit(message: &str, files: &[String]) -> Result<()> {
     Ok(())
 }
 
+/// Fold the files (all changes when empty) into the last commit, replacing its message
+/// when one is given; returns the new commit's short hash
+pub fn amend(message: Option<&str>, files: &[String]) -> Result<String> {
+    let repo = Repository::open_from_env()
+        .context("Failed to open git repository. Is this a git repo?")?;
+    let head = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .context("No commit to amend")?;
+
+    let tree = stage(&repo, files)?;
+    let oid = head.amend(Some("HEAD"), None, None, None, message, Some(&tree))?;
+    Ok(oid.to_string()[..7].to_string())
+}
+
+/// Add the files (all changes when empty) to the index and write its tree
+fn stage<'r>(repo: &'r Repository, files: &[String]) -> Result<git2::Tree<'r>> {
+    let mut index = repo.index()?;
+
+    // Add specified files to the index
+    if !files.is_empty() {
+        for file in files {
+            index
+                .add_path(Path::new(file))
+                .with_context(|| format!("Failed to add file to index: {}", file))?;
+        }
+    } else {
+        // Add all modified files
+        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+    }
+
+    index.write()?;
+
+    // Get the tree for the index
+    let tree_id = index.write_tree()?;
+    Ok(repo.find_tree(tree_id)?)
+}
+
+/// Push `branch` to `remote`, returning git's report
+/// Refuses with uncommitted changes, an unknown remote, or (unless `force`) a remote
+/// branch with commits HEAD doesn't have; `force` pushes with `--force-with-lease`
+pub fn push(remote: &str, branch: &str, force: bool) -> Result<String> {
+    // Names starting with '-' would be read as options
+    for name in [remote, branch] {
+        anyhow::ensure!(
+            !name.is_empty() && !name.starts_with('-'),
+            "Invalid remote or branch: {}",
+            name
+        );
+    }
+
+    let status = run_git(&["status", "--porcelain"])?;
+    if !status.trim().is_empty() {
+        anyhow::bail!(
+            "Uncommitted changes; commit or stash them before pushing:\n{}",
+            status.trim_end()
+        );
+    }
+
+    let remotes = run_git(&["remote", "-v"])?;
+    if !remote_names(&remotes).contains(&remote) {
+        anyhow::bail!("Remote '{}' is not configured", remote);
+    }
+
+    // A remote branch with commits HEAD doesn't have can only be overwritten
+    let tracking = format!("{}/{}", remote, branch);
+    if !force && run_git(&["rev-parse", "--verify", "--quiet", &tracking]).is_ok() {
+        let behind = run_git(&["log", "--oneline", &format!("HEAD..{}", tracking)])?;
+        let missing = behind.lines().count();
+        if missing > 0 {
+            anyhow::bail!(
+                "{} has {} commit(s) that HEAD doesn't; pull first, or push with force",
+                tracking,
+                missing
+            );
+        }
+    }
+
+    let mut args = vec!["push"];
+    if force {
+        args.push("--force-with-lease");
+    }
+    args.extend([remote, branch]);
+    let output = Command::new("git")
+        .args(&args)
+        .output()
+        .context("Failed to run git")?;
+    // git push reports progress on stderr
+    let report = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    if !output.status.success() {
+        anyhow::bail!("git {} failed: {}", args.join(" "), report.trim());
+    }
+    Ok(report.trim().to_string())
+}
+
+/// Remote names from `git remote -v` output
+fn remote_names(output: &str) -> Vec<&str> {
+    let mut names: Vec<&str> = output
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .collect();
+    names.dedup();
+    names
+}
+
 /// Get the current branch name
 pub fn current_branch() -> Result<String> {
     let repo = Repository::open_from_env()
@@ -160,6 +262,82 @@ pub fn current_branch() -> Result<String> {
     }
 }
 
+/// Name of the checked-out branch from `git rev-parse --abbrev-ref HEAD`
+/// (`HEAD` when detached)
+pub fn head_branch() -> Result<String> {
+    Ok(run_git(&["rev-parse", "--abbrev-ref", "HEAD"])?
+        .trim()
+        .to_string())
+}
+
+/// Whether a file contains merge conflict markers at the start of a line
+pub fn has_conflict_markers(content: &[u8]) -> bool {
+    let mut ours = false;
+    let mut separator = false;
+    for line in content.split(|&b| b == b'\n') {
+        if line.starts_with(b"<<<<<<<") {
+            ours = true;
+        } else if ours && line.starts_with(b"=======") {
+            separator = true;
+        } else if separator && line.starts_with(b">>>>>>>") {
+            return true;
+        }
+    }
+    false
+}
+
+/// List the stashes of the current repository, newest first
+pub fn list_stashes() -> Result<Vec<StashEntry>> {
+    let output = run_git(&["stash", "list", "--format=%gd %s"])?;
+    Ok(parse_stash_list(&output))
+}
+
+/// Show the patch stored in a stash, e.g. `stash@{0}`
+pub fn show_stash(stash_ref: &str) -> Result<String> {
+    // Refs starting with '-' would be read as options
+    anyhow::ensure!(
+        !stash_ref.is_empty() && !stash_ref.starts_with('-'),
+        "Invalid stash ref: {}",
+        stash_ref
+    );
+    let output = run_git(&["stash", "show", "-p", stash_ref])?;
+    if output.is_empty() {
+        Ok("Stash is empty".to_string())
+    } else {
+        Ok(output)
+    }
+}
+
+/// Parse `git stash list --format="%gd %s"` output
+fn parse_stash_list(output: &str) -> Vec<StashEntry> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (stash_ref, message) = line.split_once(' ').unwrap_or((line, ""));
+            (!stash_ref.is_empty()).then(|| StashEntry {
+                stash_ref: stash_ref.to_string(),
+                message: message.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Run a git subcommand the git2 bindings don't cover, returning its stdout
+fn run_git(args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -191,8 +369,54 @@ mod tests {
         let status = get_status().unwrap();
         assert!(status.contains("working directory clean"));
 
+        // Test amend, keeping and then replacing the message
+        fs::write(&test_file, "Hello again, Git!").unwrap();
+        let hash = amend(None, &[]).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert!(head.id().to_string().starts_with(&hash));
+        assert_eq!(head.message(), Some("Initial commit"));
+        assert_eq!(head.parent_count(), 0);
+        amend(Some("Greet git"), &[]).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.message(), Some("Greet git"));
+
         // Test current branch
         let branch = current_branch().unwrap();
         assert!(branch == "main" || branch == "master");
     }
+
+    #[test]
+    fn test_parse_stash_list() {
+        let stashes = parse_stash_list(
+            "stash@{0} On main: wip parser\nstash@{1} WIP on main: 1a2b3c4 Fix tests\n",
+        );
+        assert_eq!(stashes.len(), 2);
+        assert_eq!(stashes[0].stash_ref, "stash@{0}");
+        assert_eq!(stashes[0].message, "On main: wip parser");
+        assert_eq!(
+            serde_json::to_value(&stashes[1]).unwrap()["ref"],
+            "stash@{1}"
+        );
+        assert!(parse_stash_list("").is_empty());
+        assert!(show_stash("--help").is_err());
+    }
+
+    #[test]
+    fn test_push_checks() {
+        let remotes = "origin\tgit@github.com:me/repo.git (fetch)\n\
+                       origin\tgit@github.com:me/repo.git (push)\n\
+                       upstream\thttps://github.com/org/repo.git (fetch)\n";
+        assert_eq!(remote_names(remotes), vec!["origin", "upstream"]);
+        assert!(remote_names("").is_empty());
+        assert!(push("--receive-pack=evil", "main", false).is_err());
+        assert!(push("origin", "", false).is_err());
+    }
+
+    #[test]
+    fn test_has_conflict_markers() {
+        let conflicted = b"fn a() {}\n<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> feature\n";
+        assert!(has_conflict_markers(conflicted));
+        assert!(!has_conflict_markers(b"let s = \"=======\";\n"));
+        assert!(!has_conflict_markers(b"<<<<<<< HEAD\nours\n"));
+    }
 }
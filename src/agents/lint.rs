@@ -0,0 +1,285 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// How long a linter may run before it is abandoned
+const LINT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A single problem reported by a linter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LintIssue {
+    pub file: String,
+    pub line: usize,
+    pub severity: String,
+    pub message: String,
+    pub code: String,
+}
+
+/// Issues left after a lint run, and the ones an auto-fix removed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LintReport {
+    pub issues: Vec<LintIssue>,
+    pub fixed: Vec<LintIssue>,
+}
+
+/// Linters with machine-readable output
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Linter {
+    Clippy,
+    ESLint,
+    Ruff,
+}
+
+impl Linter {
+    /// Pick a linter from the file extension, falling back to the project type
+    fn detect(path: &str, project_type: Option<&str>) -> Option<Self> {
+        let by_extension = match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("rs") => Some(Linter::Clippy),
+            Some("js" | "jsx" | "ts" | "tsx") => Some(Linter::ESLint),
+            Some("py") => Some(Linter::Ruff),
+            _ => None,
+        };
+        by_extension.or_else(|| match project_type.map(|t| t.to_lowercase()) {
+            Some(t) if t == "rust" => Some(Linter::Clippy),
+            Some(t) if t == "javascript" || t == "typescript" => Some(Linter::ESLint),
+            Some(t) if t == "python" => Some(Linter::Ruff),
+            _ => None,
+        })
+    }
+
+    fn command(&self, path: &str, fix: bool) -> Command {
+        let mut cmd = match self {
+            // Clippy lints the whole crate; issues are filtered to `path` afterwards
+            Linter::Clippy => {
+                let mut cmd = Command::new("cargo");
+                cmd.args(["clippy", "--message-format=json"]);
+                if fix {
+                    cmd.args(["--fix", "--allow-dirty", "--allow-staged"]);
+                }
+                cmd
+            },
+            // Only a locally installed eslint; npx would otherwise download and run one
+            Linter::ESLint => {
+                let mut cmd = Command::new("npx");
+                cmd.args(["--no-install", "eslint", "--format", "json"]);
+                if fix {
+                    cmd.arg("--fix");
+                }
+                cmd.arg(path);
+                cmd
+            },
+            Linter::Ruff => {
+                let mut cmd = Command::new("ruff");
+                cmd.args(["check", "--output-format", "json"]);
+                if fix {
+                    cmd.arg("--fix");
+                }
+                cmd.arg(path);
+                cmd
+            },
+        };
+        cmd.kill_on_drop(true);
+        cmd
+    }
+
+    fn parse(&self, output: &str) -> Vec<LintIssue> {
+        match self {
+            Linter::Clippy => parse_clippy(output),
+            Linter::ESLint => parse_eslint(output),
+            Linter::Ruff => parse_ruff(output),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Linter::Clippy => "cargo clippy",
+            Linter::ESLint => "eslint",
+            Linter::Ruff => "ruff",
+        }
+    }
+}
+
+/// Warning for a fix confirmation when the linter's auto-fix reaches past `path`
+pub fn fix_scope_note(path: &str, project_type: Option<&str>) -> Option<&'static str> {
+    (Linter::detect(path, project_type) == Some(Linter::Clippy))
+        .then_some("cargo clippy --fix applies its fixes to the whole crate")
+}
+
+/// Lint `path` with the project's linter, auto-fixing what it can when `fix` is set
+pub async fn lint_file(path: &str, fix: bool, project_type: Option<&str>) -> Result<LintReport> {
+    let linter = Linter::detect(path, project_type)
+        .with_context(|| format!("No supported linter for {}", path))?;
+
+    let before = run_linter(linter, path, false).await?;
+    if !fix {
+        return Ok(LintReport {
+            issues: before,
+            fixed: Vec::new(),
+        });
+    }
+
+    run_linter(linter, path, true).await?;
+    let after = run_linter(linter, path, false).await?;
+    // Lines move when fixes are applied, so match issues without them
+    let remains = |issue: &LintIssue| {
+        after
+            .iter()
+            .any(|a| a.file == issue.file && a.code == issue.code && a.message == issue.message)
+    };
+    let fixed = before.into_iter().filter(|i| !remains(i)).collect();
+    Ok(LintReport {
+        issues: after,
+        fixed,
+    })
+}
+
+/// Run a linter once and parse its issues for `path`
+async fn run_linter(linter: Linter, path: &str, fix: bool) -> Result<Vec<LintIssue>> {
+    let output = tokio::time::timeout(LINT_TIMEOUT, linter.command(path, fix).output())
+        .await
+        .with_context(|| {
+            format!(
+                "{} timed out after {} seconds",
+                linter.name(),
+                LINT_TIMEOUT.as_secs()
+            )
+        })?
+        .with_context(|| format!("Failed to run {}. Is it installed?", linter.name()))?;
+
+    // Linters exit non-zero when they find issues, so only empty output is a failure
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.trim().is_empty() && !output.status.success() {
+        anyhow::bail!(
+            "{} failed: {}",
+            linter.name(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let target = Path::new(path.trim_start_matches("./"));
+    Ok(linter
+        .parse(&stdout)
+        .into_iter()
+        .filter(|issue| target.is_dir() || Path::new(&issue.file).ends_with(target))
+        .collect())
+}
+
+/// Parse `cargo clippy --message-format=json` output (one JSON object per line)
+fn parse_clippy(output: &str) -> Vec<LintIssue> {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|v| v["reason"] == "compiler-message")
+        .filter_map(|v| {
+            let message = &v["message"];
+            let span = message["spans"]
+                .as_array()?
+                .iter()
+                .find(|s| s["is_primary"] == true)?;
+            Some(LintIssue {
+                file: span["file_name"].as_str()?.to_string(),
+                line: span["line_start"].as_u64()? as usize,
+                severity: message["level"].as_str().unwrap_or("warning").to_string(),
+                message: message["message"].as_str().unwrap_or_default().to_string(),
+                code: message["code"]["code"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Parse `eslint --format json` output
+fn parse_eslint(output: &str) -> Vec<LintIssue> {
+    let Ok(Value::Array(files)) = serde_json::from_str::<Value>(output) else {
+        return Vec::new();
+    };
+    files
+        .iter()
+        .flat_map(|file| {
+            let path = file["filePath"].as_str().unwrap_or_default().to_string();
+            file["messages"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default()
+                .into_iter()
+                .map(move |m| LintIssue {
+                    file: path.clone(),
+                    line: m["line"].as_u64().unwrap_or(0) as usize,
+                    severity: if m["severity"] == 2 {
+                        "error"
+                    } else {
+                        "warning"
+                    }
+                    .to_string(),
+                    message: m["message"].as_str().unwrap_or_default().to_string(),
+                    code: m["ruleId"].as_str().unwrap_or_default().to_string(),
+                })
+        })
+        .collect()
+}
+
+/// Parse `ruff check --output-format json` output
+fn parse_ruff(output: &str) -> Vec<LintIssue> {
+    let Ok(Value::Array(issues)) = serde_json::from_str::<Value>(output) else {
+        return Vec::new();
+    };
+    issues
+        .iter()
+        .map(|i| LintIssue {
+            file: i["filename"].as_str().unwrap_or_default().to_string(),
+            line: i["location"]["row"].as_u64().unwrap_or(0) as usize,
+            // Ruff has no severity levels; everything it reports is a warning
+            severity: "warning".to_string(),
+            message: i["message"].as_str().unwrap_or_default().to_string(),
+            code: i["code"].as_str().unwrap_or_default().to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_prefers_extension() {
+        assert_eq!(
+            Linter::detect("web/app.ts", Some("rust")),
+            Some(Linter::ESLint)
+        );
+        assert_eq!(
+            Linter::detect("build.rs", Some("python")),
+            Some(Linter::Clippy)
+        );
+        assert_eq!(
+            Linter::detect("Makefile", Some("python")),
+            Some(Linter::Ruff)
+        );
+        assert_eq!(Linter::detect("notes.txt", None), None);
+    }
+
+    #[test]
+    fn test_parse_clippy_and_ruff_output() {
+        let clippy = r#"{"reason":"compiler-artifact","target":{}}
+{"reason":"compiler-message","message":{"level":"warning","message":"unused variable: `x`","code":{"code":"unused_variables"},"spans":[{"file_name":"src/lib.rs","line_start":3,"is_primary":true}]}}"#;
+        assert_eq!(
+            parse_clippy(clippy),
+            vec![LintIssue {
+                file: "src/lib.rs".to_string(),
+                line: 3,
+                severity: "warning".to_string(),
+                message: "unused variable: `x`".to_string(),
+                code: "unused_variables".to_string(),
+            }]
+        );
+
+        let ruff = r#"[{"code":"F401","filename":"/p/app.py","location":{"row":1,"column":8},"message":"`os` imported but unused"}]"#;
+        let issues = parse_ruff(ruff);
+        assert_eq!(issues.len(), 1);
+        assert_eq!((issues[0].line, issues[0].code.as_str()), (1, "F401"));
+    }
+}
@@ -3,16 +3,36 @@
 
 // Private submodules - not directly accessible from outside
 mod action_executor;
+mod ast_search;
+mod dependency;
+mod doc_gen;
 mod executor;
 mod filesystem;
+mod format;
 mod git;
+mod http_request;
+mod lint;
 mod mode_aware_executor;
 mod parser;
+mod search_replace;
+mod snapshot;
+mod test_gen;
 mod types;
+mod web_search;
 
 // Public re-exports - the ONLY way to access agent functionality
-pub use action_executor::execute_action;
-pub use filesystem::read_file;
+pub use action_executor::{execute_action, is_secret_env_key, AuditEntry, AuditLogger};
+pub use dependency::{is_read_only_command, plan_waves};
+pub use doc_gen::{generate_docs, line_diff};
+pub use executor::resolve_cd;
+pub use filesystem::{allow_workspace_root, read_file, read_project_file};
+pub use format::{can_format, format_files};
+pub use git::{has_conflict_markers, head_branch, list_stashes, StashEntry};
+pub use lint::{lint_file, LintIssue, LintReport};
 pub use mode_aware_executor::ModeAwareExecutor;
-pub use parser::parse_actions;
-pub use types::{ActionResult, AgentAction};
+pub use parser::{parse_actions, sanitize_for_model};
+pub use search_replace::affected_files;
+pub use snapshot::{Snapshot, SnapshotManager};
+pub use test_gen::generate_tests;
+pub use types::{builtin_tools, ActionResult, AgentAction, DocFormat, STDERR_MARKER};
+pub use web_search::SearchResult;
@@ -1,12 +1,28 @@
-use super::action_executor::execute_action;
+use super::action_executor::{
+    execute_action, is_loader_env_key, is_secret_env_key, mask_env, AuditEntry, AuditLogger,
+};
+use super::executor::resolve_cd;
 use super::types::{ActionResult, AgentAction};
+use crate::app::{BypassConfig, Config};
 use crate::tui::OperationMode;
 use anyhow::Result;
+use chrono::Local;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 /// Mode-aware action executor that respects operation modes
 pub struct ModeAwareExecutor {
     mode: OperationMode,
     bypass_confirmed: bool,
+    /// Settings actions run with, including the action types Bypass All still asks about
+    config: Config,
+    /// Variables set for this session, passed to every command
+    session_env: HashMap<String, String>,
+    /// Directory commands run in when they don't name one, moved by `cd`
+    working_dir: Option<String>,
+    /// Project directory `cd` can't leave and the audit log lives in, if not the current one
+    root: Option<PathBuf>,
 }
 
 impl ModeAwareExecutor {
@@ -15,9 +31,51 @@ impl ModeAwareExecutor {
         Self {
             mode,
             bypass_confirmed: false,
+            config: Config::default(),
+            session_env: HashMap::new(),
+            working_dir: None,
+            root: None,
         }
     }
 
+    /// Run actions with the loaded configuration (timeouts, URL allowlist, search)
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Use the configured Bypass All opt-outs
+    pub fn with_bypass_config(mut self, bypass: BypassConfig) -> Self {
+        self.config.mode.bypass = bypass;
+        self
+    }
+
+    /// Start from the variables already set this session
+    pub fn with_session_env(mut self, session_env: HashMap<String, String>) -> Self {
+        self.session_env = session_env;
+        self
+    }
+
+    /// Run commands without their own `working_dir` in this directory
+    pub fn with_working_dir(mut self, working_dir: impl Into<String>) -> Self {
+        self.working_dir = Some(working_dir.into());
+        self
+    }
+
+    /// Confine `cd` to, and audit actions in, this project directory
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// The project directory, defaulting to the current one
+    fn root(&self) -> PathBuf {
+        self.root
+            .clone()
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default()
+    }
+
     /// Update the operation mode
     pub fn set_mode(&mut self, mode: OperationMode) {
         self.mode = mode;
@@ -30,84 +88,270 @@ impl ModeAwareExecutor {
         if self.mode.is_planning_only() {
             return false;
         }
+        if self.bypass_opted_out(action) {
+            return true;
+        }
 
         match action {
             // File operations
-            AgentAction::WriteFile { .. } | AgentAction::DeleteFile { .. } => {
-                !self.mode.auto_accept_files()
+            AgentAction::WriteFile { .. }
+            | AgentAction::DeleteFile { .. }
+            | AgentAction::SearchReplace { .. }
+            | AgentAction::FormatFiles { .. }
+            | AgentAction::Rollback { .. }
+            | AgentAction::SetEnvironmentVariable { persist: true, .. }
+            | AgentAction::LintFile { fix: true, .. } => !self.mode.auto_accept_files(),
+
+            // Shell commands, and HTTP requests which can change remote state
+            AgentAction::ExecuteCommand { .. } | AgentAction::HttpRequest { .. } => {
+                !self.mode.auto_accept_commands()
             },
 
-            // Shell commands
-            AgentAction::ExecuteCommand { .. } => !self.mode.auto_accept_commands(),
-
             // Git operations
             AgentAction::GitCommit { .. } => !self.mode.auto_accept_git(),
 
+            // Pushing publishes commits, so it's confirmed in every mode, Bypass All included
+            AgentAction::GitPush { .. } => true,
+
             // Read operations are generally safe
-            AgentAction::ReadFile { .. } | AgentAction::GitStatus | AgentAction::GitDiff { .. } => {
-                false
-            },
+            AgentAction::ReadFile { .. }
+            | AgentAction::GitStatus
+            | AgentAction::GitDiff { .. }
+            | AgentAction::GitStashList
+            | AgentAction::GitStashShow { .. }
+            | AgentAction::AstSearch { .. }
+            | AgentAction::WebSearch { .. }
+            | AgentAction::LintFile { fix: false, .. } => false,
+
+            // Session variables change what every later command runs, so they're confirmed
+            // like commands; PATH and loader variables can swap the programs themselves
+            AgentAction::SetEnvironmentVariable {
+                key,
+                persist: false,
+                ..
+            } => is_loader_env_key(key) || !self.mode.auto_accept_commands(),
+
+            // The generated file is confirmed as a WriteFile
+            AgentAction::GenerateTests { .. } | AgentAction::GenerateDocs { .. } => false,
 
             // Directory creation needs confirmation unless in bypass mode
             AgentAction::CreateDirectory { .. } => !self.mode.auto_accept_files(),
+
+            // Function calls are as risky as the action they name
+            AgentAction::CallFunction { .. } => match action.resolve() {
+                Ok(resolved) => self.needs_confirmation(&resolved),
+                Err(_) => false, // Fails without side effects
+            },
+        }
+    }
+
+    /// Whether Bypass All is configured to still confirm this kind of action
+    fn bypass_opted_out(&self, action: &AgentAction) -> bool {
+        if self.mode != OperationMode::BypassAll {
+            return false;
+        }
+        match action {
+            AgentAction::DeleteFile { .. } => !self.config.mode.bypass.bypass_deletes,
+            AgentAction::WriteFile { .. }
+            | AgentAction::SearchReplace { .. }
+            | AgentAction::FormatFiles { .. }
+            | AgentAction::Rollback { .. }
+            | AgentAction::CreateDirectory { .. }
+            | AgentAction::SetEnvironmentVariable { persist: true, .. }
+            | AgentAction::LintFile { fix: true, .. } => {
+                !self.config.mode.bypass.bypass_file_writes
+            },
+            AgentAction::ExecuteCommand { .. }
+            | AgentAction::HttpRequest { .. }
+            | AgentAction::SetEnvironmentVariable { persist: false, .. } => {
+                !self.config.mode.bypass.bypass_commands
+            },
+            AgentAction::GitCommit { .. } => !self.config.mode.bypass.bypass_git_operations,
+            // Function calls are checked as the action they resolve to
+            _ => false,
         }
     }
 
     /// Check if an action is considered destructive
     pub fn is_destructive(&self, action: &AgentAction) -> bool {
         match action {
-            AgentAction::DeleteFile { .. } => true,
+            // Amending rewrites a commit that may already be shared
+            AgentAction::DeleteFile { .. } | AgentAction::GitCommit { amend: true, .. } => true,
             AgentAction::ExecuteCommand { command, .. } => {
                 command.contains("rm")
                     || command.contains("del")
                     || command.contains("drop")
                     || command.contains("truncate")
             },
+            AgentAction::CallFunction { .. } => action
+                .resolve()
+                .map(|resolved| self.is_destructive(&resolved))
+                .unwrap_or(false),
             _ => false,
         }
     }
 
     /// Execute an action with mode awareness
     pub async fn execute(&mut self, action: AgentAction) -> Result<ActionResult> {
+        self.run(action, false).await
+    }
+
+    /// Execute an action the user approved in a confirmation prompt
+    pub async fn execute_confirmed(&mut self, action: AgentAction) -> Result<ActionResult> {
+        self.run(action, true).await
+    }
+
+    async fn run(&mut self, action: AgentAction, user_confirmed: bool) -> Result<ActionResult> {
         // Planning mode: just return what would happen
         if self.mode.is_planning_only() {
-            return Ok(ActionResult::Success {
-                output: format!("[PLANNED]: {}", self.describe_action(&action)),
-            });
+            return Ok(ActionResult::success(format!(
+                "[PLANNED]: {}",
+                self.describe_action(&action)
+            )));
         }
 
-        // Bypass mode with destructive operation: require double confirmation
-        if self.mode == OperationMode::BypassAll && self.is_destructive(&action) {
-            if !self.bypass_confirmed {
-                self.bypass_confirmed = true;
-                return Ok(ActionResult::Success {
-                    output: format!(
-                        "[WARNING] DESTRUCTIVE OPERATION in Bypass Mode: {}\n\
-                         Press Enter to confirm or Esc to cancel.",
-                        self.describe_action(&action)
-                    ),
-                });
-            }
+        // Bypass mode with destructive operation: require double confirmation,
+        // unless the user already approved it in a prompt
+        if self.mode == OperationMode::BypassAll
+            && !user_confirmed
+            && self.is_destructive(&action)
+            && !self.bypass_confirmed
+        {
+            self.bypass_confirmed = true;
+            return Ok(ActionResult::success(format!(
+                "[WARNING] DESTRUCTIVE OPERATION in Bypass Mode: {}\n\
+                 Press Enter to confirm or Esc to cancel.",
+                self.describe_action(&action)
+            )));
         }
 
         // Execute the action
-        let result = execute_action(&action).await?;
+        let result = self.execute_audited(&action, user_confirmed).await?;
+        if let (
+            Ok(AgentAction::SetEnvironmentVariable {
+                key,
+                value,
+                persist: false,
+            }),
+            ActionResult::Success { .. },
+        ) = (action.resolve(), &result)
+        {
+            self.session_env.insert(key, value);
+        }
+        if let (
+            Ok(AgentAction::ExecuteCommand {
+                command,
+                working_dir,
+            }),
+            ActionResult::Success { .. },
+        ) = (action.resolve(), &result)
+        {
+            let base = working_dir
+                .or_else(|| self.working_dir.clone())
+                .unwrap_or_default();
+            if let Some(dir) = resolve_cd(&command, Path::new(&base), &self.root()) {
+                self.working_dir = Some(dir.to_string_lossy().to_string());
+            }
+        }
 
         // Reset bypass confirmation after successful execution
         if self.bypass_confirmed {
             self.bypass_confirmed = false;
         }
 
-        // Add mode indicator to result if not in Normal mode
+        Ok(self.tag_result(result))
+    }
+
+    /// Execute independent read-only actions concurrently, returning results in order
+    /// Destructive actions are refused here; run those through `execute`
+    pub async fn execute_parallel(&self, actions: &[AgentAction]) -> Vec<Result<ActionResult>> {
+        futures::future::join_all(actions.iter().map(|action| async move {
+            if self.mode.is_planning_only() {
+                return Ok(ActionResult::success(format!(
+                    "[PLANNED]: {}",
+                    self.describe_action(action)
+                )));
+            }
+            if self.is_destructive(action) {
+                anyhow::bail!(
+                    "{} is destructive and can't run in parallel",
+                    self.describe_action(action)
+                );
+            }
+            Ok(self.tag_result(self.execute_audited(action, false).await?))
+        }))
+        .await
+    }
+
+    /// Execute an action and append it to the project's audit log
+    async fn execute_audited(
+        &self,
+        action: &AgentAction,
+        user_confirmed: bool,
+    ) -> Result<ActionResult> {
+        let started = Instant::now();
+        let mut action = action.resolve().unwrap_or_else(|_| action.clone());
+        match &mut action {
+            AgentAction::ExecuteCommand {
+                working_dir: working_dir @ None,
+                ..
+            } => working_dir.clone_from(&self.working_dir),
+            // Relative paths are relative to wherever `cd` left the model
+            AgentAction::ReadFile { path }
+            | AgentAction::WriteFile { path, .. }
+            | AgentAction::DeleteFile { path }
+            | AgentAction::CreateDirectory { path } => {
+                if let Some(dir) = self
+                    .working_dir
+                    .as_ref()
+                    .filter(|_| Path::new(path).is_relative())
+                {
+                    *path = Path::new(dir).join(&*path).to_string_lossy().to_string();
+                }
+            },
+            _ => {},
+        }
+        let result = execute_action(&action, &self.root(), &self.session_env, &self.config).await?;
+
+        let env = match &action {
+            AgentAction::ExecuteCommand { .. } => mask_env(&self.session_env),
+            AgentAction::SetEnvironmentVariable { key, value, .. } => {
+                mask_env(&HashMap::from([(key.clone(), value.clone())]))
+            },
+            _ => HashMap::new(),
+        };
+        let entry = AuditEntry {
+            timestamp: Local::now(),
+            action_type: action.type_name().to_string(),
+            target: action.target(),
+            mode: self.mode,
+            success: matches!(result, ActionResult::Success { .. }),
+            duration_ms: started.elapsed().as_millis() as u64,
+            user_confirmed,
+            env,
+        };
+        // A failed audit write shouldn't fail the action that already ran
+        let _ = AuditLogger::new(self.root()).log(&entry);
+        Ok(result)
+    }
+
+    /// Add the mode indicator to a result if not in Normal mode
+    fn tag_result(&self, result: ActionResult) -> ActionResult {
         if self.mode != OperationMode::Normal {
             match result {
-                ActionResult::Success { output } => Ok(ActionResult::Success {
-                    output: format!("[{}] {}", self.mode.short_name(), output),
-                }),
-                other => Ok(other),
+                ActionResult::Success {
+                    stdout,
+                    stderr,
+                    exit_code,
+                } => ActionResult::Success {
+                    stdout: format!("[{}] {}", self.mode.short_name(), stdout),
+                    stderr,
+                    exit_code,
+                },
+                other => other,
             }
         } else {
-            Ok(result)
+            result
         }
     }
 
@@ -130,11 +374,20 @@ impl ModeAwareExecutor {
                 command,
                 working_dir,
             } => {
-                if let Some(dir) = working_dir {
+                let mut description = if let Some(dir) = working_dir {
                     format!("Execute command in {}: {}", dir, command)
                 } else {
                     format!("Execute command: {}", command)
+                };
+                if !self.session_env.is_empty() {
+                    let mut overrides: Vec<String> = mask_env(&self.session_env)
+                        .into_iter()
+                        .map(|(key, value)| format!("{}={}", key, value))
+                        .collect();
+                    overrides.sort();
+                    description.push_str(&format!(" (with {})", overrides.join(" ")));
                 }
+                description
             },
             AgentAction::GitDiff { path } => {
                 if let Some(p) = path {
@@ -144,11 +397,111 @@ impl ModeAwareExecutor {
                 }
             },
             AgentAction::GitStatus => "Git status".to_string(),
-            AgentAction::GitCommit { message, files } => {
-                if !files.is_empty() {
-                    format!("Git commit ({} files): {}", files.len(), message)
+            AgentAction::GitStashList => "Git stash list".to_string(),
+            AgentAction::GitPush {
+                remote,
+                branch,
+                force,
+            } => format!(
+                "{}Push branch '{}' to remote '{}'",
+                if *force { "FORCE " } else { "" },
+                branch,
+                remote
+            ),
+            AgentAction::GitStashShow { stash_ref } => format!("Git stash show: {}", stash_ref),
+            AgentAction::SearchReplace {
+                pattern,
+                replacement,
+                path_filter,
+                regex,
+            } => format!(
+                "Replace {}'{}' with '{}' in {}",
+                if *regex { "regex " } else { "" },
+                pattern,
+                replacement,
+                path_filter.as_deref().unwrap_or("all files")
+            ),
+            AgentAction::HttpRequest { method, url, .. } => {
+                format!("HTTP request: {} {}", method.to_uppercase(), url)
+            },
+            AgentAction::SetEnvironmentVariable {
+                key,
+                value,
+                persist,
+            } => format!(
+                "{} {}={}",
+                if *persist {
+                    "Add to .env:"
                 } else {
-                    format!("Git commit (all): {}", message)
+                    "Set for session:"
+                },
+                key,
+                if is_secret_env_key(key) {
+                    "****"
+                } else {
+                    value
+                }
+            ),
+            AgentAction::WebSearch { query, .. } => format!("Web search: {}", query),
+            AgentAction::AstSearch { pattern, language } => format!(
+                "AST search in {}: {}",
+                language.as_deref().unwrap_or("all languages"),
+                pattern
+            ),
+            AgentAction::GenerateTests {
+                source_file,
+                test_framework,
+            } => match test_framework {
+                Some(framework) => format!("Generate {} tests for: {}", framework, source_file),
+                None => format!("Generate tests for: {}", source_file),
+            },
+            AgentAction::GenerateDocs {
+                source_file,
+                format,
+            } => format!("Generate {:?} docs for: {}", format, source_file),
+            AgentAction::FormatFiles { paths } => format!("Format: {}", paths.join(", ")),
+            AgentAction::Rollback { to_snapshot_id } => {
+                format!("Roll back to snapshot: {}", to_snapshot_id)
+            },
+            AgentAction::LintFile { path, fix } => {
+                if *fix {
+                    let dir = self
+                        .working_dir
+                        .as_ref()
+                        .map(std::path::PathBuf::from)
+                        .or_else(|| std::env::current_dir().ok());
+                    let project_type =
+                        dir.and_then(|dir| crate::context::detect_project_type(&dir));
+                    match super::lint::fix_scope_note(path, project_type.as_deref()) {
+                        Some(note) => format!("Lint and auto-fix: {} ({})", path, note),
+                        None => format!("Lint and auto-fix: {}", path),
+                    }
+                } else {
+                    format!("Lint: {}", path)
+                }
+            },
+            AgentAction::CallFunction { name, arguments } => match action.resolve() {
+                Ok(resolved) => format!("Call {}: {}", name, self.describe_action(&resolved)),
+                Err(_) => format!("Call function: {}({})", name, arguments),
+            },
+            AgentAction::GitCommit {
+                message,
+                files,
+                amend,
+            } => {
+                let kind = if *amend {
+                    "Amend last commit"
+                } else {
+                    "Git commit"
+                };
+                let files = if files.is_empty() {
+                    "all".to_string()
+                } else {
+                    format!("{} files", files.len())
+                };
+                match (*amend, message.is_empty()) {
+                    (true, true) => format!("{} ({}), keeping its message", kind, files),
+                    _ => format!("{} ({}): {}", kind, files, message),
                 }
             },
         }
@@ -174,6 +527,69 @@ impl ModeAwareExecutor {
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_cd_is_confined_to_the_root() {
+        let project = tempfile::TempDir::new().unwrap();
+        let root = project.path().canonicalize().unwrap();
+        assert_ne!(Some(&root), std::env::current_dir().ok().as_ref());
+        std::fs::create_dir(root.join("src")).unwrap();
+        let cd = |command: &str| AgentAction::ExecuteCommand {
+            command: command.to_string(),
+            working_dir: None,
+        };
+
+        let mut executor = ModeAwareExecutor::new(OperationMode::BypassAll)
+            .with_root(&root)
+            .with_working_dir(root.to_string_lossy());
+        executor.execute(cd("cd src")).await.unwrap();
+        assert_eq!(
+            executor.working_dir,
+            Some(root.join("src").to_string_lossy().to_string())
+        );
+
+        // A bare `cd` returns to the project root, not the process's directory
+        executor.execute(cd("cd")).await.unwrap();
+        assert_eq!(
+            executor.working_dir,
+            Some(root.to_string_lossy().to_string())
+        );
+        assert!(root.join(".mermaid/audit.log").exists());
+    }
+
+    #[tokio::test]
+    async fn test_actions_resolve_against_the_root() {
+        let project = tempfile::TempDir::new().unwrap();
+        let root = project.path().canonicalize().unwrap();
+        assert_ne!(Some(&root), std::env::current_dir().ok().as_ref());
+        crate::agents::allow_workspace_root(root.clone());
+
+        let mut executor = ModeAwareExecutor::new(OperationMode::BypassAll)
+            .with_root(&root)
+            .with_working_dir(root.to_string_lossy());
+        let write = executor
+            .execute(AgentAction::WriteFile {
+                path: "notes.txt".to_string(),
+                content: "hi".to_string(),
+            })
+            .await
+            .unwrap();
+        assert!(matches!(write, ActionResult::Success { .. }));
+        assert!(root.join("notes.txt").exists());
+
+        executor
+            .execute(AgentAction::SetEnvironmentVariable {
+                key: "APP_MODE".to_string(),
+                value: "dev".to_string(),
+                persist: true,
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(root.join(".env")).unwrap(),
+            "APP_MODE=dev\n"
+        );
+    }
+
     #[test]
     fn test_needs_confirmation() {
         let executor = ModeAwareExecutor::new(OperationMode::Normal);
@@ -225,9 +641,98 @@ mod tests {
         assert!(!executor.needs_confirmation(&AgentAction::GitCommit {
             message: "test".to_string(),
             files: vec![],
+            amend: false,
         }));
     }
 
+    #[test]
+    fn test_bypass_opt_outs() {
+        let executor =
+            ModeAwareExecutor::new(OperationMode::BypassAll).with_bypass_config(BypassConfig {
+                bypass_commands: false,
+                bypass_deletes: false,
+                ..BypassConfig::default()
+            });
+
+        // Opted-out action types are confirmed again
+        assert!(executor.needs_confirmation(&AgentAction::ExecuteCommand {
+            command: "cargo test".to_string(),
+            working_dir: None,
+        }));
+        assert!(executor.needs_confirmation(&AgentAction::DeleteFile {
+            path: "old.rs".to_string(),
+        }));
+
+        // The rest are still bypassed
+        assert!(!executor.needs_confirmation(&AgentAction::WriteFile {
+            path: "test.txt".to_string(),
+            content: "test".to_string(),
+        }));
+        assert!(!executor.needs_confirmation(&AgentAction::GitCommit {
+            message: "test".to_string(),
+            files: vec![],
+            amend: false,
+        }));
+
+        // Opt-outs only apply to Bypass All
+        let mut executor = executor;
+        executor.set_mode(OperationMode::AcceptEdits);
+        assert!(!executor.needs_confirmation(&AgentAction::DeleteFile {
+            path: "old.rs".to_string(),
+        }));
+    }
+
+    #[test]
+    fn test_session_env_confirmed() {
+        let set = |key: &str| AgentAction::SetEnvironmentVariable {
+            key: key.to_string(),
+            value: "/tmp/evil".to_string(),
+            persist: false,
+        };
+        assert!(ModeAwareExecutor::new(OperationMode::Normal).needs_confirmation(&set("RUST_LOG")));
+
+        // Loader variables are confirmed even where commands aren't
+        let executor = ModeAwareExecutor::new(OperationMode::BypassAll);
+        assert!(!executor.needs_confirmation(&set("RUST_LOG")));
+        assert!(executor.needs_confirmation(&set("PATH")));
+        assert!(executor.needs_confirmation(&set("LD_PRELOAD")));
+        assert!(executor.needs_confirmation(&set("DYLD_INSERT_LIBRARIES")));
+
+        // Commands list the variables they'll run with
+        let executor = executor.with_session_env(HashMap::from([
+            ("RUST_LOG".to_string(), "debug".to_string()),
+            ("API_TOKEN".to_string(), "abc".to_string()),
+        ]));
+        assert_eq!(
+            executor.describe_action(&AgentAction::ExecuteCommand {
+                command: "cargo run".to_string(),
+                working_dir: None,
+            }),
+            "Execute command: cargo run (with API_TOKEN=**** RUST_LOG=debug)"
+        );
+    }
+
+    #[test]
+    fn test_git_push_always_confirmed() {
+        let push = AgentAction::GitPush {
+            remote: "origin".to_string(),
+            branch: "main".to_string(),
+            force: false,
+        };
+        for mode in [
+            OperationMode::Normal,
+            OperationMode::AcceptEdits,
+            OperationMode::BypassAll,
+        ] {
+            assert!(ModeAwareExecutor::new(mode).needs_confirmation(&push));
+        }
+        let executor = ModeAwareExecutor::new(OperationMode::BypassAll);
+        assert_eq!(
+            executor.describe_action(&push),
+            "Push branch 'main' to remote 'origin'"
+        );
+    }
+
     #[test]
     fn test_destructive_detection() {
         let executor = ModeAwareExecutor::new(OperationMode::Normal);
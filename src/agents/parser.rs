@@ -1,112 +1,586 @@
+use std::collections::HashMap;
+
 use super::types::AgentAction;
+use super::web_search::DEFAULT_SEARCH_RESULTS;
+use crate::models::parse_tool_call;
+
+/// Action blocks with a `[TYPE: header] ... [/TYPE]` form
+const BLOCK_TYPES: &[&str] = &[
+    "FILE_WRITE",
+    "FILE_READ",
+    "COMMAND",
+    "HTTP_REQUEST",
+    "SEARCH_REPLACE",
+    "AST_SEARCH",
+];
 
 /// Parse actions from AI response text
+/// Markers inside a block's content (and escaped `\[/TYPE\]` closers) are
+/// content, not actions, and malformed output yields no action rather than a panic
 pub fn parse_actions(response: &str) -> Vec<AgentAction> {
     let mut actions = Vec::new();
+    let (blocks, outside) = scan_blocks(response);
+    // Block actions come first, grouped by type in the order of BLOCK_TYPES
+    for block_type in BLOCK_TYPES {
+        let of_type = blocks
+            .iter()
+            .filter(|block| block.block_type == *block_type);
+        actions.extend(of_type.filter_map(block_action));
+    }
 
-    // Parse file write actions
-    if let Some(captures) = extract_block(response, "FILE_WRITE") {
-        for capture in captures {
-            // Extract path from [FILE_WRITE: path] format
-            if let Some(path) = extract_path_from_header(&capture, "FILE_WRITE") {
-                actions.push(AgentAction::WriteFile {
-                    path,
-                    content: extract_content(&capture),
-                });
-            }
-        }
+    // The remaining markers only count outside blocks
+    let response = outside.as_str();
+
+    // Parse git operations
+    if response.contains("[GIT_DIFF]") {
+        actions.push(AgentAction::GitDiff { path: None });
     }
 
-    // Parse file read actions
-    if let Some(captures) = extract_block(response, "FILE_READ") {
-        for capture in captures {
-            // Extract path from [FILE_READ: path] format
-            if let Some(path) = extract_path_from_header(&capture, "FILE_READ") {
-                actions.push(AgentAction::ReadFile { path });
-            }
+    if response.contains("[GIT_STATUS]") {
+        actions.push(AgentAction::GitStatus);
+    }
+
+    if response.contains("[GIT_STASH_LIST]") {
+        actions.push(AgentAction::GitStashList);
+    }
+
+    // Parse stash inspection in [GIT_STASH_SHOW: stash@{n}] format
+    let mut remaining = response;
+    while let Some(start) = remaining.find("[GIT_STASH_SHOW:") {
+        let rest = &remaining[start + "[GIT_STASH_SHOW:".len()..];
+        let Some(end) = rest.find(']') else {
+            break;
+        };
+        let stash_ref = rest[..end].trim();
+        actions.push(AgentAction::GitStashShow {
+            stash_ref: if stash_ref.is_empty() {
+                "stash@{0}".to_string()
+            } else {
+                stash_ref.to_string()
+            },
+        });
+        remaining = &rest[end..];
+    }
+
+    // Parse rollbacks in [ROLLBACK: id] format
+    let mut remaining = response;
+    while let Some(start) = remaining.find("[ROLLBACK:") {
+        let rest = &remaining[start + "[ROLLBACK:".len()..];
+        let Some(end) = rest.find(']') else {
+            break;
+        };
+        actions.push(AgentAction::Rollback {
+            to_snapshot_id: rest[..end].trim().to_string(),
+        });
+        remaining = &rest[end..];
+    }
+
+    // Parse environment variables in [SET_ENV: KEY=value] format, saved to .env with persist=true
+    let mut remaining = response;
+    while let Some(start) = remaining.find("[SET_ENV:") {
+        let rest = &remaining[start + "[SET_ENV:".len()..];
+        let Some(end) = rest.find(']') else {
+            break;
+        };
+        let mut assignment = rest[..end].trim();
+        let persist = match assignment.strip_suffix(" persist=true") {
+            Some(stripped) => {
+                assignment = stripped;
+                true
+            },
+            None => false,
+        };
+        if let Some((key, value)) = assignment.split_once('=') {
+            actions.push(AgentAction::SetEnvironmentVariable {
+                key: key.trim().to_string(),
+                value: value.trim().trim_matches('"').to_string(),
+                persist,
+            });
         }
+        remaining = &rest[end..];
     }
 
-    // Parse command execution
-    if let Some(captures) = extract_block(response, "COMMAND") {
-        for capture in captures {
-            // For COMMAND, the command itself is after the colon
-            if let Some(cmd) = extract_path_from_header(&capture, "COMMAND") {
-                // Check if there's a dir= attribute
-                if let Some(dir_pos) = cmd.find(" dir=") {
-                    let command_part = cmd[..dir_pos].to_string();
-                    let dir_part = cmd[dir_pos + 5..].trim_matches('"').to_string();
-                    actions.push(AgentAction::ExecuteCommand {
-                        command: command_part,
-                        working_dir: Some(dir_part),
-                    });
-                } else {
-                    actions.push(AgentAction::ExecuteCommand {
-                        command: cmd,
-                        working_dir: None,
-                    });
-                }
-            }
+    // Parse pushes in [GIT_PUSH: remote branch] format, with force=true to force
+    let mut remaining = response;
+    while let Some(start) = remaining.find("[GIT_PUSH:") {
+        let rest = &remaining[start + "[GIT_PUSH:".len()..];
+        let Some(end) = rest.find(']') else {
+            break;
+        };
+        let mut words: Vec<&str> = rest[..end].split_whitespace().collect();
+        let force = words.last() == Some(&"force=true");
+        if force {
+            words.pop();
+        }
+        let target = match words.as_slice() {
+            [branch] => Some(("origin", *branch)),
+            [remote, branch] => Some((*remote, *branch)),
+            _ => None,
+        };
+        if let Some((remote, branch)) = target {
+            actions.push(AgentAction::GitPush {
+                remote: remote.to_string(),
+                branch: branch.to_string(),
+                force,
+            });
         }
+        remaining = &rest[end..];
     }
 
-    // Parse git operations
-    if response.contains("[GIT_DIFF]") {
-        actions.push(AgentAction::GitDiff { path: None });
+    // Parse amends in [GIT_COMMIT_AMEND: message] format; an empty message keeps the old one
+    let mut remaining = response;
+    while let Some(start) = remaining.find("[GIT_COMMIT_AMEND:") {
+        let rest = &remaining[start + "[GIT_COMMIT_AMEND:".len()..];
+        let Some(end) = rest.find(']') else {
+            break;
+        };
+        actions.push(AgentAction::GitCommit {
+            message: rest[..end].trim().to_string(),
+            files: Vec::new(),
+            amend: true,
+        });
+        remaining = &rest[end..];
     }
 
-    if response.contains("[GIT_STATUS]") {
-        actions.push(AgentAction::GitStatus);
+    // Parse web searches in [WEB_SEARCH: query] format
+    let mut remaining = response;
+    while let Some(start) = remaining.find("[WEB_SEARCH:") {
+        let rest = &remaining[start + "[WEB_SEARCH:".len()..];
+        let Some(end) = rest.find(']') else {
+            break;
+        };
+        let query = rest[..end].trim();
+        if !query.is_empty() {
+            actions.push(AgentAction::WebSearch {
+                query: query.to_string(),
+                num_results: DEFAULT_SEARCH_RESULTS,
+            });
+        }
+        remaining = &rest[end..];
+    }
+
+    // Parse native function calls emitted as [TOOL_CALL: name]:args lines
+    for line in response.lines() {
+        if let Some((name, arguments)) = parse_tool_call(line) {
+            actions.push(AgentAction::CallFunction {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            });
+        }
     }
 
     actions
 }
 
-/// Extract blocks of a specific type from the response
-fn extract_block(text: &str, block_type: &str) -> Option<Vec<String>> {
-    let start_tag = format!("[{}:", block_type);
-    let end_tag = format!("[/{}]", block_type);
+/// The action a closed block asks for, or None when its content is malformed
+fn block_action(block: &Block) -> Option<AgentAction> {
+    match block.block_type {
+        "FILE_WRITE" => Some(AgentAction::WriteFile {
+            path: block.header.clone(),
+            content: block.content.trim().to_string(),
+        }),
+        "FILE_READ" => Some(AgentAction::ReadFile {
+            path: block.header.clone(),
+        }),
+        "COMMAND" => {
+            // For COMMAND, the command itself is after the colon
+            let cmd = &block.header;
+            // Check if there's a dir= attribute
+            Some(match cmd.find(" dir=") {
+                Some(dir_pos) => AgentAction::ExecuteCommand {
+                    command: cmd[..dir_pos].to_string(),
+                    working_dir: Some(cmd[dir_pos + 5..].trim_matches('"').to_string()),
+                },
+                None => AgentAction::ExecuteCommand {
+                    command: cmd.clone(),
+                    working_dir: None,
+                },
+            })
+        },
+        // [HTTP_REQUEST: METHOD url] with the body as content
+        "HTTP_REQUEST" => {
+            let (method, url) = match block.header.split_once(char::is_whitespace) {
+                Some((method, url)) => (method.to_string(), url.trim().to_string()),
+                None => ("GET".to_string(), block.header.clone()),
+            };
+            let body = block.content.trim();
+            Some(AgentAction::HttpRequest {
+                method,
+                url,
+                headers: HashMap::new(),
+                body: (!body.is_empty()).then(|| body.to_string()),
+            })
+        },
+        // [SEARCH_REPLACE: glob regex=true] with the pattern and replacement as content,
+        // separated by a ===== line
+        "SEARCH_REPLACE" => {
+            let content = block.content.trim_matches(['\r', '\n']);
+            let (pattern, replacement) = content
+                .split_once("\n=====\n")
+                .or_else(|| content.strip_suffix("\n=====").map(|pattern| (pattern, "")))?;
+            let (filter, regex) = match block.header.strip_suffix("regex=true") {
+                Some(filter) => (filter.trim(), true),
+                None => (block.header.as_str(), false),
+            };
+            Some(AgentAction::SearchReplace {
+                pattern: pattern.to_string(),
+                replacement: replacement.to_string(),
+                path_filter: (!filter.is_empty()).then(|| filter.to_string()),
+                regex,
+            })
+        },
+        // [AST_SEARCH: language] with a tree-sitter query as content
+        "AST_SEARCH" => {
+            let pattern = block.content.trim();
+            (!pattern.is_empty()).then(|| AgentAction::AstSearch {
+                pattern: pattern.to_string(),
+                language: (!block.header.is_empty()).then(|| block.header.clone()),
+            })
+        },
+        _ => None,
+    }
+}
+
+/// Copy of a response for the message history, with each action block replaced by
+/// `[action: <type> <outcome>]` so the model doesn't repeat blocks that already ran
+/// `outcome` names what became of a block's action; None leaves the outcome out
+pub fn sanitize_for_model(
+    content: &str,
+    mut outcome: impl FnMut(&AgentAction) -> Option<&'static str>,
+) -> String {
+    let (blocks, outside) = scan_blocks(content);
+    let mut out = String::with_capacity(outside.len());
+    let mut copied = 0;
+    for block in &blocks {
+        out.push_str(&outside[copied..block.position]);
+        match block_action(block).and_then(|action| outcome(&action)) {
+            Some(outcome) => out.push_str(&format!("[action: {} {}]", block.block_type, outcome)),
+            None => out.push_str(&format!("[action: {}]", block.block_type)),
+        }
+        copied = block.position;
+    }
+    out.push_str(&outside[copied..]);
+    out
+}
+
+/// A closed top-level block such as `[FILE_WRITE: path] content [/FILE_WRITE]`
+#[derive(Debug)]
+struct Block {
+    block_type: &'static str,
+    header: String,
+    content: String,
+    /// Where the block was, as a byte offset into the outside text
+    position: usize,
+}
 
+/// A block whose closing marker hasn't been seen yet
+struct OpenBlock {
+    block_type: &'static str,
+    header: String,
+    /// The opening marker as written, for when the block turns out to be content
+    opening: String,
+    content: String,
+}
+
+/// Split a response into its top-level blocks and the text outside them
+/// Openers push onto a stack and closers pop back to the matching opener, so
+/// a nested block stays inside its parent's content. Unclosed openers turn
+/// back into text, and a closer with no opener is left as text
+fn scan_blocks(text: &str) -> (Vec<Block>, String) {
     let mut blocks = Vec::new();
-    let mut remaining = text;
-
-    while let Some(start) = remaining.find(&start_tag) {
-        let block_start = start;
-        if let Some(end) = remaining[block_start..].find(&end_tag) {
-            let block = remaining[block_start..block_start + end + end_tag.len()].to_string();
-            blocks.push(block);
-            remaining = &remaining[block_start + end + end_tag.len()..];
-        } else {
-            break;
+    let mut outside = String::new();
+    let mut stack: Vec<OpenBlock> = Vec::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        // Escaped closer: literal `[/TYPE]`
+        if let Some((block_type, len)) = match_marker(rest, "\\[/", "\\]") {
+            push_text(&mut stack, &mut outside, &format!("[/{}]", block_type));
+            rest = &rest[len..];
+            continue;
+        }
+
+        if let Some((block_type, len)) = match_marker(rest, "[/", "]") {
+            match stack.iter().rposition(|open| open.block_type == block_type) {
+                Some(index) => {
+                    // Openers above the match were never closed, so they're content
+                    while stack.len() > index + 1 {
+                        let unclosed = stack.pop().expect("stack is longer than index");
+                        let text = unclosed.opening + &unclosed.content;
+                        push_text(&mut stack, &mut outside, &text);
+                    }
+                    let closed = stack.pop().expect("index is within the stack");
+                    match stack.last_mut() {
+                        Some(parent) => {
+                            parent.content.push_str(&closed.opening);
+                            parent.content.push_str(&closed.content);
+                            parent.content.push_str(&rest[..len]);
+                        },
+                        None => blocks.push(Block {
+                            block_type: closed.block_type,
+                            header: closed.header,
+                            content: closed.content,
+                            position: outside.len(),
+                        }),
+                    }
+                },
+                None => push_text(&mut stack, &mut outside, &rest[..len]),
+            }
+            rest = &rest[len..];
+            continue;
+        }
+
+        if let Some((block_type, header, len)) = match_opener(rest) {
+            stack.push(OpenBlock {
+                block_type,
+                header,
+                opening: rest[..len].to_string(),
+                content: String::new(),
+            });
+            rest = &rest[len..];
+            continue;
         }
+
+        let len = c.len_utf8();
+        push_text(&mut stack, &mut outside, &rest[..len]);
+        rest = &rest[len..];
     }
 
-    if blocks.is_empty() {
-        None
-    } else {
-        Some(blocks)
+    // Blocks left open at the end were never actions
+    for unclosed in stack.drain(..) {
+        outside.push_str(&unclosed.opening);
+        outside.push_str(&unclosed.content);
     }
+    (blocks, outside)
 }
 
-/// Extract content from a block (everything between the tags)
-fn extract_content(block: &str) -> String {
-    if let Some(header_end) = block.find(']') {
-        if let Some(footer_start) = block.rfind("[/") {
-            return block[header_end + 1..footer_start].trim().to_string();
-        }
+/// Append text to the innermost open block, or to the outside text
+fn push_text(stack: &mut [OpenBlock], outside: &mut String, text: &str) {
+    match stack.last_mut() {
+        Some(open) => open.content.push_str(text),
+        None => outside.push_str(text),
+    }
+}
+
+/// Match `{prefix}TYPE{suffix}` at the start of `text`, returning the type and length
+fn match_marker(text: &str, prefix: &str, suffix: &str) -> Option<(&'static str, usize)> {
+    let after_prefix = text.strip_prefix(prefix)?;
+    BLOCK_TYPES.iter().find_map(|&block_type| {
+        after_prefix
+            .strip_prefix(block_type)?
+            .strip_prefix(suffix)
+            .map(|_| (block_type, prefix.len() + block_type.len() + suffix.len()))
+    })
+}
+
+/// Match a `[TYPE: header]` opener on a single line at the start of `text`
+fn match_opener(text: &str) -> Option<(&'static str, String, usize)> {
+    let (block_type, tag_len) = match_marker(text, "[", ":")?;
+    let after_tag = &text[tag_len..];
+    let end = after_tag.find([']', '\n'])?;
+    if !after_tag[end..].starts_with(']') {
+        return None;
     }
-    String::new()
+    Some((
+        block_type,
+        after_tag[..end].trim().to_string(),
+        tag_len + end + 1,
+    ))
 }
 
-/// Extract path/command from header format [TYPE: path/command]
-fn extract_path_from_header(block: &str, block_type: &str) -> Option<String> {
-    let start_tag = format!("[{}:", block_type);
-    if let Some(start) = block.find(&start_tag) {
-        let path_start = start + start_tag.len();
-        if let Some(end) = block[path_start..].find(']') {
-            let path = block[path_start..path_start + end].trim();
-            return Some(path.to_string());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_search_replace_blocks() {
+        let actions = parse_actions(
+            "[SEARCH_REPLACE: src/**/*.rs]\nold_name\n=====\nnew_name\n[/SEARCH_REPLACE]\n\
+             [SEARCH_REPLACE: regex=true]\nfoo\\d+\n=====\n[/SEARCH_REPLACE]\n\
+             [SEARCH_REPLACE:]\nno separator\n[/SEARCH_REPLACE]",
+        );
+        assert_eq!(actions.len(), 2, "{:?}", actions);
+        assert!(matches!(
+            &actions[0],
+            AgentAction::SearchReplace { pattern, replacement, path_filter: Some(filter), regex: false }
+                if pattern == "old_name" && replacement == "new_name" && filter == "src/**/*.rs"
+        ));
+        assert!(matches!(
+            &actions[1],
+            AgentAction::SearchReplace { pattern, replacement, path_filter: None, regex: true }
+                if pattern == "foo\\d+" && replacement.is_empty()
+        ));
+    }
+
+    #[test]
+    fn test_ast_search_blocks() {
+        let actions = parse_actions(
+            "[AST_SEARCH: rust]\n(function_item name: (identifier) @name)\n[/AST_SEARCH]\n\
+             [AST_SEARCH:]\n(call_expression) @call\n[/AST_SEARCH]",
+        );
+        assert_eq!(actions.len(), 2, "{:?}", actions);
+        assert!(matches!(
+            &actions[0],
+            AgentAction::AstSearch { pattern, language: Some(language) }
+                if pattern == "(function_item name: (identifier) @name)" && language == "rust"
+        ));
+        assert!(matches!(
+            &actions[1],
+            AgentAction::AstSearch { language: None, .. }
+        ));
+    }
+
+    #[test]
+    fn test_git_stash_markers() {
+        let actions =
+            parse_actions("[GIT_STASH_LIST] then [GIT_STASH_SHOW: stash@{2}] [GIT_STASH_SHOW:]");
+        assert_eq!(actions.len(), 3, "{:?}", actions);
+        assert!(matches!(actions[0], AgentAction::GitStashList));
+        assert!(matches!(
+            &actions[1],
+            AgentAction::GitStashShow { stash_ref } if stash_ref == "stash@{2}"
+        ));
+        assert!(matches!(
+            &actions[2],
+            AgentAction::GitStashShow { stash_ref } if stash_ref == "stash@{0}"
+        ));
+    }
+
+    #[test]
+    fn test_git_push_marker() {
+        let actions =
+            parse_actions("[GIT_PUSH: upstream feature/x] [GIT_PUSH: main force=true] [GIT_PUSH:]");
+        assert_eq!(actions.len(), 2, "{:?}", actions);
+        assert!(matches!(
+            &actions[0],
+            AgentAction::GitPush { remote, branch, force: false }
+                if remote == "upstream" && branch == "feature/x"
+        ));
+        assert!(matches!(
+            &actions[1],
+            AgentAction::GitPush { remote, branch, force: true }
+                if remote == "origin" && branch == "main"
+        ));
+    }
+
+    #[test]
+    fn test_nested_and_escaped_blocks() {
+        let response = "Writing the parser docs.\n\
+            [FILE_WRITE: docs/actions.md]\n\
+            Write a file with [FILE_WRITE: a.rs] fn a() {} [/FILE_WRITE]\n\
+            and close it with \\[/FILE_WRITE\\]. Try [COMMAND: ls] too.\n\
+            [/FILE_WRITE]\n\
+            [COMMAND: cargo test dir=\"crate\"]\n[/COMMAND]\n\
+            [GIT_STATUS] stray [/FILE_READ]";
+        let actions = parse_actions(response);
+        assert_eq!(actions.len(), 3, "{:?}", actions);
+        match &actions[0] {
+            AgentAction::WriteFile { path, content } => {
+                assert_eq!(path, "docs/actions.md");
+                assert_eq!(
+                    content,
+                    "Write a file with [FILE_WRITE: a.rs] fn a() {} [/FILE_WRITE]\n\
+                     and close it with [/FILE_WRITE]. Try [COMMAND: ls] too."
+                );
+            },
+            other => panic!("expected a write, got {:?}", other),
+        }
+        assert!(matches!(
+            &actions[1],
+            AgentAction::ExecuteCommand { command, working_dir: Some(dir) }
+                if command == "cargo test" && dir == "crate"
+        ));
+        assert!(matches!(actions[2], AgentAction::GitStatus));
+
+        // Markers inside a block don't trigger actions of their own
+        let quoted = "[FILE_WRITE: notes.md]\n[GIT_DIFF] and [ROLLBACK: abc]\n[/FILE_WRITE]";
+        assert_eq!(parse_actions(quoted).len(), 1);
+        assert!(parse_actions("[FILE_WRITE: open.rs]\nfn main() {").is_empty());
+    }
+
+    #[test]
+    fn test_git_commit_amend() {
+        let actions =
+            parse_actions("[GIT_COMMIT_AMEND: Fix typo in README] and [GIT_COMMIT_AMEND:]");
+        assert!(matches!(
+            &actions[..],
+            [
+                AgentAction::GitCommit { message, amend: true, .. },
+                AgentAction::GitCommit { message: kept, amend: true, .. },
+            ] if message == "Fix typo in README" && kept.is_empty()
+        ));
+    }
+
+    #[test]
+    fn test_sanitize_for_model() {
+        let response = "Updating it.\n\
+            [FILE_WRITE: src/a.rs]\nfn a() {}\n[/FILE_WRITE]\n\
+            Then [COMMAND: cargo test][/COMMAND] and [FILE_READ: unclosed.rs]";
+        // Each block shows what became of its own action
+        let outcome = |action: &AgentAction| match action {
+            AgentAction::WriteFile { path, .. } if path == "src/a.rs" => Some("failed"),
+            _ => Some("executed"),
+        };
+        assert_eq!(
+            sanitize_for_model(response, outcome),
+            "Updating it.\n[action: FILE_WRITE failed]\n\
+             Then [action: COMMAND executed] and [FILE_READ: unclosed.rs]"
+        );
+        assert!(parse_actions(&sanitize_for_model(response, outcome)).is_empty());
+        assert_eq!(
+            sanitize_for_model(response, |_| None),
+            "Updating it.\n[action: FILE_WRITE]\n\
+             Then [action: COMMAND] and [FILE_READ: unclosed.rs]"
+        );
+        assert_eq!(
+            sanitize_for_model("No actions here", outcome),
+            "No actions here"
+        );
+    }
+
+    /// Randomized malformed output; the cargo-fuzz target in `fuzz/` explores further
+    #[test]
+    fn test_fuzz_malformed_output() {
+        const FRAGMENTS: &[&str] = &[
+            "[FILE_WRITE:",
+            "[FILE_WRITE: a.rs]",
+            "[/FILE_WRITE]",
+            "\\[/FILE_WRITE\\]",
+            "[FILE_READ: b.rs]",
+            "[/FILE_READ]",
+            "[COMMAND: ls dir=",
+            "[/COMMAND]",
+            "[ROLLBACK:",
+            "[GIT_DIFF]",
+            "[TOOL_CALL: read_file]:{",
+            "[",
+            "]",
+            "\\",
+            "/",
+            ":",
+            "\n",
+            " ",
+            "text",
+            "é",
+            "🦀",
+        ];
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        for _ in 0..5_000 {
+            let input: String = (0..rng.gen_range(0..40))
+                .map(|_| FRAGMENTS[rng.gen_range(0..FRAGMENTS.len())])
+                .collect();
+            let actions = parse_actions(&input);
+            let writes = actions
+                .iter()
+                .filter(|a| matches!(a, AgentAction::WriteFile { .. }))
+                .count();
+            assert!(
+                writes <= input.matches("[/FILE_WRITE]").count(),
+                "{:?}",
+                input
+            );
         }
     }
-    None
 }
@@ -0,0 +1,193 @@
+use anyhow::{Context, Result};
+use ignore::{overrides::OverrideBuilder, WalkBuilder};
+use regex::{NoExpand, Regex};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::filesystem::{check_write, write_atomic};
+
+/// New content for one file touched by a search and replace
+#[derive(Debug)]
+pub struct FileReplacement {
+    pub path: PathBuf,
+    pub content: String,
+    pub replacements: usize,
+}
+
+/// Compute the replacements in every project file under `root` matching `path_filter`
+pub fn plan_replacements(
+    root: &Path,
+    pattern: &str,
+    replacement: &str,
+    path_filter: Option<&str>,
+    regex: bool,
+) -> Result<Vec<FileReplacement>> {
+    let matcher = if regex {
+        Regex::new(pattern).with_context(|| format!("Invalid regex: {}", pattern))?
+    } else {
+        Regex::new(&regex::escape(pattern))?
+    };
+
+    let mut walker = WalkBuilder::new(root);
+    if let Some(filter) = path_filter.filter(|f| !f.trim().is_empty()) {
+        let overrides = OverrideBuilder::new(root)
+            .add(filter)
+            .and_then(|builder| builder.build())
+            .with_context(|| format!("Invalid path filter: {}", filter))?;
+        walker.overrides(overrides);
+    }
+
+    let mut changes = Vec::new();
+    for entry in walker.build().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        // Binary and non-UTF-8 files are left alone
+        let Ok(content) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+
+        let replacements = matcher.find_iter(&content).count();
+        if replacements == 0 {
+            continue;
+        }
+        let new_content = if regex {
+            matcher.replace_all(&content, replacement)
+        } else {
+            matcher.replace_all(&content, NoExpand(replacement))
+        };
+        changes.push(FileReplacement {
+            path: entry.path().to_path_buf(),
+            content: new_content.into_owned(),
+            replacements,
+        });
+    }
+
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(changes)
+}
+
+/// Write each file to a temp path first, then rename it over the original
+/// Every file is checked before any is written, so a refused one leaves the rest untouched
+pub fn apply_replacements(changes: &[FileReplacement]) -> Result<()> {
+    for change in changes {
+        check_write(&change.path, &change.content)?;
+    }
+    for change in changes {
+        write_atomic(&change.path, &change.content)?;
+    }
+    Ok(())
+}
+
+/// Files a search and replace in the project at `root` would modify, with their counts
+pub async fn affected_files(
+    root: &Path,
+    pattern: &str,
+    replacement: &str,
+    path_filter: Option<&str>,
+    regex: bool,
+) -> Result<Vec<String>> {
+    let changes = plan_in_background(root, pattern, replacement, path_filter, regex).await?;
+    Ok(changes
+        .iter()
+        .map(|c| format!("{} ({})", relative(root, &c.path), c.replacements))
+        .collect())
+}
+
+/// Search and replace across the project at `root`
+pub async fn search_replace(
+    root: &Path,
+    pattern: &str,
+    replacement: &str,
+    path_filter: Option<&str>,
+    regex: bool,
+) -> Result<String> {
+    let changes = plan_in_background(root, pattern, replacement, path_filter, regex).await?;
+    apply_replacements(&changes)?;
+
+    let total: usize = changes.iter().map(|c| c.replacements).sum();
+    let mut summary = format!(
+        "{} files modified, {} replacements made",
+        changes.len(),
+        total
+    );
+    for change in &changes {
+        summary.push_str(&format!(
+            "\n  {} ({})",
+            relative(root, &change.path),
+            change.replacements
+        ));
+    }
+    Ok(summary)
+}
+
+/// `plan_replacements` on a blocking thread, since it reads the whole tree
+async fn plan_in_background(
+    root: &Path,
+    pattern: &str,
+    replacement: &str,
+    path_filter: Option<&str>,
+    regex: bool,
+) -> Result<Vec<FileReplacement>> {
+    let (root, pattern, replacement) = (
+        root.to_path_buf(),
+        pattern.to_string(),
+        replacement.to_string(),
+    );
+    let path_filter = path_filter.map(str::to_string);
+    tokio::task::spawn_blocking(move || {
+        plan_replacements(&root, &pattern, &replacement, path_filter.as_deref(), regex)
+    })
+    .await?
+}
+
+/// Display a path relative to the project root
+fn relative(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_plan_and_apply_replacements() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        crate::agents::allow_workspace_root(root.canonicalize().unwrap());
+        fs::write(root.join("a.rs"), "fn old_name() {}\nold_name();\n").unwrap();
+        fs::write(root.join("b.txt"), "old_name").unwrap();
+        fs::write(root.join("c.rs"), "untouched").unwrap();
+
+        let changes = plan_replacements(root, r"old_(\w+)", "new_$1", Some("*.rs"), true).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].replacements, 2);
+
+        apply_replacements(&changes).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("a.rs")).unwrap(),
+            "fn new_name() {}\nnew_name();\n"
+        );
+        assert_eq!(fs::read_to_string(root.join("b.txt")).unwrap(), "old_name");
+
+        // Literal mode doesn't expand `$` in the replacement
+        let literal = plan_replacements(root, "new_name", "$1", None, false).unwrap();
+        assert_eq!(literal[0].content, "fn $1() {}\n$1();\n");
+
+        // One refused file stops the whole replacement
+        let mut changes = plan_replacements(root, "new_name", "newer_name", None, false).unwrap();
+        changes.push(FileReplacement {
+            path: root.join(".ssh/config"),
+            content: String::new(),
+            replacements: 1,
+        });
+        assert!(apply_replacements(&changes).is_err());
+        assert!(fs::read_to_string(root.join("a.rs"))
+            .unwrap()
+            .contains("new_name"));
+    }
+}
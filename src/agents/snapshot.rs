@@ -0,0 +1,222 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Snapshots older than this are purged when the manager opens
+const SNAPSHOT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// File contents captured before a batch of actions ran
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub id: String,
+    pub created_at: DateTime<Local>,
+    /// Absolute path -> content
+    pub files: HashMap<String, String>,
+    /// Paths that didn't exist yet, removed again on rollback
+    #[serde(default)]
+    pub absent: Vec<String>,
+}
+
+/// Stores snapshots as JSON files under `.mermaid/snapshots/`
+pub struct SnapshotManager {
+    project_dir: PathBuf,
+    snapshots_dir: PathBuf,
+}
+
+impl SnapshotManager {
+    /// Open the snapshot store for a project, purging expired snapshots
+    pub fn new(project_dir: impl AsRef<Path>) -> Result<Self> {
+        let project_dir = project_dir.as_ref().to_path_buf();
+        let snapshots_dir = project_dir.join(".mermaid").join("snapshots");
+        fs::create_dir_all(&snapshots_dir)?;
+
+        let manager = Self {
+            project_dir,
+            snapshots_dir,
+        };
+        manager.purge_older_than(SNAPSHOT_MAX_AGE)?;
+        Ok(manager)
+    }
+
+    /// Snapshot the current on-disk content of `paths` (relative ones are in the
+    /// project directory), returning the snapshot ID
+    /// Paths that don't exist are recorded as absent; ones that aren't text are left out
+    pub fn take<P: AsRef<Path>>(&self, paths: impl IntoIterator<Item = P>) -> Result<String> {
+        let mut files = HashMap::new();
+        let mut absent = Vec::new();
+        for path in paths {
+            let path = self.project_dir.join(path);
+            let key = path.to_string_lossy().into_owned();
+            if !path.exists() {
+                absent.push(key);
+            } else if let Ok(content) = fs::read_to_string(&path) {
+                files.insert(key, content);
+            }
+        }
+        let snapshot = Snapshot {
+            id: new_snapshot_id(),
+            created_at: Local::now(),
+            files,
+            absent,
+        };
+
+        let json = serde_json::to_string(&snapshot)?;
+        fs::write(self.snapshot_path(&snapshot.id), json).context("Failed to write snapshot")?;
+        Ok(snapshot.id)
+    }
+
+    /// Load a snapshot by ID
+    pub fn load(&self, id: &str) -> Result<Snapshot> {
+        // IDs are used as file names, so keep them to UUID characters
+        anyhow::ensure!(
+            !id.is_empty() && id.chars().all(|c| c.is_ascii_hexdigit() || c == '-'),
+            "Invalid snapshot ID: {}",
+            id
+        );
+        let json = fs::read_to_string(self.snapshot_path(id))
+            .with_context(|| format!("Snapshot not found: {}", id))?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// Write a snapshot's files back as captured and delete the ones created since,
+    /// returning how many of each
+    /// Every path must be inside the project directory; otherwise nothing is touched
+    pub fn restore(&self, snapshot: &Snapshot) -> Result<(usize, usize)> {
+        let target = |key: &String| -> Result<PathBuf> {
+            let relative = Path::new(key)
+                .strip_prefix(&self.project_dir)
+                .ok()
+                .filter(|p| p.components().all(|c| matches!(c, Component::Normal(_))))
+                .with_context(|| format!("Snapshot path is outside the project: {}", key))?;
+            Ok(self.project_dir.join(relative))
+        };
+        let mut files = snapshot
+            .files
+            .iter()
+            .map(|(key, content)| Ok((target(key)?, content)))
+            .collect::<Result<Vec<_>>>()?;
+        files.sort();
+        let absent = snapshot
+            .absent
+            .iter()
+            .map(target)
+            .collect::<Result<Vec<_>>>()?;
+
+        for (path, content) in &files {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, content)
+                .with_context(|| format!("Failed to restore {}", path.display()))?;
+        }
+        let mut removed = 0;
+        for path in absent.iter().filter(|path| path.is_file()) {
+            fs::remove_file(path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+            removed += 1;
+        }
+        Ok((files.len(), removed))
+    }
+
+    /// Delete snapshots last written more than `max_age` ago
+    pub fn purge_older_than(&self, max_age: Duration) -> Result<usize> {
+        let now = SystemTime::now();
+        let mut purged = 0;
+        for entry in fs::read_dir(&self.snapshots_dir)?.filter_map(|e| e.ok()) {
+            let expired = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_some_and(|age| age > max_age);
+            if expired && fs::remove_file(entry.path()).is_ok() {
+                purged += 1;
+            }
+        }
+        Ok(purged)
+    }
+
+    fn snapshot_path(&self, id: &str) -> PathBuf {
+        self.snapshots_dir.join(format!("{}.json", id))
+    }
+}
+
+/// A random (version 4) UUID
+fn new_snapshot_id() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_take_and_load_snapshot() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("a.rs"), "fn a() {}").unwrap();
+        let manager = SnapshotManager::new(temp_dir.path()).unwrap();
+
+        let paths = vec!["a.rs".to_string(), "missing.rs".to_string()];
+        let id = manager.take(&paths).unwrap();
+        assert_eq!(id.len(), 36);
+
+        let key = |name: &str| temp_dir.path().join(name).to_string_lossy().into_owned();
+        let snapshot = manager.load(&id).unwrap();
+        assert_eq!(snapshot.files.len(), 1);
+        assert_eq!(snapshot.files[&key("a.rs")], "fn a() {}");
+        assert_eq!(snapshot.absent, vec![key("missing.rs")]);
+        assert!(manager.load("../../etc/passwd").is_err());
+
+        assert_eq!(manager.purge_older_than(Duration::ZERO).unwrap(), 1);
+        assert!(manager.load(&id).is_err());
+    }
+
+    #[test]
+    fn test_restore_snapshot() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir(root.join("config")).unwrap();
+        fs::write(root.join("config/app.toml"), "token = \"[REDACTED]\"").unwrap();
+        let manager = SnapshotManager::new(root).unwrap();
+        let id = manager.take(["config/app.toml", "new.rs"]).unwrap();
+
+        fs::write(root.join("config/app.toml"), "changed").unwrap();
+        fs::write(root.join("new.rs"), "fn new() {}").unwrap();
+
+        // Content is restored byte for byte, placeholders included
+        let mut snapshot = manager.load(&id).unwrap();
+        assert_eq!(manager.restore(&snapshot).unwrap(), (1, 1));
+        assert_eq!(
+            fs::read_to_string(root.join("config/app.toml")).unwrap(),
+            "token = \"[REDACTED]\""
+        );
+        assert!(!root.join("new.rs").exists());
+
+        // Paths outside the project are refused before anything is written
+        fs::write(root.join("config/app.toml"), "changed").unwrap();
+        snapshot
+            .files
+            .insert(format!("{}/../outside.txt", root.display()), String::new());
+        assert!(manager.restore(&snapshot).is_err());
+        assert_eq!(
+            fs::read_to_string(root.join("config/app.toml")).unwrap(),
+            "changed"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use super::types::AgentAction;
+use crate::context::{SymbolKind, TreeParser};
+use crate::models::{ChatMessage, MessageRole, Model, ModelConfig, ProjectContext};
+
+/// Conventional test file name for a source file
+/// `src/foo.rs` -> `src/foo_test.rs`, `foo.py` -> `test_foo.py`, `foo.ts` -> `foo.test.ts`
+pub fn test_file_path(source_file: &str) -> String {
+    let path = Path::new(source_file);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("tests");
+    let extension = path.extension().and_then(|e| e.to_str());
+
+    let file_name = match extension {
+        Some("py") => format!("test_{}.py", stem),
+        Some(ext @ ("js" | "jsx" | "ts" | "tsx")) => format!("{}.test.{}", stem, ext),
+        Some(ext) => format!("{}_test.{}", stem, ext),
+        None => format!("{}_test", stem),
+    };
+    path.with_file_name(file_name).to_string_lossy().to_string()
+}
+
+/// Signatures of the public functions and methods in a source file
+pub fn public_signatures(path: &Path, content: &str) -> Result<Vec<String>> {
+    let mut parser = TreeParser::new()?;
+    let is_rust = path.extension().is_some_and(|e| e == "rs");
+
+    Ok(parser
+        .parse_file(path, content)?
+        .into_iter()
+        .filter(|s| matches!(s.kind, SymbolKind::Function | SymbolKind::Method))
+        // Leading underscores mark private names outside Rust
+        .filter(|s| is_rust || !s.name.starts_with('_'))
+        .filter_map(|s| s.signature)
+        .filter(|sig| !is_rust || sig.starts_with("pub"))
+        .collect())
+}
+
+/// Framework used when the request doesn't name one
+fn default_framework(source_file: &str) -> &'static str {
+    match Path::new(source_file).extension().and_then(|e| e.to_str()) {
+        Some("py") => "pytest",
+        Some("js" | "jsx" | "ts" | "tsx") => "jest",
+        _ => "the built-in #[test] harness",
+    }
+}
+
+/// Ask the model for tests of a source file's public API
+/// Returns the WriteFile action for the generated test file, ready to confirm
+pub async fn generate_tests(
+    model: &mut dyn Model,
+    context: &ProjectContext,
+    source_file: &str,
+    test_framework: Option<&str>,
+) -> Result<AgentAction> {
+    let content = std::fs::read_to_string(source_file)
+        .with_context(|| format!("Failed to read {}", source_file))?;
+    let signatures = public_signatures(Path::new(source_file), &content)?;
+    anyhow::ensure!(
+        !signatures.is_empty(),
+        "No public functions found in {}",
+        source_file
+    );
+
+    let framework = test_framework.unwrap_or_else(|| default_framework(source_file));
+    let prompt = format!(
+        "Generate unit tests for these functions using {}.\n\
+         They are defined in {}:\n\n{}\n\n\
+         Reply with only the contents of the test file, without explanations.",
+        framework,
+        source_file,
+        signatures.join("\n")
+    );
+    let messages = vec![ChatMessage {
+        role: MessageRole::User,
+        content: prompt,
+        timestamp: chrono::Local::now(),
+    }];
+    let config = ModelConfig {
+        max_tokens: Some(2000),
+        ..ModelConfig::default()
+    };
+
+    let response = model.chat(&messages, context, &config, None).await?;
+    Ok(AgentAction::WriteFile {
+        path: test_file_path(source_file),
+        content: strip_code_fence(&response.content),
+    })
+}
+
+/// Unwrap a reply that arrived as a single fenced code block
+pub fn strip_code_fence(reply: &str) -> String {
+    let trimmed = reply.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return format!("{}\n", trimmed);
+    };
+    // Drop the language tag line and the closing fence
+    let body = rest.split_once('\n').map(|(_, body)| body).unwrap_or("");
+    let body = body.trim_end().strip_suffix("```").unwrap_or(body);
+    format!("{}\n", body.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_test_file_path_conventions() {
+        assert_eq!(test_file_path("src/foo.rs"), "src/foo_test.rs");
+        assert_eq!(test_file_path("app/util.py"), "app/test_util.py");
+        assert_eq!(test_file_path("web/api.ts"), "web/api.test.ts");
+    }
+
+    #[test]
+    fn test_public_signatures_skip_private_functions() {
+        let source = "pub fn open(path: &str) -> File {}\nfn helper() {}\n";
+        let signatures = public_signatures(Path::new("src/foo.rs"), source).unwrap();
+        assert_eq!(signatures, vec!["pub fn open(path: &str) -> File {}"]);
+    }
+}
@@ -1,4 +1,9 @@
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+
+use super::web_search::DEFAULT_SEARCH_RESULTS;
+use crate::models::ToolDefinition;
 
 /// Represents an action that the AI wants to perform
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,16 +34,637 @@ pub enum AgentAction {
     GitDiff {
         path: Option<String>,
     },
+    /// Commit the files (all changes when empty); `amend` rewrites the last commit
+    /// instead, keeping its message when `message` is empty
     GitCommit {
         message: String,
         files: Vec<String>,
+        #[serde(default)]
+        amend: bool,
+    },
+    /// Push a branch, refusing with uncommitted changes or (without `force`) a diverged remote
+    GitPush {
+        remote: String,
+        branch: String,
+        force: bool,
     },
     GitStatus,
+    /// List stashes as JSON `[{ "ref", "message" }]`
+    GitStashList,
+    /// Show the patch stored in a stash
+    GitStashShow {
+        stash_ref: String,
+    },
+    /// Search and replace across the project files matching `path_filter` (a glob)
+    SearchReplace {
+        pattern: String,
+        replacement: String,
+        path_filter: Option<String>,
+        regex: bool,
+    },
+    /// Structural search with a tree-sitter query, optionally limited to one language
+    AstSearch {
+        pattern: String,
+        language: Option<String>,
+    },
+    /// Generate unit tests for the public functions in `source_file`
+    GenerateTests {
+        source_file: String,
+        test_framework: Option<String>,
+    },
+    /// Add doc comments to the public API of `source_file`
+    GenerateDocs {
+        source_file: String,
+        format: DocFormat,
+    },
+    /// Run the project's linter on `path`, applying its auto-fixes when `fix` is set
+    LintFile {
+        path: String,
+        fix: bool,
+    },
+    /// Format files in place with the formatter for each file's language
+    FormatFiles {
+        paths: Vec<String>,
+    },
+    /// Send an HTTP request, e.g. to test an endpoint the model just wrote
+    HttpRequest {
+        method: String,
+        url: String,
+        headers: HashMap<String, String>,
+        body: Option<String>,
+    },
+    /// Set an environment variable for later commands, or append it to `.env` with `persist`
+    SetEnvironmentVariable {
+        key: String,
+        value: String,
+        persist: bool,
+    },
+    /// Search the web with the configured search provider
+    WebSearch {
+        query: String,
+        num_results: usize,
+    },
+    /// Restore the files captured in a snapshot taken before an earlier batch
+    Rollback {
+        to_snapshot_id: String,
+    },
+    /// Native function call from a model that supports tools
+    CallFunction {
+        name: String,
+        /// Arguments as a JSON object string
+        arguments: String,
+    },
 }
 
+impl AgentAction {
+    /// Map a function call onto the built-in action of the same name
+    pub fn from_function_call(name: &str, arguments: &str) -> anyhow::Result<AgentAction> {
+        let args: serde_json::Value = if arguments.trim().is_empty() {
+            serde_json::json!({})
+        } else {
+            serde_json::from_str(arguments)?
+        };
+        let string_arg = |key: &str| -> anyhow::Result<String> {
+            args.get(key)
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+                .ok_or_else(|| anyhow::anyhow!("Missing '{}' argument for {}", key, name))
+        };
+        let optional_arg = |key: &str| args.get(key).and_then(|v| v.as_str()).map(String::from);
+
+        Ok(match name {
+            "read_file" => AgentAction::ReadFile {
+                path: string_arg("path")?,
+            },
+            "write_file" => AgentAction::WriteFile {
+                path: string_arg("path")?,
+                content: string_arg("content")?,
+            },
+            "delete_file" => AgentAction::DeleteFile {
+                path: string_arg("path")?,
+            },
+            "create_directory" => AgentAction::CreateDirectory {
+                path: string_arg("path")?,
+            },
+            "execute_command" => AgentAction::ExecuteCommand {
+                command: string_arg("command")?,
+                working_dir: optional_arg("working_dir"),
+            },
+            "git_diff" => AgentAction::GitDiff {
+                path: optional_arg("path"),
+            },
+            "git_status" => AgentAction::GitStatus,
+            "git_stash_list" => AgentAction::GitStashList,
+            "git_stash_show" => AgentAction::GitStashShow {
+                stash_ref: string_arg("stash_ref")?,
+            },
+            "git_commit" => AgentAction::GitCommit {
+                message: string_arg("message")?,
+                files: args
+                    .get("files")
+                    .and_then(|v| v.as_array())
+                    .map(|files| {
+                        files
+                            .iter()
+                            .filter_map(|f| f.as_str().map(String::from))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                amend: args.get("amend").and_then(|v| v.as_bool()).unwrap_or(false),
+            },
+            "git_push" => AgentAction::GitPush {
+                remote: optional_arg("remote").unwrap_or_else(|| "origin".to_string()),
+                branch: string_arg("branch")?,
+                force: args.get("force").and_then(|v| v.as_bool()).unwrap_or(false),
+            },
+            "search_replace" => AgentAction::SearchReplace {
+                pattern: string_arg("pattern")?,
+                replacement: string_arg("replacement")?,
+                path_filter: optional_arg("path_filter"),
+                regex: args.get("regex").and_then(|v| v.as_bool()).unwrap_or(false),
+            },
+            "ast_search" => AgentAction::AstSearch {
+                pattern: string_arg("pattern")?,
+                language: optional_arg("language"),
+            },
+            "generate_tests" => AgentAction::GenerateTests {
+                source_file: string_arg("source_file")?,
+                test_framework: optional_arg("test_framework"),
+            },
+            "generate_docs" => {
+                let source_file = string_arg("source_file")?;
+                let format = match optional_arg("format") {
+                    Some(format) => DocFormat::from_name(&format)?,
+                    None => DocFormat::for_file(&source_file),
+                };
+                AgentAction::GenerateDocs {
+                    source_file,
+                    format,
+                }
+            },
+            "lint_file" => AgentAction::LintFile {
+                path: string_arg("path")?,
+                fix: args.get("fix").and_then(|v| v.as_bool()).unwrap_or(false),
+            },
+            "format_files" => AgentAction::FormatFiles {
+                paths: args
+                    .get("paths")
+                    .and_then(|v| v.as_array())
+                    .map(|paths| {
+                        paths
+                            .iter()
+                            .filter_map(|p| p.as_str().map(String::from))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            },
+            "http_request" => AgentAction::HttpRequest {
+                method: optional_arg("method").unwrap_or_else(|| "GET".to_string()),
+                url: string_arg("url")?,
+                headers: args
+                    .get("headers")
+                    .and_then(|v| v.as_object())
+                    .map(|headers| {
+                        headers
+                            .iter()
+                            .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                body: optional_arg("body"),
+            },
+            "set_environment_variable" => AgentAction::SetEnvironmentVariable {
+                key: string_arg("key")?,
+                value: string_arg("value")?,
+                persist: args
+                    .get("persist")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false),
+            },
+            "web_search" => AgentAction::WebSearch {
+                query: string_arg("query")?,
+                num_results: args
+                    .get("num_results")
+                    .and_then(|v| v.as_u64())
+                    .map_or(DEFAULT_SEARCH_RESULTS, |n| n as usize),
+            },
+            "rollback" => AgentAction::Rollback {
+                to_snapshot_id: string_arg("to_snapshot_id")?,
+            },
+            _ => anyhow::bail!("Unknown function: {}", name),
+        })
+    }
+
+    /// Name of the action type, matching its built-in tool name
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            AgentAction::ReadFile { .. } => "read_file",
+            AgentAction::WriteFile { .. } => "write_file",
+            AgentAction::DeleteFile { .. } => "delete_file",
+            AgentAction::CreateDirectory { .. } => "create_directory",
+            AgentAction::ExecuteCommand { .. } => "execute_command",
+            AgentAction::GitDiff { .. } => "git_diff",
+            AgentAction::GitCommit { .. } => "git_commit",
+            AgentAction::GitPush { .. } => "git_push",
+            AgentAction::GitStatus => "git_status",
+            AgentAction::GitStashList => "git_stash_list",
+            AgentAction::GitStashShow { .. } => "git_stash_show",
+            AgentAction::SearchReplace { .. } => "search_replace",
+            AgentAction::AstSearch { .. } => "ast_search",
+            AgentAction::GenerateTests { .. } => "generate_tests",
+            AgentAction::GenerateDocs { .. } => "generate_docs",
+            AgentAction::LintFile { .. } => "lint_file",
+            AgentAction::FormatFiles { .. } => "format_files",
+            AgentAction::HttpRequest { .. } => "http_request",
+            AgentAction::SetEnvironmentVariable { .. } => "set_environment_variable",
+            AgentAction::WebSearch { .. } => "web_search",
+            AgentAction::Rollback { .. } => "rollback",
+            AgentAction::CallFunction { .. } => "call_function",
+        }
+    }
+
+    /// What the action operates on: a path, command, pattern or message
+    pub fn target(&self) -> String {
+        match self {
+            AgentAction::ReadFile { path }
+            | AgentAction::WriteFile { path, .. }
+            | AgentAction::DeleteFile { path }
+            | AgentAction::CreateDirectory { path }
+            | AgentAction::LintFile { path, .. } => path.clone(),
+            AgentAction::ExecuteCommand { command, .. } => command.clone(),
+            AgentAction::GitDiff { path } => path.clone().unwrap_or_else(|| ".".to_string()),
+            AgentAction::GitCommit { message, .. } => message.clone(),
+            AgentAction::GitPush { remote, branch, .. } => format!("{}/{}", remote, branch),
+            AgentAction::GitStatus | AgentAction::GitStashList => ".".to_string(),
+            AgentAction::GitStashShow { stash_ref } => stash_ref.clone(),
+            AgentAction::SearchReplace { pattern, .. } | AgentAction::AstSearch { pattern, .. } => {
+                pattern.clone()
+            },
+            AgentAction::GenerateTests { source_file, .. }
+            | AgentAction::GenerateDocs { source_file, .. } => source_file.clone(),
+            AgentAction::FormatFiles { paths } => paths.join(", "),
+            AgentAction::HttpRequest { url, .. } => url.clone(),
+            AgentAction::SetEnvironmentVariable { key, .. } => key.clone(),
+            AgentAction::WebSearch { query, .. } => query.clone(),
+            AgentAction::Rollback { to_snapshot_id } => to_snapshot_id.clone(),
+            AgentAction::CallFunction { name, .. } => name.clone(),
+        }
+    }
+
+    /// The built-in action a function call maps to, or the action itself
+    pub fn resolve(&self) -> anyhow::Result<AgentAction> {
+        match self {
+            AgentAction::CallFunction { name, arguments } => {
+                Self::from_function_call(name, arguments)
+            },
+            other => Ok(other.clone()),
+        }
+    }
+}
+
+/// Doc comment style used by GenerateDocs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DocFormat {
+    RustDoc,
+    JSDoc,
+    Sphinx,
+    Markdown,
+}
+
+impl DocFormat {
+    /// Parse a format name such as "jsdoc" or "sphinx"
+    pub fn from_name(name: &str) -> anyhow::Result<Self> {
+        match name.to_lowercase().as_str() {
+            "rustdoc" | "rust" => Ok(DocFormat::RustDoc),
+            "jsdoc" | "js" => Ok(DocFormat::JSDoc),
+            "sphinx" | "python" => Ok(DocFormat::Sphinx),
+            "markdown" | "md" => Ok(DocFormat::Markdown),
+            _ => anyhow::bail!(
+                "Unknown doc format: {} (use rustdoc, jsdoc, sphinx or markdown)",
+                name
+            ),
+        }
+    }
+
+    /// The usual format for a source file's language
+    pub fn for_file(path: &str) -> Self {
+        match std::path::Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+        {
+            Some("rs") => DocFormat::RustDoc,
+            Some("js" | "jsx" | "ts" | "tsx") => DocFormat::JSDoc,
+            Some("py") => DocFormat::Sphinx,
+            _ => DocFormat::Markdown,
+        }
+    }
+
+    /// How the model should write comments in this format
+    pub fn instructions(&self) -> &'static str {
+        match self {
+            DocFormat::RustDoc => {
+                "Rust doc comments: `///` triple-slash lines directly above each item"
+            },
+            DocFormat::JSDoc => "JSDoc `/** ... */` blocks with @param and @returns tags",
+            DocFormat::Sphinx => "Sphinx-style docstrings with :param: and :returns: fields",
+            DocFormat::Markdown => {
+                "comments in the language's comment syntax, formatted as Markdown"
+            },
+        }
+    }
+}
+
+/// Separates a command's stdout from its stderr in `ActionResult::output`
+pub const STDERR_MARKER: &str = "--- stderr ---";
+
 /// Result of an agent action
+/// Only commands have a stderr and exit code; other actions succeed with 0 and fail with 1
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ActionResult {
-    Success { output: String },
-    Error { error: String },
+    Success {
+        // Results saved before stderr was split out only have `output`
+        #[serde(alias = "output")]
+        stdout: String,
+        #[serde(default)]
+        stderr: String,
+        #[serde(default)]
+        exit_code: i32,
+    },
+    Error {
+        error: String,
+        #[serde(default = "failure_exit_code")]
+        exit_code: i32,
+    },
+}
+
+fn failure_exit_code() -> i32 {
+    1
+}
+
+impl ActionResult {
+    /// Successful result whose output is all stdout
+    pub fn success(output: impl Into<String>) -> Self {
+        ActionResult::Success {
+            stdout: output.into(),
+            stderr: String::new(),
+            exit_code: 0,
+        }
+    }
+
+    /// Failed result of an action that isn't a process
+    pub fn error(error: impl Into<String>) -> Self {
+        ActionResult::Error {
+            error: error.into(),
+            exit_code: failure_exit_code(),
+        }
+    }
+
+    /// Text for the model: stdout, then any stderr after `STDERR_MARKER` and a non-zero exit code
+    pub fn output(&self) -> String {
+        match self {
+            ActionResult::Success {
+                stdout,
+                stderr,
+                exit_code,
+            } => {
+                let mut output = stdout.clone();
+                if !stderr.is_empty() {
+                    output.push_str(&format!("\n{}\n{}", STDERR_MARKER, stderr));
+                }
+                if *exit_code != 0 {
+                    output.push_str(&format!(
+                        "\n--- Command exited with status: {} ---",
+                        exit_code
+                    ));
+                }
+                output
+            },
+            ActionResult::Error { error, .. } => error.clone(),
+        }
+    }
+}
+
+/// Function definitions for the built-in actions, for models with native tool support
+pub fn builtin_tools() -> Vec<ToolDefinition> {
+    let tool = |name: &str, description: &str, parameters: serde_json::Value| ToolDefinition {
+        name: name.to_string(),
+        description: description.to_string(),
+        parameters,
+    };
+    let path_only = |description: &str| {
+        json!({
+            "type": "object",
+            "properties": { "path": { "type": "string", "description": description } },
+            "required": ["path"],
+        })
+    };
+
+    vec![
+        tool(
+            "read_file",
+            "Read a file's contents",
+            path_only("File to read"),
+        ),
+        tool(
+            "write_file",
+            "Create or overwrite a file",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string" },
+                    "content": { "type": "string" },
+                },
+                "required": ["path", "content"],
+            }),
+        ),
+        tool("delete_file", "Delete a file", path_only("File to delete")),
+        tool(
+            "create_directory",
+            "Create a directory and its parents",
+            path_only("Directory to create"),
+        ),
+        tool(
+            "execute_command",
+            "Run a shell command",
+            json!({
+                "type": "object",
+                "properties": {
+                    "command": { "type": "string" },
+                    "working_dir": { "type": "string" },
+                },
+                "required": ["command"],
+            }),
+        ),
+        tool(
+            "git_diff",
+            "Show uncommitted changes",
+            json!({ "type": "object", "properties": { "path": { "type": "string" } } }),
+        ),
+        tool(
+            "git_status",
+            "Show the working tree status",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        tool(
+            "git_stash_list",
+            "List the repository's stashes",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        tool(
+            "git_stash_show",
+            "Show the changes stored in a stash",
+            json!({
+                "type": "object",
+                "properties": { "stash_ref": { "type": "string", "description": "e.g. stash@{0}" } },
+                "required": ["stash_ref"]
+            }),
+        ),
+        tool(
+            "git_commit",
+            "Commit changes",
+            json!({
+                "type": "object",
+                "properties": {
+                    "message": { "type": "string" },
+                    "files": { "type": "array", "items": { "type": "string" } },
+                    "amend": { "type": "boolean", "description": "Rewrite the last commit; an empty message keeps its message" },
+                },
+                "required": ["message"],
+            }),
+        ),
+        tool(
+            "git_push",
+            "Push a branch to a remote; always asks the user first",
+            json!({
+                "type": "object",
+                "properties": {
+                    "remote": { "type": "string", "description": "Defaults to origin" },
+                    "branch": { "type": "string" },
+                    "force": { "type": "boolean", "description": "Overwrite a diverged remote branch" },
+                },
+                "required": ["branch"],
+            }),
+        ),
+        tool(
+            "search_replace",
+            "Replace text in every project file matching a glob",
+            json!({
+                "type": "object",
+                "properties": {
+                    "pattern": { "type": "string" },
+                    "replacement": { "type": "string" },
+                    "path_filter": { "type": "string", "description": "Glob such as src/**/*.rs" },
+                    "regex": { "type": "boolean", "description": "Treat pattern as a regex" },
+                },
+                "required": ["pattern", "replacement"],
+            }),
+        ),
+        tool(
+            "ast_search",
+            "Find code structures with a tree-sitter query",
+            json!({
+                "type": "object",
+                "properties": {
+                    "pattern": { "type": "string", "description": "Tree-sitter query, e.g. (function_item name: (identifier) @name)" },
+                    "language": { "type": "string", "description": "rust, python, javascript or typescript" },
+                },
+                "required": ["pattern"],
+            }),
+        ),
+        tool(
+            "generate_tests",
+            "Generate unit tests for the public functions in a source file",
+            json!({
+                "type": "object",
+                "properties": {
+                    "source_file": { "type": "string" },
+                    "test_framework": { "type": "string", "description": "e.g. pytest or jest" },
+                },
+                "required": ["source_file"],
+            }),
+        ),
+        tool(
+            "generate_docs",
+            "Add doc comments to the public functions in a source file",
+            json!({
+                "type": "object",
+                "properties": {
+                    "source_file": { "type": "string" },
+                    "format": { "type": "string", "enum": ["rustdoc", "jsdoc", "sphinx", "markdown"] },
+                },
+                "required": ["source_file"],
+            }),
+        ),
+        tool(
+            "lint_file",
+            "Run the project's linter on a file or directory",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string" },
+                    "fix": { "type": "boolean", "description": "Apply the linter's auto-fixes" },
+                },
+                "required": ["path"],
+            }),
+        ),
+        tool(
+            "format_files",
+            "Format files with rustfmt, prettier, black or gofmt",
+            json!({
+                "type": "object",
+                "properties": {
+                    "paths": { "type": "array", "items": { "type": "string" } },
+                },
+                "required": ["paths"],
+            }),
+        ),
+        tool(
+            "http_request",
+            "Send an HTTP request and return the status, headers and body",
+            json!({
+                "type": "object",
+                "properties": {
+                    "method": { "type": "string", "description": "GET, POST, PUT, PATCH or DELETE; defaults to GET" },
+                    "url": { "type": "string" },
+                    "headers": { "type": "object", "additionalProperties": { "type": "string" } },
+                    "body": { "type": "string" },
+                },
+                "required": ["url"],
+            }),
+        ),
+        tool(
+            "set_environment_variable",
+            "Set an environment variable for later commands, optionally saving it to .env",
+            json!({
+                "type": "object",
+                "properties": {
+                    "key": { "type": "string" },
+                    "value": { "type": "string" },
+                    "persist": { "type": "boolean", "description": "Append to the project's .env instead of only this session" },
+                },
+                "required": ["key", "value"],
+            }),
+        ),
+        tool(
+            "web_search",
+            "Search the web for documentation, error messages or library APIs",
+            json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" },
+                    "num_results": { "type": "integer", "description": "Defaults to 5" },
+                },
+                "required": ["query"],
+            }),
+        ),
+        tool(
+            "rollback",
+            "Restore files to a snapshot taken before an earlier batch of actions",
+            json!({
+                "type": "object",
+                "properties": { "to_snapshot_id": { "type": "string" } },
+                "required": ["to_snapshot_id"],
+            }),
+        ),
+    ]
 }
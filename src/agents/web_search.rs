@@ -0,0 +1,172 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::app::SearchProviderConfig;
+
+/// Results requested when the model doesn't say how many it wants
+pub const DEFAULT_SEARCH_RESULTS: usize = 5;
+
+/// Upper bound on results, to keep the feedback prompt small
+const MAX_SEARCH_RESULTS: usize = 20;
+
+const SEARCH_TIMEOUT_SECS: u64 = 15;
+
+/// One web search hit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+/// Search API the provider URL points at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProviderKind {
+    SearxNG,
+    Brave,
+    Serper,
+}
+
+impl ProviderKind {
+    fn from_url(url: &str) -> Self {
+        if url.contains("search.brave.com") {
+            ProviderKind::Brave
+        } else if url.contains("serper.dev") {
+            ProviderKind::Serper
+        } else {
+            ProviderKind::SearxNG
+        }
+    }
+}
+
+/// Run a search against the configured provider
+pub async fn web_search(
+    provider: &SearchProviderConfig,
+    query: &str,
+    num_results: usize,
+) -> Result<Vec<SearchResult>> {
+    let num_results = num_results.clamp(1, MAX_SEARCH_RESULTS);
+    let kind = ProviderKind::from_url(&provider.url);
+    let count = num_results.to_string();
+    let mut params = vec![("q", query)];
+    match kind {
+        ProviderKind::SearxNG => params.push(("format", "json")),
+        ProviderKind::Brave => params.push(("count", &count)),
+        ProviderKind::Serper => params.push(("num", &count)),
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(SEARCH_TIMEOUT_SECS))
+        .build()?;
+    let mut request = client.get(&provider.url).query(&params);
+    if let Some(var) = &provider.api_key_env {
+        let key = std::env::var(var)
+            .with_context(|| format!("Search API key variable {} is not set", var))?;
+        request = match kind {
+            ProviderKind::Brave => request.header("X-Subscription-Token", key),
+            ProviderKind::Serper => request.header("X-API-KEY", key),
+            ProviderKind::SearxNG => request.bearer_auth(key),
+        };
+    }
+
+    let response = request
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach search provider at {}", provider.url))?;
+    let status = response.status();
+    if !status.is_success() {
+        anyhow::bail!("Search provider responded with {}", status);
+    }
+    let body: Value = response
+        .json()
+        .await
+        .context("Search provider returned invalid JSON")?;
+    Ok(parse_results(&body, num_results))
+}
+
+/// Read results from a SearxNG, Brave or Serper response
+fn parse_results(body: &Value, limit: usize) -> Vec<SearchResult> {
+    // (results array, URL field, snippet field) for each provider's format
+    let (items, url_key, snippet_key) = if let Some(items) = body["web"]["results"].as_array() {
+        (items, "url", "description")
+    } else if let Some(items) = body["organic"].as_array() {
+        (items, "link", "snippet")
+    } else if let Some(items) = body["results"].as_array() {
+        (items, "url", "content")
+    } else {
+        return Vec::new();
+    };
+
+    items
+        .iter()
+        .filter_map(|item| {
+            Some(SearchResult {
+                title: item["title"].as_str()?.trim().to_string(),
+                url: item[url_key].as_str()?.to_string(),
+                snippet: item[snippet_key]
+                    .as_str()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+            })
+        })
+        .take(limit)
+        .collect()
+}
+
+/// Results as a numbered Markdown list for the model
+pub fn format_results(query: &str, results: &[SearchResult]) -> String {
+    if results.is_empty() {
+        return format!("No web results for \"{}\"", query);
+    }
+    let mut out = format!("Web results for \"{}\":\n", query);
+    for (i, result) in results.iter().enumerate() {
+        out.push_str(&format!(
+            "\n{}. [{}]({})\n",
+            i + 1,
+            result.title,
+            result.url
+        ));
+        if !result.snippet.is_empty() {
+            out.push_str(&format!("   {}\n", result.snippet));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_provider_results() {
+        let searxng = json!({ "results": [
+            { "title": "serde - Rust", "url": "https://docs.rs/serde", "content": "A framework" },
+            { "title": "No URL" },
+        ]});
+        let brave = json!({ "web": { "results": [
+            { "title": "Tokio", "url": "https://tokio.rs", "description": "Async runtime" },
+        ]}});
+        let serper = json!({ "organic": [
+            { "title": "a", "link": "https://a.dev" },
+            { "title": "b", "link": "https://b.dev" },
+        ]});
+
+        let results = parse_results(&searxng, 5);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].snippet, "A framework");
+        assert_eq!(parse_results(&brave, 5)[0].url, "https://tokio.rs");
+        assert_eq!(parse_results(&serper, 1).len(), 1);
+        assert!(parse_results(&json!({ "error": "rate limited" }), 5).is_empty());
+
+        assert_eq!(
+            ProviderKind::from_url("https://api.search.brave.com/res/v1/web/search"),
+            ProviderKind::Brave
+        );
+        let markdown = format_results("serde", &results);
+        assert!(markdown.contains("1. [serde - Rust](https://docs.rs/serde)\n   A framework"));
+    }
+}
@@ -1,4 +1,9 @@
-use crate::constants::{DEFAULT_LITELLM_PROXY_URL, DEFAULT_OLLAMA_PORT};
+use crate::agents::read_project_file;
+use crate::constants::{
+    DEFAULT_LITELLM_PROXY_URL, DEFAULT_OLLAMA_PORT, PROXY_HEALTH_CHECK_INTERVAL_SECS,
+};
+use crate::models::ModelConfig;
+use crate::utils::{log_warn, MermaidError};
 use anyhow::{Context, Result};
 use directories::ProjectDirs;
 use figment::{
@@ -6,7 +11,11 @@ use figment::{
     Figment,
 };
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+use super::wizard::run_setup_wizard;
 
 /// Main configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +51,46 @@ pub struct Config {
     /// Operation mode configuration
     #[serde(default)]
     pub mode: ModeConfig,
+
+    /// Cache configuration
+    #[serde(default)]
+    pub cache: CacheConfig,
+
+    /// Values substituted for `{{NAME}}` placeholders in system prompts
+    #[serde(default)]
+    pub prompt_variables: HashMap<String, String>,
+
+    /// Spending limits for paid models; requests stop once one is reached
+    #[serde(default)]
+    pub budget_limits: Option<BudgetLimits>,
+
+    /// Search API for the model's `[WEB_SEARCH: query]` action; searching is off without it
+    #[serde(default)]
+    pub search_provider: Option<SearchProviderConfig>,
+
+    /// Log output format: colored text on stderr or JSON Lines in a log file
+    #[serde(default)]
+    pub log_format: LogFormat,
+
+    /// Per-module log levels, e.g. `proxy = "debug"`
+    #[serde(default)]
+    pub log_levels: HashMap<String, String>,
+
+    /// How long to wait for a prompt piped on stdin
+    #[serde(default = "default_stdin_prompt_timeout_secs")]
+    pub stdin_prompt_timeout_secs: u64,
+
+    /// Most system messages (tool feedback, notices) kept in the model's context window
+    #[serde(default = "default_max_system_messages_in_history")]
+    pub max_system_messages_in_history: usize,
+
+    /// Where conversations are saved: "filesystem" (JSON files) or "sqlite"
+    #[serde(default = "default_storage_backend")]
+    pub storage_backend: String,
+
+    /// Model parameter presets for :preset, kept in presets.toml rather than here
+    #[serde(skip)]
+    pub presets: HashMap<String, ModelConfig>,
 }
 
 impl Default for Config {
@@ -55,10 +104,89 @@ impl Default for Config {
             ui: UIConfig::default(),
             context: ContextConfig::default(),
             mode: ModeConfig::default(),
+            cache: CacheConfig::default(),
+            prompt_variables: HashMap::new(),
+            budget_limits: None,
+            search_provider: None,
+            log_format: LogFormat::default(),
+            log_levels: HashMap::new(),
+            stdin_prompt_timeout_secs: default_stdin_prompt_timeout_secs(),
+            max_system_messages_in_history: default_max_system_messages_in_history(),
+            storage_backend: default_storage_backend(),
+            presets: builtin_presets(),
+        }
+    }
+}
+
+fn default_stdin_prompt_timeout_secs() -> u64 {
+    5
+}
+
+fn default_max_system_messages_in_history() -> usize {
+    5
+}
+
+fn default_storage_backend() -> String {
+    "filesystem".to_string()
+}
+
+/// How log output is written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Colored text on stderr, shown with --verbose
+    #[default]
+    Pretty,
+    /// JSON Lines appended to ~/.local/share/mermaid/mermaid.log
+    Json,
+}
+
+/// Daily and monthly spending limits in USD
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetLimits {
+    pub daily_usd: Option<f64>,
+    pub monthly_usd: Option<f64>,
+    /// Warn once a request would bring spending to this share of a limit
+    pub warn_at_percent: u8,
+}
+
+impl Default for BudgetLimits {
+    fn default() -> Self {
+        Self {
+            daily_usd: None,
+            monthly_usd: None,
+            warn_at_percent: 80,
         }
     }
 }
 
+/// Web search endpoint: SearxNG, Brave Search or Serper, told apart by URL
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchProviderConfig {
+    /// e.g. `http://localhost:8080/search` or `https://api.search.brave.com/res/v1/web/search`
+    pub url: String,
+    /// Environment variable holding the API key, if the provider needs one
+    pub api_key_env: Option<String>,
+}
+
+/// Presets available before any are saved: creative, precise and balanced
+pub fn builtin_presets() -> HashMap<String, ModelConfig> {
+    let preset = |temperature: f32, top_p: f32| ModelConfig {
+        temperature: Some(temperature),
+        top_p: Some(top_p),
+        max_tokens: None,
+        ..ModelConfig::default().parameters()
+    };
+    HashMap::from([
+        (String::from("creative"), preset(1.0, 0.95)),
+        (String::from("precise"), preset(0.2, 0.7)),
+        // The ModelConfig defaults
+        (String::from("balanced"), preset(0.7, 1.0)),
+    ])
+}
+
 /// Default model settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelSettings {
@@ -72,6 +200,11 @@ pub struct ModelSettings {
     pub max_tokens: usize,
     /// System prompt
     pub system_prompt: Option<String>,
+    /// Context window size to use instead of the detected one
+    pub context_window_override: Option<usize>,
+    /// Short names for model IDs, e.g. `gpt4 = "openai/gpt-4o"`
+    #[serde(default)]
+    pub model_aliases: HashMap<String, String>,
 }
 
 impl ModelSettings {
@@ -79,6 +212,8 @@ impl ModelSettings {
     pub fn default_system_prompt() -> String {
         r#"You are Mermaid, an AI pair programmer assistant that can read, write, and execute code.
 
+You are running as {{MODEL_NAME}} in {{WORKING_DIR}} ({{PROJECT_TYPE}} project). Today is {{DATE}}.
+
 ## IMPORTANT: Action Blocks
 
 You have the ability to perform actions by using special action blocks in your responses. These blocks will be automatically parsed and executed.
@@ -100,6 +235,23 @@ To read a file, use:
 [/FILE_READ]
 ```
 
+To replace text in every project file (optionally only those matching a glob; add
+`regex=true` for a regular expression), put the search text, a ===== line, then the replacement:
+```
+[SEARCH_REPLACE: src/**/*.rs]
+old_name
+=====
+new_name
+[/SEARCH_REPLACE]
+```
+
+To find code by structure, write a tree-sitter query (the language is optional):
+```
+[AST_SEARCH: rust]
+(function_item name: (identifier) @name)
+[/AST_SEARCH]
+```
+
 ### Shell Commands
 
 To execute shell commands, use:
@@ -126,6 +278,46 @@ To see git diff:
 [GIT_DIFF]
 ```
 
+To list stashes, or see what one changes:
+```
+[GIT_STASH_LIST]
+[GIT_STASH_SHOW: stash@{0}]
+```
+
+To fold the current changes into the last commit (leave the message empty to keep it):
+```
+[GIT_COMMIT_AMEND: Fix typo in README]
+```
+
+To push a branch (the remote defaults to origin; add `force=true` only when asked to).
+The user always confirms pushes:
+```
+[GIT_PUSH: origin main]
+```
+
+### HTTP Requests
+
+To test an API endpoint (the body is optional):
+```
+[HTTP_REQUEST: POST http://localhost:8080/users]
+{"name": "test"}
+[/HTTP_REQUEST]
+```
+
+### Environment Variables
+
+To set a variable for later commands (add `persist=true` to also save it to `.env`):
+```
+[SET_ENV: DATABASE_URL=postgres://localhost/test]
+```
+
+### Web Search
+
+To look up documentation, error messages or library APIs (when a search provider is configured):
+```
+[WEB_SEARCH: tokio select macro]
+```
+
 ## Guidelines
 
 1. When asked to create or modify files, ALWAYS use the [FILE_WRITE:] action block
@@ -146,17 +338,26 @@ impl Default for ModelSettings {
             temperature: 0.7,
             max_tokens: 4096,
             system_prompt: Some(Self::default_system_prompt()),
+            context_window_override: None,
+            model_aliases: HashMap::new(),
         }
     }
 }
 
 /// LiteLLM proxy configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct LiteLLMConfig {
     /// Proxy server URL
     pub proxy_url: String,
     /// Master key for authentication
     pub master_key: Option<String>,
+    /// Seconds between proxy health checks while running
+    pub proxy_health_check_interval_secs: u64,
+    /// Restart the proxy automatically if it goes down
+    pub auto_restart: bool,
+    /// Container runtime for the proxy (podman, docker or nerdctl); detected when unset
+    pub container_runtime: Option<String>,
 }
 
 impl Default for LiteLLMConfig {
@@ -164,6 +365,9 @@ impl Default for LiteLLMConfig {
         Self {
             proxy_url: DEFAULT_LITELLM_PROXY_URL.to_string(),
             master_key: None,
+            proxy_health_check_interval_secs: PROXY_HEALTH_CHECK_INTERVAL_SECS,
+            auto_restart: true,
+            container_runtime: None,
         }
     }
 }
@@ -221,6 +425,7 @@ impl Default for AnthropicConfig {
 
 /// UI configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct UIConfig {
     /// Color theme
     pub theme: String,
@@ -228,8 +433,18 @@ pub struct UIConfig {
     pub syntax_theme: String,
     /// Show line numbers in code blocks
     pub show_line_numbers: bool,
+    /// Number the lines of code blocks in the chat
+    pub show_code_line_numbers: bool,
     /// Show file sidebar by default
     pub show_sidebar: bool,
+    /// Show message timestamps in the chat
+    pub show_timestamps: bool,
+    /// strftime-style format for message timestamps
+    pub timestamp_format: String,
+    /// Show the estimated cost of a message before it is sent (local models are free and never show one)
+    pub show_cost_estimate: bool,
+    /// Show git information, such as stashes, in the sidebar
+    pub git_status: bool,
 }
 
 impl Default for UIConfig {
@@ -238,7 +453,12 @@ impl Default for UIConfig {
             theme: String::from("dark"),
             syntax_theme: String::from("monokai"),
             show_line_numbers: true,
+            show_code_line_numbers: true,
             show_sidebar: true,
+            show_timestamps: false,
+            timestamp_format: String::from("%H:%M"),
+            show_cost_estimate: true,
+            git_status: true,
         }
     }
 }
@@ -256,6 +476,29 @@ pub struct ContextConfig {
     pub include_patterns: Vec<String>,
     /// Always exclude these patterns
     pub exclude_patterns: Vec<String>,
+    /// Files hidden from the model, in addition to `.mermaid_ignore` files
+    #[serde(default)]
+    pub ignore: ContextIgnore,
+    /// Other directories loaded into the same context, e.g. monorepo packages
+    #[serde(default)]
+    pub additional_roots: Vec<PathBuf>,
+    /// Load `.env` files with secret values redacted
+    #[serde(default = "default_env_file_support")]
+    pub env_file_support: bool,
+    /// Remove comments from source files to save tokens
+    #[serde(default)]
+    pub strip_comments: bool,
+}
+
+fn default_env_file_support() -> bool {
+    true
+}
+
+/// `[context.ignore]`: gitignore-style patterns, e.g. for sensitive files
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContextIgnore {
+    pub patterns: Vec<String>,
 }
 
 impl Default for ContextConfig {
@@ -266,21 +509,38 @@ impl Default for ContextConfig {
             max_context_tokens: 50000,
             include_patterns: vec![],
             exclude_patterns: vec![String::from("*.log"), String::from("*.tmp")],
+            ignore: ContextIgnore::default(),
+            additional_roots: Vec::new(),
+            env_file_support: true,
+            strip_comments: false,
         }
     }
 }
 
 /// Operation mode configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ModeConfig {
     /// Default operation mode (normal, accept_edits, plan_mode, bypass_all)
     pub default_mode: String,
     /// Remember mode between sessions
     pub remember_mode: bool,
+    /// Let `remember_mode` restore Bypass All, which otherwise starts in Normal
+    pub allow_persist_bypass: bool,
     /// Auto-commit in AcceptEdits mode
     pub auto_commit_on_accept: bool,
     /// Require double confirmation for destructive operations in BypassAll mode
     pub require_destructive_confirmation: bool,
+    /// Generate a short conversation title after the first response
+    pub auto_title: bool,
+    /// Timeout in seconds per action type (e.g. `execute_command = 600`)
+    pub action_timeouts: HashMap<String, u64>,
+    /// Timeout in seconds for action types not in `action_timeouts`
+    pub default_action_timeout_secs: u64,
+    /// URLs the model's HTTP requests may reach, with `*` wildcards (empty = any)
+    pub allowed_url_patterns: Vec<String>,
+    /// Which kinds of action Bypass All approves without asking
+    pub bypass: BypassConfig,
 }
 
 impl Default for ModeConfig {
@@ -288,10 +548,143 @@ impl Default for ModeConfig {
         Self {
             default_mode: String::from("normal"),
             remember_mode: false,
+            allow_persist_bypass: false,
             auto_commit_on_accept: false,
             require_destructive_confirmation: true,
+            auto_title: true,
+            action_timeouts: DEFAULT_ACTION_TIMEOUTS
+                .iter()
+                .map(|&(action, secs)| (action.to_string(), secs))
+                .collect(),
+            default_action_timeout_secs: 60,
+            allowed_url_patterns: Vec::new(),
+            bypass: BypassConfig::default(),
+        }
+    }
+}
+
+/// Per-action-type opt-outs for Bypass All; a `false` flag brings back the prompt
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BypassConfig {
+    /// File writes, edits, formatting and directory creation
+    pub bypass_file_writes: bool,
+    /// Shell commands and HTTP requests
+    pub bypass_commands: bool,
+    /// Git commits
+    pub bypass_git_operations: bool,
+    /// File deletions
+    pub bypass_deletes: bool,
+}
+
+impl Default for BypassConfig {
+    fn default() -> Self {
+        Self {
+            bypass_file_writes: true,
+            bypass_commands: true,
+            bypass_git_operations: true,
+            bypass_deletes: true,
+        }
+    }
+}
+
+/// Built-in `action_timeouts`, kept for action types a user's table leaves out
+/// Linters check the whole crate or package, which can take a while
+const DEFAULT_ACTION_TIMEOUTS: &[(&str, u64)] = &[("lint_file", 120)];
+
+impl ModeConfig {
+    /// How long an action of the given type may run
+    /// A user's `action_timeouts` table adds to the built-in one rather than replacing it
+    pub fn action_timeout(&self, action_type: &str) -> std::time::Duration {
+        let secs = self
+            .action_timeouts
+            .get(action_type)
+            .copied()
+            .or_else(|| {
+                DEFAULT_ACTION_TIMEOUTS
+                    .iter()
+                    .find(|(action, _)| *action == action_type)
+                    .map(|&(_, secs)| secs)
+            })
+            .unwrap_or(self.default_action_timeout_secs);
+        std::time::Duration::from_secs(secs)
+    }
+}
+
+/// Cache configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Maximum entries kept in each in-memory cache
+    pub max_memory_entries: usize,
+    /// Maximum on-disk cache size in megabytes before old entries are evicted
+    pub max_disk_size_mb: usize,
+    /// Expire cache entries after this many seconds (unset = only when the file changes)
+    pub default_cache_ttl: Option<u64>,
+    /// Compress on-disk cache entries (entries under 256 bytes are always stored as-is)
+    pub use_compression: bool,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_memory_entries: 1000,
+            max_disk_size_mb: 500,
+            default_cache_ttl: None,
+            use_compression: true,
+        }
+    }
+}
+
+/// Replace `{{NAME}}` placeholders with their values, leaving unknown ones untouched
+pub fn substitute_prompt_variables(template: &str, vars: &HashMap<String, String>) -> String {
+    substitute_with(template, |name| vars.get(name).cloned())
+}
+
+/// Replace `{{KEY}}` placeholders from `vars` (later pairs win) and `{{file:path}}`
+/// with the content of that file under `root`, leaving unknown, unreadable, sensitive
+/// or out-of-project ones untouched
+pub fn substitute_vars(template: &str, vars: &[(String, String)], root: &Path) -> String {
+    substitute_with(template, |name| match name.strip_prefix("file:") {
+        Some(path) => match read_project_file(root, path.trim()) {
+            Ok(content) => Some(content),
+            Err(e) => {
+                log_warn("WARNING", format!("Can't inline {}: {}", path.trim(), e));
+                None
+            },
+        },
+        None => vars
+            .iter()
+            .rev()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone()),
+    })
+}
+
+fn substitute_with(template: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let name = after[..end].trim();
+                match lookup(name) {
+                    Some(value) => result.push_str(&value),
+                    None => result.push_str(&rest[start..start + 2 + end + 2]),
+                }
+                rest = &after[end + 2..];
+            },
+            None => {
+                result.push_str(&rest[start..]);
+                rest = "";
+            },
         }
     }
+    result.push_str(rest);
+    result
 }
 
 /// Load configuration from multiple sources
@@ -318,9 +711,55 @@ pub fn load_config() -> Result<Config> {
     figment = figment.merge(Env::prefixed("MERMAID_"));
 
     // Extract and return config
-    figment
+    let mut config: Config = figment
         .extract()
-        .context("Failed to load configuration. Check that config files are valid TOML format.")
+        .context("Failed to load configuration. Check that config files are valid TOML format.")?;
+    // An unreadable presets file shouldn't stop the app from starting
+    config.presets = load_presets().unwrap_or_else(|_| builtin_presets());
+    Ok(config)
+}
+
+fn presets_path() -> Result<PathBuf> {
+    Ok(get_config_dir()?.join("presets.toml"))
+}
+
+/// Saved presets from `presets.toml`, on top of the built-in ones
+fn load_presets() -> Result<HashMap<String, ModelConfig>> {
+    let mut presets = builtin_presets();
+    presets.extend(saved_presets()?);
+    Ok(presets)
+}
+
+fn saved_presets() -> Result<HashMap<String, ModelConfig>> {
+    let path = presets_path()?;
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let content = std::fs::read_to_string(&path)?;
+    toml::from_str(&content)
+        .map_err(|e| toml_parse_error(&path, &content, &e))
+        .with_context(|| format!("Invalid presets file: {}", path.display()))
+}
+
+/// A TOML error as a `ParseError` pointing at the offending line
+fn toml_parse_error(path: &Path, content: &str, error: &toml::de::Error) -> MermaidError {
+    let line = error
+        .span()
+        .map_or(1, |span| content[..span.start].matches('\n').count() + 1);
+    MermaidError::ParseError {
+        file: path.to_path_buf(),
+        line,
+        message: error.message().to_string(),
+    }
+}
+
+/// Save a preset to `presets.toml`, replacing any preset with the same name
+pub fn save_preset(name: &str, preset: &ModelConfig) -> Result<()> {
+    let mut presets = saved_presets()?;
+    presets.insert(name.to_string(), preset.parameters());
+    let path = presets_path()?;
+    std::fs::write(&path, toml::to_string_pretty(&presets)?)
+        .with_context(|| format!("Failed to write presets to {}", path.display()))
 }
 
 /// Get the configuration directory
@@ -355,18 +794,23 @@ pub fn save_config(config: &Config, path: Option<PathBuf>) -> Result<()> {
     Ok(())
 }
 
-/// Create a default configuration file if it doesn't exist
-pub fn init_config() -> Result<()> {
+/// Create the global configuration with the setup wizard, or with defaults when
+/// stdin isn't a terminal, unless it already exists
+pub async fn init_config() -> Result<()> {
     let config_dir = get_config_dir()?;
     let config_file = config_dir.join("config.toml");
 
     if !config_file.exists() {
-        let default_config = Config::default();
-        save_config(&default_config, Some(config_file.clone()))?;
-        println!(
-            "Created default configuration at: {}",
-            config_file.display()
-        );
+        if std::io::stdin().is_terminal() {
+            run_setup_wizard().await?;
+        } else {
+            let default_config = Config::default();
+            save_config(&default_config, Some(config_file.clone()))?;
+            println!(
+                "Created default configuration at: {}",
+                config_file.display()
+            );
+        }
     }
 
     // Create example local config
@@ -398,3 +842,83 @@ include_patterns = ["src/**/*.rs", "Cargo.toml"]
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_timeouts_add_to_defaults() {
+        let mode: ModeConfig =
+            toml::from_str("[action_timeouts]\nexecute_command = 600\n").unwrap();
+        assert_eq!(mode.action_timeout("execute_command").as_secs(), 600);
+        assert_eq!(mode.action_timeout("lint_file").as_secs(), 120);
+        assert_eq!(mode.action_timeout("read_file").as_secs(), 60);
+    }
+
+    #[test]
+    fn test_toml_parse_error_line() {
+        let content = "[precise]\ntemperature = 0.2\ntop_p = \n";
+        let error = toml::from_str::<HashMap<String, ModelConfig>>(content).unwrap_err();
+        match toml_parse_error(Path::new("presets.toml"), content, &error) {
+            MermaidError::ParseError { line, .. } => assert_eq!(line, 3),
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn test_substitute_prompt_variables() {
+        let vars = HashMap::from([
+            ("TEAM".to_string(), "Platform".to_string()),
+            ("DATE".to_string(), "2024-05-01".to_string()),
+        ]);
+
+        assert_eq!(
+            substitute_prompt_variables("{{TEAM}} team, {{ DATE }}, {{UNKNOWN}} {{open", &vars),
+            "Platform team, 2024-05-01, {{UNKNOWN}} {{open"
+        );
+    }
+
+    #[test]
+    fn test_substitute_vars() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let project = dir.path().join("project");
+        std::fs::create_dir_all(project.join("docs")).unwrap();
+        std::fs::write(project.join("docs/greeting.txt"), "hello").unwrap();
+        std::fs::write(project.join(".env"), "TOKEN=secret").unwrap();
+        std::fs::write(dir.path().join("outside.txt"), "outside").unwrap();
+        let vars = vec![
+            ("MODEL".to_string(), "ollama/tinyllama".to_string()),
+            ("MODEL".to_string(), "openai/gpt-4o".to_string()),
+        ];
+
+        // Paths resolve against the project root, not the current directory
+        let template = "{{MODEL}}: {{file:docs/greeting.txt}} {{file:missing.txt}}";
+        assert_eq!(
+            substitute_vars(template, &vars, &project),
+            "openai/gpt-4o: hello {{file:missing.txt}}"
+        );
+
+        // Files outside the project and sensitive files are not inlined
+        let outside = dir.path().join("outside.txt");
+        for template in [
+            "{{file:../outside.txt}}".to_string(),
+            format!("{{{{file:{}}}}}", outside.display()),
+            "{{file:.env}}".to_string(),
+        ] {
+            assert_eq!(substitute_vars(&template, &vars, &project), template);
+        }
+    }
+
+    #[test]
+    fn test_presets_round_trip_through_toml() {
+        let presets = builtin_presets();
+        let toml_string = toml::to_string_pretty(&presets).unwrap();
+        assert!(!toml_string.contains("system_prompt"));
+
+        let loaded: HashMap<String, ModelConfig> = toml::from_str(&toml_string).unwrap();
+        assert_eq!(loaded["precise"].temperature, Some(0.2));
+        assert_eq!(loaded["creative"].top_p, Some(0.95));
+        assert_eq!(loaded["balanced"].max_tokens, None);
+    }
+}
@@ -4,7 +4,15 @@
 // Private submodules - not directly accessible from outside
 mod config;
 mod state;
+mod validate;
+mod wizard;
 
 // Public re-exports - the ONLY way to access app functionality
-pub use config::{init_config, load_config, save_config, Config};
+pub use config::{
+    get_config_dir, init_config, load_config, save_config, save_preset,
+    substitute_prompt_variables, substitute_vars, BudgetLimits, BypassConfig, CacheConfig, Config,
+    LiteLLMConfig, LogFormat, SearchProviderConfig,
+};
 pub use state::AppState;
+pub use validate::{remove_unknown_keys, validate_config, ConfigIssue};
+pub use wizard::{render_config, run_setup_wizard, WizardAnswers};
@@ -0,0 +1,258 @@
+use anyhow::Result;
+use figment::{
+    providers::{Format, Serialized, Toml},
+    Figment,
+};
+use std::fmt;
+
+use super::config::{BudgetLimits, Config, SearchProviderConfig};
+
+/// Tables whose keys are user-defined, so any key is allowed below them
+const FREE_FORM_TABLES: &[&str] = &[
+    "default_model.model_aliases",
+    "prompt_variables",
+    "log_levels",
+    "mode.action_timeouts",
+];
+
+/// One problem found in a config file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+    /// Dotted path of the offending key, e.g. `default_model.temperature`
+    pub path: String,
+    pub message: String,
+    /// Unknown keys are the ones `--fix` can remove
+    pub unknown_key: bool,
+}
+
+impl ConfigIssue {
+    fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+            unknown_key: false,
+        }
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.path, self.message)
+    }
+}
+
+/// Check a config file for syntax errors, unknown keys, type mismatches,
+/// missing fields and out-of-range values, collecting every issue
+/// With `standalone`, the file must be complete on its own (as with `--config`);
+/// otherwise it is layered over the defaults like `config.toml`
+pub fn validate_config(toml_str: &str, standalone: bool) -> Vec<ConfigIssue> {
+    let table: toml::Table = match toml_str.parse() {
+        Ok(table) => table,
+        Err(e) => return vec![ConfigIssue::new("toml", e.message().to_string())],
+    };
+
+    let mut issues: Vec<ConfigIssue> = unknown_keys(&table)
+        .into_iter()
+        .map(|path| ConfigIssue {
+            unknown_key: true,
+            ..ConfigIssue::new(path, "unknown key")
+        })
+        .collect();
+
+    // Each section on its own, so one bad section doesn't hide the others
+    let mut all_sections_valid = true;
+    for (key, value) in &table {
+        let section = toml::Table::from_iter([(key.clone(), value.clone())]);
+        if let Err(e) = extract(&section, standalone) {
+            all_sections_valid = false;
+            issues.extend((*e).into_iter().map(|e| {
+                let path = if e.path.is_empty() {
+                    key.clone()
+                } else {
+                    e.path.join(".")
+                };
+                ConfigIssue::new(path, e.kind.to_string())
+            }));
+        }
+    }
+
+    if all_sections_valid {
+        match extract(&table, standalone) {
+            Ok(config) => issues.extend(range_issues(&config)),
+            Err(e) => issues.push(ConfigIssue::new("config", e.to_string())),
+        }
+    }
+    issues
+}
+
+/// Remove the unknown keys from a config file, keeping comments and layout
+pub fn remove_unknown_keys(toml_str: &str) -> Result<(String, usize)> {
+    let table: toml::Table = toml_str.parse()?;
+    let mut doc: toml_edit::DocumentMut = toml_str.parse()?;
+    let unknown = unknown_keys(&table);
+    for path in &unknown {
+        let mut keys: Vec<&str> = path.split('.').collect();
+        let Some(last) = keys.pop() else {
+            continue;
+        };
+        let mut item = doc.as_item_mut();
+        for key in keys {
+            item = &mut item[key];
+        }
+        if let Some(parent) = item.as_table_like_mut() {
+            parent.remove(last);
+        }
+    }
+    Ok((doc.to_string(), unknown.len()))
+}
+
+fn extract(table: &toml::Table, standalone: bool) -> Result<Config, Box<figment::Error>> {
+    let toml_str = toml::to_string(table).unwrap_or_default();
+    let figment = if standalone {
+        Figment::new()
+    } else {
+        Figment::from(Serialized::defaults(Config::default()))
+    };
+    figment
+        .merge(Toml::string(&toml_str))
+        .extract()
+        .map_err(Box::new)
+}
+
+/// Dotted paths of keys that no config field reads
+fn unknown_keys(table: &toml::Table) -> Vec<String> {
+    // Optional sections are filled in so their fields are known too
+    let reference = serde_json::to_value(Config {
+        budget_limits: Some(BudgetLimits::default()),
+        search_provider: Some(SearchProviderConfig::default()),
+        ..Config::default()
+    })
+    .unwrap_or_default();
+    let mut unknown = Vec::new();
+    collect_unknown(table, &reference, "", &mut unknown);
+    unknown
+}
+
+fn collect_unknown(
+    table: &toml::Table,
+    reference: &serde_json::Value,
+    prefix: &str,
+    unknown: &mut Vec<String>,
+) {
+    for (key, value) in table {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match (reference.get(key), value) {
+            (None, _) => unknown.push(path),
+            (Some(_), _) if FREE_FORM_TABLES.contains(&path.as_str()) => {},
+            (Some(nested @ serde_json::Value::Object(_)), toml::Value::Table(table)) => {
+                collect_unknown(table, nested, &path, unknown)
+            },
+            _ => {},
+        }
+    }
+}
+
+/// Values that parse but make no sense, such as `temperature = 3.0`
+fn range_issues(config: &Config) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+    let temperature = config.default_model.temperature;
+    if !(0.0..=2.0).contains(&temperature) {
+        issues.push(ConfigIssue::new(
+            "default_model.temperature",
+            format!("must be between 0.0 and 2.0, got {}", temperature),
+        ));
+    }
+    if config.default_model.max_tokens == 0 {
+        issues.push(ConfigIssue::new(
+            "default_model.max_tokens",
+            "must be greater than 0",
+        ));
+    }
+    if config.ollama.port == 0 {
+        issues.push(ConfigIssue::new("ollama.port", "must be greater than 0"));
+    }
+    if config.litellm.proxy_health_check_interval_secs == 0 {
+        issues.push(ConfigIssue::new(
+            "litellm.proxy_health_check_interval_secs",
+            "must be greater than 0",
+        ));
+    }
+    if config.context.max_files == 0 {
+        issues.push(ConfigIssue::new(
+            "context.max_files",
+            "must be greater than 0",
+        ));
+    }
+    if let Some(limits) = &config.budget_limits {
+        if limits.warn_at_percent > 100 {
+            issues.push(ConfigIssue::new(
+                "budget_limits.warn_at_percent",
+                format!("must be at most 100, got {}", limits.warn_at_percent),
+            ));
+        }
+        for (name, limit) in [
+            ("daily_usd", limits.daily_usd),
+            ("monthly_usd", limits.monthly_usd),
+        ] {
+            if limit.is_some_and(|usd| usd < 0.0) {
+                issues.push(ConfigIssue::new(
+                    format!("budget_limits.{}", name),
+                    "must not be negative",
+                ));
+            }
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_config() {
+        let toml_str = r#"
+colour = "blue"
+
+[default_model]
+temperature = 3.0
+unknown_option = true
+
+[ui]
+show_sidebar = "yes"
+
+# Shared with the team
+[prompt_variables]
+TEAM = "Platform"
+"#;
+        let issues: Vec<String> = validate_config(toml_str, false)
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect();
+        assert!(issues.contains(&"[colour] unknown key".to_string()));
+        assert!(issues.contains(&"[default_model.unknown_option] unknown key".to_string()));
+        assert!(issues
+            .iter()
+            .any(|i| i.starts_with("[ui.show_sidebar] invalid type")));
+        // Range checks need every section to parse
+        assert!(!issues.iter().any(|i| i.contains("temperature")));
+
+        let issues = validate_config("[default_model]\ntemperature = 3.0\n", false);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].path, "default_model.temperature");
+        assert!(validate_config("[default_model]\nname = \"x\"\n", true)
+            .iter()
+            .any(|issue| issue.message.contains("missing field")));
+        assert!(validate_config("", false).is_empty());
+
+        let (fixed, removed) = remove_unknown_keys(toml_str).unwrap();
+        assert_eq!(removed, 2);
+        assert!(fixed.contains("# Shared with the team"));
+        assert!(!fixed.contains("colour") && !fixed.contains("unknown_option"));
+        assert!(fixed.contains("TEAM = \"Platform\""));
+    }
+}
@@ -0,0 +1,250 @@
+use anyhow::{Context, Result};
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::config::{get_config_dir, Config};
+use crate::constants::DEFAULT_LITELLM_PROXY_URL;
+
+/// Providers offered by the wizard: (provider, default model, API key variable)
+const PROVIDERS: &[(&str, &str, Option<&str>)] = &[
+    ("ollama", "tinyllama", None),
+    ("openai", "gpt-4o", Some("OPENAI_API_KEY")),
+    (
+        "anthropic",
+        "claude-3-5-sonnet-latest",
+        Some("ANTHROPIC_API_KEY"),
+    ),
+];
+
+const THEMES: &[&str] = &["dark", "light", "high-contrast"];
+
+/// Choices collected by the wizard
+#[derive(Debug, Clone)]
+pub struct WizardAnswers {
+    pub provider: String,
+    pub model: String,
+    pub proxy_url: String,
+    /// Environment variable holding the provider's API key
+    pub api_key_env: Option<String>,
+    pub theme: String,
+}
+
+/// Ask for the settings a first run needs and write them to the global config
+pub async fn run_setup_wizard() -> Result<PathBuf> {
+    let path = get_config_dir()?.join("config.toml");
+    if path.exists()
+        && !confirm(
+            &format!("{} already exists. Overwrite it?", path.display()),
+            false,
+        )?
+    {
+        return Ok(path);
+    }
+    println!("Welcome to Mermaid! Let's set up your configuration.\n");
+
+    let names: Vec<&str> = PROVIDERS.iter().map(|(name, _, _)| *name).collect();
+    let (provider, default_model, key_env) = PROVIDERS[select("Default provider", &names)?];
+
+    let model = loop {
+        let model = ask("Model name", default_model)?;
+        match validate_model_name(&model) {
+            Ok(()) => break model,
+            Err(e) => println!("  {}", e),
+        }
+    };
+
+    let proxy_url = loop {
+        let url = ask("LiteLLM proxy URL", DEFAULT_LITELLM_PROXY_URL)?;
+        if let Err(e) = reqwest::Url::parse(&url) {
+            println!("  Not a valid URL: {}", e);
+            continue;
+        }
+        match check_proxy(&url).await {
+            Ok(()) => break url,
+            // The proxy is usually started by Mermaid itself, so it may not be up yet
+            Err(e)
+                if confirm(
+                    &format!("  {} is not responding ({}). Use it anyway?", url, e),
+                    true,
+                )? =>
+            {
+                break url
+            },
+            Err(_) => {},
+        }
+    };
+
+    // Keys stay in the environment; the config only names the variable
+    let api_key_env = match key_env {
+        Some(default_env) => {
+            let env = ask("Environment variable holding your API key", default_env)?;
+            if std::env::var(&env).is_err() {
+                println!("  {} is not set - export it before starting Mermaid", env);
+            }
+            Some(env)
+        },
+        None => None,
+    };
+
+    let theme = THEMES[select("UI theme", THEMES)?].to_string();
+
+    let answers = WizardAnswers {
+        provider: provider.to_string(),
+        model,
+        proxy_url,
+        api_key_env,
+        theme,
+    };
+    std::fs::write(&path, render_config(&answers))
+        .with_context(|| format!("Failed to write config to {}", path.display()))?;
+    println!("\nConfiguration written to {}", path.display());
+    Ok(path)
+}
+
+/// The config file for the wizard's answers, with a comment on each section
+pub fn render_config(answers: &WizardAnswers) -> String {
+    let defaults = Config::default();
+    let mut out = String::from(
+        "# Mermaid configuration, generated by the setup wizard\n\
+         # Project settings in .mermaid/config.toml override this file\n\n",
+    );
+    out.push_str(&format!(
+        "# Model used when --model isn't given\n\
+         [default_model]\n\
+         provider = {:?}\n\
+         name = {:?}\n\
+         temperature = {:.1}\n\
+         max_tokens = {}\n\n",
+        answers.provider,
+        answers.model,
+        defaults.default_model.temperature,
+        defaults.default_model.max_tokens
+    ));
+    out.push_str(&format!(
+        "# LiteLLM proxy that routes requests to every provider\n\
+         [litellm]\n\
+         proxy_url = {:?}\n\n",
+        answers.proxy_url
+    ));
+    if let Some(env) = &answers.api_key_env {
+        out.push_str(&format!(
+            "# API key is read from this environment variable\n\
+             [{}]\n\
+             api_key_env = {:?}\n\n",
+            answers.provider, env
+        ));
+    }
+    out.push_str(&format!(
+        "# Terminal UI appearance: dark, light or high-contrast\n\
+         [ui]\n\
+         theme = {:?}\n",
+        answers.theme
+    ));
+    out
+}
+
+/// Model names are passed to the proxy as `provider/name`
+fn validate_model_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        Err("Model name can't be empty".to_string())
+    } else if name.contains(char::is_whitespace) {
+        Err("Model name can't contain spaces".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+/// Send a HEAD request to the proxy; any HTTP response counts as reachable
+async fn check_proxy(url: &str) -> Result<()> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(2))
+        .build()?
+        .head(url)
+        .send()
+        .await?;
+    Ok(())
+}
+
+/// 1-based menu choice, or the first option for an empty answer
+fn parse_selection(input: &str, options: usize) -> Option<usize> {
+    if input.is_empty() {
+        return Some(0);
+    }
+    input
+        .parse::<usize>()
+        .ok()
+        .filter(|n| (1..=options).contains(n))
+        .map(|n| n - 1)
+}
+
+fn read_line() -> Result<String> {
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line)? == 0 {
+        anyhow::bail!("Setup cancelled");
+    }
+    Ok(line.trim().to_string())
+}
+
+fn ask(prompt: &str, default: &str) -> Result<String> {
+    print!("{} [{}]: ", prompt, default);
+    io::stdout().flush()?;
+    let answer = read_line()?;
+    Ok(if answer.is_empty() {
+        default.to_string()
+    } else {
+        answer
+    })
+}
+
+fn select(prompt: &str, options: &[&str]) -> Result<usize> {
+    println!("{}:", prompt);
+    for (i, option) in options.iter().enumerate() {
+        println!("  {}) {}", i + 1, option);
+    }
+    loop {
+        print!("Choose 1-{} [1]: ", options.len());
+        io::stdout().flush()?;
+        match parse_selection(&read_line()?, options.len()) {
+            Some(index) => return Ok(index),
+            None => println!("  Enter a number from 1 to {}", options.len()),
+        }
+    }
+}
+
+fn confirm(prompt: &str, default: bool) -> Result<bool> {
+    print!("{} [{}]: ", prompt, if default { "Y/n" } else { "y/N" });
+    io::stdout().flush()?;
+    Ok(match read_line()?.to_lowercase().as_str() {
+        "" => default,
+        answer => answer.starts_with('y'),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_config_and_validation() {
+        let answers = WizardAnswers {
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            proxy_url: "http://localhost:4000".to_string(),
+            api_key_env: Some("MY_OPENAI_KEY".to_string()),
+            theme: "light".to_string(),
+        };
+        let toml_str = render_config(&answers);
+        assert!(toml_str.contains("# LiteLLM proxy"));
+        // The file also has to work on its own with --config
+        let config: Config = toml::from_str(&toml_str).unwrap();
+        assert_eq!(config.default_model.name, "gpt-4o");
+        assert_eq!(config.openai.api_key_env, "MY_OPENAI_KEY");
+        assert_eq!(config.ui.theme, "light");
+
+        assert_eq!(parse_selection("", 3), Some(0));
+        assert_eq!(parse_selection("3", 3), Some(2));
+        assert_eq!(parse_selection("4", 3), None);
+        assert!(validate_model_name("llama3 70b").is_err());
+    }
+}
@@ -1,12 +1,16 @@
 use anyhow::Result;
 use directories::ProjectDirs;
+use ignore::WalkBuilder;
+use lru::LruCache;
 use rayon::prelude::*;
-use std::collections::HashMap;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
 use super::file_cache::FileCache;
-use super::types::{CacheKey, CachedSymbols, CachedTokens};
+use super::types::{CacheKey, CacheMetadata, CachedSymbols, CachedTokens};
+use crate::app::{load_config, CacheConfig};
 use crate::context::{Symbol, SymbolReference, TreeParser};
 
 /// Main cache manager for the application
@@ -17,18 +21,36 @@ pub struct CacheManager {
     cache_dir: PathBuf,
 }
 
-/// In-memory cache for hot data
-#[derive(Debug, Default)]
+/// In-memory cache for hot data, evicting least recently used entries
+#[derive(Debug)]
 struct MemoryCache {
-    symbols: HashMap<CacheKey, CachedSymbols>,
-    tokens: HashMap<CacheKey, CachedTokens>,
+    symbols: LruCache<CacheKey, CachedSymbols>,
+    tokens: LruCache<CacheKey, CachedTokens>,
     hits: usize,
     misses: usize,
 }
 
+impl MemoryCache {
+    fn new(max_entries: usize) -> Self {
+        let capacity = NonZeroUsize::new(max_entries).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            symbols: LruCache::new(capacity),
+            tokens: LruCache::new(capacity),
+            hits: 0,
+            misses: 0,
+        }
+    }
+}
+
 impl CacheManager {
-    /// Create a new cache manager
+    /// Create a new cache manager using the cache settings from the loaded config
     pub fn new() -> Result<Self> {
+        let config = load_config().map(|c| c.cache).unwrap_or_default();
+        Self::with_config(&config)
+    }
+
+    /// Create a new cache manager with explicit cache limits
+    pub fn with_config(config: &CacheConfig) -> Result<Self> {
         // Get cache directory (~/.cache/mermaid on Linux, ~/Library/Caches/mermaid on macOS)
         let cache_dir = if let Some(proj_dirs) = ProjectDirs::from("", "", "mermaid") {
             proj_dirs.cache_dir().to_path_buf()
@@ -38,8 +60,13 @@ impl CacheManager {
             PathBuf::from(home).join(".cache").join("mermaid")
         };
 
-        let file_cache = Arc::new(FileCache::new(cache_dir.clone())?);
-        let memory_cache = Arc::new(Mutex::new(MemoryCache::default()));
+        let max_disk_bytes = config.max_disk_size_mb as u64 * 1024 * 1024;
+        let file_cache = Arc::new(
+            FileCache::with_max_size(cache_dir.clone(), max_disk_bytes)?
+                .with_ttl(config.default_cache_ttl)
+                .with_compression(config.use_compression),
+        );
+        let memory_cache = Arc::new(Mutex::new(MemoryCache::new(config.max_memory_entries)));
 
         Ok(Self {
             file_cache,
@@ -73,7 +100,7 @@ impl CacheManager {
             if self.file_cache.is_valid(&key)? {
                 // Store in memory cache
                 let mut mem_cache = self.memory_cache.lock().unwrap();
-                mem_cache.symbols.insert(key.clone(), cached.clone());
+                mem_cache.symbols.put(key.clone(), cached.clone());
                 mem_cache.hits += 1;
                 return Ok((cached.symbols, cached.references));
             } else {
@@ -104,7 +131,7 @@ impl CacheManager {
         // Save to memory cache
         {
             let mut mem_cache = self.memory_cache.lock().unwrap();
-            mem_cache.symbols.insert(key, cached);
+            mem_cache.symbols.put(key, cached);
         }
 
         Ok((symbols, references))
@@ -138,7 +165,7 @@ impl CacheManager {
                 if self.file_cache.is_valid(&key)? {
                     // Store in memory cache
                     let mut mem_cache = self.memory_cache.lock().unwrap();
-                    mem_cache.tokens.insert(key.clone(), cached.clone());
+                    mem_cache.tokens.put(key.clone(), cached.clone());
                     mem_cache.hits += 1;
                     return Ok(cached.count);
                 } else {
@@ -170,7 +197,7 @@ impl CacheManager {
         // Save to memory cache
         {
             let mut mem_cache = self.memory_cache.lock().unwrap();
-            mem_cache.tokens.insert(key, cached);
+            mem_cache.tokens.put(key, cached);
         }
 
         Ok(count)
@@ -216,6 +243,27 @@ impl CacheManager {
             .collect()
     }
 
+    /// Parse source files under `root` modified after `changed_since`, so their
+    /// symbols are cached before they're first needed
+    /// Returns the number of files parsed
+    pub fn prewarm(&self, root: &Path, changed_since: SystemTime) -> Result<usize> {
+        let parser = TreeParser::new()?;
+        let files: Vec<PathBuf> = WalkBuilder::new(root)
+            .build()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+            .filter(|e| parser.language_of(e.path()).is_some())
+            .filter(|e| {
+                e.metadata()
+                    .ok()
+                    .and_then(|m| m.modified().ok())
+                    .is_some_and(|modified| modified > changed_since)
+            })
+            .map(|e| e.into_path())
+            .collect();
+        Ok(self.parse_files_cached(&files).len())
+    }
+
     /// Invalidate cache for a specific file
     pub fn invalidate(&self, path: &Path) -> Result<()> {
         let key = FileCache::generate_key(path)?;
@@ -223,8 +271,8 @@ impl CacheManager {
         // Remove from memory cache
         {
             let mut mem_cache = self.memory_cache.lock().unwrap();
-            mem_cache.symbols.remove(&key);
-            mem_cache.tokens.remove(&key);
+            mem_cache.symbols.pop(&key);
+            mem_cache.tokens.pop(&key);
         }
 
         // Remove from file cache
@@ -233,6 +281,11 @@ impl CacheManager {
         Ok(())
     }
 
+    /// Read one on-disk entry's metadata, if the cache has any
+    pub fn sample_entry(&self) -> Result<Option<CacheMetadata>> {
+        self.file_cache.sample_entry()
+    }
+
     /// Clear all caches
     pub fn clear_all(&self) -> Result<()> {
         // Clear memory cache
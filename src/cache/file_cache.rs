@@ -1,23 +1,64 @@
 use anyhow::Result;
 use sha2::{Digest, Sha256};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::SystemTime;
 
-use super::types::{CacheEntry, CacheKey, CacheMetadata};
+use super::types::{CacheEntry, CacheKey, CacheMetadata, TREE_SITTER_VERSION};
+
+/// Default on-disk cache limit (500 MB)
+const DEFAULT_MAX_DISK_BYTES: u64 = 500 * 1024 * 1024;
+
+/// Entries smaller than this are stored uncompressed, since the overhead outweighs the gain
+const MIN_COMPRESS_BYTES: usize = 256;
 
 /// File-level cache operations
 #[derive(Debug)]
 pub struct FileCache {
     cache_dir: std::path::PathBuf,
+    /// Size limit in bytes before least recently used entries are evicted
+    max_disk_bytes: u64,
+    /// Running estimate of bytes on disk
+    disk_usage: AtomicU64,
+    /// TTL applied to new entries
+    default_ttl: Option<u64>,
+    /// Compress entries of at least `MIN_COMPRESS_BYTES`
+    use_compression: bool,
 }
 
 impl FileCache {
     /// Create a new file cache
     pub fn new(cache_dir: std::path::PathBuf) -> Result<Self> {
+        Self::with_max_size(cache_dir, DEFAULT_MAX_DISK_BYTES)
+    }
+
+    /// Create a new file cache that evicts old entries beyond `max_disk_bytes`
+    pub fn with_max_size(cache_dir: std::path::PathBuf, max_disk_bytes: u64) -> Result<Self> {
         // Ensure cache directory exists
         fs::create_dir_all(&cache_dir)?;
-        Ok(Self { cache_dir })
+        let cache = Self {
+            cache_dir,
+            max_disk_bytes,
+            disk_usage: AtomicU64::new(0),
+            default_ttl: None,
+            use_compression: true,
+        };
+        let usage = cache.cache_files()?.iter().map(|(_, size)| size).sum();
+        cache.disk_usage.store(usage, Ordering::Relaxed);
+        Ok(cache)
+    }
+
+    /// Expire new entries after `ttl_seconds`
+    pub fn with_ttl(mut self, ttl_seconds: Option<u64>) -> Self {
+        self.default_ttl = ttl_seconds;
+        self
+    }
+
+    /// Enable or disable compression of new entries
+    pub fn with_compression(mut self, use_compression: bool) -> Self {
+        self.use_compression = use_compression;
+        self
     }
 
     /// Compute SHA256 hash of a file
@@ -47,23 +88,31 @@ impl FileCache {
         let serialized = bincode::serialize(data)?;
         let original_size = serialized.len();
 
-        // Compress data
-        let compressed = lz4::block::compress(&serialized, None, true)?;
-        let compressed_size = compressed.len();
+        // Compress data unless it's too small to benefit
+        let compressed = self.use_compression && original_size >= MIN_COMPRESS_BYTES;
+        let stored = if compressed {
+            lz4::block::compress(&serialized, None, true)?
+        } else {
+            serialized
+        };
+        let compressed_size = stored.len();
 
         // Create metadata
         let metadata = CacheMetadata {
             created_at: SystemTime::now(),
             last_accessed: SystemTime::now(),
             file_size: original_size as u64,
-            compressed_size,
-            compression_ratio: original_size as f32 / compressed_size as f32,
+            compressed_size: compressed_size as u64,
+            compression_ratio: original_size as f32 / compressed_size.max(1) as f32,
+            compressed,
+            ttl_seconds: self.default_ttl,
+            tree_sitter_version: TREE_SITTER_VERSION.to_string(),
         };
 
         // Create cache entry
         let entry = CacheEntry {
             key: key.clone(),
-            data: compressed,
+            data: stored,
             metadata,
         };
 
@@ -75,10 +124,24 @@ impl FileCache {
             fs::create_dir_all(parent)?;
         }
 
-        // Write to file
+        // Write to file, replacing any earlier entry for the key
         let entry_data = bincode::serialize(&entry)?;
+        let written = entry_data.len() as u64;
+        let replaced = fs::metadata(&cache_path).map(|m| m.len()).unwrap_or(0);
         fs::write(cache_path, entry_data)?;
 
+        // Evict old entries once the size limit is exceeded
+        let previous = self
+            .disk_usage
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |u| {
+                Some(u.saturating_sub(replaced) + written)
+            })
+            .unwrap_or_default();
+        let usage = previous.saturating_sub(replaced) + written;
+        if usage > self.max_disk_bytes {
+            self.evict_lru()?;
+        }
+
         Ok(())
     }
 
@@ -94,19 +157,33 @@ impl FileCache {
             return Ok(None);
         }
 
-        // Read cache entry
+        // Read cache entry; entries in an older format are dropped
         let entry_data = fs::read(&cache_path)?;
-        let mut entry: CacheEntry<Vec<u8>> = bincode::deserialize(&entry_data)?;
+        let entry: CacheEntry<Vec<u8>> = match bincode::deserialize(&entry_data) {
+            Ok(entry) => entry,
+            Err(_) => {
+                self.remove(key)?;
+                return Ok(None);
+            },
+        };
 
-        // Update last accessed time
-        entry.metadata.last_accessed = SystemTime::now();
+        // Drop entries that outlived their TTL or came from another parser version
+        if entry.metadata.is_expired() || entry.metadata.is_stale_parser() {
+            self.remove(key)?;
+            return Ok(None);
+        }
 
-        // Decompress data
-        let decompressed =
-            lz4::block::decompress(&entry.data, Some(entry.metadata.file_size as i32))?;
+        // Touch the file so eviction keeps recently used entries, without rewriting it
+        if let Ok(file) = fs::File::options().write(true).open(&cache_path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
 
-        // Deserialize data
-        let data: T = bincode::deserialize(&decompressed)?;
+        // Decompress data if needed (the size is prepended by `save`)
+        let data: T = if entry.metadata.compressed {
+            bincode::deserialize(&lz4::block::decompress(&entry.data, None)?)?
+        } else {
+            bincode::deserialize(&entry.data)?
+        };
 
         Ok(Some(data))
     }
@@ -127,11 +204,71 @@ impl FileCache {
     pub fn remove(&self, key: &CacheKey) -> Result<()> {
         let cache_path = self.cache_path(key);
         if cache_path.exists() {
+            let size = fs::metadata(&cache_path).map(|m| m.len()).unwrap_or(0);
             fs::remove_file(cache_path)?;
+            let _ = self
+                .disk_usage
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |u| {
+                    Some(u.saturating_sub(size))
+                });
         }
         Ok(())
     }
 
+    /// List all cache files with their sizes
+    fn cache_files(&self) -> Result<Vec<(PathBuf, u64)>> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.cache_dir)? {
+            let entry = entry?;
+            if entry.path().is_dir() {
+                for cache_file in fs::read_dir(entry.path())? {
+                    let cache_file = cache_file?;
+                    files.push((cache_file.path(), cache_file.metadata()?.len()));
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    /// Read the metadata of one stored entry, if any, to check the cache is readable
+    pub fn sample_entry(&self) -> Result<Option<CacheMetadata>> {
+        let Some((path, _)) = self.cache_files()?.into_iter().next() else {
+            return Ok(None);
+        };
+        let entry: CacheEntry<Vec<u8>> = bincode::deserialize(&fs::read(path)?)?;
+        Ok(Some(entry.metadata))
+    }
+
+    /// Remove least recently accessed entries until the cache fits within its limit
+    /// Entries are touched when loaded, so a file's modification time is its last access
+    pub fn evict_lru(&self) -> Result<usize> {
+        let mut entries: Vec<(PathBuf, u64, SystemTime)> = self
+            .cache_files()?
+            .into_iter()
+            .map(|(path, size)| {
+                let last_accessed = fs::metadata(&path)
+                    .and_then(|m| m.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                (path, size, last_accessed)
+            })
+            .collect();
+        entries.sort_by_key(|(_, _, last_accessed)| *last_accessed);
+
+        let mut usage: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        let mut evicted = 0;
+        for (path, size, _) in entries {
+            if usage <= self.max_disk_bytes {
+                break;
+            }
+            fs::remove_file(&path)?;
+            usage -= size;
+            evicted += 1;
+        }
+
+        self.disk_usage.store(usage, Ordering::Relaxed);
+        Ok(evicted)
+    }
+
     /// Generate cache file path for a key
     fn cache_path(&self, key: &CacheKey) -> std::path::PathBuf {
         // Use first 2 chars of hash for directory sharding
@@ -154,18 +291,16 @@ impl FileCache {
         let mut total_size = 0;
         let mut total_compressed_size = 0;
 
-        // Walk cache directory
-        for entry in fs::read_dir(&self.cache_dir)? {
-            let entry = entry?;
-            if entry.path().is_dir() {
-                for cache_file in fs::read_dir(entry.path())? {
-                    let cache_file = cache_file?;
-                    let metadata = cache_file.metadata()?;
-                    total_entries += 1;
-                    total_compressed_size += metadata.len() as usize;
-                    // Estimate original size (we'd need to read entries for exact)
-                    total_size += (metadata.len() as f32 * 3.0) as usize;
-                }
+        // Sum the sizes recorded in each entry's metadata
+        for (path, _) in self.cache_files()? {
+            total_entries += 1;
+            let metadata = fs::read(&path)
+                .ok()
+                .and_then(|data| bincode::deserialize::<CacheEntry<Vec<u8>>>(&data).ok())
+                .map(|e| e.metadata);
+            if let Some(metadata) = metadata {
+                total_size += metadata.file_size as usize;
+                total_compressed_size += metadata.compressed_size as usize;
             }
         }
 
@@ -192,3 +327,131 @@ pub struct CacheStats {
     pub compression_ratio: f32,
     pub cache_dir: std::path::PathBuf,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_evicts_least_recently_used_entries() {
+        let cache_dir = TempDir::new().unwrap();
+        let source_dir = TempDir::new().unwrap();
+
+        let mut keys = Vec::new();
+        for i in 0..3 {
+            let path = source_dir.path().join(format!("file{}.rs", i));
+            fs::write(&path, format!("fn f{}() {{}}", i)).unwrap();
+            keys.push(FileCache::generate_key(&path).unwrap());
+        }
+
+        let payload = vec![7u8; 4096];
+        let cache = FileCache::with_max_size(cache_dir.path().to_path_buf(), u64::MAX).unwrap();
+        for key in &keys {
+            cache.save(key, &payload).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        // Touch the oldest entry so the middle one becomes least recently used
+        assert!(cache.load::<Vec<u8>>(&keys[0]).unwrap().is_some());
+
+        let entry_size = cache.cache_files().unwrap()[0].1;
+        let limited =
+            FileCache::with_max_size(cache_dir.path().to_path_buf(), entry_size * 2).unwrap();
+        assert_eq!(limited.evict_lru().unwrap(), 1);
+
+        assert!(limited.load::<Vec<u8>>(&keys[0]).unwrap().is_some());
+        assert!(limited.load::<Vec<u8>>(&keys[1]).unwrap().is_none());
+        assert!(limited.load::<Vec<u8>>(&keys[2]).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_resaving_an_entry_counts_its_size_once() {
+        let cache_dir = TempDir::new().unwrap();
+        let source_dir = TempDir::new().unwrap();
+        let path = source_dir.path().join("lib.rs");
+        fs::write(&path, "pub fn lib() {}").unwrap();
+        let key = FileCache::generate_key(&path).unwrap();
+
+        let cache = FileCache::new(cache_dir.path().to_path_buf()).unwrap();
+        cache.save(&key, &"symbols".to_string()).unwrap();
+        cache.save(&key, &"symbols".to_string()).unwrap();
+        let on_disk: u64 = cache
+            .cache_files()
+            .unwrap()
+            .iter()
+            .map(|(_, size)| size)
+            .sum();
+        assert_eq!(cache.disk_usage.load(Ordering::Relaxed), on_disk);
+
+        // Loading leaves the entry as it was
+        let before = fs::read(cache.cache_path(&key)).unwrap();
+        assert!(cache.load::<String>(&key).unwrap().is_some());
+        assert_eq!(fs::read(cache.cache_path(&key)).unwrap(), before);
+    }
+
+    #[test]
+    fn test_expired_and_stale_entries_are_dropped() {
+        let cache_dir = TempDir::new().unwrap();
+        let source_dir = TempDir::new().unwrap();
+        let path = source_dir.path().join("lib.rs");
+        fs::write(&path, "pub fn lib() {}").unwrap();
+        let key = FileCache::generate_key(&path).unwrap();
+
+        let cache = FileCache::new(cache_dir.path().to_path_buf())
+            .unwrap()
+            .with_ttl(Some(3600));
+        cache.save(&key, &"symbols".to_string()).unwrap();
+        assert!(cache.load::<String>(&key).unwrap().is_some());
+
+        // Rewrite the entry as if it were created long ago by an older parser
+        let cache_path = cache.cache_path(&key);
+        let mut entry: CacheEntry<Vec<u8>> =
+            bincode::deserialize(&fs::read(&cache_path).unwrap()).unwrap();
+        entry.metadata.tree_sitter_version = "0.20".to_string();
+        fs::write(&cache_path, bincode::serialize(&entry).unwrap()).unwrap();
+        assert!(cache.load::<String>(&key).unwrap().is_none());
+
+        cache.save(&key, &"symbols".to_string()).unwrap();
+        let mut entry: CacheEntry<Vec<u8>> =
+            bincode::deserialize(&fs::read(&cache_path).unwrap()).unwrap();
+        entry.metadata.created_at = SystemTime::now() - std::time::Duration::from_secs(7200);
+        fs::write(&cache_path, bincode::serialize(&entry).unwrap()).unwrap();
+        assert!(cache.load::<String>(&key).unwrap().is_none());
+        assert!(!cache_path.exists());
+    }
+
+    #[test]
+    fn test_small_entries_are_stored_uncompressed() {
+        let cache_dir = TempDir::new().unwrap();
+        let source_dir = TempDir::new().unwrap();
+        let small_path = source_dir.path().join("small.rs");
+        let large_path = source_dir.path().join("large.rs");
+        fs::write(&small_path, "fn small() {}").unwrap();
+        fs::write(&large_path, "fn large() {}").unwrap();
+        let small_key = FileCache::generate_key(&small_path).unwrap();
+        let large_key = FileCache::generate_key(&large_path).unwrap();
+
+        let cache = FileCache::new(cache_dir.path().to_path_buf()).unwrap();
+        cache.save(&small_key, &"tiny".to_string()).unwrap();
+        cache.save(&large_key, &"symbol ".repeat(200)).unwrap();
+
+        let metadata = |key: &CacheKey| {
+            let data = fs::read(cache.cache_path(key)).unwrap();
+            bincode::deserialize::<CacheEntry<Vec<u8>>>(&data)
+                .unwrap()
+                .metadata
+        };
+        assert!(!metadata(&small_key).compressed);
+        assert!(metadata(&large_key).compressed);
+        assert_eq!(cache.load::<String>(&small_key).unwrap().unwrap(), "tiny");
+        assert_eq!(
+            cache.load::<String>(&large_key).unwrap().unwrap(),
+            "symbol ".repeat(200)
+        );
+
+        let stats = cache.get_stats().unwrap();
+        assert_eq!(stats.total_entries, 2);
+        assert!(stats.compression_ratio > 1.0);
+    }
+}
@@ -4,7 +4,7 @@ mod types;
 
 pub use cache_manager::{CacheManager, CacheStats};
 pub use file_cache::FileCache;
-pub use types::{CacheEntry, CacheKey, CacheMetadata};
+pub use types::{CacheEntry, CacheKey, CacheMetadata, TREE_SITTER_VERSION};
 
 /// Initialize the cache system
 pub fn init() -> anyhow::Result<CacheManager> {
@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
+
+/// Parser version baked into every cache entry; bump with the tree-sitter dependency
+/// so symbols cached by an older grammar are re-parsed
+pub const TREE_SITTER_VERSION: &str = "0.24";
 
 /// Key for cache entries
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
@@ -15,8 +19,32 @@ pub struct CacheMetadata {
     pub created_at: SystemTime,
     pub last_accessed: SystemTime,
     pub file_size: u64,
-    pub compressed_size: usize,
+    pub compressed_size: u64,
     pub compression_ratio: f32,
+    /// Whether `data` is LZ4-compressed; small entries are stored as-is
+    pub compressed: bool,
+    /// Time-to-live in seconds; `None` keeps the entry until the file changes
+    pub ttl_seconds: Option<u64>,
+    /// Parser version the entry was produced with
+    pub tree_sitter_version: String,
+}
+
+impl CacheMetadata {
+    /// Check whether the entry outlived its TTL
+    pub fn is_expired(&self) -> bool {
+        match self.ttl_seconds {
+            Some(ttl) => SystemTime::now()
+                .duration_since(self.created_at)
+                .map(|age| age > Duration::from_secs(ttl))
+                .unwrap_or(false),
+            None => false,
+        }
+    }
+
+    /// Check whether the entry was produced by a different parser version
+    pub fn is_stale_parser(&self) -> bool {
+        self.tree_sitter_version != TREE_SITTER_VERSION
+    }
 }
 
 /// Cache entry containing parsed data
@@ -22,6 +22,14 @@ pub struct Cli {
     #[arg(short, long)]
     pub path: Option<PathBuf>,
 
+    /// Open a project saved by `mermaid discover --save`, by name
+    #[arg(long, conflicts_with = "path")]
+    pub project: Option<String>,
+
+    /// Also load files from this directory (repeatable), keyed by absolute path
+    #[arg(long = "root", value_name = "PATH")]
+    pub extra_roots: Vec<PathBuf>,
+
     /// Skip automatic model installation
     #[arg(long)]
     pub no_auto_install: bool,
@@ -46,22 +54,91 @@ pub struct Cli {
     #[arg(short, long, conflicts_with_all = &["resume", "continue"])]
     pub prompt: Option<String>,
 
-    /// Output format for non-interactive mode
-    #[arg(long, value_enum, default_value_t = OutputFormat::Text, requires = "prompt")]
+    /// Output format for non-interactive mode; csv gives one row per action for spreadsheets
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
     pub output_format: OutputFormat,
 
     /// Maximum tokens to generate in response (non-interactive mode)
-    #[arg(long, requires = "prompt")]
+    #[arg(long)]
     pub max_tokens: Option<usize>,
 
-    /// Don't execute agent actions automatically (non-interactive mode)
-    #[arg(long, requires = "prompt")]
+    /// Show the actions the model proposes without running them; exits with 2 if there are any
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Deprecated alias for --dry-run
+    #[arg(long, hide = true)]
     pub no_execute: bool,
 
+    /// Request a JSON response and validate it (non-interactive mode)
+    #[arg(long)]
+    pub json_response: bool,
+
+    /// Value for a `{{KEY}}` placeholder in the prompt (repeatable), e.g. --var FILE=src/main.rs
+    #[arg(long = "var", value_name = "KEY=VALUE", value_parser = parse_var)]
+    pub prompt_vars: Vec<(String, String)>,
+
+    /// Run the prompt once per line of this file, with the line appended (non-interactive mode)
+    #[arg(long, value_name = "PATH")]
+    pub prompts_file: Option<PathBuf>,
+
+    /// Send the messages of this saved conversation (JSON) before the prompt
+    #[arg(long, value_name = "PATH")]
+    pub context_file: Option<PathBuf>,
+
+    /// Save the conversation, with this prompt and its response appended, to this file
+    #[arg(long, value_name = "PATH", conflicts_with = "prompts_file")]
+    pub update_context_file: Option<PathBuf>,
+
+    /// Number of prompts from --prompts-file to run at once
+    #[arg(long, default_value_t = 1, requires = "prompts_file")]
+    pub parallel: usize,
+
+    /// Log full proxy requests and responses to ~/.local/share/mermaid/debug_requests.log
+    #[arg(long)]
+    pub debug_requests: bool,
+
+    /// Keep sending requests after a budget limit has been reached
+    #[arg(long)]
+    pub override_budget: bool,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
 
+impl Cli {
+    /// The first non-interactive flag given, checked once a piped prompt has been read
+    /// (clap's `requires` would reject them before stdin is looked at)
+    pub fn non_interactive_flag(&self) -> Option<&'static str> {
+        [
+            (
+                !matches!(self.output_format, OutputFormat::Text),
+                "--output-format",
+            ),
+            (self.max_tokens.is_some(), "--max-tokens"),
+            (self.dry_run, "--dry-run"),
+            (self.no_execute, "--no-execute"),
+            (self.json_response, "--json-response"),
+            (!self.prompt_vars.is_empty(), "--var"),
+            (self.prompts_file.is_some(), "--prompts-file"),
+            (self.context_file.is_some(), "--context-file"),
+            (self.update_context_file.is_some(), "--update-context-file"),
+        ]
+        .into_iter()
+        .find_map(|(given, flag)| given.then_some(flag))
+    }
+}
+
+/// Parse a `KEY=VALUE` pair for --var
+fn parse_var(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        },
+        _ => Err(format!("expected KEY=VALUE, got '{}'", s)),
+    }
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Initialize configuration
@@ -74,6 +151,77 @@ pub enum Commands {
     Version,
     /// Check status of dependencies
     Status,
+    /// Run diagnostic checks and report PASS/WARN/FAIL for each
+    Doctor {
+        /// Also write the report as JSON to this file
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Find projects under a directory (defaults to the home directory)
+    Discover {
+        /// Directory to search instead of the home directory
+        search_path: Option<PathBuf>,
+        /// Order of the results
+        #[arg(long, value_enum, default_value_t = DiscoverSort::LastModified)]
+        sort_by: DiscoverSort,
+        /// Save the projects to ~/.config/mermaid/projects.toml for `--project <name>`
+        #[arg(long)]
+        save: bool,
+    },
+    /// Manage configuration files
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommands,
+    },
+    /// Manage saved conversations
+    Conversations {
+        #[command(subcommand)]
+        action: ConversationCommands,
+    },
+    /// Manage the conversation search index
+    Index {
+        #[command(subcommand)]
+        action: IndexCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommands {
+    /// Run the setup wizard and write the global config
+    Init,
+    /// Report every problem in the config files, exiting with 1 if there are any
+    Validate {
+        /// Check this file on its own (as used with --config) instead of the global and project configs
+        path: Option<PathBuf>,
+        /// Remove unknown keys and write the file back
+        #[arg(long)]
+        fix: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConversationCommands {
+    /// Restore a conversation from this project's trash
+    Restore {
+        /// ID of the trashed conversation
+        id: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum IndexCommands {
+    /// Rebuild the search index from this project's conversations
+    Rebuild,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DiscoverSort {
+    /// Most recently modified first
+    LastModified,
+    /// Alphabetically by path
+    Path,
+    /// Grouped by project type
+    Type,
 }
 
 #[derive(Debug, Clone, ValueEnum)]
@@ -84,4 +232,8 @@ pub enum OutputFormat {
     Json,
     /// Markdown formatted output
     Markdown,
+    /// One JSON object per line
+    Ndjson,
+    /// Actions as CSV rows, with the response as the last row
+    Csv,
 }
@@ -1,22 +1,28 @@
 use anyhow::Result;
 use colored::Colorize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::{
-    app::init_config,
+    app::{
+        get_config_dir, init_config, load_config, remove_unknown_keys, run_setup_wizard,
+        validate_config,
+    },
     models::ModelFactory,
     ollama::{is_installed as is_ollama_installed, list_models as get_ollama_models},
     proxy::is_proxy_running,
+    session::ConversationManager,
 };
 
-use super::Commands;
+use super::discover::run_discover;
+use super::doctor::run_doctor;
+use super::{Commands, ConfigCommands, ConversationCommands, IndexCommands};
 
 /// Handle CLI subcommands
 pub async fn handle_command(command: &Commands) -> Result<bool> {
     match command {
         Commands::Init => {
             println!("Initializing Mermaid configuration...");
-            init_config()?;
+            init_config().await?;
             println!("Configuration initialized successfully!");
             Ok(true)
         },
@@ -32,6 +38,58 @@ pub async fn handle_command(command: &Commands) -> Result<bool> {
             show_status().await?;
             Ok(true)
         },
+        Commands::Doctor { output } => {
+            run_doctor(output.as_deref()).await?;
+            Ok(true)
+        },
+        Commands::Discover {
+            search_path,
+            sort_by,
+            save,
+        } => {
+            run_discover(search_path.as_deref(), *sort_by, *save)?;
+            Ok(true)
+        },
+        Commands::Config { action } => {
+            match action {
+                ConfigCommands::Init => {
+                    run_setup_wizard().await?;
+                },
+                ConfigCommands::Validate { path, fix } => {
+                    if !validate_config_files(path.as_deref(), *fix)? {
+                        std::process::exit(1);
+                    }
+                },
+            }
+            Ok(true)
+        },
+        Commands::Conversations { action } => {
+            match action {
+                ConversationCommands::Restore { id } => {
+                    let backend = load_config().unwrap_or_default().storage_backend;
+                    let project_dir = std::env::current_dir()?;
+                    ConversationManager::restore_conversation(&project_dir, id, &backend)?;
+                    println!(
+                        "[OK] Restored conversation {} to {}",
+                        id.green(),
+                        project_dir.display()
+                    );
+                },
+            }
+            Ok(true)
+        },
+        Commands::Index { action } => {
+            match action {
+                IndexCommands::Rebuild => {
+                    let backend = load_config().unwrap_or_default().storage_backend;
+                    let manager =
+                        ConversationManager::with_backend(std::env::current_dir()?, &backend)?;
+                    let count = manager.rebuild_index()?;
+                    println!("[OK] Indexed {} conversations", count);
+                },
+            }
+            Ok(true)
+        },
         Commands::Chat => Ok(false), // Continue to chat interface
     }
 }
@@ -52,6 +110,50 @@ pub fn show_version() {
     println!("   An open-source, model-agnostic AI pair programmer");
 }
 
+/// Validate the given file, or the global and project configs, returning whether all are valid
+fn validate_config_files(path: Option<&Path>, fix: bool) -> Result<bool> {
+    let files: Vec<(PathBuf, bool)> = match path {
+        Some(path) => vec![(path.to_path_buf(), true)],
+        None => [
+            get_config_dir()?.join("config.toml"),
+            PathBuf::from(".mermaid/config.toml"),
+        ]
+        .into_iter()
+        .filter(|path| path.exists())
+        .map(|path| (path, false))
+        .collect(),
+    };
+    if files.is_empty() {
+        println!("No config files found (defaults are used)");
+        return Ok(true);
+    }
+
+    let mut valid = true;
+    for (file, standalone) in files {
+        let mut toml_str = std::fs::read_to_string(&file)?;
+        if fix {
+            let (fixed, removed) = remove_unknown_keys(&toml_str)?;
+            if removed > 0 {
+                std::fs::write(&file, &fixed)?;
+                println!("Removed {} unknown keys from {}", removed, file.display());
+                toml_str = fixed;
+            }
+        }
+
+        let issues = validate_config(&toml_str, standalone);
+        if issues.is_empty() {
+            println!("{} {}", "[OK]".green(), file.display());
+        } else {
+            valid = false;
+            println!("{} {}", "[INVALID]".red(), file.display());
+            for issue in &issues {
+                println!("  {}", issue);
+            }
+        }
+    }
+    Ok(valid)
+}
+
 /// Show status of all dependencies
 async fn show_status() -> Result<()> {
     println!("Mermaid Status:");
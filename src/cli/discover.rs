@@ -0,0 +1,232 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::DiscoverSort;
+use crate::{app::get_config_dir, context::detect_project_type};
+
+/// How many directory levels below the search root are scanned
+const MAX_DEPTH: usize = 4;
+
+/// Files or directories that mark a project root
+const PROJECT_MARKERS: &[&str] = &["Cargo.toml", "package.json", "go.mod", ".git"];
+
+/// Directories never worth descending into
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "vendor", "dist", "build", "venv"];
+
+/// A project root found by `mermaid discover`
+#[derive(Debug, Clone)]
+pub struct DiscoveredProject {
+    pub path: PathBuf,
+    pub project_type: String,
+    pub last_modified: DateTime<Local>,
+    /// Whether the project has a `.mermaid/` directory
+    pub has_config: bool,
+}
+
+/// Find project roots up to four levels below `root`, not descending into projects
+pub fn discover_projects(root: &Path) -> Vec<DiscoveredProject> {
+    let mut projects = Vec::new();
+    scan_dir(root, 0, &mut projects);
+    projects
+}
+
+fn scan_dir(dir: &Path, depth: usize, projects: &mut Vec<DiscoveredProject>) {
+    if PROJECT_MARKERS
+        .iter()
+        .any(|marker| dir.join(marker).exists())
+    {
+        projects.push(DiscoveredProject {
+            path: dir.to_path_buf(),
+            project_type: detect_project_type(dir).unwrap_or_else(|| "unknown".to_string()),
+            last_modified: last_modified(dir),
+            has_config: dir.join(".mermaid").is_dir(),
+        });
+        return;
+    }
+    if depth >= MAX_DEPTH {
+        return;
+    }
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+        if is_dir && !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_ref()) {
+            scan_dir(&entry.path(), depth + 1, projects);
+        }
+    }
+}
+
+/// Newest modification time of the directory or its direct entries
+fn last_modified(dir: &Path) -> DateTime<Local> {
+    let entries = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok());
+    fs::metadata(dir)
+        .into_iter()
+        .chain(entries)
+        .filter_map(|m| m.modified().ok())
+        .max()
+        .map(DateTime::from)
+        .unwrap_or_else(Local::now)
+}
+
+fn sort_projects(projects: &mut [DiscoveredProject], sort_by: DiscoverSort) {
+    match sort_by {
+        DiscoverSort::LastModified => projects.sort_by_key(|p| std::cmp::Reverse(p.last_modified)),
+        DiscoverSort::Path => projects.sort_by(|a, b| a.path.cmp(&b.path)),
+        DiscoverSort::Type => projects.sort_by(|a, b| {
+            a.project_type
+                .cmp(&b.project_type)
+                .then_with(|| a.path.cmp(&b.path))
+        }),
+    }
+}
+
+/// Print the projects under `search_path` (or `~`), optionally saving them to the registry
+pub fn run_discover(search_path: Option<&Path>, sort_by: DiscoverSort, save: bool) -> Result<()> {
+    let root = match search_path {
+        Some(path) => path.to_path_buf(),
+        None => PathBuf::from(std::env::var("HOME").context("Could not determine home directory")?),
+    };
+    let mut projects = discover_projects(&root);
+    if projects.is_empty() {
+        println!("No projects found under {}", root.display());
+        return Ok(());
+    }
+    sort_projects(&mut projects, sort_by);
+
+    let path_width = projects
+        .iter()
+        .map(|p| p.path.display().to_string().len())
+        .max()
+        .unwrap_or(0);
+    println!(
+        "{:<path_width$}  {:<12}  {:<16}  {}",
+        "PATH".bold(),
+        "TYPE".bold(),
+        "LAST MODIFIED".bold(),
+        ".mermaid".bold()
+    );
+    for project in &projects {
+        println!(
+            "{:<path_width$}  {:<12}  {:<16}  {}",
+            project.path.display(),
+            project.project_type,
+            project.last_modified.format("%Y-%m-%d %H:%M"),
+            if project.has_config {
+                "yes".green()
+            } else {
+                "no".normal()
+            }
+        );
+    }
+    println!("\n{} projects found", projects.len());
+
+    if save {
+        let path = save_registry(&projects)?;
+        println!(
+            "Saved to {} (open one with --project <name>)",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Get the path to the registry (~/.config/mermaid/projects.toml)
+fn registry_file() -> Result<PathBuf> {
+    Ok(get_config_dir()?.join("projects.toml"))
+}
+
+/// Name each project after its directory, then write `[projects] name = "path"`
+fn save_registry(projects: &[DiscoveredProject]) -> Result<PathBuf> {
+    let path = registry_file()?;
+    let registry = Registry {
+        projects: registry_names(projects),
+    };
+    fs::write(&path, toml::to_string_pretty(&registry)?)?;
+    Ok(path)
+}
+
+/// Directory names, with `-2`, `-3`, ... added to repeats
+fn registry_names(projects: &[DiscoveredProject]) -> BTreeMap<String, PathBuf> {
+    let mut names = BTreeMap::new();
+    for project in projects {
+        let base = project
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "project".to_string());
+        let mut name = base.clone();
+        let mut n = 2;
+        while names.contains_key(&name) {
+            name = format!("{}-{}", base, n);
+            n += 1;
+        }
+        names.insert(name, project.path.clone());
+    }
+    names
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Registry {
+    projects: BTreeMap<String, PathBuf>,
+}
+
+/// Look up a project saved by `mermaid discover --save`
+pub fn resolve_project(name: &str) -> Result<PathBuf> {
+    let path = registry_file()?;
+    let toml_str = fs::read_to_string(&path).with_context(|| {
+        format!(
+            "No project registry at {} - run `mermaid discover --save` first",
+            path.display()
+        )
+    })?;
+    let registry: Registry = toml::from_str(&toml_str)?;
+    registry
+        .projects
+        .get(name)
+        .cloned()
+        .with_context(|| format!("Unknown project: {}", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_discover_projects() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("code/api/.mermaid")).unwrap();
+        fs::write(root.join("code/api/Cargo.toml"), "").unwrap();
+        // Nested inside a project, so not listed separately
+        fs::create_dir_all(root.join("code/api/web")).unwrap();
+        fs::write(root.join("code/api/web/package.json"), "").unwrap();
+        fs::create_dir_all(root.join("other/api/.git")).unwrap();
+        fs::create_dir_all(root.join("a/b/c/d/e")).unwrap();
+        fs::write(root.join("a/b/c/d/e/go.mod"), "").unwrap();
+
+        let mut projects = discover_projects(root);
+        sort_projects(&mut projects, DiscoverSort::Path);
+        assert_eq!(projects.len(), 2);
+        assert_eq!(projects[0].project_type, "rust");
+        assert!(projects[0].has_config);
+        assert_eq!(projects[1].project_type, "unknown");
+        assert!(!projects[1].has_config);
+
+        let names = registry_names(&projects);
+        assert_eq!(names["api"], root.join("code/api"));
+        assert_eq!(names["api-2"], root.join("other/api"));
+    }
+}
@@ -0,0 +1,334 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::Colorize;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::{
+    app::{get_config_dir, load_config, Config},
+    cache::CacheManager,
+    constants::DEFAULT_LITELLM_PROXY_URL,
+    diagnostics::HardwareMonitor,
+    ollama::{is_installed as is_ollama_installed, list_models_async},
+};
+
+/// Free space in the cache directory below which the disk check warns
+const LOW_DISK_BYTES: u64 = 1024 * 1024 * 1024;
+/// Free space below which the disk check fails
+const CRITICAL_DISK_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Outcome of a single doctor check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    fn label(&self) -> String {
+        match self {
+            CheckStatus::Pass => "[PASS]".green().to_string(),
+            CheckStatus::Warn => "[WARN]".yellow().to_string(),
+            CheckStatus::Fail => "[FAIL]".red().to_string(),
+        }
+    }
+}
+
+/// Result of one check, e.g. proxy reachability
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl CheckResult {
+    fn new(name: &str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Report produced by `mermaid doctor`, shareable as JSON in bug reports
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorReport {
+    pub version: String,
+    pub generated_at: DateTime<Local>,
+    pub checks: Vec<CheckResult>,
+}
+
+impl DoctorReport {
+    /// Human-readable report, one line per check
+    pub fn format(&self) -> String {
+        let mut out = format!("Mermaid Doctor (v{})\n\n", self.version);
+        for check in &self.checks {
+            out.push_str(&format!(
+                "  {} {}: {}\n",
+                check.status.label(),
+                check.name,
+                check.detail
+            ));
+        }
+        let count = |status| self.checks.iter().filter(|c| c.status == status).count();
+        out.push_str(&format!(
+            "\n  {} passed, {} warnings, {} failed",
+            count(CheckStatus::Pass),
+            count(CheckStatus::Warn),
+            count(CheckStatus::Fail)
+        ));
+        out
+    }
+}
+
+/// Run every check and print the report, writing it as JSON to `output` if given
+pub async fn run_doctor(output: Option<&Path>) -> Result<()> {
+    let mut checks = vec![check_proxy().await, check_ollama().await];
+    checks.extend(check_config_layers());
+    let (cache_check, cache_dir) = check_cache();
+    checks.push(cache_check);
+    checks.push(check_gpu());
+    checks.push(check_disk_space(cache_dir.as_deref()));
+
+    let report = DoctorReport {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        generated_at: Local::now(),
+        checks,
+    };
+    println!("{}", report.format());
+
+    if let Some(path) = output {
+        std::fs::write(path, serde_json::to_string_pretty(&report)?)?;
+        println!("\nReport written to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Proxy reachability and the LiteLLM version it reports
+async fn check_proxy() -> CheckResult {
+    let name = "LiteLLM proxy";
+    let proxy_url = std::env::var("LITELLM_PROXY_URL")
+        .unwrap_or_else(|_| DEFAULT_LITELLM_PROXY_URL.to_string());
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(2))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => return CheckResult::new(name, CheckStatus::Fail, e.to_string()),
+    };
+
+    match client
+        .get(format!("{}/health/readiness", proxy_url))
+        .send()
+        .await
+    {
+        Ok(resp) if resp.status().is_success() => {
+            let body: serde_json::Value = resp.json().await.unwrap_or_default();
+            let version = body["litellm_version"].as_str().unwrap_or("unknown");
+            CheckResult::new(
+                name,
+                CheckStatus::Pass,
+                format!("Reachable at {} (version {})", proxy_url, version),
+            )
+        },
+        Ok(resp) => CheckResult::new(
+            name,
+            CheckStatus::Warn,
+            format!("{} responded with {}", proxy_url, resp.status()),
+        ),
+        Err(_) => CheckResult::new(
+            name,
+            CheckStatus::Fail,
+            format!("Not reachable at {}", proxy_url),
+        ),
+    }
+}
+
+/// Ollama installation and installed models
+async fn check_ollama() -> CheckResult {
+    let name = "Ollama";
+    if !is_ollama_installed() {
+        return CheckResult::new(
+            name,
+            CheckStatus::Warn,
+            "Not installed (local models unavailable)",
+        );
+    }
+    match list_models_async().await {
+        Ok(models) if models.is_empty() => {
+            CheckResult::new(name, CheckStatus::Warn, "Installed, but no models pulled")
+        },
+        Ok(models) => CheckResult::new(
+            name,
+            CheckStatus::Pass,
+            format!("{} models installed: {}", models.len(), models.join(", ")),
+        ),
+        Err(e) => CheckResult::new(
+            name,
+            CheckStatus::Warn,
+            format!("Installed, but not responding: {}", e),
+        ),
+    }
+}
+
+/// Parse the global and project config files on their own, then the merged result
+fn check_config_layers() -> Vec<CheckResult> {
+    let global = get_config_dir().map(|dir| dir.join("config.toml")).ok();
+    let layers = [
+        ("Config (global)", global),
+        (
+            "Config (project)",
+            Some(PathBuf::from(".mermaid/config.toml")),
+        ),
+    ];
+
+    let mut checks: Vec<CheckResult> = layers
+        .into_iter()
+        .map(|(name, path)| match path {
+            Some(path) if path.exists() => match std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|toml_str| Ok(toml::from_str::<Config>(&toml_str)?))
+            {
+                Ok(_) => CheckResult::new(name, CheckStatus::Pass, path.display().to_string()),
+                Err(e) => CheckResult::new(
+                    name,
+                    CheckStatus::Fail,
+                    format!("{}: {}", path.display(), e),
+                ),
+            },
+            Some(path) => CheckResult::new(
+                name,
+                CheckStatus::Pass,
+                format!("{} not present (defaults used)", path.display()),
+            ),
+            None => CheckResult::new(name, CheckStatus::Warn, "Config directory unavailable"),
+        })
+        .collect();
+
+    // Environment overrides only show up once every layer is merged
+    checks.push(match load_config() {
+        Ok(_) => CheckResult::new("Config (merged)", CheckStatus::Pass, "Valid"),
+        Err(e) => CheckResult::new("Config (merged)", CheckStatus::Fail, format!("{:#}", e)),
+    });
+    checks
+}
+
+/// Open the cache and read one entry, returning the cache directory for the disk check
+fn check_cache() -> (CheckResult, Option<PathBuf>) {
+    let name = "Cache";
+    let cache = match CacheManager::new() {
+        Ok(cache) => cache,
+        Err(e) => {
+            return (
+                CheckResult::new(name, CheckStatus::Fail, e.to_string()),
+                None,
+            )
+        },
+    };
+    let stats = match cache.get_stats() {
+        Ok(stats) => stats,
+        Err(e) => {
+            return (
+                CheckResult::new(name, CheckStatus::Fail, e.to_string()),
+                None,
+            )
+        },
+    };
+    let location = stats.cache_directory.display().to_string();
+    let check = match cache.sample_entry() {
+        Ok(Some(_)) => CheckResult::new(
+            name,
+            CheckStatus::Pass,
+            format!(
+                "{} entries, readable ({})",
+                stats.file_cache_entries, location
+            ),
+        ),
+        Ok(None) => CheckResult::new(name, CheckStatus::Pass, format!("Empty ({})", location)),
+        Err(e) => CheckResult::new(
+            name,
+            CheckStatus::Warn,
+            format!("Unreadable entry in {}: {}", location, e),
+        ),
+    };
+    (check, Some(stats.cache_directory))
+}
+
+/// GPU availability for local inference
+fn check_gpu() -> CheckResult {
+    let name = "GPU";
+    match HardwareMonitor::new().get_stats() {
+        Ok(stats) => match stats.gpu {
+            Some(gpu) => CheckResult::new(
+                name,
+                CheckStatus::Pass,
+                format!("{} ({:.1} GB VRAM)", gpu.name, gpu.memory_total_gb),
+            ),
+            None => CheckResult::new(
+                name,
+                CheckStatus::Warn,
+                "No GPU detected (local models run on CPU)",
+            ),
+        },
+        Err(e) => CheckResult::new(name, CheckStatus::Warn, e.to_string()),
+    }
+}
+
+/// Free space on the disk holding the cache directory
+fn check_disk_space(cache_dir: Option<&Path>) -> CheckResult {
+    let name = "Disk space";
+    let Some(cache_dir) = cache_dir else {
+        return CheckResult::new(name, CheckStatus::Warn, "Cache directory unknown");
+    };
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    let disk = disks
+        .iter()
+        .filter(|disk| cache_dir.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len());
+    let Some(disk) = disk else {
+        return CheckResult::new(name, CheckStatus::Warn, "Could not find the cache disk");
+    };
+
+    let available = disk.available_space();
+    let status = if available < CRITICAL_DISK_BYTES {
+        CheckStatus::Fail
+    } else if available < LOW_DISK_BYTES {
+        CheckStatus::Warn
+    } else {
+        CheckStatus::Pass
+    };
+    CheckResult::new(
+        name,
+        status,
+        format!(
+            "{:.1} GB free on {}",
+            available as f64 / 1_073_741_824.0,
+            disk.mount_point().display()
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_json_and_summary() {
+        let report = DoctorReport {
+            version: "0.1.0".to_string(),
+            generated_at: Local::now(),
+            checks: vec![
+                CheckResult::new("Cache", CheckStatus::Pass, "Empty"),
+                CheckResult::new("GPU", CheckStatus::Warn, "No GPU detected"),
+            ],
+        };
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["checks"][1]["status"], "WARN");
+        assert!(report.format().ends_with("1 passed, 1 warnings, 0 failed"));
+    }
+}
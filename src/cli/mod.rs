@@ -1,6 +1,12 @@
 /// CLI argument parsing and command handling - Gateway
 mod args;
 mod commands;
+mod discover;
+mod doctor;
 
-pub use args::{Cli, Commands, OutputFormat};
+pub use args::{
+    Cli, Commands, ConfigCommands, ConversationCommands, DiscoverSort, IndexCommands, OutputFormat,
+};
 pub use commands::{handle_command, list_models, show_version};
+pub use discover::{discover_projects, resolve_project, DiscoveredProject};
+pub use doctor::{CheckResult, CheckStatus, DoctorReport};
@@ -13,6 +13,8 @@ pub const PROXY_STARTUP_WAIT_SECS: u64 = 3;
 pub const PROXY_CHECK_INTERVAL_SECS: u64 = 1;
 pub const PROXY_POLL_INTERVAL_MS: u64 = 100; // Fast polling for proxy startup
 pub const PROXY_MAX_STARTUP_ATTEMPTS: usize = 10;
+pub const PROXY_HEALTH_CHECK_INTERVAL_SECS: u64 = 30;
+pub const PROXY_MAX_RESTART_ATTEMPTS: u32 = 3; // Consecutive failures before giving up
 
 // UI Configuration
 pub const UI_REFRESH_INTERVAL_MS: u64 = 50;
@@ -0,0 +1,152 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::cell::RefCell;
+use std::path::Path;
+
+use super::spec_summary::file_content;
+use super::tree_parser::TreeParser;
+use crate::models::FileContent;
+
+/// Whole-line `//` comments and `/* */` blocks that start a line
+static SLASH_COMMENTS: Lazy<Vec<Regex>> = Lazy::new(|| {
+    vec![
+        Regex::new(r"(?m)^[ \t]*//.*$").unwrap(),
+        Regex::new(r"(?ms)^[ \t]*/\*.*?\*/").unwrap(),
+    ]
+});
+
+/// Whole-line `#` comments
+static HASH_COMMENTS: Lazy<Vec<Regex>> =
+    Lazy::new(|| vec![Regex::new(r"(?m)^[ \t]*#.*$").unwrap()]);
+
+/// Whole-line `--` comments
+static DASH_COMMENTS: Lazy<Vec<Regex>> =
+    Lazy::new(|| vec![Regex::new(r"(?m)^[ \t]*--.*$").unwrap()]);
+
+thread_local! {
+    /// Building the grammars is slow, so each loader thread keeps its parser
+    static PARSER: RefCell<Option<TreeParser>> = RefCell::new(TreeParser::new().ok());
+}
+
+/// Source without its comments, or None for files that aren't code
+/// Languages with a tree-sitter grammar lose every comment node; others only lose
+/// comments on lines of their own, so markers inside strings are left alone
+/// Either way a leading `#!` shebang is kept, since it says how to run the file
+pub fn strip_comments(path: &Path, content: &str) -> Option<String> {
+    let mut ranges = PARSER
+        .with(|parser| {
+            parser
+                .borrow_mut()
+                .as_mut()
+                .and_then(|parser| parser.comment_ranges(path, content))
+        })
+        .or_else(|| line_comment_ranges(path, content))?;
+    if content.starts_with("#!") {
+        ranges.retain(|&(start, _)| start != 0);
+    }
+    Some(remove_ranges(content, &ranges))
+}
+
+/// Context entry for source whose comments were stripped; spec files are still
+/// summarized, anything else is marked so it isn't written back over the original
+pub fn stripped_file_content(path: &Path, stripped: String) -> FileContent {
+    match file_content(path, stripped) {
+        FileContent::Raw(text) => FileContent::CommentsStripped(text),
+        summary => summary,
+    }
+}
+
+/// Byte ranges of comments matched by the patterns for the file's extension
+fn line_comment_ranges(path: &Path, content: &str) -> Option<Vec<(usize, usize)>> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    let patterns = match extension.as_str() {
+        "c" | "h" | "cpp" | "hpp" | "cc" | "cxx" | "cs" | "go" | "java" | "kt" | "scala"
+        | "swift" | "php" | "css" | "scss" => &SLASH_COMMENTS,
+        "sh" | "bash" | "rb" | "r" | "yaml" | "yml" | "toml" => &HASH_COMMENTS,
+        "sql" => &DASH_COMMENTS,
+        _ => return None,
+    };
+    let mut ranges: Vec<(usize, usize)> = patterns
+        .iter()
+        .flat_map(|re| re.find_iter(content).map(|m| (m.start(), m.end())))
+        .collect();
+    ranges.sort_unstable();
+    Some(ranges)
+}
+
+/// Copy of `content` without the given ranges (sorted by start), along with the
+/// whitespace before each one; a comment alone on its line takes the line break too
+fn remove_ranges(content: &str, ranges: &[(usize, usize)]) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut copied = 0;
+    for &(start, end) in ranges {
+        // Ranges nested in one already removed
+        if start < copied {
+            continue;
+        }
+        let end = start + content[start..end].trim_end_matches(['\r', '\n']).len();
+        let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+        let from = line_start.max(copied);
+        let cut_start = from + content[from..start].trim_end_matches([' ', '\t']).len();
+        let mut cut_end = end;
+        if content[line_start..start].trim().is_empty() {
+            let rest = &content[end..];
+            if rest.starts_with("\r\n") {
+                cut_end += 2;
+            } else if rest.starts_with('\n') {
+                cut_end += 1;
+            }
+        }
+        out.push_str(&content[copied..cut_start]);
+        copied = cut_end;
+    }
+    out.push_str(&content[copied..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_comments() {
+        let rust = "/// Adds one\n\
+                    fn inc(x: i32) -> i32 {\n    \
+                        // The answer\n    \
+                        x + 1 /* overflow? */ // trailing\n\
+                    }\n\
+                    const URL: &str = \"http://example.com\";\n";
+        assert_eq!(
+            strip_comments(Path::new("lib.rs"), rust).unwrap(),
+            "fn inc(x: i32) -> i32 {\n    x + 1\n}\nconst URL: &str = \"http://example.com\";\n"
+        );
+
+        let python = "#!/usr/bin/env python\n# Entry point\nprint('# not a comment')  # greet\n";
+        assert_eq!(
+            strip_comments(Path::new("main.py"), python).unwrap(),
+            "#!/usr/bin/env python\nprint('# not a comment')\n"
+        );
+
+        // No grammar for Go, so whole-line comments go and the URL stays
+        let go = "package main\n\n/*\n * Server\n */\n// Port to use\nvar url = \"http://x\"\n";
+        assert_eq!(
+            strip_comments(Path::new("main.go"), go).unwrap(),
+            "package main\n\nvar url = \"http://x\"\n"
+        );
+        // Shebangs are kept with or without a grammar
+        let shell = "#!/bin/sh\n  # Build\nmake\n";
+        assert_eq!(
+            strip_comments(Path::new("build.sh"), shell).unwrap(),
+            "#!/bin/sh\nmake\n"
+        );
+        assert!(strip_comments(Path::new("README.md"), "# Title\n").is_none());
+    }
+
+    #[test]
+    fn test_stripped_file_content_is_marked() {
+        assert_eq!(
+            stripped_file_content(Path::new("lib.rs"), "fn main() {}\n".to_string()),
+            FileContent::CommentsStripped("fn main() {}\n".to_string())
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use serde_json::Value as JsonValue;
+use toml::Value as TomlValue;
+
+/// Root manifests whose dependencies are summarized, in summary order
+pub const DEPENDENCY_MANIFESTS: &[&str] = &["Cargo.toml", "package.json", "requirements.txt"];
+
+/// Summarize the dependencies declared in a manifest as `name = version` pairs
+/// `manifest` is the file name, one of `DEPENDENCY_MANIFESTS`
+pub fn summarize_dependencies(manifest: &str, content: &str) -> Option<String> {
+    let sections = match manifest {
+        "Cargo.toml" => cargo_dependencies(content)?,
+        "package.json" => npm_dependencies(content)?,
+        "requirements.txt" => vec![("dependencies", python_requirements(content))],
+        _ => return None,
+    };
+
+    let lines: Vec<String> = sections
+        .into_iter()
+        .filter(|(_, deps)| !deps.is_empty())
+        .map(|(section, deps)| format!("{} {}: {}", manifest, section, deps.join(", ")))
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+type Sections = Vec<(&'static str, Vec<String>)>;
+
+fn cargo_dependencies(content: &str) -> Option<Sections> {
+    let manifest: TomlValue = toml::from_str(content).ok()?;
+    let section = |name: &str| -> Vec<String> {
+        manifest
+            .get(name)
+            .and_then(|deps| deps.as_table())
+            .map(|deps| {
+                deps.iter()
+                    .map(|(name, spec)| format!("{} = {}", name, cargo_version(spec)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    Some(vec![
+        ("dependencies", section("dependencies")),
+        ("dev-dependencies", section("dev-dependencies")),
+    ])
+}
+
+/// Version of a Cargo dependency, or where it comes from when unversioned
+fn cargo_version(spec: &TomlValue) -> String {
+    if let Some(version) = spec.as_str() {
+        return version.to_string();
+    }
+    let field = |key: &str| spec.get(key).and_then(|v| v.as_str());
+    match (field("version"), field("path"), field("git")) {
+        (Some(version), _, _) => version.to_string(),
+        (None, Some(path), _) => format!("path {}", path),
+        (None, None, Some(git)) => format!("git {}", git),
+        // `workspace = true` and similar
+        _ => "*".to_string(),
+    }
+}
+
+fn npm_dependencies(content: &str) -> Option<Sections> {
+    let package: JsonValue = serde_json::from_str(content).ok()?;
+    let section = |name: &str| -> Vec<String> {
+        package[name]
+            .as_object()
+            .map(|deps| {
+                deps.iter()
+                    .map(|(name, version)| {
+                        format!("{} = {}", name, version.as_str().unwrap_or("*"))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    Some(vec![
+        ("dependencies", section("dependencies")),
+        ("devDependencies", section("devDependencies")),
+    ])
+}
+
+fn python_requirements(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        // Skip blanks and pip options such as `-r other.txt`
+        .filter(|line| !line.is_empty() && !line.starts_with('-'))
+        .map(|line| {
+            let split = line.find(|c: char| "=<>~!;[ ".contains(c));
+            match split {
+                Some(i) => {
+                    let constraint = line[i..].split(';').next().unwrap_or_default().trim();
+                    let constraint = constraint.trim_start_matches('=').trim();
+                    let constraint = if constraint.is_empty() {
+                        "*"
+                    } else {
+                        constraint
+                    };
+                    format!("{} = {}", &line[..i], constraint)
+                },
+                None => format!("{} = *", line),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_manifests() {
+        let cargo = r#"
+[package]
+name = "demo"
+
+[dependencies]
+serde = { version = "1.0", features = ["derive"] }
+tokio = "1.42"
+local = { path = "../local" }
+
+[dev-dependencies]
+tempfile = "3.14"
+"#;
+        assert_eq!(
+            summarize_dependencies("Cargo.toml", cargo).unwrap(),
+            "Cargo.toml dependencies: local = path ../local, serde = 1.0, tokio = 1.42\n\
+             Cargo.toml dev-dependencies: tempfile = 3.14"
+        );
+
+        let requirements =
+            "# web\nflask==3.0.0\nrequests>=2.31 ; python_version > '3.8'\nnumpy\n-r dev.txt\n";
+        assert_eq!(
+            summarize_dependencies("requirements.txt", requirements).unwrap(),
+            "requirements.txt dependencies: flask = 3.0.0, requests = >=2.31, numpy = *"
+        );
+    }
+}
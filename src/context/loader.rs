@@ -1,14 +1,36 @@
 use anyhow::{Context, Result};
-use ignore::{DirEntry, WalkBuilder};
+use ignore::{gitignore::Gitignore, overrides::OverrideBuilder, DirEntry, WalkBuilder};
 use rayon::prelude::*;
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use tiktoken_rs::{cl100k_base, CoreBPE};
 
+use super::comments::{strip_comments, stripped_file_content};
+use super::dependencies::DEPENDENCY_MANIFESTS;
+use super::env_file::is_env_file;
+use super::spec_summary::file_content;
+use crate::agents::allow_workspace_root;
+use crate::app::load_config;
 use crate::cache::CacheManager;
-use crate::models::ProjectContext;
+use crate::models::{FileContent, ProjectContext};
+
+/// Per-directory file of gitignore-style patterns hidden from the model
+pub const MERMAID_IGNORE_FILE: &str = ".mermaid_ignore";
+
+/// Bytes inspected when deciding whether a file is binary
+const BINARY_SNIFF_BYTES: usize = 8 * 1024;
+
+/// Signatures of common binary formats: ELF, PDF, PNG and ZIP (also jar, docx, ...)
+const BINARY_MAGIC: &[&[u8]] = &[b"\x7fELF", b"%PDF", b"\x89PNG", b"PK\x03\x04"];
+
+/// Share of control characters above which content counts as binary
+const MAX_NON_PRINTABLE_RATIO: f64 = 0.10;
+
+/// Progress callback invoked with (files_processed, total_files)
+pub type ProgressCallback = Arc<dyn Fn(usize, usize) + Send + Sync>;
 
 /// Configuration for the context loader
 #[derive(Debug, Clone)]
@@ -23,6 +45,14 @@ pub struct LoaderConfig {
     pub priority_extensions: Vec<String>,
     /// Additional patterns to ignore
     pub ignore_patterns: Vec<String>,
+    /// Gitignore-style patterns excluded from context, from `[context.ignore]`
+    pub excluded_paths: Vec<String>,
+    /// Other directories loaded into the same context, e.g. monorepo packages
+    pub additional_roots: Vec<PathBuf>,
+    /// Load `.env` files (even gitignored ones) with secret values redacted
+    pub env_file_support: bool,
+    /// Remove comments from source files to save tokens
+    pub strip_comments: bool,
 }
 
 impl Default for LoaderConfig {
@@ -52,6 +82,7 @@ impl Default for LoaderConfig {
                 "scala".to_string(),
                 "r".to_string(),
                 "sql".to_string(),
+                "proto".to_string(),
                 "sh".to_string(),
                 "yaml".to_string(),
                 "yml".to_string(),
@@ -92,6 +123,10 @@ impl Default for LoaderConfig {
                 "*.rar".to_string(),
                 "*.7z".to_string(),
             ],
+            excluded_paths: Vec::new(),
+            additional_roots: Vec::new(),
+            env_file_support: true,
+            strip_comments: false,
         }
     }
 }
@@ -101,16 +136,30 @@ pub struct ContextLoader {
     config: LoaderConfig,
     tokenizer: CoreBPE,
     cache: Option<Arc<CacheManager>>,
+    progress: Option<ProgressCallback>,
+    /// Tokens saved so far by `strip_comments`
+    stripped_tokens: AtomicUsize,
 }
 
 impl ContextLoader {
-    /// Create a new context loader with default config
+    /// Create a new context loader with default config plus the configured
+    /// ignore patterns and additional roots
     pub fn new() -> Result<Self> {
         let cache = CacheManager::new().ok().map(Arc::new);
+        let context_config = load_config().map(|c| c.context).unwrap_or_default();
+        let config = LoaderConfig {
+            excluded_paths: context_config.ignore.patterns,
+            additional_roots: context_config.additional_roots,
+            env_file_support: context_config.env_file_support,
+            strip_comments: context_config.strip_comments,
+            ..LoaderConfig::default()
+        };
         Ok(Self {
-            config: LoaderConfig::default(),
+            config,
             tokenizer: cl100k_base()?,
             cache,
+            progress: None,
+            stripped_tokens: AtomicUsize::new(0),
         })
     }
 
@@ -121,9 +170,28 @@ impl ContextLoader {
             config,
             tokenizer: cl100k_base()?,
             cache,
+            progress: None,
+            stripped_tokens: AtomicUsize::new(0),
         })
     }
 
+    /// Tokens removed by comment stripping in the files loaded so far
+    pub fn stripped_comment_tokens(&self) -> usize {
+        self.stripped_tokens.load(Ordering::Relaxed)
+    }
+
+    /// Also load files from these directories, e.g. from `--root`
+    pub fn with_additional_roots(mut self, roots: impl IntoIterator<Item = PathBuf>) -> Self {
+        self.config.additional_roots.extend(roots);
+        self
+    }
+
+    /// Report file collection progress through the given callback
+    pub fn with_progress_callback(mut self, callback: ProgressCallback) -> Self {
+        self.progress = Some(callback);
+        self
+    }
+
     /// Load project context from the given path (alias for compatibility)
     pub fn load(&self, root_path: &Path) -> Result<ProjectContext> {
         self.load_context(root_path)
@@ -136,7 +204,9 @@ impl ContextLoader {
 
         // Create lazy context with just paths
         let lazy_context =
-            crate::models::LazyProjectContext::new(root_path.to_string_lossy().to_string(), files);
+            crate::models::LazyProjectContext::new(root_path.to_string_lossy().to_string(), files)
+                .with_priority_extensions(&self.config.priority_extensions)
+                .with_comment_stripping(self.config.strip_comments);
 
         Ok(lazy_context)
     }
@@ -150,16 +220,143 @@ impl ContextLoader {
 
         // Collect all files using the ignore crate
         let files = self.collect_files(root_path)?;
+        let loaded_contents = self.load_files(&files, root_path, None, &context);
+
+        // Add all loaded files to context
+        let mut actual_total_tokens = 0;
+        for (path, content, tokens) in loaded_contents {
+            context.add_file(path, content);
+            actual_total_tokens += tokens;
+        }
+
+        context.token_count = actual_total_tokens;
+
+        // Auto-include important files
+        self.auto_include_important_files(&mut context, root_path);
+
+        self.merge_additional_roots(&mut context)?;
+
+        Ok(context)
+    }
+
+    /// Load each additional root (with its own ignore files) into `context`, keyed by
+    /// absolute path so file actions can find them, and let file actions reach the root
+    /// Returns the number of files added
+    pub fn merge_additional_roots(&self, context: &mut ProjectContext) -> Result<usize> {
+        let mut added = 0;
+        for root in &self.config.additional_roots {
+            let root = root
+                .canonicalize()
+                .with_context(|| format!("Additional root not found: {}", root.display()))?;
+            allow_workspace_root(root.clone());
+            let prefix = root.to_string_lossy();
+            let files = self.collect_files(&root)?;
+            for (path, content, tokens) in self.load_files(&files, &root, Some(&prefix), context) {
+                context.add_file(path, content);
+                context.token_count += tokens;
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
 
+    /// Apply file changes to an already loaded context, reading only the changed files
+    /// Changed files a full load would leave out (ignored, too large, over the limits) are skipped
+    /// Returns the entry each touched path had before, None for paths it added
+    pub fn update_context(
+        &self,
+        root_path: &Path,
+        context: &mut ProjectContext,
+        changed: &[PathBuf],
+        deleted: &[PathBuf],
+    ) -> Result<Vec<(String, Option<FileContent>)>> {
+        let mut previous = Vec::new();
+        let absolute_root = root_path.canonicalize().unwrap_or(root_path.to_path_buf());
+        let relative = |path: &Path| {
+            path.strip_prefix(root_path)
+                .or_else(|_| path.strip_prefix(&absolute_root))
+                .unwrap_or(path)
+                .to_string_lossy()
+                .to_string()
+        };
+
+        // A deleted directory takes its files with it
+        for path in deleted {
+            let key = relative(path);
+            let dir_prefix = format!("{}/", key);
+            let removed: Vec<String> = context
+                .files
+                .keys()
+                .filter(|p| **p == key || p.starts_with(&dir_prefix))
+                .cloned()
+                .collect();
+            for path in removed {
+                if let Some(content) = context.files.remove(&path) {
+                    let tokens = self.count_tokens(content.text());
+                    context.token_count = context.token_count.saturating_sub(tokens);
+                    previous.push((path, Some(content)));
+                }
+            }
+        }
+        if changed.is_empty() {
+            return Ok(previous);
+        }
+
+        let wanted: Vec<PathBuf> = changed
+            .iter()
+            .map(|path| root_path.join(relative(path)))
+            .collect();
+        let included: HashSet<String> = self
+            .collect_files_in(root_path, Some(&wanted))?
+            .iter()
+            .map(|path| relative(path))
+            .collect();
+        for path in changed {
+            let key = relative(path);
+            if !included.contains(&key) {
+                continue;
+            }
+            let Ok(content) = self.load_file(&root_path.join(&key)) else {
+                continue;
+            };
+            let tokens = self.count_tokens(content.text());
+            match context.files.get(&key) {
+                Some(previous) => {
+                    let previous_tokens = self.count_tokens(previous.text());
+                    context.token_count = context.token_count.saturating_sub(previous_tokens);
+                },
+                None if context.files.len() >= self.config.max_files
+                    || context.token_count + tokens > self.config.max_context_tokens =>
+                {
+                    continue
+                },
+                None => {},
+            }
+            context.token_count += tokens;
+            previous.push((key.clone(), context.files.get(&key).cloned()));
+            context.add_file(key, content);
+        }
+        Ok(previous)
+    }
+
+    /// Read files in parallel until the file or token limit is reached, counting
+    /// what `context` already holds, and key them by path relative to `root_path`
+    fn load_files(
+        &self,
+        files: &[PathBuf],
+        root_path: &Path,
+        prefix: Option<&str>,
+        context: &ProjectContext,
+    ) -> Vec<(String, FileContent, usize)> {
         // Use atomic counters for thread-safe tracking
-        let total_tokens = Arc::new(AtomicUsize::new(0));
-        let loaded_files = Arc::new(AtomicUsize::new(0));
+        let total_tokens = Arc::new(AtomicUsize::new(context.token_count));
+        let loaded_files = Arc::new(AtomicUsize::new(context.files.len()));
 
         // Create a shared tokenizer for all threads
         let tokenizer = Arc::new(self.tokenizer.clone());
 
         // Process files in parallel and collect results
-        let loaded_contents: Vec<(String, String, usize)> = files
+        files
             .par_iter()
             .filter_map(|file_path| {
                 // Check if we've hit the file limit
@@ -170,7 +367,7 @@ impl ContextLoader {
                 // Try to load the file
                 if let Ok(content) = self.load_file(file_path) {
                     // Count tokens using the shared tokenizer
-                    let tokens = tokenizer.encode_with_special_tokens(&content).len();
+                    let tokens = tokenizer.encode_with_special_tokens(content.text()).len();
 
                     // Check if adding this file would exceed token limit
                     let current_total = total_tokens.load(Ordering::Relaxed);
@@ -187,31 +384,27 @@ impl ContextLoader {
                         .unwrap_or(file_path)
                         .to_string_lossy()
                         .to_string();
+                    let relative_path = match prefix {
+                        Some(prefix) => format!("{}/{}", prefix, relative_path),
+                        None => relative_path,
+                    };
 
                     Some((relative_path, content, tokens))
                 } else {
                     None
                 }
             })
-            .collect();
-
-        // Add all loaded files to context
-        let mut actual_total_tokens = 0;
-        for (path, content, tokens) in loaded_contents {
-            context.add_file(path, content);
-            actual_total_tokens += tokens;
-        }
-
-        context.token_count = actual_total_tokens;
-
-        // Auto-include important files
-        self.auto_include_important_files(&mut context, root_path);
-
-        Ok(context)
+            .collect()
     }
 
     /// Collect all relevant files from the project
     fn collect_files(&self, root_path: &Path) -> Result<Vec<PathBuf>> {
+        self.collect_files_in(root_path, None)
+    }
+
+    /// Collect the relevant files, walking only towards `only` when given so a few
+    /// changed files are checked against the same ignore rules without a full walk
+    fn collect_files_in(&self, root_path: &Path, only: Option<&[PathBuf]>) -> Result<Vec<PathBuf>> {
         let mut files = Vec::new();
         let mut priority_files = Vec::new();
         let mut other_files = Vec::new();
@@ -231,35 +424,75 @@ impl ContextLoader {
         for pattern in &self.config.ignore_patterns {
             walker.add_custom_ignore_filename(pattern);
         }
+        walker.add_custom_ignore_filename(MERMAID_IGNORE_FILE);
+
+        // Patterns from config are negated overrides, so they only ever exclude
+        let mut overrides = OverrideBuilder::new(root_path);
+        for pattern in &self.config.excluded_paths {
+            overrides
+                .add(&format!("!{}", pattern))
+                .with_context(|| format!("Invalid context ignore pattern: {}", pattern))?;
+        }
+        let overrides = overrides.build()?;
+        if !self.config.excluded_paths.is_empty() {
+            walker.overrides(overrides.clone());
+        }
+        if let Some(only) = only {
+            let wanted: HashSet<PathBuf> = only
+                .iter()
+                .flat_map(|path| path.ancestors().map(Path::to_path_buf))
+                .collect();
+            walker.filter_entry(move |entry| wanted.contains(entry.path()));
+        }
 
         // Walk the directory
+        let mut entries = Vec::new();
         for result in walker.build() {
             let entry = result?;
 
-            if !self.should_include_entry(&entry) {
-                continue;
+            if self.should_include_entry(&entry) && entry.path().is_file() {
+                entries.push(entry.into_path());
             }
+        }
 
-            let path = entry.path();
-            if path.is_file() {
-                // Check file size
-                if let Ok(metadata) = fs::metadata(path) {
-                    if metadata.len() > self.config.max_file_size as u64 {
-                        continue;
-                    }
+        // A gitignored .env is skipped by the walk, but the model can still see it redacted
+        let env_path = root_path.join(".env");
+        if !self.config.env_file_support {
+            entries.retain(|path| !is_env_file(path));
+        } else if env_path.is_file()
+            && !entries.contains(&env_path)
+            && !overrides.matched(&env_path, false).is_ignore()
+            && !Gitignore::new(root_path.join(MERMAID_IGNORE_FILE))
+                .0
+                .matched(&env_path, false)
+                .is_ignore()
+        {
+            entries.push(env_path);
+        }
+
+        let total_files = entries.len();
+        for (processed, path) in entries.iter().enumerate() {
+            if let Some(ref progress) = self.progress {
+                progress(processed + 1, total_files);
+            }
+
+            // Check file size
+            if let Ok(metadata) = fs::metadata(path) {
+                if metadata.len() > self.config.max_file_size as u64 {
+                    continue;
                 }
+            }
 
-                // Prioritize certain extensions
-                if let Some(ext) = path.extension() {
-                    let ext_str = ext.to_string_lossy().to_lowercase();
-                    if self.config.priority_extensions.contains(&ext_str) {
-                        priority_files.push(path.to_path_buf());
-                    } else {
-                        other_files.push(path.to_path_buf());
-                    }
+            // Prioritize certain extensions
+            if let Some(ext) = path.extension() {
+                let ext_str = ext.to_string_lossy().to_lowercase();
+                if self.config.priority_extensions.contains(&ext_str) {
+                    priority_files.push(path.to_path_buf());
                 } else {
                     other_files.push(path.to_path_buf());
                 }
+            } else {
+                other_files.push(path.to_path_buf());
             }
         }
 
@@ -312,9 +545,29 @@ impl ContextLoader {
         true
     }
 
-    /// Load a single file
-    fn load_file(&self, path: &Path) -> Result<String> {
-        fs::read_to_string(path).with_context(|| format!("Failed to read file: {}", path.display()))
+    /// Load a single file, summarizing spec files
+    /// Binary files are rejected by content, whatever their extension
+    fn load_file(&self, path: &Path) -> Result<FileContent> {
+        let bytes =
+            fs::read(path).with_context(|| format!("Failed to read file: {}", path.display()))?;
+        if is_binary(&bytes) {
+            anyhow::bail!("Skipping binary file: {}", path.display());
+        }
+        let content = String::from_utf8(bytes)
+            .with_context(|| format!("File is not valid UTF-8: {}", path.display()))?;
+        if !self.config.strip_comments {
+            return Ok(file_content(path, content));
+        }
+        match strip_comments(path, &content) {
+            Some(stripped) => {
+                let saved = self
+                    .count_tokens(&content)
+                    .saturating_sub(self.count_tokens(&stripped));
+                self.stripped_tokens.fetch_add(saved, Ordering::Relaxed);
+                Ok(stripped_file_content(path, stripped))
+            },
+            None => Ok(file_content(path, content)),
+        }
     }
 
     /// Count tokens in a string
@@ -324,31 +577,7 @@ impl ContextLoader {
 
     /// Detect the project type based on configuration files
     fn detect_project_type(&self, root_path: &Path) -> Option<String> {
-        let checks = [
-            ("Cargo.toml", "rust"),
-            ("package.json", "javascript"),
-            ("requirements.txt", "python"),
-            ("setup.py", "python"),
-            ("pyproject.toml", "python"),
-            ("go.mod", "go"),
-            ("pom.xml", "java"),
-            ("build.gradle", "java"),
-            ("composer.json", "php"),
-            ("Gemfile", "ruby"),
-            ("mix.exs", "elixir"),
-            ("project.clj", "clojure"),
-            ("build.sbt", "scala"),
-            ("Package.swift", "swift"),
-            ("tsconfig.json", "typescript"),
-        ];
-
-        for (file, project_type) in &checks {
-            if root_path.join(file).exists() {
-                return Some(project_type.to_string());
-            }
-        }
-
-        None
+        detect_project_type(root_path)
     }
 
     /// Auto-include important files based on project type
@@ -379,14 +608,66 @@ impl ContextLoader {
             let file_path = root_path.join(file_name);
             if file_path.exists() && !context.files.contains_key(file_name) {
                 if let Ok(content) = self.load_file(&file_path) {
-                    context.included_files.push(file_name.to_string());
                     context.add_file(file_name.to_string(), content);
+                    // Manifests are covered by the dependency summary instead
+                    let summarized = DEPENDENCY_MANIFESTS.contains(&file_name)
+                        && context.dependencies_summary.is_some();
+                    if !summarized {
+                        context.included_files.push(file_name.to_string());
+                    }
                 }
             }
         }
     }
 }
 
+/// Whether file content looks binary: a null byte or known signature in the
+/// first 8KB, or more than 10% control characters there
+pub fn is_binary(content: &[u8]) -> bool {
+    let sample = &content[..content.len().min(BINARY_SNIFF_BYTES)];
+    if sample.is_empty() {
+        return false;
+    }
+    if sample.contains(&0) || BINARY_MAGIC.iter().any(|magic| sample.starts_with(magic)) {
+        return true;
+    }
+    // Bytes from 0x80 up are left to UTF-8 decoding
+    let non_printable = sample
+        .iter()
+        .filter(|&&b| (b < 0x20 && !b"\t\n\r\x0c\x1b".contains(&b)) || b == 0x7f)
+        .count();
+    non_printable as f64 / sample.len() as f64 > MAX_NON_PRINTABLE_RATIO
+}
+
+/// Detect the project type based on configuration files, e.g. `Cargo.toml` -> `rust`
+pub fn detect_project_type(root_path: &Path) -> Option<String> {
+    let checks = [
+        ("Cargo.toml", "rust"),
+        ("package.json", "javascript"),
+        ("requirements.txt", "python"),
+        ("setup.py", "python"),
+        ("pyproject.toml", "python"),
+        ("go.mod", "go"),
+        ("pom.xml", "java"),
+        ("build.gradle", "java"),
+        ("composer.json", "php"),
+        ("Gemfile", "ruby"),
+        ("mix.exs", "elixir"),
+        ("project.clj", "clojure"),
+        ("build.sbt", "scala"),
+        ("Package.swift", "swift"),
+        ("tsconfig.json", "typescript"),
+    ];
+
+    for (file, project_type) in &checks {
+        if root_path.join(file).exists() {
+            return Some(project_type.to_string());
+        }
+    }
+
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -394,6 +675,21 @@ mod tests {
     use std::io::Write;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_is_binary() {
+        assert!(is_binary(b"\x7fELF\x02\x01\x01"));
+        assert!(is_binary(b"%PDF-1.7\n"));
+        assert!(is_binary(b"PK\x03\x04\x14\x00"));
+        assert!(is_binary(b"text with a \0 null"));
+        assert!(is_binary(&[0x01, 0x02, b'a', b'b', b'c', b'd', 0x03]));
+
+        assert!(!is_binary(b""));
+        assert!(!is_binary(
+            "fn main() {\n\tprintln!(\"h\u{e9}llo\");\r\n}".as_bytes()
+        ));
+        assert!(!is_binary(b"\x1b[31mred\x1b[0m log line"));
+    }
+
     #[test]
     fn test_detect_project_type() {
         let temp_dir = TempDir::new().unwrap();
@@ -437,4 +733,137 @@ mod tests {
         assert!(context.files.contains_key("src/main.rs"));
         assert!(context.token_count > 0);
     }
+
+    #[test]
+    fn test_mermaid_ignore_and_config_patterns() {
+        let temp_dir = TempDir::new().unwrap();
+        let docs_dir = temp_dir.path().join("docs");
+        fs::create_dir(&docs_dir).unwrap();
+        fs::write(temp_dir.path().join("main.rs"), "fn main() {}").unwrap();
+        fs::write(temp_dir.path().join("secret.rs"), "const KEY: &str = \"\";").unwrap();
+        fs::write(temp_dir.path().join("server.pem"), "-----BEGIN-----").unwrap();
+        fs::write(docs_dir.join("draft.md"), "# Draft").unwrap();
+        fs::write(docs_dir.join("guide.md"), "# Guide").unwrap();
+        fs::write(temp_dir.path().join(MERMAID_IGNORE_FILE), "secret.rs\n").unwrap();
+        fs::write(docs_dir.join(MERMAID_IGNORE_FILE), "draft.md\n").unwrap();
+
+        let loader = ContextLoader::with_config(LoaderConfig {
+            excluded_paths: vec!["*.pem".to_string()],
+            ..LoaderConfig::default()
+        })
+        .unwrap();
+        let files: Vec<String> = loader
+            .collect_files(temp_dir.path())
+            .unwrap()
+            .iter()
+            .filter_map(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .collect();
+
+        assert!(files.contains(&"main.rs".to_string()));
+        assert!(files.contains(&"guide.md".to_string()));
+        assert!(!files.contains(&"secret.rs".to_string()));
+        assert!(!files.contains(&"draft.md".to_string()));
+        assert!(!files.contains(&"server.pem".to_string()));
+    }
+
+    #[test]
+    fn test_update_context_reads_only_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("a.rs"), "fn a() {}").unwrap();
+        fs::write(root.join("b.rs"), "fn b() {}").unwrap();
+        fs::write(root.join(MERMAID_IGNORE_FILE), "hidden.rs\n").unwrap();
+
+        let loader = ContextLoader::with_config(LoaderConfig::default()).unwrap();
+        let mut context = loader.load_context(root).unwrap();
+
+        fs::write(root.join("a.rs"), "fn a() { println!(\"changed\"); }").unwrap();
+        fs::write(root.join("c.rs"), "fn c() {}").unwrap();
+        fs::write(root.join("hidden.rs"), "fn hidden() {}").unwrap();
+        fs::remove_file(root.join("b.rs")).unwrap();
+        let changed = vec![root.join("a.rs"), root.join("c.rs"), root.join("hidden.rs")];
+        loader
+            .update_context(root, &mut context, &changed, &[root.join("b.rs")])
+            .unwrap();
+
+        assert!(context.files["a.rs"].text().contains("changed"));
+        assert!(context.files.contains_key("c.rs"));
+        assert!(!context.files.contains_key("b.rs"));
+        assert!(!context.files.contains_key("hidden.rs"));
+        assert_eq!(
+            context.token_count,
+            loader.load_context(root).unwrap().token_count
+        );
+
+        // Nested files are found by walking only their directories
+        fs::create_dir_all(root.join("src/inner")).unwrap();
+        fs::write(root.join("src/inner/d.rs"), "fn d() {}").unwrap();
+        fs::write(root.join("src/inner/hidden.rs"), "fn hidden() {}").unwrap();
+        let changed = vec![
+            root.join("src/inner/d.rs"),
+            root.join("src/inner/hidden.rs"),
+        ];
+        loader
+            .update_context(root, &mut context, &changed, &[])
+            .unwrap();
+        assert!(context.files.contains_key("src/inner/d.rs"));
+        assert!(!context.files.contains_key("src/inner/hidden.rs"));
+    }
+
+    #[test]
+    fn test_gitignored_env_file_is_redacted() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join(".git")).unwrap();
+        fs::write(temp_dir.path().join(".gitignore"), ".env\n").unwrap();
+        fs::write(
+            temp_dir.path().join(".env"),
+            "API_URL=https://api.local\nAPI_TOKEN=abc123\n",
+        )
+        .unwrap();
+
+        let loader = ContextLoader::with_config(LoaderConfig::default()).unwrap();
+        let context = loader.load_context(temp_dir.path()).unwrap();
+        assert_eq!(
+            context.files[".env"].text(),
+            "API_URL=https://api.local\nAPI_TOKEN=[REDACTED]"
+        );
+
+        let loader = ContextLoader::with_config(LoaderConfig {
+            env_file_support: false,
+            ..LoaderConfig::default()
+        })
+        .unwrap();
+        let context = loader.load_context(temp_dir.path()).unwrap();
+        assert!(!context.files.contains_key(".env"));
+    }
+
+    #[test]
+    fn test_additional_roots_use_absolute_paths() {
+        let workspace = TempDir::new().unwrap();
+        let frontend = workspace.path().join("frontend");
+        let backend = workspace.path().join("services/api");
+        let other = workspace.path().join("tools/api");
+        fs::create_dir_all(frontend.join("src")).unwrap();
+        fs::create_dir_all(backend.join("src")).unwrap();
+        fs::create_dir_all(other.join("src")).unwrap();
+        fs::write(frontend.join("src/App.tsx"), "export default App;").unwrap();
+        fs::write(backend.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(other.join("src/main.rs"), "fn main() { run() }").unwrap();
+        fs::write(backend.join(MERMAID_IGNORE_FILE), "*.env\n").unwrap();
+        fs::write(backend.join("prod.env"), "TOKEN=1").unwrap();
+
+        let loader = ContextLoader::with_config(LoaderConfig::default())
+            .unwrap()
+            .with_additional_roots([backend.clone(), other.clone()]);
+        let context = loader.load_context(&frontend).unwrap();
+
+        // Roots with the same directory name don't collide
+        let key = |root: &Path, file: &str| {
+            format!("{}/{}", root.canonicalize().unwrap().display(), file)
+        };
+        assert!(context.files.contains_key("src/App.tsx"));
+        assert!(context.files.contains_key(&key(&backend, "src/main.rs")));
+        assert!(context.files.contains_key(&key(&other, "src/main.rs")));
+        assert!(!context.files.contains_key(&key(&backend, "prod.env")));
+    }
 }
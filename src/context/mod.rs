@@ -2,15 +2,25 @@
 // All external access must go through this gateway
 
 // Private submodules - not directly accessible from outside
+mod comments;
+mod dependencies;
+mod env_file;
 mod loader;
+mod proto;
 mod ranker;
 mod repo_graph;
 mod repomap;
+mod spec_summary;
 mod tree_parser;
+mod yaml;
 
 // Public re-exports - the ONLY way to access context functionality
-pub use loader::{ContextLoader, LoaderConfig};
+pub use comments::{strip_comments, stripped_file_content};
+pub use dependencies::{summarize_dependencies, DEPENDENCY_MANIFESTS};
+pub use env_file::{is_env_file, redact_env_file, REDACTED};
+pub use loader::{detect_project_type, is_binary, ContextLoader, LoaderConfig, ProgressCallback};
 pub use ranker::{RankerConfig, RepoRanker};
 pub use repo_graph::RepoGraph;
 pub use repomap::{generate_repo_map, RepoMap, RepoMapStats};
-pub use tree_parser::{Symbol, SymbolKind, SymbolReference, TreeParser};
+pub use spec_summary::file_content;
+pub use tree_parser::{QueryMatch, Symbol, SymbolKind, SymbolReference, TreeParser};
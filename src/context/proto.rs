@@ -0,0 +1,328 @@
+/// Summarize a `.proto` file: package, services with their RPC signatures,
+/// and message and enum definitions, one line each
+/// Returns None when the file defines nothing worth summarizing
+pub fn summarize_proto(content: &str) -> Option<String> {
+    let tokens = tokenize(content);
+    let mut parser = Parser {
+        tokens: &tokens,
+        pos: 0,
+    };
+    let mut summary = Summary::default();
+    parser.parse_top_level(&mut summary);
+
+    if summary.services.is_empty() && summary.types.is_empty() {
+        return None;
+    }
+    let mut out = Vec::new();
+    if let Some(package) = summary.package {
+        out.push(format!("package {}", package));
+    }
+    out.extend(summary.services);
+    out.extend(summary.types);
+    Some(out.join("\n"))
+}
+
+#[derive(Default)]
+struct Summary {
+    package: Option<String>,
+    services: Vec<String>,
+    types: Vec<String>,
+}
+
+/// Split proto source into identifiers, string literals and single-character symbols
+fn tokenize(content: &str) -> Vec<String> {
+    let chars: Vec<char> = content.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else if c == '"' || c == '\'' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            i += 1;
+            tokens.push(chars[start..i.min(chars.len())].iter().collect());
+        } else if c.is_alphanumeric() || c == '_' || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '.')) {
+                i += 1;
+            }
+            tokens.push(chars[start..i].iter().collect());
+        } else {
+            tokens.push(c.to_string());
+            i += 1;
+        }
+    }
+    tokens
+}
+
+struct Parser<'a> {
+    tokens: &'a [String],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<&str> {
+        let token = self.tokens.get(self.pos).map(String::as_str);
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Skip to the end of the current statement or block
+    fn skip_statement(&mut self) {
+        let mut depth = 0;
+        while let Some(token) = self.next() {
+            match token {
+                "{" | "[" | "(" => depth += 1,
+                "]" | ")" => depth -= 1,
+                "}" => {
+                    depth -= 1;
+                    if depth <= 0 {
+                        return;
+                    }
+                },
+                ";" if depth == 0 => return,
+                _ => {},
+            }
+        }
+    }
+
+    fn parse_top_level(&mut self, summary: &mut Summary) {
+        while let Some(token) = self.peek() {
+            match token {
+                "package" => {
+                    self.pos += 1;
+                    summary.package = self.next().map(String::from);
+                    self.skip_statement();
+                },
+                "service" => {
+                    self.pos += 1;
+                    let service = self.parse_service();
+                    summary.services.push(service);
+                },
+                "message" => {
+                    self.pos += 1;
+                    self.parse_message("", &mut summary.types);
+                },
+                "enum" => {
+                    self.pos += 1;
+                    let definition = self.parse_enum("");
+                    summary.types.push(definition);
+                },
+                _ => self.skip_statement(),
+            }
+        }
+    }
+
+    fn parse_service(&mut self) -> String {
+        let name = self.next().unwrap_or("?").to_string();
+        let mut rpcs = Vec::new();
+        if self.eat("{") {
+            while let Some(token) = self.peek() {
+                match token {
+                    "}" => {
+                        self.pos += 1;
+                        break;
+                    },
+                    "rpc" => {
+                        self.pos += 1;
+                        rpcs.push(self.parse_rpc());
+                    },
+                    _ => self.skip_statement(),
+                }
+            }
+        }
+        format!("service {} {{ {} }}", name, rpcs.join("; "))
+    }
+
+    /// `rpc Name(Request) returns (Response)`, keeping `stream` markers
+    fn parse_rpc(&mut self) -> String {
+        let name = self.next().unwrap_or("?").to_string();
+        let request = self.parse_rpc_type();
+        self.eat("returns");
+        let response = self.parse_rpc_type();
+        // Either `;` or an options block follows
+        if self.peek() == Some(";") {
+            self.pos += 1;
+        } else if self.peek() == Some("{") {
+            self.skip_statement();
+        }
+        format!("rpc {}({}) returns ({})", name, request, response)
+    }
+
+    fn parse_rpc_type(&mut self) -> String {
+        let mut parts = Vec::new();
+        if self.eat("(") {
+            while let Some(token) = self.next() {
+                if token == ")" {
+                    break;
+                }
+                parts.push(token.to_string());
+            }
+        }
+        parts.join(" ")
+    }
+
+    /// Parse a message body, pushing it (and any nested types) onto `types`
+    fn parse_message(&mut self, scope: &str, types: &mut Vec<String>) {
+        let name = qualified(scope, self.next().unwrap_or("?"));
+        let mut fields = Vec::new();
+        let mut nested = Vec::new();
+        if self.eat("{") {
+            self.parse_fields(&name, &mut fields, &mut nested);
+        }
+        types.push(format!("message {} {{ {} }}", name, fields.join("; ")));
+        types.extend(nested);
+    }
+
+    /// Read fields up to the closing brace, descending into `oneof` groups
+    fn parse_fields(&mut self, scope: &str, fields: &mut Vec<String>, nested: &mut Vec<String>) {
+        while let Some(token) = self.peek() {
+            match token {
+                "}" => {
+                    self.pos += 1;
+                    return;
+                },
+                "message" => {
+                    self.pos += 1;
+                    self.parse_message(scope, nested);
+                },
+                "enum" => {
+                    self.pos += 1;
+                    let definition = self.parse_enum(scope);
+                    nested.push(definition);
+                },
+                "oneof" => {
+                    self.pos += 2;
+                    if self.eat("{") {
+                        self.parse_fields(scope, fields, nested);
+                    }
+                },
+                "option" | "reserved" | "extensions" | "extend" => self.skip_statement(),
+                _ => {
+                    if let Some(field) = self.parse_field() {
+                        fields.push(field);
+                    }
+                },
+            }
+        }
+    }
+
+    /// `repeated Type name = 1 [opts];` -> `repeated Type name`
+    fn parse_field(&mut self) -> Option<String> {
+        let mut parts = Vec::new();
+        while let Some(token) = self.peek() {
+            if matches!(token, "=" | ";" | "}") {
+                break;
+            }
+            parts.push(token.to_string());
+            self.pos += 1;
+        }
+        if self.peek() != Some("}") {
+            self.skip_statement();
+        }
+        let field = parts
+            .join(" ")
+            .replace(" < ", "<")
+            .replace(" , ", ", ")
+            .replace(" > ", "> ");
+        (!field.is_empty()).then_some(field)
+    }
+
+    fn parse_enum(&mut self, scope: &str) -> String {
+        let name = qualified(scope, self.next().unwrap_or("?"));
+        let mut values = Vec::new();
+        if self.eat("{") {
+            while let Some(token) = self.peek() {
+                match token {
+                    "}" => {
+                        self.pos += 1;
+                        break;
+                    },
+                    "option" | "reserved" => self.skip_statement(),
+                    value => {
+                        values.push(value.to_string());
+                        self.skip_statement();
+                    },
+                }
+            }
+        }
+        format!("enum {} {{ {} }}", name, values.join(", "))
+    }
+}
+
+fn qualified(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_proto() {
+        let proto = r#"
+syntax = "proto3";
+package shop.v1;
+
+import "google/protobuf/timestamp.proto";
+
+// Order management
+service Orders {
+  rpc GetOrder(GetOrderRequest) returns (Order);
+  rpc Watch(stream WatchRequest) returns (stream Order) {
+    option deprecated = true;
+  }
+}
+
+message Order {
+  string id = 1;
+  repeated Item items = 2 [packed = true];
+  map<string, int32> counts = 3;
+  oneof payment {
+    string card = 4;
+  }
+  enum Status { UNKNOWN = 0; PAID = 1; }
+}
+"#;
+        assert_eq!(
+            summarize_proto(proto).unwrap(),
+            "package shop.v1\n\
+             service Orders { rpc GetOrder(GetOrderRequest) returns (Order); \
+             rpc Watch(stream WatchRequest) returns (stream Order) }\n\
+             message Order { string id; repeated Item items; map<string, int32> counts; string card }\n\
+             enum Order.Status { UNKNOWN, PAID }"
+        );
+    }
+}
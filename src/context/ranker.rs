@@ -1,9 +1,18 @@
 use anyhow::Result;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use tiktoken_rs::{cl100k_base, CoreBPE};
 
 use super::repo_graph::{RankedSymbol, RepoGraph};
 use super::tree_parser::{Symbol, SymbolKind};
+use crate::models::ProjectContext;
+
+/// Query words too common to say which files a prompt is about
+const QUERY_STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "this", "that", "with", "what", "how", "why", "does", "can", "you", "are",
+    "from", "into", "when", "where", "which", "should", "would", "could", "please", "file", "code",
+    "make", "add", "fix", "use",
+];
 
 /// Configuration for the ranking system
 #[derive(Debug, Clone)]
@@ -32,6 +41,8 @@ pub struct RepoRanker {
     config: RankerConfig,
     tokenizer: CoreBPE,
     graph: RepoGraph,
+    /// Weights from the latest prompt (see `personalize_from_query`)
+    query_weights: HashMap<PathBuf, f64>,
 }
 
 impl RepoRanker {
@@ -41,9 +52,59 @@ impl RepoRanker {
             config,
             tokenizer,
             graph: RepoGraph::new(),
+            query_weights: HashMap::new(),
         })
     }
 
+    /// Personalization weights for the files a prompt is about, 1.0 for the best match
+    /// A query term in a file's path counts twice as much as one in its content
+    pub fn personalize_from_query(query: &str, context: &ProjectContext) -> HashMap<PathBuf, f64> {
+        let mut terms: Vec<String> = query
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .map(str::to_lowercase)
+            .filter(|term| term.chars().count() >= 3 && !QUERY_STOP_WORDS.contains(&term.as_str()))
+            .collect();
+        terms.sort();
+        terms.dedup();
+        if terms.is_empty() {
+            return HashMap::new();
+        }
+
+        let root = PathBuf::from(&context.root_path);
+        let mut weights: HashMap<PathBuf, f64> = context
+            .files
+            .iter()
+            .filter_map(|(path, content)| {
+                let lower_path = path.to_lowercase();
+                let lower_content = content.text().to_lowercase();
+                let score: f64 = terms
+                    .iter()
+                    .map(|term| {
+                        if lower_path.contains(term.as_str()) {
+                            1.0
+                        } else if lower_content.contains(term.as_str()) {
+                            0.5
+                        } else {
+                            0.0
+                        }
+                    })
+                    .sum();
+                (score > 0.0).then(|| (root.join(path), score))
+            })
+            .collect();
+
+        let max = weights.values().cloned().fold(0.0_f64, f64::max);
+        for weight in weights.values_mut() {
+            *weight /= max;
+        }
+        weights
+    }
+
+    /// Use query weights in the next ranking, replacing the previous prompt's
+    pub fn set_query_weights(&mut self, weights: HashMap<PathBuf, f64>) {
+        self.query_weights = weights;
+    }
+
     /// Get mutable reference to the graph
     pub fn graph_mut(&mut self) -> &mut RepoGraph {
         &mut self.graph
@@ -59,7 +120,7 @@ impl RepoRanker {
         let personalization = super::repo_graph::create_personalization(
             chat_files,
             mentioned_files,
-            &[], // Other files handled internally
+            &self.query_weights,
         );
 
         // Run PageRank
@@ -72,7 +133,8 @@ impl RepoRanker {
         Ok(())
     }
 
-    /// Find optimal symbol set using binary search to fit token budget
+    /// Find the largest prefix of the ranked symbols that fits the token budget
+    /// If even the top symbol doesn't fit, it's returned with its signature cut short
     pub fn optimize_symbols(&self, token_budget: usize) -> Result<Vec<RankedSymbol>> {
         // Get all ranked symbols
         let all_symbols = self.graph.get_ranked_symbols(None);
@@ -81,29 +143,45 @@ impl RepoRanker {
             return Ok(Vec::new());
         }
 
-        // Binary search for the maximum number of symbols that fit
-        let mut left = 0;
-        let mut right = all_symbols.len();
-        let mut best_fit = 0;
-
-        while left <= right {
-            let mid = (left + right) / 2;
-            let symbols = &all_symbols[0..mid];
+        // Running token totals, so each step of the binary search is a lookup
+        let mut prefix_tokens = Vec::with_capacity(all_symbols.len());
+        let mut total_tokens = 0;
+        for ranked_symbol in &all_symbols {
+            total_tokens += self.estimate_symbol_tokens(&ranked_symbol.symbol)?;
+            prefix_tokens.push(total_tokens);
+        }
+        let best_fit = prefix_tokens.partition_point(|&tokens| tokens <= token_budget);
 
-            let token_count = self.estimate_token_count(symbols)?;
+        if best_fit == 0 {
+            return Ok(vec![self.truncate_symbol(&all_symbols[0], token_budget)?]);
+        }
+        Ok(all_symbols[..best_fit].to_vec())
+    }
 
-            if token_count <= token_budget {
-                best_fit = mid;
-                left = mid + 1;
+    /// Shorten a symbol's signature to the longest prefix that fits the budget
+    fn truncate_symbol(&self, ranked: &RankedSymbol, token_budget: usize) -> Result<RankedSymbol> {
+        let mut truncated = ranked.clone();
+        truncated.symbol.doc_comment = None;
+        let signature: Vec<char> = ranked
+            .symbol
+            .signature
+            .as_deref()
+            .unwrap_or_default()
+            .chars()
+            .collect();
+
+        let (mut low, mut high) = (0, signature.len());
+        while low < high {
+            let mid = (low + high).div_ceil(2);
+            truncated.symbol.signature = Some(signature[..mid].iter().collect());
+            if self.estimate_symbol_tokens(&truncated.symbol)? <= token_budget {
+                low = mid;
             } else {
-                if mid == 0 {
-                    break;
-                }
-                right = mid - 1;
+                high = mid - 1;
             }
         }
-
-        Ok(all_symbols[0..best_fit].to_vec())
+        truncated.symbol.signature = (low > 0).then(|| signature[..low].iter().collect());
+        Ok(truncated)
     }
 
     /// Estimate token count for a set of symbols
@@ -118,40 +196,10 @@ impl RepoRanker {
         Ok(total_tokens)
     }
 
-    /// Estimate tokens for a single symbol
+    /// Estimate tokens for a single symbol, as its entry is rendered in the map
     fn estimate_symbol_tokens(&self, symbol: &Symbol) -> Result<usize> {
-        let mut text = String::new();
-
-        // File path (shortened)
-        if let Some(file_name) = symbol.file_path.file_name() {
-            text.push_str(&format!("{}:", file_name.to_string_lossy()));
-        }
-
-        // Line number
-        text.push_str(&format!("{} ", symbol.line));
-
-        // Symbol kind and name
-        text.push_str(&format!("{:?} {}", symbol.kind, symbol.name));
-
-        // Include signature if configured
-        if self.config.include_signatures {
-            if let Some(ref signature) = symbol.signature {
-                text.push_str(&format!(" {}", signature));
-            }
-        }
-
-        // Include doc comment if configured
-        if self.config.include_doc_comments {
-            if let Some(ref doc) = symbol.doc_comment {
-                text.push_str(&format!(" // {}", doc));
-            }
-        }
-
-        text.push('\n');
-
-        // Count tokens
-        let tokens = self.tokenizer.encode_with_special_tokens(&text);
-        Ok(tokens.len())
+        let entry = self.format_symbol_entry(symbol)?;
+        Ok(self.tokenizer.encode_with_special_tokens(&entry).len())
     }
 
     /// Generate repository map optimized for token budget
@@ -222,8 +270,8 @@ impl RepoRanker {
         if self.config.include_signatures {
             if let Some(ref signature) = symbol.signature {
                 // Truncate long signatures
-                let sig = if signature.len() > 60 {
-                    format!("{}...", &signature[..57])
+                let sig = if signature.chars().count() > 60 {
+                    format!("{}...", signature.chars().take(57).collect::<String>())
                 } else {
                     signature.clone()
                 };
@@ -231,6 +279,13 @@ impl RepoRanker {
             }
         }
 
+        // Add the first line of the doc comment if configured
+        if self.config.include_doc_comments {
+            if let Some(summary) = symbol.doc_comment.as_deref().and_then(|d| d.lines().next()) {
+                entry.push_str(&format!("\n{}  // {}", indent, summary));
+            }
+        }
+
         entry.push('\n');
         Ok(entry)
     }
@@ -259,3 +314,100 @@ pub struct RankingStats {
     pub estimated_tokens: usize,
     pub max_token_budget: usize,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranker_with_symbols(count: usize) -> RepoRanker {
+        let mut ranker = RepoRanker::new(RankerConfig::default()).unwrap();
+        let symbols = (0..count)
+            .map(|i| Symbol {
+                name: format!("symbol_{}", i),
+                kind: SymbolKind::Function,
+                file_path: PathBuf::from("lib.rs"),
+                line: i + 1,
+                // Signatures of growing length give each symbol a different token size
+                signature: Some(format!("fn symbol_{}({})", i, "x, ".repeat(i % 7))),
+                doc_comment: None,
+            })
+            .collect();
+        ranker
+            .graph_mut()
+            .add_file(PathBuf::from("lib.rs"), symbols);
+        ranker
+    }
+
+    #[test]
+    fn test_personalize_from_query() {
+        let mut context = ProjectContext::new("/repo".to_string());
+        context.add_file(
+            "src/auth/login.rs".to_string(),
+            "pub fn login() {}".to_string(),
+        );
+        context.add_file(
+            "src/session.rs".to_string(),
+            "// Called after login succeeds".to_string(),
+        );
+        context.add_file("src/render.rs".to_string(), "fn draw() {}".to_string());
+
+        let weights = RepoRanker::personalize_from_query("How does the LOGIN flow work?", &context);
+        assert_eq!(weights.len(), 2);
+        assert_eq!(weights[&PathBuf::from("/repo/src/auth/login.rs")], 1.0);
+        assert_eq!(weights[&PathBuf::from("/repo/src/session.rs")], 0.5);
+        assert!(RepoRanker::personalize_from_query("what is this?", &context).is_empty());
+    }
+
+    #[test]
+    fn test_optimize_symbols_finds_exact_boundary() {
+        let ranker = ranker_with_symbols(100);
+        let ranked = ranker.graph.get_ranked_symbols(None);
+        let sizes: Vec<usize> = ranked
+            .iter()
+            .map(|r| ranker.estimate_symbol_tokens(&r.symbol).unwrap())
+            .collect();
+
+        for boundary in [1, 37, 99, 100] {
+            let budget: usize = sizes[..boundary].iter().sum();
+            assert_eq!(ranker.optimize_symbols(budget).unwrap().len(), boundary);
+            if boundary > 1 {
+                assert_eq!(
+                    ranker.optimize_symbols(budget - 1).unwrap().len(),
+                    boundary - 1
+                );
+            }
+        }
+        assert_eq!(ranker.optimize_symbols(usize::MAX).unwrap().len(), 100);
+
+        // A budget below the top symbol still returns it, with a shorter signature
+        let top = ranker.optimize_symbols(sizes[0] - 2).unwrap();
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].symbol.name, ranked[0].symbol.name);
+        assert!(ranker.estimate_symbol_tokens(&top[0].symbol).unwrap() <= sizes[0] - 2);
+        assert!(
+            top[0].symbol.signature.as_ref().unwrap().len()
+                < ranked[0].symbol.signature.as_ref().unwrap().len()
+        );
+    }
+
+    #[test]
+    fn test_long_signatures_are_cut_by_characters() {
+        let ranker = ranker_with_symbols(0);
+        let symbol = Symbol {
+            name: "grüße".to_string(),
+            kind: SymbolKind::Function,
+            file_path: PathBuf::from("lib.rs"),
+            line: 1,
+            signature: Some(format!("fn grüße({})", "ü".repeat(60))),
+            doc_comment: None,
+        };
+        let entry = ranker.format_symbol_entry(&symbol).unwrap();
+        let signature = entry.lines().nth(1).unwrap().trim();
+        assert_eq!(signature.chars().count(), 60);
+        assert!(signature.ends_with("üü..."));
+        assert_eq!(
+            ranker.estimate_symbol_tokens(&symbol).unwrap(),
+            ranker.tokenizer.encode_with_special_tokens(&entry).len()
+        );
+    }
+}
@@ -2,7 +2,7 @@ use anyhow::Result;
 use petgraph::graph::{DiGraph, NodeIndex};
 use petgraph::visit::EdgeRef;
 use rayon::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
@@ -65,8 +65,55 @@ impl RepoGraph {
         index
     }
 
+    /// Remove a file node together with all incident edges
+    pub fn remove_file(&mut self, path: &Path) -> bool {
+        let Some(index) = self.file_indices.remove(path) else {
+            return false;
+        };
+
+        // Forget the symbols this file defined
+        if let Some(node) = self.graph.node_weight(index) {
+            for symbol in &node.symbols {
+                if let Some(files) = self.symbol_locations.get_mut(&symbol.name) {
+                    files.retain(|f| f != path);
+                    if files.is_empty() {
+                        self.symbol_locations.remove(&symbol.name);
+                    }
+                }
+            }
+        }
+
+        // petgraph moves the last node into the freed slot, so re-point its index
+        let last_index = NodeIndex::new(self.graph.node_count() - 1);
+        self.graph.remove_node(index);
+        if last_index != index {
+            if let Some(moved) = self.graph.node_weight(index) {
+                self.file_indices.insert(moved.path.clone(), index);
+            }
+        }
+
+        true
+    }
+
     /// Add references between files based on symbol usage
     pub fn add_references(&mut self, references: Vec<SymbolReference>) {
+        self.add_references_filtered(references, None);
+    }
+
+    /// Add references, only creating edges that point at `targets`
+    pub fn add_references_to(
+        &mut self,
+        references: Vec<SymbolReference>,
+        targets: &HashSet<PathBuf>,
+    ) {
+        self.add_references_filtered(references, Some(targets));
+    }
+
+    fn add_references_filtered(
+        &mut self,
+        references: Vec<SymbolReference>,
+        targets: Option<&HashSet<PathBuf>>,
+    ) {
         // Group references by source file
         let mut references_by_file: HashMap<PathBuf, Vec<SymbolReference>> = HashMap::new();
         for reference in references {
@@ -90,6 +137,9 @@ impl RepoGraph {
                 // Try to resolve where this symbol is defined
                 if let Some(defining_files) = self.symbol_locations.get(&reference.symbol_name) {
                     for defining_file in defining_files {
+                        if targets.is_some_and(|t| !t.contains(defining_file)) {
+                            continue;
+                        }
                         if defining_file != &from_file {
                             // Don't create self-edges
                             reference_counts
@@ -339,6 +389,32 @@ impl RepoGraph {
         }
     }
 
+    /// Render the file dependency graph in Graphviz DOT format, labelling nodes with importance
+    pub fn to_dot(&self) -> String {
+        let escape = |path: &Path| path.display().to_string().replace('"', "\\\"");
+
+        let mut dot = String::from("digraph repo {\n    rankdir=LR;\n    node [shape=box];\n");
+        for node in self.graph.node_weights() {
+            let name = escape(&node.path);
+            dot.push_str(&format!(
+                "    \"{}\" [label=\"{}\\n{:.4}\"];\n",
+                name, name, node.importance_score
+            ));
+        }
+        for edge in self.graph.edge_references() {
+            let from = escape(&self.graph[edge.source()].path);
+            let to = escape(&self.graph[edge.target()].path);
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                from,
+                to,
+                edge.weight().weight
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
     /// Clear the graph
     pub fn clear(&mut self) {
         self.graph.clear();
@@ -365,10 +441,11 @@ pub struct GraphStats {
 
 /// Create personalization vector for PageRank
 /// Higher weights for files in active context
+/// `query_weights` (0.0-1.0) go up to the mentioned-file weight
 pub fn create_personalization(
     chat_files: &[PathBuf],
     mentioned_files: &[PathBuf],
-    other_files: &[PathBuf],
+    query_weights: &HashMap<PathBuf, f64>,
 ) -> HashMap<PathBuf, f64> {
     let mut personalization = HashMap::new();
 
@@ -382,9 +459,9 @@ pub fn create_personalization(
         personalization.insert(file.clone(), 5.0);
     }
 
-    // Other files get base weight
-    for file in other_files {
-        personalization.entry(file.clone()).or_insert(1.0);
+    // Files matching the prompt are weighted by how well they match
+    for (file, weight) in query_weights {
+        personalization.entry(file.clone()).or_insert(5.0 * weight);
     }
 
     // Normalize weights
@@ -397,3 +474,69 @@ pub fn create_personalization(
 
     personalization
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(name: &str, file: &str) -> Symbol {
+        Symbol {
+            name: name.to_string(),
+            kind: SymbolKind::Function,
+            file_path: PathBuf::from(file),
+            line: 1,
+            signature: None,
+            doc_comment: None,
+        }
+    }
+
+    fn reference(name: &str, from: &str) -> SymbolReference {
+        SymbolReference {
+            symbol_name: name.to_string(),
+            from_file: PathBuf::from(from),
+            from_line: 1,
+            to_file: None,
+        }
+    }
+
+    #[test]
+    fn test_remove_file_keeps_indices_consistent() {
+        let mut graph = RepoGraph::new();
+        graph.add_file(PathBuf::from("a.rs"), vec![symbol("alpha", "a.rs")]);
+        graph.add_file(PathBuf::from("b.rs"), vec![symbol("beta", "b.rs")]);
+        graph.add_file(PathBuf::from("c.rs"), vec![symbol("gamma", "c.rs")]);
+        graph.add_references(vec![reference("alpha", "b.rs"), reference("alpha", "c.rs")]);
+        assert_eq!(graph.stats().total_edges, 2);
+
+        assert!(graph.remove_file(Path::new("a.rs")));
+        assert!(!graph.remove_file(Path::new("a.rs")));
+
+        let stats = graph.stats();
+        assert_eq!(stats.total_files, 2);
+        assert_eq!(stats.total_edges, 0);
+        assert_eq!(stats.unique_symbols, 2);
+
+        // The node moved into the freed slot must still resolve to its own symbols
+        assert_eq!(
+            graph.get_file_symbols(Path::new("c.rs")).unwrap()[0].name,
+            "gamma"
+        );
+        assert_eq!(
+            graph.get_file_symbols(Path::new("b.rs")).unwrap()[0].name,
+            "beta"
+        );
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let mut graph = RepoGraph::new();
+        graph.add_file(PathBuf::from("a.rs"), vec![symbol("alpha", "a.rs")]);
+        graph.add_file(PathBuf::from("b.rs"), vec![]);
+        graph.add_references(vec![reference("alpha", "b.rs")]);
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph repo {"));
+        assert!(dot.contains("\"a.rs\" [label=\"a.rs\\n1.0000\"];"));
+        assert!(dot.contains("\"b.rs\" -> \"a.rs\" [label=\"1\"];"));
+    }
+}
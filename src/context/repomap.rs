@@ -1,6 +1,6 @@
 use anyhow::Result;
 use rayon::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -219,6 +219,12 @@ impl RepoMap {
         Ok(map)
     }
 
+    /// Rank toward the files a prompt is about (see `RepoRanker::personalize_from_query`)
+    pub async fn set_query_weights(&mut self, weights: HashMap<PathBuf, f64>) {
+        self.ranker.set_query_weights(weights);
+        self.cache.lock().await.last_map = None;
+    }
+
     /// Update map when files change
     pub async fn update_files(&mut self, changed_files: &[PathBuf]) -> Result<()> {
         let mut cache = self.cache.lock().await;
@@ -244,6 +250,72 @@ impl RepoMap {
         Ok(())
     }
 
+    /// Update the map in place: drop deleted files and re-parse only the changed ones,
+    /// rebuilding just the edges that touch them
+    pub async fn update_incremental(
+        &mut self,
+        changed: &[PathBuf],
+        deleted: &[PathBuf],
+    ) -> Result<()> {
+        let supported_extensions = self.parser.supported_extensions();
+        let changed: Vec<PathBuf> = changed
+            .iter()
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .extension()
+                        .and_then(|e| e.to_str())
+                        .is_some_and(|ext| supported_extensions.contains(ext))
+            })
+            .cloned()
+            .collect();
+
+        // Drop stale data for deleted and changed files (removes their edges too)
+        {
+            let mut cache = self.cache.lock().await;
+            for file in deleted.iter().chain(changed.iter()) {
+                cache.parsed_files.remove(file);
+                cache.file_references.remove(file);
+                self.graph.remove_file(file);
+            }
+            cache.last_map = None;
+        }
+
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        self.parse_files(&changed).await?;
+
+        let cache = self.cache.lock().await;
+        for file in &changed {
+            if let Some(symbols) = cache.parsed_files.get(file) {
+                self.graph.add_file(file.clone(), symbols.clone());
+            }
+        }
+
+        // Outgoing edges from the changed files
+        let outgoing: Vec<SymbolReference> = changed
+            .iter()
+            .filter_map(|file| cache.file_references.get(file))
+            .flatten()
+            .cloned()
+            .collect();
+        self.graph.add_references(outgoing);
+
+        // Incoming edges from unchanged files into the changed ones
+        let changed_set: HashSet<PathBuf> = changed.into_iter().collect();
+        let incoming: Vec<SymbolReference> = cache
+            .file_references
+            .iter()
+            .filter(|(file, _)| !changed_set.contains(*file))
+            .flat_map(|(_, refs)| refs.iter().cloned())
+            .collect();
+        self.graph.add_references_to(incoming, &changed_set);
+
+        Ok(())
+    }
+
     /// Get statistics about the repository map
     pub fn get_stats(&self) -> RepoMapStats {
         let graph_stats = self.graph.stats();
@@ -259,6 +331,20 @@ impl RepoMap {
         }
     }
 
+    /// Render the dependency graph in Graphviz DOT format
+    pub fn to_dot(&self) -> String {
+        self.graph.to_dot()
+    }
+
+    /// Get all files with their importance scores, most important first
+    pub fn get_ranked_files(&self) -> Vec<(PathBuf, f64)> {
+        self.graph
+            .get_ranked_files()
+            .into_iter()
+            .map(|(path, score)| (path.to_path_buf(), score))
+            .collect()
+    }
+
     /// Get the most important files
     pub fn get_top_files(&self, limit: usize) -> Vec<PathBuf> {
         self.graph
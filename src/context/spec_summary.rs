@@ -0,0 +1,314 @@
+use serde_json::Value;
+use std::path::Path;
+
+use super::env_file::{is_env_file, redact_env_file};
+use super::proto::summarize_proto;
+use super::yaml::parse_yaml;
+use crate::models::FileContent;
+
+/// Properties listed per schema before the rest are elided
+const MAX_SCHEMA_PROPERTIES: usize = 12;
+
+const HTTP_METHODS: &[&str] = &[
+    "get", "put", "post", "delete", "patch", "head", "options", "trace",
+];
+
+/// Build the context entry for a loaded file, summarizing verbose spec files
+pub fn file_content(path: &Path, content: String) -> FileContent {
+    // Secrets in dotenv files never reach the model
+    if is_env_file(path) {
+        return FileContent::Raw(redact_env_file(&content));
+    }
+    let summary = if is_api_spec(path) {
+        summarize_openapi(&content)
+    } else if path.extension().is_some_and(|e| e == "proto") {
+        summarize_proto(&content)
+    } else {
+        None
+    };
+    match summary {
+        Some(summary) => FileContent::SpecSummary(summary),
+        None => FileContent::Raw(content),
+    }
+}
+
+/// Whether a file name marks an OpenAPI or Swagger document
+fn is_api_spec(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    ["openapi", "swagger"].iter().any(|kind| {
+        ["yaml", "yml", "json"]
+            .iter()
+            .any(|ext| name.ends_with(&format!("{}.{}", kind, ext)))
+    })
+}
+
+/// Summarize an OpenAPI 3 or Swagger 2 document (JSON or YAML)
+/// Returns None when the content isn't a spec, so the raw file is kept instead
+pub fn summarize_openapi(content: &str) -> Option<String> {
+    let spec = serde_json::from_str::<Value>(content)
+        .ok()
+        .or_else(|| parse_yaml(content))?;
+    let version = spec
+        .get("openapi")
+        .or_else(|| spec.get("swagger"))
+        .and_then(scalar_text)?;
+    let paths = spec.get("paths")?.as_object()?;
+
+    let info = &spec["info"];
+    let mut out = vec![format!(
+        "API: {} {} (OpenAPI {})",
+        info["title"].as_str().unwrap_or("Untitled"),
+        scalar_text(&info["version"]).unwrap_or_default(),
+        version
+    )];
+
+    let servers: Vec<&str> = spec["servers"]
+        .as_array()
+        .map(|s| s.iter().filter_map(|s| s["url"].as_str()).collect())
+        .unwrap_or_default();
+    if !servers.is_empty() {
+        out.push(format!("Servers: {}", servers.join(", ")));
+    } else if let Some(host) = spec["host"].as_str() {
+        out.push(format!(
+            "Server: {}{}",
+            host,
+            spec["basePath"].as_str().unwrap_or("")
+        ));
+    }
+
+    let schemes = spec["components"]["securitySchemes"]
+        .as_object()
+        .or_else(|| spec["securityDefinitions"].as_object());
+    if let Some(schemes) = schemes {
+        let auth: Vec<String> = schemes
+            .iter()
+            .map(|(name, scheme)| format!("{} ({})", name, auth_kind(scheme)))
+            .collect();
+        out.push(format!("Auth: {}", auth.join(", ")));
+    }
+
+    out.push("Endpoints:".to_string());
+    for (path, item) in paths {
+        let shared_params = item["parameters"].as_array();
+        for method in HTTP_METHODS {
+            let Some(op) = item.get(*method) else {
+                continue;
+            };
+            let summary = op["summary"]
+                .as_str()
+                .or_else(|| op["operationId"].as_str())
+                .map(|s| format!(" - {}", s))
+                .unwrap_or_default();
+            out.push(format!("  {} {}{}", method.to_uppercase(), path, summary));
+
+            let params: Vec<String> = shared_params
+                .into_iter()
+                .flatten()
+                .chain(op["parameters"].as_array().into_iter().flatten())
+                .filter(|p| p["in"] != "body")
+                .map(|p| {
+                    let kind = p["schema"]
+                        .as_object()
+                        .map(|_| schema_name(&p["schema"]))
+                        .unwrap_or_else(|| p["type"].as_str().unwrap_or("any").to_string());
+                    format!(
+                        "{}{} ({}, {})",
+                        p["name"].as_str().unwrap_or("?"),
+                        if p["required"] == true { "" } else { "?" },
+                        p["in"].as_str().unwrap_or("?"),
+                        kind
+                    )
+                })
+                .collect();
+            if !params.is_empty() {
+                out.push(format!("    params: {}", params.join(", ")));
+            }
+
+            let body = first_media_schema(&op["requestBody"]["content"]).or_else(|| {
+                op["parameters"]
+                    .as_array()?
+                    .iter()
+                    .find(|p| p["in"] == "body")
+                    .map(|p| &p["schema"])
+            });
+            if let Some(body) = body {
+                out.push(format!("    body: {}", schema_name(body)));
+            }
+
+            if let Some(responses) = op["responses"].as_object() {
+                let responses: Vec<String> = responses
+                    .iter()
+                    .map(|(code, response)| {
+                        match first_media_schema(&response["content"])
+                            .or_else(|| response.get("schema"))
+                        {
+                            Some(schema) => format!("{}: {}", code, schema_name(schema)),
+                            None => code.clone(),
+                        }
+                    })
+                    .collect();
+                out.push(format!("    returns: {}", responses.join(", ")));
+            }
+        }
+    }
+
+    let schemas = spec["components"]["schemas"]
+        .as_object()
+        .or_else(|| spec["definitions"].as_object());
+    if let Some(schemas) = schemas.filter(|s| !s.is_empty()) {
+        out.push("Schemas:".to_string());
+        for (name, schema) in schemas {
+            out.push(format!("  {}{}", name, schema_fields(schema)));
+        }
+    }
+
+    Some(out.join("\n"))
+}
+
+/// Describe a security scheme, e.g. "http bearer" or "apiKey in header X-Key"
+fn auth_kind(scheme: &Value) -> String {
+    let kind = scheme["type"].as_str().unwrap_or("unknown");
+    match (scheme["scheme"].as_str(), scheme["in"].as_str()) {
+        (Some(http_scheme), _) => format!("{} {}", kind, http_scheme),
+        (None, Some(location)) => format!(
+            "{} in {} {}",
+            kind,
+            location,
+            scheme["name"].as_str().unwrap_or("")
+        )
+        .trim_end()
+        .to_string(),
+        _ => kind.to_string(),
+    }
+}
+
+/// Schema of the first media type in a `content` map
+fn first_media_schema(content: &Value) -> Option<&Value> {
+    content
+        .as_object()?
+        .values()
+        .next()
+        .map(|media| &media["schema"])
+        .filter(|schema| !schema.is_null())
+}
+
+/// Short name for a schema: a `$ref` target, `Item[]`, or the type
+fn schema_name(schema: &Value) -> String {
+    if let Some(reference) = schema["$ref"].as_str() {
+        return reference
+            .rsplit('/')
+            .next()
+            .unwrap_or(reference)
+            .to_string();
+    }
+    match schema["type"].as_str() {
+        Some("array") => format!("{}[]", schema_name(&schema["items"])),
+        Some(kind) => kind.to_string(),
+        None if schema["properties"].is_object() => "object".to_string(),
+        None => "any".to_string(),
+    }
+}
+
+/// Property list for a schema definition, e.g. ` { id: integer, tag?: string }`
+fn schema_fields(schema: &Value) -> String {
+    let Some(properties) = schema["properties"].as_object() else {
+        return format!(": {}", schema_name(schema));
+    };
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .map(|r| r.iter().filter_map(|r| r.as_str()).collect())
+        .unwrap_or_default();
+
+    let mut fields: Vec<String> = properties
+        .iter()
+        .take(MAX_SCHEMA_PROPERTIES)
+        .map(|(name, prop)| {
+            let optional = if required.contains(&name.as_str()) {
+                ""
+            } else {
+                "?"
+            };
+            format!("{}{}: {}", name, optional, schema_name(prop))
+        })
+        .collect();
+    if properties.len() > MAX_SCHEMA_PROPERTIES {
+        fields.push(format!(
+            "... {} more",
+            properties.len() - MAX_SCHEMA_PROPERTIES
+        ));
+    }
+    format!(" {{ {} }}", fields.join(", "))
+}
+
+fn scalar_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_openapi_yaml() {
+        let spec = r#"
+openapi: 3.0.1
+info:
+  title: Pet Store
+  version: 1.0.0
+components:
+  securitySchemes:
+    bearerAuth:
+      type: http
+      scheme: bearer
+  schemas:
+    Pet:
+      type: object
+      required: [id]
+      properties:
+        id:
+          type: integer
+        tag:
+          type: string
+paths:
+  /pets/{id}:
+    get:
+      summary: Find a pet
+      parameters:
+        - name: id
+          in: path
+          required: true
+          schema:
+            type: integer
+      responses:
+        '200':
+          content:
+            application/json:
+              schema:
+                $ref: '#/components/schemas/Pet'
+        '404':
+          description: Not found
+"#;
+        let summary = summarize_openapi(spec).unwrap();
+        assert_eq!(
+            summary,
+            "API: Pet Store 1.0.0 (OpenAPI 3.0.1)\n\
+             Auth: bearerAuth (http bearer)\n\
+             Endpoints:\n  \
+             GET /pets/{id} - Find a pet\n    \
+             params: id (path, integer)\n    \
+             returns: 200: Pet, 404\n\
+             Schemas:\n  \
+             Pet { id: integer, tag?: string }"
+        );
+
+        assert!(summarize_openapi("name: not a spec").is_none());
+        assert!(is_api_spec(Path::new("api/petstore.openapi.yaml")));
+    }
+}
@@ -39,6 +39,15 @@ pub struct SymbolReference {
     pub to_file: Option<PathBuf>, // None if unresolved
 }
 
+/// A node captured by a structural (tree-sitter query) search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryMatch {
+    pub file_path: PathBuf,
+    pub line: usize,
+    pub capture: String,
+    pub text: String,
+}
+
 /// Tree-sitter based code parser
 pub struct TreeParser {
     parsers: HashMap<String, Parser>,
@@ -225,13 +234,21 @@ impl TreeParser {
                 // Extract signature if available
                 let signature = self.extract_signature(&node, source)?;
 
+                // `@x.name` captures sit inside the definition, `@x` captures are the definition
+                let definition = if capture_name.contains('.') {
+                    node.parent().unwrap_or(node)
+                } else {
+                    node
+                };
+                let doc_comment = extract_doc_comment(&definition, source);
+
                 symbols.push(Symbol {
                     name: symbol_name.to_string(),
                     kind,
                     file_path: file_path.to_path_buf(),
                     line,
                     signature,
-                    doc_comment: None, // Doc comment extraction not yet implemented
+                    doc_comment,
                 });
             }
         }
@@ -261,6 +278,68 @@ impl TreeParser {
         Ok(None)
     }
 
+    /// Compile a tree-sitter query for one of the supported languages
+    pub fn compile_query(&self, language: &str, source: &str) -> Result<Query> {
+        let grammar: tree_sitter::Language = match language {
+            "rust" => tree_sitter_rust::LANGUAGE.into(),
+            "python" => tree_sitter_python::LANGUAGE.into(),
+            // TypeScript files are parsed with the JavaScript grammar
+            "javascript" | "typescript" => tree_sitter_javascript::LANGUAGE.into(),
+            _ => anyhow::bail!("No parser for language: {}", language),
+        };
+        Query::new(&grammar, source)
+            .map_err(|e| anyhow::anyhow!("Invalid {} query: {}", language, e))
+    }
+
+    /// Run a compiled query over a file, returning every captured node
+    pub fn query_file(
+        &mut self,
+        path: &Path,
+        content: &str,
+        query: &Query,
+    ) -> Result<Vec<QueryMatch>> {
+        let language = self.detect_language(path)?;
+        let parser = self
+            .parsers
+            .get_mut(&language)
+            .context(format!("No parser for language: {}", language))?;
+        let tree = parser
+            .parse(content, None)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        let mut results = Vec::new();
+        let mut cursor = QueryCursor::new();
+        let mut matches = cursor.matches(query, tree.root_node(), content.as_bytes());
+        while let Some(m) = matches.next() {
+            for capture in m.captures {
+                let text = capture.node.utf8_text(content.as_bytes())?;
+                results.push(QueryMatch {
+                    file_path: path.to_path_buf(),
+                    line: capture.node.start_position().row + 1,
+                    capture: query.capture_names()[capture.index as usize].to_string(),
+                    text: text.lines().next().unwrap_or_default().to_string(),
+                });
+            }
+        }
+        Ok(results)
+    }
+
+    /// Byte ranges of the file's comment nodes in source order, or None without a parser
+    pub fn comment_ranges(&mut self, path: &Path, content: &str) -> Option<Vec<(usize, usize)>> {
+        let language = self.detect_language(path).ok()?;
+        let tree = self.parsers.get_mut(&language)?.parse(content, None)?;
+        let mut ranges = Vec::new();
+        collect_comment_ranges(&tree.root_node(), &mut ranges);
+        Some(ranges)
+    }
+
+    /// Language name used for a file, if it has a parser
+    pub fn language_of(&self, path: &Path) -> Option<String> {
+        self.detect_language(path)
+            .ok()
+            .filter(|language| self.parsers.contains_key(language))
+    }
+
     /// Find references to symbols in code
     pub fn find_references(&mut self, path: &Path, content: &str) -> Result<Vec<SymbolReference>> {
         let language = self.detect_language(path)?;
@@ -385,3 +464,127 @@ impl TreeParser {
         extensions
     }
 }
+
+/// Add the outermost comment nodes under `node`, e.g. `line_comment` but not its `doc_comment`
+fn collect_comment_ranges(node: &Node, ranges: &mut Vec<(usize, usize)>) {
+    if node.kind().contains("comment") {
+        ranges.push((node.start_byte(), node.end_byte()));
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_comment_ranges(&child, ranges);
+    }
+}
+
+/// Doc comment of a definition: the `///` lines above a Rust item or a Python docstring
+fn extract_doc_comment(definition: &Node, source: &str) -> Option<String> {
+    let text = |node: Node| node.utf8_text(source.as_bytes()).unwrap_or_default();
+    let doc = match definition.kind() {
+        "function_item" | "struct_item" | "enum_item" | "trait_item" => {
+            let mut lines = Vec::new();
+            let mut sibling = definition.prev_sibling();
+            while let Some(node) = sibling {
+                match node.kind() {
+                    // Attributes such as #[derive] may sit between the comment and the item
+                    "attribute_item" => {},
+                    "line_comment" => {
+                        let comment = text(node);
+                        match comment.strip_prefix("///") {
+                            Some(line) if !line.starts_with('/') => {
+                                lines.push(line.strip_prefix(' ').unwrap_or(line).trim_end())
+                            },
+                            _ => break,
+                        }
+                    },
+                    _ => break,
+                }
+                sibling = node.prev_sibling();
+            }
+            lines.reverse();
+            lines.join("\n")
+        },
+        "function_definition" | "class_definition" => {
+            let first = definition
+                .child_by_field_name("body")?
+                .named_child(0)
+                .filter(|node| node.kind() == "expression_statement")?
+                .named_child(0)
+                .filter(|node| node.kind() == "string")?;
+            let literal = text(first).trim_start_matches(|c: char| c.is_ascii_alphabetic());
+            let quote = ["\"\"\"", "\'\'\'", "\"", "\'"]
+                .into_iter()
+                .find(|quote| literal.starts_with(quote))?;
+            let inner = literal
+                .strip_prefix(quote)?
+                .strip_suffix(quote)
+                .unwrap_or_default();
+            inner.lines().map(str::trim).collect::<Vec<_>>().join("\n")
+        },
+        _ => return None,
+    };
+    let doc = doc.trim();
+    (!doc.is_empty()).then(|| doc.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc_of(symbols: &[Symbol], name: &str) -> Option<String> {
+        symbols
+            .iter()
+            .find(|s| s.name == name)
+            .and_then(|s| s.doc_comment.clone())
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let mut parser = TreeParser::new().unwrap();
+        let rust = r#"
+/// Parsed settings
+///
+/// Loaded once at startup
+#[derive(Debug)]
+struct Settings;
+
+// Not a doc comment
+fn plain() {}
+
+/// Load the settings
+fn load() -> Settings { Settings }
+"#;
+        let symbols = parser.parse_file(Path::new("lib.rs"), rust).unwrap();
+        assert_eq!(
+            doc_of(&symbols, "Settings").as_deref(),
+            Some("Parsed settings\n\nLoaded once at startup")
+        );
+        assert_eq!(
+            doc_of(&symbols, "load").as_deref(),
+            Some("Load the settings")
+        );
+        assert_eq!(doc_of(&symbols, "plain"), None);
+
+        let python = r#"
+class Cache:
+    """Keeps recent results.
+
+    Entries expire after an hour.
+    """
+
+    def get(self, key):
+        'Look up a key'
+        return None
+
+def helper():
+    x = "not a docstring"
+"#;
+        let symbols = parser.parse_file(Path::new("cache.py"), python).unwrap();
+        assert_eq!(
+            doc_of(&symbols, "Cache").as_deref(),
+            Some("Keeps recent results.\n\nEntries expire after an hour.")
+        );
+        assert_eq!(doc_of(&symbols, "get").as_deref(), Some("Look up a key"));
+        assert_eq!(doc_of(&symbols, "helper"), None);
+    }
+}
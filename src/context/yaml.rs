@@ -0,0 +1,224 @@
+use serde_json::{Map, Value};
+
+/// A non-blank YAML line with its indentation
+struct YamlLine {
+    indent: usize,
+    text: String,
+}
+
+/// Parse the block-style YAML subset used by API specs into JSON values
+/// Handles nested mappings, `-` sequences, quoted scalars, `|`/`>` block
+/// scalars and simple flow sequences; anchors and tags are not supported
+pub fn parse_yaml(content: &str) -> Option<Value> {
+    let mut lines: Vec<YamlLine> = content
+        .lines()
+        .map(strip_comment)
+        .filter(|line| !line.trim().is_empty())
+        .filter(|line| !matches!(line.trim(), "---" | "..."))
+        .map(|line| YamlLine {
+            indent: line.len() - line.trim_start().len(),
+            text: line.trim().to_string(),
+        })
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+
+    let mut index = 0;
+    let indent = lines[0].indent;
+    Some(parse_block(&mut lines, &mut index, indent))
+}
+
+fn parse_block(lines: &mut [YamlLine], index: &mut usize, indent: usize) -> Value {
+    if is_sequence_item(&lines[*index].text) {
+        parse_sequence(lines, index, indent)
+    } else {
+        parse_mapping(lines, index, indent)
+    }
+}
+
+fn parse_mapping(lines: &mut [YamlLine], index: &mut usize, indent: usize) -> Value {
+    let mut map = Map::new();
+    while *index < lines.len() && lines[*index].indent >= indent {
+        if lines[*index].indent > indent || is_sequence_item(&lines[*index].text) {
+            // Stray deeper line or a sequence where a key was expected
+            *index += 1;
+            continue;
+        }
+        let Some((key, rest)) = split_key(&lines[*index].text) else {
+            *index += 1;
+            continue;
+        };
+        *index += 1;
+
+        let value = match rest.as_str() {
+            "" => nested_value(lines, index, indent),
+            "|" | "|-" | ">" | ">-" => Value::String(block_scalar(lines, index, indent, &rest)),
+            _ => scalar(&rest),
+        };
+        map.insert(key, value);
+    }
+    Value::Object(map)
+}
+
+fn parse_sequence(lines: &mut [YamlLine], index: &mut usize, indent: usize) -> Value {
+    let mut items = Vec::new();
+    while *index < lines.len()
+        && lines[*index].indent == indent
+        && is_sequence_item(&lines[*index].text)
+    {
+        let item = lines[*index].text[1..].trim_start().to_string();
+        if item.is_empty() {
+            *index += 1;
+            items.push(nested_value(lines, index, indent));
+        } else if split_key(&item).is_some() {
+            // `- key: value` starts a mapping indented past the dash
+            let offset = lines[*index].text.len() - item.len();
+            lines[*index] = YamlLine {
+                indent: indent + offset,
+                text: item,
+            };
+            items.push(parse_mapping(lines, index, indent + offset));
+        } else {
+            *index += 1;
+            items.push(scalar(&item));
+        }
+    }
+    Value::Array(items)
+}
+
+/// Value of a key with nothing after the colon: a nested block or null
+fn nested_value(lines: &mut [YamlLine], index: &mut usize, indent: usize) -> Value {
+    match lines.get(*index) {
+        Some(next) if next.indent > indent => {
+            let child_indent = next.indent;
+            parse_block(lines, index, child_indent)
+        },
+        // Sequences may sit at the same indentation as their key
+        Some(next) if next.indent == indent && is_sequence_item(&next.text) => {
+            parse_sequence(lines, index, indent)
+        },
+        _ => Value::Null,
+    }
+}
+
+fn block_scalar(lines: &[YamlLine], index: &mut usize, indent: usize, style: &str) -> String {
+    let mut parts = Vec::new();
+    while *index < lines.len() && lines[*index].indent > indent {
+        parts.push(lines[*index].text.clone());
+        *index += 1;
+    }
+    let separator = if style.starts_with('>') { " " } else { "\n" };
+    parts.join(separator)
+}
+
+fn is_sequence_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// Split `key: rest` at the first colon outside quotes
+fn split_key(text: &str) -> Option<(String, String)> {
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') if i == 0 => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, ':') if text[i + 1..].is_empty() || text[i + 1..].starts_with(' ') => {
+                let key = unquote(text[..i].trim());
+                return Some((key, text[i + 1..].trim().to_string()));
+            },
+            _ => {},
+        }
+    }
+    None
+}
+
+fn scalar(text: &str) -> Value {
+    let text = text.trim();
+    if let Some(inner) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        return Value::Array(
+            inner
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(scalar)
+                .collect(),
+        );
+    }
+    match text {
+        "{}" => Value::Object(Map::new()),
+        "null" | "~" => Value::Null,
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ if text.starts_with('"') || text.starts_with('\'') => Value::String(unquote(text)),
+        _ => text
+            .parse::<i64>()
+            .map(Value::from)
+            .or_else(|_| text.parse::<f64>().map(Value::from))
+            .unwrap_or_else(|_| Value::String(text.to_string())),
+    }
+}
+
+fn unquote(text: &str) -> String {
+    let quoted = text.len() >= 2
+        && ((text.starts_with('"') && text.ends_with('"'))
+            || (text.starts_with('\'') && text.ends_with('\'')));
+    if quoted {
+        text[1..text.len() - 1].to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+/// Drop a trailing `# comment` that sits outside quotes
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') if previous == ' ' || previous == ':' => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '#') if previous.is_whitespace() => return &line[..i],
+            _ => {},
+        }
+        previous = c;
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_yaml_spec_subset() {
+        let yaml = r#"
+openapi: 3.0.0  # version
+info:
+  title: "Pets: the API"
+paths:
+  /pets:
+    get:
+      tags: [pets, read]
+      parameters:
+        - name: limit
+          in: query
+      responses:
+        '200':
+          $ref: '#/components/responses/Pets'
+"#;
+        assert_eq!(
+            parse_yaml(yaml).unwrap(),
+            json!({
+                "openapi": "3.0.0",
+                "info": { "title": "Pets: the API" },
+                "paths": { "/pets": { "get": {
+                    "tags": ["pets", "read"],
+                    "parameters": [{ "name": "limit", "in": "query" }],
+                    "responses": { "200": { "$ref": "#/components/responses/Pets" } },
+                } } },
+            })
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use super::types::{GpuInfo, GpuType};
 use anyhow::Result;
+use serde_json::Value;
+use std::collections::HashMap;
 use std::process::Command;
 
 /// Detect which type of GPU is available
@@ -51,55 +53,32 @@ fn is_nvidia_available() -> bool {
         .unwrap_or(false)
 }
 
-/// Get NVIDIA GPU information
+/// Get NVIDIA GPU information for the first GPU
 fn get_nvidia_info() -> Result<GpuInfo> {
-    // Query GPU name
-    let name_output = Command::new("nvidia-smi")
-        .arg("--query-gpu=name")
-        .arg("--format=csv,noheader")
-        .output()?;
-    let name = String::from_utf8_lossy(&name_output.stdout)
-        .trim()
-        .to_string();
-
-    // Query GPU utilization
-    let util_output = Command::new("nvidia-smi")
-        .arg("--query-gpu=utilization.gpu")
+    let output = Command::new("nvidia-smi")
+        .arg("--query-gpu=name,memory.total,memory.used,temperature.gpu,utilization.gpu")
         .arg("--format=csv,noheader,nounits")
         .output()?;
-    let usage_percent: f32 = String::from_utf8_lossy(&util_output.stdout)
-        .trim()
-        .parse()
-        .unwrap_or(0.0);
-
-    // Query memory usage
-    let mem_output = Command::new("nvidia-smi")
-        .arg("--query-gpu=memory.used,memory.total")
-        .arg("--format=csv,noheader,nounits")
-        .output()?;
-    let mem_string = String::from_utf8_lossy(&mem_output.stdout);
-    let mem_parts: Vec<&str> = mem_string.trim().split(',').map(|s| s.trim()).collect();
-
-    let memory_used_mb: f32 = mem_parts.get(0).and_then(|s| s.parse().ok()).unwrap_or(0.0);
-    let memory_total_mb: f32 = mem_parts.get(1).and_then(|s| s.parse().ok()).unwrap_or(1.0);
-
-    // Query temperature
-    let temp_output = Command::new("nvidia-smi")
-        .arg("--query-gpu=temperature.gpu")
-        .arg("--format=csv,noheader,nounits")
-        .output()?;
-    let temperature_celsius: Option<f32> = String::from_utf8_lossy(&temp_output.stdout)
-        .trim()
-        .parse()
-        .ok();
+    parse_nvidia_smi(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| anyhow::anyhow!("Unexpected nvidia-smi output"))
+}
 
-    Ok(GpuInfo {
-        name,
+/// Parse a `name, memory.total, memory.used, temperature.gpu, utilization.gpu` CSV row
+/// Fields nvidia-smi can't read come back as `[N/A]` and are left unset
+fn parse_nvidia_smi(output: &str) -> Option<GpuInfo> {
+    let fields: Vec<&str> = output.lines().next()?.split(',').map(str::trim).collect();
+    let [name, total_mb, used_mb, temperature, utilization] = fields[..] else {
+        return None;
+    };
+    let memory_total_mb: f32 = total_mb.parse().ok()?;
+    Some(GpuInfo {
+        name: name.to_string(),
         gpu_type: GpuType::Nvidia,
-        usage_percent,
-        memory_used_gb: memory_used_mb / 1024.0,
+        utilization_percent: utilization.parse().ok(),
+        memory_used_gb: used_mb.parse::<f32>().unwrap_or(0.0) / 1024.0,
         memory_total_gb: memory_total_mb / 1024.0,
-        temperature_celsius,
+        temperature_celsius: temperature.parse().ok(),
+        model_vram_mb: HashMap::new(),
     })
 }
 
@@ -113,17 +92,45 @@ fn is_amd_available() -> bool {
         .unwrap_or(false)
 }
 
-/// Get AMD GPU information
+/// Get AMD GPU information for the first card ROCm reports
 fn get_amd_info() -> Result<GpuInfo> {
-    // This is a simplified version - AMD GPU monitoring is more complex
-    // and would need proper ROCm integration
-    Ok(GpuInfo {
-        name: "AMD GPU".to_string(),
+    let output = Command::new("rocm-smi")
+        .args([
+            "--showmeminfo",
+            "vram",
+            "--showtemp",
+            "--showuse",
+            "--showproductname",
+        ])
+        .arg("--json")
+        .output()?;
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    parse_rocm_smi(&json).ok_or_else(|| anyhow::anyhow!("Unexpected rocm-smi output"))
+}
+
+/// Parse `rocm-smi --json` output, keyed by card (`card0`, ...) with string values
+fn parse_rocm_smi(json: &Value) -> Option<GpuInfo> {
+    let (_, card) = json
+        .as_object()?
+        .iter()
+        .find(|(key, _)| key.starts_with("card"))?;
+    let field = |prefix: &str| {
+        card.as_object()?
+            .iter()
+            .find(|(key, _)| key.starts_with(prefix))
+            .and_then(|(_, value)| value.as_str()?.trim().parse::<f64>().ok())
+    };
+    const BYTES_PER_GB: f64 = 1024.0 * 1024.0 * 1024.0;
+    Some(GpuInfo {
+        name: card["Card series"]
+            .as_str()
+            .map_or("AMD GPU".to_string(), |name| name.trim().to_string()),
         gpu_type: GpuType::Amd,
-        usage_percent: 0.0,
-        memory_used_gb: 0.0,
-        memory_total_gb: 0.0,
-        temperature_celsius: None,
+        utilization_percent: field("GPU use (%)").map(|v| v as f32),
+        memory_used_gb: (field("VRAM Total Used Memory (B)").unwrap_or(0.0) / BYTES_PER_GB) as f32,
+        memory_total_gb: (field("VRAM Total Memory (B)")? / BYTES_PER_GB) as f32,
+        temperature_celsius: field("Temperature (Sensor edge)").map(|v| v as f32),
+        model_vram_mb: HashMap::new(),
     })
 }
 
@@ -136,9 +143,8 @@ fn is_apple_silicon() -> bool {
         .output()
         .map(|output| {
             let cpu_info = String::from_utf8_lossy(&output.stdout);
-            cpu_info.contains("Apple M1")
-                || cpu_info.contains("Apple M2")
-                || cpu_info.contains("Apple M3")
+            // M1 and later; Intel Macs report an Intel brand string
+            cpu_info.contains("Apple M")
         })
         .unwrap_or(false)
 }
@@ -152,41 +158,32 @@ fn is_apple_silicon() -> bool {
 fn get_apple_silicon_info() -> Result<GpuInfo> {
     #[cfg(target_os = "macos")]
     {
-        // Get system info using sysctl
-        let output = Command::new("sysctl")
-            .arg("-n")
-            .arg("machdep.cpu.brand_string")
+        let output = Command::new("system_profiler")
+            .arg("SPDisplaysDataType")
+            .arg("-json")
             .output()?;
-        let cpu_info = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        let json: Value = serde_json::from_slice(&output.stdout)?;
+        let name = parse_displays_json(&json).unwrap_or_else(|| "Apple GPU".to_string());
 
-        // Parse unified memory from system_profiler
-        let mem_output = Command::new("system_profiler")
-            .arg("SPHardwareDataType")
+        // The GPU shares unified memory with the CPU
+        let mem_output = Command::new("sysctl")
+            .arg("-n")
+            .arg("hw.memsize")
             .output()?;
-        let mem_info = String::from_utf8_lossy(&mem_output.stdout);
-
-        // Extract memory size (rough parsing)
-        let memory_gb = if mem_info.contains("64 GB") {
-            64.0
-        } else if mem_info.contains("32 GB") {
-            32.0
-        } else if mem_info.contains("16 GB") {
-            16.0
-        } else if mem_info.contains("8 GB") {
-            8.0
-        } else {
-            16.0
-        }; // Default
-
-        // For Apple Silicon, we can't easily get real-time GPU usage
-        // This would require using Metal Performance Shaders
+        let memory_bytes: f64 = String::from_utf8_lossy(&mem_output.stdout)
+            .trim()
+            .parse()
+            .unwrap_or(0.0);
+
+        // Utilization and temperature need Metal or powermetrics (root)
         Ok(GpuInfo {
-            name: cpu_info,
+            name,
             gpu_type: GpuType::AppleSilicon,
-            usage_percent: 0.0,  // Would need Metal API
+            utilization_percent: None,
             memory_used_gb: 0.0, // Unified memory - hard to separate
-            memory_total_gb: memory_gb,
+            memory_total_gb: (memory_bytes / (1024.0 * 1024.0 * 1024.0)) as f32,
             temperature_celsius: None,
+            model_vram_mb: HashMap::new(),
         })
     }
 
@@ -196,6 +193,31 @@ fn get_apple_silicon_info() -> Result<GpuInfo> {
     }
 }
 
+/// GPU name from `system_profiler SPDisplaysDataType -json`, with its core
+/// count and Metal family, e.g. `Apple M2 Pro (19 cores, Metal 3)`
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn parse_displays_json(json: &Value) -> Option<String> {
+    let gpu = json["SPDisplaysDataType"]
+        .as_array()?
+        .iter()
+        .find(|gpu| gpu["sppci_model"].is_string())?;
+    let mut details = Vec::new();
+    if let Some(cores) = gpu["sppci_cores"].as_str() {
+        details.push(format!("{} cores", cores));
+    }
+    if let Some(metal) = gpu["spdisplays_mtlgpufamilysupport"].as_str() {
+        // e.g. `spdisplays_metal3`
+        let version = metal.trim_start_matches("spdisplays_metal");
+        details.push(format!("Metal {}", version).trim().to_string());
+    }
+    let model = gpu["sppci_model"].as_str()?;
+    Some(if details.is_empty() {
+        model.to_string()
+    } else {
+        format!("{} ({})", model, details.join(", "))
+    })
+}
+
 /// Check if Intel GPU is available
 fn is_intel_gpu_available() -> bool {
     // Check for Intel GPU tools
@@ -212,9 +234,115 @@ fn get_intel_info() -> Result<GpuInfo> {
     Ok(GpuInfo {
         name: "Intel GPU".to_string(),
         gpu_type: GpuType::Intel,
-        usage_percent: 0.0,
+        utilization_percent: None,
         memory_used_gb: 0.0,
         memory_total_gb: 0.0,
         temperature_celsius: None,
+        model_vram_mb: HashMap::new(),
     })
 }
+
+/// Get VRAM used by each model Ollama currently has loaded
+pub fn get_ollama_model_vram() -> HashMap<String, u64> {
+    Command::new("ollama")
+        .arg("ps")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| parse_ollama_ps(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or_default()
+}
+
+/// Parse `ollama ps` output into model name -> VRAM in MB
+///
+/// Rows look like `qwen2.5:7b  845dbda0ea48  6.0 GB  48%/52% CPU/GPU  4 minutes from now`;
+/// only the GPU share of the model size counts towards VRAM.
+fn parse_ollama_ps(output: &str) -> HashMap<String, u64> {
+    let mut models = HashMap::new();
+
+    for line in output.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 6 {
+            continue;
+        }
+
+        let Ok(size) = fields[2].parse::<f64>() else {
+            continue;
+        };
+        let size_mb = match fields[3] {
+            "GB" => size * 1024.0,
+            "MB" => size,
+            "KB" => size / 1024.0,
+            _ => continue,
+        };
+
+        let percents: Vec<f64> = fields[4]
+            .split('/')
+            .filter_map(|p| p.trim_end_matches('%').parse().ok())
+            .collect();
+        let gpu_share = match (fields[5], percents.as_slice()) {
+            ("GPU", [gpu]) => gpu / 100.0,
+            ("CPU/GPU", [_, gpu]) => gpu / 100.0,
+            _ => 0.0,
+        };
+
+        if gpu_share > 0.0 {
+            models.insert(fields[0].to_string(), (size_mb * gpu_share).round() as u64);
+        }
+    }
+
+    models
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ollama_ps() {
+        let output = "NAME            ID              SIZE      PROCESSOR          UNTIL\n\
+                      qwen2.5:7b      845dbda0ea48    6.0 GB    100% GPU           4 minutes from now\n\
+                      llama3:8b       365c0bd3c000    8.0 GB    50%/50% CPU/GPU    2 minutes from now\n\
+                      phi3:mini       4f2222927938    2.2 GB    100% CPU           Forever\n";
+
+        let models = parse_ollama_ps(output);
+        assert_eq!(models.len(), 2);
+        assert_eq!(models["qwen2.5:7b"], 6144);
+        assert_eq!(models["llama3:8b"], 4096);
+    }
+
+    #[test]
+    fn test_parse_vendor_tools() {
+        let nvidia = parse_nvidia_smi("NVIDIA GeForce RTX 4090, 24564, 6142, 61, 37\n").unwrap();
+        assert_eq!(nvidia.name, "NVIDIA GeForce RTX 4090");
+        assert_eq!(nvidia.memory_total_gb, 24564.0 / 1024.0);
+        assert_eq!(nvidia.temperature_celsius, Some(61.0));
+        assert_eq!(nvidia.utilization_percent, Some(37.0));
+        let no_sensors = parse_nvidia_smi("Tesla T4, 15360, 0, [N/A], [N/A]").unwrap();
+        assert_eq!(no_sensors.temperature_celsius, None);
+        assert!(parse_nvidia_smi("").is_none());
+
+        let rocm = serde_json::json!({ "card0": {
+            "Card series": "Radeon RX 7900 XTX",
+            "Temperature (Sensor edge) (C)": "48.0",
+            "GPU use (%)": "12",
+            "VRAM Total Memory (B)": "25753026560",
+            "VRAM Total Used Memory (B)": "1073741824",
+        }});
+        let amd = parse_rocm_smi(&rocm).unwrap();
+        assert_eq!(amd.name, "Radeon RX 7900 XTX");
+        assert_eq!(amd.memory_used_gb, 1.0);
+        assert_eq!(amd.temperature_celsius, Some(48.0));
+        assert_eq!(amd.utilization_percent, Some(12.0));
+
+        let displays = serde_json::json!({ "SPDisplaysDataType": [{
+            "sppci_model": "Apple M2 Pro",
+            "sppci_cores": "19",
+            "spdisplays_mtlgpufamilysupport": "spdisplays_metal3",
+        }]});
+        assert_eq!(
+            parse_displays_json(&displays).unwrap(),
+            "Apple M2 Pro (19 cores, Metal 3)"
+        );
+    }
+}
@@ -9,7 +9,11 @@ mod types;
 
 // Public re-exports - the ONLY way to access diagnostics functionality
 pub use monitor::{
-    create_monitoring_task, estimate_model_memory, HardwareMonitor, SharedHardwareMonitor,
+    create_monitoring_task, estimate_model_memory, HardwareMonitor, MemoryEstimate,
+    SharedHardwareMonitor,
 };
 pub use panel::render_diagnostics_panel;
-pub use types::{DiagnosticsMode, GpuInfo, GpuType, HardwareStats, ModelInfo};
+pub use types::{
+    estimate_cost, DailyUsage, DiagnosticsMode, GpuInfo, GpuType, HardwareStats, ModelInfo,
+    NetworkStats, SharedNetworkStats, PRICING,
+};
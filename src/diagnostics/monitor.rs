@@ -4,8 +4,8 @@ use std::time::{Duration, Instant};
 use sysinfo::System;
 use tokio::sync::Mutex;
 
-use super::gpu::{detect_gpu_type, get_gpu_info};
-use super::types::{GpuType, HardwareStats, ModelInfo};
+use super::gpu::{detect_gpu_type, get_gpu_info, get_ollama_model_vram};
+use super::types::{GpuType, HardwareStats, ModelInfo, SharedNetworkStats};
 
 /// Hardware monitoring service
 pub struct HardwareMonitor {
@@ -14,6 +14,8 @@ pub struct HardwareMonitor {
     last_update: Instant,
     update_interval: Duration,
     cached_stats: Option<HardwareStats>,
+    network_stats: Option<SharedNetworkStats>,
+    last_network_sample: Option<(u64, Instant)>,
 }
 
 impl HardwareMonitor {
@@ -30,6 +32,8 @@ impl HardwareMonitor {
                 .unwrap_or(Instant::now()),
             update_interval: Duration::from_secs(2),
             cached_stats: None,
+            network_stats: None,
+            last_network_sample: None,
         }
     }
 
@@ -49,6 +53,12 @@ impl HardwareMonitor {
 
         // Get CPU usage
         let cpu_usage_percent = self.system.global_cpu_usage();
+        let cpu_cores = self
+            .system
+            .cpus()
+            .iter()
+            .map(|cpu| cpu.cpu_usage())
+            .collect();
 
         // Get RAM usage
         let ram_used_bytes = self.system.used_memory();
@@ -58,18 +68,44 @@ impl HardwareMonitor {
 
         // Get GPU info if available
         let gpu = if self.gpu_type != GpuType::None {
-            get_gpu_info(self.gpu_type).ok()
+            get_gpu_info(self.gpu_type).ok().map(|mut gpu| {
+                gpu.model_vram_mb = get_ollama_model_vram();
+                gpu
+            })
         } else {
             None
         };
 
+        // Get proxy traffic and bandwidth since the previous sample
+        let network = self
+            .network_stats
+            .as_ref()
+            .and_then(|stats| stats.lock().ok().map(|s| s.clone()));
+        let bandwidth_bytes_per_sec = match (&network, self.last_network_sample) {
+            (Some(network), Some((last_bytes, last_time))) => {
+                let elapsed = last_time.elapsed().as_secs_f64();
+                if elapsed > 0.0 {
+                    network.bytes_total().saturating_sub(last_bytes) as f64 / elapsed
+                } else {
+                    0.0
+                }
+            },
+            _ => 0.0,
+        };
+        if let Some(ref network) = network {
+            self.last_network_sample = Some((network.bytes_total(), Instant::now()));
+        }
+
         let stats = HardwareStats {
             gpu,
             cpu_usage_percent,
+            cpu_cores,
             ram_used_gb,
             ram_total_gb,
             inference_speed: None, // Will be updated by the model during inference
             model_info: None,      // Will be updated when model is loaded
+            network,
+            bandwidth_bytes_per_sec,
         };
 
         // Cache the stats
@@ -93,6 +129,11 @@ impl HardwareMonitor {
         }
     }
 
+    /// Track proxy traffic from the given network counters
+    pub fn set_network_stats(&mut self, stats: SharedNetworkStats) {
+        self.network_stats = Some(stats);
+    }
+
     /// Get GPU type
     pub fn gpu_type(&self) -> GpuType {
         self.gpu_type
@@ -130,30 +171,138 @@ pub fn create_monitoring_task(
     })
 }
 
-/// Estimate model memory requirements based on model name
-pub fn estimate_model_memory(model_name: &str) -> Option<f32> {
-    // Rough estimates based on common model sizes
+/// Bytes per weight for GGUF quantization tags, most specific first
+const QUANTIZATION_BYTES: &[(&str, f32)] = &[
+    ("q2", 0.25),
+    ("q3", 0.375),
+    ("q4", 0.5),
+    ("q5", 0.625),
+    ("q6", 0.75),
+    ("q8", 1.0),
+    ("bf16", 2.0),
+    ("fp16", 2.0),
+    ("f16", 2.0),
+    ("fp32", 4.0),
+    ("f32", 4.0),
+];
+
+/// Ollama's default tags are 4-bit (Q4_K_M / Q4_0)
+const DEFAULT_BYTES_PER_PARAM: f32 = 0.5;
+
+/// Parameter count (billions) assumed for local models whose name doesn't give one
+const DEFAULT_PARAMS_BILLIONS: f32 = 7.0;
+
+/// The KV cache is kept in f16
+const KV_BYTES_PER_ELEMENT: f64 = 2.0;
+
+const BYTES_PER_GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// Estimated memory for running a local model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemoryEstimate {
+    /// Model weights at the model's quantization
+    pub weights_gb: f32,
+    /// Keys and values for a full context window
+    pub kv_cache_gb: f32,
+}
+
+impl MemoryEstimate {
+    pub fn total_gb(&self) -> f32 {
+        self.weights_gb + self.kv_cache_gb
+    }
+}
+
+/// Estimate model memory requirements from the model name, e.g.
+/// `ollama/qwen2.5-coder:7b-instruct-q8_0`; None for API models
+pub fn estimate_model_memory(model_name: &str, context_length: usize) -> Option<MemoryEstimate> {
     let lower = model_name.to_lowercase();
+    let api_model = ["gpt-", "claude", "gemini", "o1-", "o3-"]
+        .iter()
+        .any(|name| lower.contains(name));
+    if api_model && !lower.starts_with("ollama/") {
+        return None;
+    }
 
-    if lower.contains("70b") || lower.contains("llama-2-70b") {
-        Some(40.0) // 70B models typically need ~40GB in 4-bit
-    } else if lower.contains("34b") || lower.contains("yi-34b") {
-        Some(20.0)
-    } else if lower.contains("13b") || lower.contains("llama-2-13b") {
-        Some(8.0)
-    } else if lower.contains("7b") || lower.contains("mistral") {
-        Some(4.5)
-    } else if lower.contains("3b") || lower.contains("phi") {
-        Some(2.0)
-    } else if lower.contains("1b") {
-        Some(1.0)
-    } else if lower.contains("gpt-4") {
-        None // API model
-    } else if lower.contains("gpt-3.5") {
-        None // API model
-    } else if lower.contains("claude") {
-        None // API model
+    let params = parse_params_billions(&lower).unwrap_or(if lower.contains("phi") {
+        3.8
     } else {
-        Some(4.0) // Default estimate
+        DEFAULT_PARAMS_BILLIONS
+    });
+    let weights_bytes = params as f64 * 1e9 * quantization_bytes(&lower) as f64;
+
+    // K and V per layer per token: 2 * layers * context * hidden * bytes per element
+    let (layers, hidden_dim) = llama_shape(params);
+    let kv_bytes =
+        2.0 * layers as f64 * context_length as f64 * hidden_dim as f64 * KV_BYTES_PER_ELEMENT;
+
+    Some(MemoryEstimate {
+        weights_gb: (weights_bytes / BYTES_PER_GB) as f32,
+        kv_cache_gb: (kv_bytes / BYTES_PER_GB) as f32,
+    })
+}
+
+/// Parameters in billions from a size tag such as `7b`, `3.8b` or `8x7b`
+fn parse_params_billions(name: &str) -> Option<f32> {
+    name.split(|c: char| !(c.is_ascii_alphanumeric() || c == '.'))
+        .filter_map(|token| {
+            let size = token.strip_suffix('b')?;
+            match size.split_once('x') {
+                // Mixture of experts: all experts are loaded
+                Some((experts, size)) => {
+                    Some(experts.parse::<f32>().ok()? * size.parse::<f32>().ok()?)
+                },
+                None => size.parse().ok(),
+            }
+        })
+        .find(|params| *params > 0.0)
+}
+
+/// Bytes per parameter from a quantization tag in the name (`q4_k_m`, `q8_0`, `f16`, ...)
+fn quantization_bytes(name: &str) -> f32 {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .find_map(|token| {
+            QUANTIZATION_BYTES
+                .iter()
+                .find(|(tag, _)| token == *tag || (tag.starts_with('q') && token.starts_with(tag)))
+                .map(|(_, bytes)| *bytes)
+        })
+        .unwrap_or(DEFAULT_BYTES_PER_PARAM)
+}
+
+/// Typical (layers, hidden size) of a Llama-style model of this many billion parameters
+fn llama_shape(params_billions: f32) -> (usize, usize) {
+    match params_billions {
+        p if p <= 1.5 => (22, 2048),
+        p if p <= 4.0 => (32, 3072),
+        p if p <= 9.0 => (32, 4096),
+        p if p <= 15.0 => (40, 5120),
+        p if p <= 35.0 => (60, 6656),
+        _ => (80, 8192),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_model_memory() {
+        assert_eq!(parse_params_billions("ollama/phi3:3.8b"), Some(3.8));
+        assert_eq!(parse_params_billions("mixtral:8x7b-instruct"), Some(56.0));
+        assert_eq!(parse_params_billions("qwen2.5-coder"), None);
+        assert_eq!(quantization_bytes("llama3:8b-instruct-q8_0"), 1.0);
+        assert_eq!(quantization_bytes("llama3:8b-instruct-fp16"), 2.0);
+        assert_eq!(quantization_bytes("tinyllama:1.1b-chat-v1-q4_k_m"), 0.5);
+        assert_eq!(quantization_bytes("llama3:8b"), DEFAULT_BYTES_PER_PARAM);
+
+        let q4 = estimate_model_memory("ollama/llama3:8b", 4096).unwrap();
+        let q8 = estimate_model_memory("ollama/llama3:8b-q8_0", 4096).unwrap();
+        assert!((q8.weights_gb / q4.weights_gb - 2.0).abs() < 1e-3);
+        // 2 * 32 layers * 4096 tokens * 4096 hidden * 2 bytes = 2 GiB
+        assert_eq!(q4.kv_cache_gb, 2.0);
+        assert_eq!(q4.total_gb(), q4.weights_gb + 2.0);
+
+        assert!(estimate_model_memory("openai/gpt-4o", 4096).is_none());
+        assert!(estimate_model_memory("anthropic/claude-3-5-sonnet", 4096).is_none());
     }
 }
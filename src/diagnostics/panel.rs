@@ -6,13 +6,19 @@ use ratatui::{
     Frame,
 };
 
-use super::types::HardwareStats;
+use super::monitor::estimate_model_memory;
+use super::types::{DailyUsage, GpuInfo, HardwareStats};
 
 /// Render the diagnostics panel
-pub fn render_diagnostics_panel(frame: &mut Frame, area: Rect, stats: &HardwareStats) {
+pub fn render_diagnostics_panel(
+    frame: &mut Frame,
+    area: Rect,
+    stats: &HardwareStats,
+    usage: &DailyUsage,
+) {
     // Create centered panel
     let panel_width = 50.min(area.width);
-    let panel_height = 20.min(area.height);
+    let panel_height = 21.min(area.height);
 
     let x = (area.width.saturating_sub(panel_width)) / 2;
     let y = (area.height.saturating_sub(panel_height)) / 2;
@@ -24,10 +30,11 @@ pub fn render_diagnostics_panel(frame: &mut Frame, area: Rect, stats: &HardwareS
         .direction(Direction::Vertical)
         .margin(1)
         .constraints([
-            Constraint::Length(5), // GPU section
-            Constraint::Length(3), // Model section
+            Constraint::Length(6), // GPU section
+            Constraint::Length(4), // Model section
             Constraint::Length(3), // Performance section
             Constraint::Length(3), // System section
+            Constraint::Length(2), // Usage section
             Constraint::Min(1),    // Help text
         ])
         .split(panel_area);
@@ -57,6 +64,9 @@ pub fn render_diagnostics_panel(frame: &mut Frame, area: Rect, stats: &HardwareS
     // System Section
     render_system_section(frame, chunks[3], stats);
 
+    // Usage Section
+    render_usage_section(frame, chunks[4], usage);
+
     // Help text
     let help = Paragraph::new(Line::from(vec![
         Span::raw("Press "),
@@ -68,11 +78,11 @@ pub fn render_diagnostics_panel(frame: &mut Frame, area: Rect, stats: &HardwareS
     .alignment(Alignment::Center)
     .style(Style::default().fg(Color::DarkGray));
 
-    frame.render_widget(help, chunks[4]);
+    frame.render_widget(help, chunks[5]);
 }
 
 /// Render GPU section
-fn render_gpu_section(frame: &mut Frame, area: Rect, gpu: &super::types::GpuInfo) {
+fn render_gpu_section(frame: &mut Frame, area: Rect, gpu: &GpuInfo) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -80,6 +90,8 @@ fn render_gpu_section(frame: &mut Frame, area: Rect, gpu: &super::types::GpuInfo
             Constraint::Length(1), // Usage gauge
             Constraint::Length(1), // VRAM gauge
             Constraint::Length(1), // Temperature
+            Constraint::Length(1), // Per-model VRAM bar
+            Constraint::Length(1), // Per-model VRAM legend
         ])
         .split(area);
 
@@ -96,14 +108,19 @@ fn render_gpu_section(frame: &mut Frame, area: Rect, gpu: &super::types::GpuInfo
     );
     frame.render_widget(gpu_name, chunks[0]);
 
-    // GPU usage gauge
-    let usage_color = get_usage_color(gpu.usage_percent);
-    let usage_gauge = Gauge::default()
-        .block(Block::default().title(format!("Usage: {:.0}%", gpu.usage_percent)))
-        .gauge_style(Style::default().fg(usage_color))
-        .percent(gpu.usage_percent as u16)
-        .label("");
-    frame.render_widget(usage_gauge, chunks[1]);
+    // GPU utilization bar
+    if let Some(utilization) = gpu.utilization_percent {
+        let usage_gauge = Gauge::default()
+            .block(Block::default().title(format!("Utilization: {:.0}%", utilization)))
+            .gauge_style(Style::default().fg(get_usage_color(utilization)))
+            .percent(utilization.clamp(0.0, 100.0) as u16)
+            .label("");
+        frame.render_widget(usage_gauge, chunks[1]);
+    } else {
+        let unknown =
+            Paragraph::new("Utilization: n/a").style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(unknown, chunks[1]);
+    }
 
     // VRAM gauge
     let vram_percent = (gpu.memory_used_gb / gpu.memory_total_gb * 100.0).min(100.0);
@@ -118,26 +135,63 @@ fn render_gpu_section(frame: &mut Frame, area: Rect, gpu: &super::types::GpuInfo
         .label("");
     frame.render_widget(vram_gauge, chunks[2]);
 
-    // Temperature if available
+    // Temperature gauge, on a 0-100°C scale
     if let Some(temp) = gpu.temperature_celsius {
-        let temp_color = if temp > 85.0 {
-            Color::Red
-        } else if temp > 75.0 {
-            Color::Yellow
-        } else {
-            Color::Green
-        };
+        let temp_gauge = Gauge::default()
+            .block(Block::default().title(format!("Temperature: {:.0}°C", temp)))
+            .gauge_style(Style::default().fg(get_temperature_color(temp)))
+            .percent(temp.clamp(0.0, 100.0) as u16)
+            .label("");
+        frame.render_widget(temp_gauge, chunks[3]);
+    }
 
-        let temp_text = Paragraph::new(format!("Temperature: {:.0}°C", temp))
-            .style(Style::default().fg(temp_color));
-        frame.render_widget(temp_text, chunks[3]);
+    // VRAM per loaded model as a stacked bar
+    if !gpu.model_vram_mb.is_empty() {
+        render_model_vram(frame, chunks[4], chunks[5], gpu);
     }
 }
 
+/// Render a stacked bar with one segment per loaded model, plus a legend
+fn render_model_vram(frame: &mut Frame, bar_area: Rect, legend_area: Rect, gpu: &GpuInfo) {
+    const COLORS: [Color; 5] = [
+        Color::Magenta,
+        Color::Blue,
+        Color::Yellow,
+        Color::Cyan,
+        Color::Red,
+    ];
+
+    let mut models: Vec<(&String, &u64)> = gpu.model_vram_mb.iter().collect();
+    models.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+    let total_mb = (gpu.memory_total_gb * 1024.0).max(1.0);
+    let width = bar_area.width as f32;
+
+    let mut bar = Vec::new();
+    let mut legend = Vec::new();
+    for (i, (name, vram_mb)) in models.iter().enumerate() {
+        let color = COLORS[i % COLORS.len()];
+        let segment = ((**vram_mb as f32 / total_mb) * width).round().max(1.0) as usize;
+        bar.push(Span::styled(
+            "█".repeat(segment),
+            Style::default().fg(color),
+        ));
+        legend.push(Span::styled("■ ", Style::default().fg(color)));
+        legend.push(Span::raw(format!(
+            "{} {:.1}GB  ",
+            name,
+            **vram_mb as f32 / 1024.0
+        )));
+    }
+
+    frame.render_widget(Paragraph::new(Line::from(bar)), bar_area);
+    frame.render_widget(Paragraph::new(Line::from(legend)), legend_area);
+}
+
 /// Render model section
 fn render_model_section(frame: &mut Frame, area: Rect, stats: &HardwareStats) {
     if let Some(model) = &stats.model_info {
-        let lines = vec![
+        let mut lines = vec![
             Line::from(vec![
                 Span::raw("Model: "),
                 Span::styled(&model.name, Style::default().fg(Color::Cyan)),
@@ -147,6 +201,12 @@ fn render_model_section(frame: &mut Frame, area: Rect, stats: &HardwareStats) {
                 model.context_used, model.context_length
             )),
         ];
+        if let Some(estimate) = estimate_model_memory(&model.name, model.context_length) {
+            lines.push(Line::from(format!(
+                "Memory: ~{:.1}GB weights + ~{:.1}GB KV cache",
+                estimate.weights_gb, estimate.kv_cache_gb
+            )));
+        }
 
         let model_info = Paragraph::new(lines);
         frame.render_widget(model_info, area);
@@ -194,6 +254,14 @@ fn render_system_section(frame: &mut Frame, area: Rect, stats: &HardwareStats) {
     let ram_percent = (stats.ram_used_gb / stats.ram_total_gb * 100.0).min(100.0);
     let ram_color = get_usage_color(ram_percent);
 
+    let mut cores = vec![Span::raw("Cores: ")];
+    cores.extend(stats.cpu_cores.iter().map(|&usage| {
+        Span::styled(
+            core_bar(usage).to_string(),
+            Style::default().fg(get_usage_color(usage)),
+        )
+    }));
+
     let lines = vec![
         Line::from(vec![
             Span::raw("CPU: "),
@@ -202,6 +270,7 @@ fn render_system_section(frame: &mut Frame, area: Rect, stats: &HardwareStats) {
                 Style::default().fg(cpu_color),
             ),
         ]),
+        Line::from(cores),
         Line::from(vec![
             Span::raw("RAM: "),
             Span::styled(
@@ -215,6 +284,34 @@ fn render_system_section(frame: &mut Frame, area: Rect, stats: &HardwareStats) {
     frame.render_widget(system, area);
 }
 
+/// Render today's token usage section
+fn render_usage_section(frame: &mut Frame, area: Rect, usage: &DailyUsage) {
+    let lines = vec![
+        Line::from(vec![
+            Span::raw("Tokens today: "),
+            Span::styled(
+                format!(
+                    "{} ({} in / {} out)",
+                    usage.total_tokens(),
+                    usage.prompt_tokens,
+                    usage.completion_tokens
+                ),
+                Style::default().fg(Color::Cyan),
+            ),
+        ]),
+        Line::from(vec![
+            Span::raw("Est. cost: "),
+            Span::styled(
+                format!("${:.4}", usage.estimated_cost),
+                Style::default().fg(Color::Yellow),
+            ),
+        ]),
+    ];
+
+    let usage_info = Paragraph::new(lines);
+    frame.render_widget(usage_info, area);
+}
+
 /// Render compact status line at bottom of screen
 pub fn render_status_line(frame: &mut Frame, area: Rect, stats: &HardwareStats) {
     let status_text = stats.to_status_line();
@@ -232,6 +329,13 @@ pub fn render_status_line(frame: &mut Frame, area: Rect, stats: &HardwareStats)
     frame.render_widget(status, area);
 }
 
+/// Block character whose height is proportional to a core's usage
+fn core_bar(percent: f32) -> char {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let level = (percent.clamp(0.0, 100.0) / 100.0 * (BARS.len() - 1) as f32).round() as usize;
+    BARS[level]
+}
+
 /// Get color based on usage percentage
 fn get_usage_color(percent: f32) -> Color {
     if percent > 90.0 {
@@ -242,3 +346,14 @@ fn get_usage_color(percent: f32) -> Color {
         Color::Green
     }
 }
+
+/// Get color based on GPU temperature
+fn get_temperature_color(celsius: f32) -> Color {
+    if celsius >= 85.0 {
+        Color::Red
+    } else if celsius >= 70.0 {
+        Color::Yellow
+    } else {
+        Color::Green
+    }
+}
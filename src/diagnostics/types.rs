@@ -1,25 +1,63 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
 /// Hardware statistics for monitoring
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct HardwareStats {
     pub gpu: Option<GpuInfo>,
     pub cpu_usage_percent: f32,
+    #[serde(default)]
+    pub cpu_cores: Vec<f32>, // per-core usage percent
     pub ram_used_gb: f32,
     pub ram_total_gb: f32,
     pub inference_speed: Option<f32>, // tokens/sec
     pub model_info: Option<ModelInfo>,
+    #[serde(default)]
+    pub network: Option<NetworkStats>,
+    #[serde(default)]
+    pub bandwidth_bytes_per_sec: f64, // proxy traffic since the previous sample
 }
 
+/// Traffic counters for requests to the LiteLLM proxy
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NetworkStats {
+    pub bytes_sent_total: u64,
+    pub bytes_received_total: u64,
+    pub requests_total: u64,
+    pub requests_in_flight: u8,
+    pub avg_latency_ms: f64,
+}
+
+impl NetworkStats {
+    /// Total bytes transferred in either direction
+    pub fn bytes_total(&self) -> u64 {
+        self.bytes_sent_total + self.bytes_received_total
+    }
+
+    /// Fold a completed request's latency into the running average
+    pub fn record_latency(&mut self, latency_ms: f64) {
+        self.requests_total += 1;
+        self.avg_latency_ms += (latency_ms - self.avg_latency_ms) / self.requests_total as f64;
+    }
+}
+
+/// Network counters shared between the model client and the hardware monitor
+pub type SharedNetworkStats = Arc<Mutex<NetworkStats>>;
+
 /// GPU information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GpuInfo {
     pub name: String,
     pub gpu_type: GpuType,
-    pub usage_percent: f32,
+    /// None when the vendor tool can't report it (e.g. Apple Silicon)
+    #[serde(default)]
+    pub utilization_percent: Option<f32>,
     pub memory_used_gb: f32,
     pub memory_total_gb: f32,
     pub temperature_celsius: Option<f32>,
+    #[serde(default)]
+    pub model_vram_mb: HashMap<String, u64>, // VRAM per loaded Ollama model
 }
 
 /// Model runtime information
@@ -32,6 +70,50 @@ pub struct ModelInfo {
     pub context_used: usize,
 }
 
+/// Per-1k-token rates in USD as (model name fragment, prompt rate, completion rate)
+/// More specific fragments must come before their prefixes (e.g. "gpt-4o-mini" before "gpt-4o")
+pub const PRICING: &[(&str, f64, f64)] = &[
+    ("gpt-4o-mini", 0.00015, 0.0006),
+    ("gpt-4o", 0.0025, 0.01),
+    ("gpt-4-turbo", 0.01, 0.03),
+    ("gpt-4", 0.03, 0.06),
+    ("gpt-3.5", 0.0005, 0.0015),
+    ("claude-3-opus", 0.015, 0.075),
+    ("claude-3-5-sonnet", 0.003, 0.015),
+    ("claude-3-sonnet", 0.003, 0.015),
+    ("claude-3-haiku", 0.00025, 0.00125),
+    ("gemini-1.5-pro", 0.00125, 0.005),
+    ("gemini-1.5-flash", 0.000075, 0.0003),
+    ("groq/", 0.0002, 0.0002),
+];
+
+/// Estimate the cost in USD of a request; local models are free
+pub fn estimate_cost(model: &str, prompt_tokens: u64, completion_tokens: u64) -> f64 {
+    PRICING
+        .iter()
+        .find(|(fragment, _, _)| model.contains(fragment))
+        .map(|(_, prompt_rate, completion_rate)| {
+            (prompt_tokens as f64 / 1000.0) * prompt_rate
+                + (completion_tokens as f64 / 1000.0) * completion_rate
+        })
+        .unwrap_or(0.0)
+}
+
+/// Token usage accumulated today across all models
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DailyUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub estimated_cost: f64,
+}
+
+impl DailyUsage {
+    /// Total tokens used today
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
 /// Type of GPU detected
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum GpuType {
@@ -75,17 +157,21 @@ impl HardwareStats {
 
         // GPU info
         if let Some(gpu) = &self.gpu {
-            let gpu_color = if gpu.usage_percent > 90.0 {
+            let usage = gpu.utilization_percent.unwrap_or(0.0);
+            let gpu_color = if usage > 90.0 {
                 "[HIGH]"
-            } else if gpu.usage_percent > 70.0 {
+            } else if usage > 70.0 {
                 "[WARN]"
             } else {
                 "[OK]"
             };
+            let usage = gpu
+                .utilization_percent
+                .map_or("--".to_string(), |percent| format!("{:.0}%", percent));
 
             parts.push(format!(
-                "{} GPU: {:.0}% ({:.1}/{:.1}GB)",
-                gpu_color, gpu.usage_percent, gpu.memory_used_gb, gpu.memory_total_gb
+                "{} GPU: {} ({:.1}/{:.1}GB)",
+                gpu_color, usage, gpu.memory_used_gb, gpu.memory_total_gb
             ));
         }
 
@@ -118,6 +204,15 @@ impl HardwareStats {
             parts.push(format!("{:.1} tok/s", speed));
         }
 
+        // Proxy traffic
+        if let Some(network) = self.network.as_ref().filter(|n| n.requests_total > 0) {
+            parts.push(format!(
+                "NET: {}/s {:.0}ms",
+                format_bytes(self.bandwidth_bytes_per_sec as u64),
+                network.avg_latency_ms
+            ));
+        }
+
         parts.join(" │ ")
     }
 
@@ -132,7 +227,8 @@ impl HardwareStats {
         }
 
         if let Some(gpu) = &self.gpu {
-            if gpu.usage_percent > 90.0 || (gpu.memory_used_gb / gpu.memory_total_gb) * 100.0 > 90.0
+            if gpu.utilization_percent.unwrap_or(0.0) > 90.0
+                || (gpu.memory_used_gb / gpu.memory_total_gb) * 100.0 > 90.0
             {
                 return true;
             }
@@ -141,3 +237,20 @@ impl HardwareStats {
         false
     }
 }
+
+/// Format a byte count with a binary unit suffix
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{}{}", bytes, UNITS[0])
+    } else {
+        format!("{:.1}{}", value, UNITS[unit])
+    }
+}
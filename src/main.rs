@@ -1,27 +1,64 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
+use std::io::{IsTerminal, Read};
+use std::time::Duration;
 
 use mermaid::{
-    app::load_config,
-    cli::Cli,
+    app::{get_config_dir, load_config, run_setup_wizard, substitute_vars, Config},
+    cli::{resolve_project, Cli},
+    context::detect_project_type,
+    models::set_request_debugging,
+    models::{ChatMessage, MessageRole},
     ollama::ensure_model as ensure_ollama_model,
     proxy::{ensure_proxy, is_proxy_running},
-    runtime::{NonInteractiveRunner, Orchestrator},
+    runtime::{batch_prompts, context_messages, NonInteractiveRunner, Orchestrator},
+    session::{BudgetStatus, ConversationHistory, UsageReport},
+    utils::init_logger,
 };
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Parse CLI arguments
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
 
-    // Set up logging if verbose
-    if cli.verbose {
-        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    // First launch of the interactive app: set up the config before anything reads it
+    let first_launch = cli.command.is_none()
+        && cli.prompt.is_none()
+        && cli.config.is_none()
+        && !get_config_dir()?.join("config.toml").exists();
+    if first_launch && std::io::stdin().is_terminal() {
+        run_setup_wizard().await?;
+    }
+    if let Some(name) = &cli.project {
+        cli.path = Some(resolve_project(name)?);
+    }
+
+    // Set up logging from the config's log settings
+    let log_config = match &cli.config {
+        Some(path) => std::fs::read_to_string(path)
+            .ok()
+            .and_then(|toml_str| toml::from_str::<Config>(&toml_str).ok()),
+        None => load_config().ok(),
+    }
+    .unwrap_or_default();
+    init_logger(log_config.log_format, &log_config.log_levels, cli.verbose);
+    set_request_debugging(cli.debug_requests);
+
+    // A prompt piped on stdin also means non-interactive mode
+    let from_flag = cli.prompt.is_some();
+    if cli.prompt.is_none() && cli.command.is_none() && !std::io::stdin().is_terminal() {
+        let timeout = Duration::from_secs(log_config.stdin_prompt_timeout_secs);
+        cli.prompt = read_stdin_prompt(timeout)?;
+    }
+    if cli.prompt.is_none() {
+        if let Some(flag) = cli.non_interactive_flag() {
+            anyhow::bail!("{} needs a prompt, from --prompt or piped on stdin", flag);
+        }
     }
 
     // Check if running in non-interactive mode
     if let Some(prompt) = cli.prompt.clone() {
-        run_non_interactive(cli, prompt).await
+        run_non_interactive(cli, prompt, from_flag).await
     } else {
         // Create and run the orchestrator for interactive mode
         let orchestrator = Orchestrator::new(cli)?;
@@ -29,8 +66,33 @@ async fn main() -> Result<()> {
     }
 }
 
+/// Read a prompt piped on stdin, or None if nothing was piped
+fn read_stdin_prompt(timeout: Duration) -> Result<Option<String>> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = tx.send(std::io::stdin().read_to_end(&mut bytes).map(|_| bytes));
+    });
+    let bytes = match rx.recv_timeout(timeout) {
+        Ok(read) => read?,
+        Err(_) => anyhow::bail!(
+            "Timed out after {}s waiting for a prompt on stdin (use --prompt instead)",
+            timeout.as_secs()
+        ),
+    };
+    let prompt = String::from_utf8(bytes).map_err(|e| {
+        anyhow::anyhow!(
+            "stdin is not UTF-8 text (invalid byte at offset {}); pipe text or use --prompt",
+            e.utf8_error().valid_up_to()
+        )
+    })?;
+    Ok((!prompt.trim().is_empty()).then_some(prompt))
+}
+
 /// Run in non-interactive mode
-async fn run_non_interactive(cli: Cli, prompt: String) -> Result<()> {
+/// `{{VAR}}` placeholders are expanded only when `expand_vars` is set, i.e. for a
+/// `--prompt` template rather than text piped on stdin
+async fn run_non_interactive(cli: Cli, mut prompt: String, expand_vars: bool) -> Result<()> {
     // Load configuration
     let config = if let Some(config_path) = &cli.config {
         let toml_str = std::fs::read_to_string(config_path)?;
@@ -39,6 +101,17 @@ async fn run_non_interactive(cli: Cli, prompt: String) -> Result<()> {
         load_config().unwrap_or_default()
     };
 
+    // Stop before spending past a budget limit
+    if let Some(limits) = &config.budget_limits {
+        if let BudgetStatus::Exceeded(error) =
+            UsageReport::check_budget(limits, &UsageReport::load(), 0.0)
+        {
+            if !cli.override_budget {
+                anyhow::bail!("{}. Run with --override-budget to continue.", error);
+            }
+        }
+    }
+
     // Determine model to use
     let model_id = if let Some(model) = &cli.model {
         model.clone()
@@ -58,29 +131,97 @@ async fn run_non_interactive(cli: Cli, prompt: String) -> Result<()> {
     ensure_ollama_model(&model_id, cli.no_auto_install).await?;
 
     // Determine project path
-    let project_path = cli.path.unwrap_or_else(|| std::path::PathBuf::from("."));
+    // Piped runs usually have no --path, so load from the working directory
+    let project_path = match cli.path {
+        Some(path) => path,
+        None => std::env::current_dir()?,
+    };
+
+    // Expand {{VAR}} placeholders; --var values override the built-ins
+    let mut vars = vec![
+        (
+            "DATE".to_string(),
+            chrono::Local::now().format("%Y-%m-%d").to_string(),
+        ),
+        ("CWD".to_string(), project_path.display().to_string()),
+        (
+            "PROJECT_TYPE".to_string(),
+            detect_project_type(&project_path).unwrap_or_else(|| "unknown".to_string()),
+        ),
+        ("MODEL".to_string(), model_id.clone()),
+    ];
+    vars.extend(cli.prompt_vars.iter().cloned());
+    if expand_vars {
+        prompt = substitute_vars(&prompt, &vars, &project_path);
+    }
+
+    // Earlier turns from --context-file; a file that --update-context-file will create may not exist yet
+    let mut conversation = match &cli.context_file {
+        Some(path) if path.exists() || cli.update_context_file.is_none() => {
+            let json = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read context file {}", path.display()))?;
+            serde_json::from_str::<ConversationHistory>(&json)
+                .with_context(|| format!("{} is not a saved conversation", path.display()))?
+        },
+        _ => ConversationHistory::new(project_path.display().to_string(), model_id.clone()),
+    };
+
+    if cli.no_execute {
+        eprintln!("[WARNING] --no-execute is deprecated, use --dry-run");
+    }
+    let dry_run = cli.dry_run || cli.no_execute;
 
     // Create and run the non-interactive runner
     let runner = NonInteractiveRunner::new(
         model_id,
         project_path,
         config,
-        cli.no_execute,
+        dry_run,
         cli.max_tokens,
+        cli.json_response,
+        cli.extra_roots,
+        context_messages(&conversation),
     )
     .await?;
 
+    // Run the prompt against each line of the prompts file
+    if let Some(prompts_file) = &cli.prompts_file {
+        let prompts = batch_prompts(&prompt, &std::fs::read_to_string(prompts_file)?);
+        let runner = runner.with_parallelism(cli.parallel).await?;
+        let batch = runner.execute_batch(prompts, cli.parallel).await;
+        println!("{}", runner.format_batch(&batch, cli.output_format));
+        match batch.exit_code(dry_run) {
+            0 => return Ok(()),
+            code => std::process::exit(code),
+        }
+    }
+
     // Execute the prompt
     let result = runner.execute(prompt).await?;
 
+    // Append this turn for the next step of a scripted workflow
+    if let Some(path) = &cli.update_context_file {
+        if !result.response.is_empty() {
+            let message = |role, content: &str| ChatMessage {
+                role,
+                content: content.to_string(),
+                timestamp: chrono::Local::now(),
+            };
+            conversation.add_messages(&[
+                message(MessageRole::User, &result.prompt),
+                message(MessageRole::Assistant, &result.response),
+            ]);
+            std::fs::write(path, serde_json::to_string_pretty(&conversation)?)?;
+        }
+    }
+
     // Format and output the result
     let formatted = runner.format_result(&result, cli.output_format);
     println!("{}", formatted);
 
-    // Exit with appropriate code
-    if !result.errors.is_empty() {
-        std::process::exit(1);
+    // Exit with appropriate code: 1 on errors, 2 when a dry run proposed actions
+    match result.exit_code(dry_run) {
+        0 => Ok(()),
+        code => std::process::exit(code),
     }
-
-    Ok(())
 }
@@ -0,0 +1,214 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+/// Set by `--debug-requests`
+static DEBUG_REQUESTS: AtomicBool = AtomicBool::new(false);
+
+/// Once the log grows past this, its oldest half is dropped
+const MAX_LOG_BYTES: u64 = 50 * 1024 * 1024;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Body keys whose values are always redacted
+const SENSITIVE_KEYS: &[&str] = &[
+    "api_key",
+    "apikey",
+    "authorization",
+    "x-api-key",
+    "access_token",
+    "secret",
+    "password",
+];
+
+/// Log every proxy request and response to `debug_requests.log`
+pub fn set_request_debugging(enabled: bool) {
+    DEBUG_REQUESTS.store(enabled, Ordering::Relaxed);
+}
+
+/// Capture of one chat request, written before and after the HTTP call
+/// Does nothing unless request debugging is on
+pub(crate) struct RequestLog {
+    enabled: bool,
+    started: Instant,
+    request_start_ms: i64,
+    first_token_ms: Option<u128>,
+    /// Streamed bytes, decoded only once complete so characters split across chunks survive
+    raw_response: Vec<u8>,
+}
+
+impl RequestLog {
+    /// Log the outgoing request (with credentials redacted) and start timing
+    pub fn start(url: &str, authorized: bool, body: &Value) -> Self {
+        let enabled = DEBUG_REQUESTS.load(Ordering::Relaxed);
+        let log = Self {
+            enabled,
+            started: Instant::now(),
+            request_start_ms: chrono::Utc::now().timestamp_millis(),
+            first_token_ms: None,
+            raw_response: Vec::new(),
+        };
+        if enabled {
+            let headers = if authorized {
+                json!({ "Authorization": REDACTED })
+            } else {
+                json!({})
+            };
+            log.write(json!({
+                "type": "request",
+                "request_start_ms": log.request_start_ms,
+                "url": url,
+                "headers": headers,
+                "body": redact(body),
+            }));
+        }
+        log
+    }
+
+    /// Note the arrival of the first streamed token
+    pub fn first_token(&mut self) {
+        if self.enabled && self.first_token_ms.is_none() {
+            self.first_token_ms = Some(self.started.elapsed().as_millis());
+        }
+    }
+
+    /// Keep a raw chunk of a streamed response
+    pub fn record_chunk(&mut self, chunk: &[u8]) {
+        if self.enabled {
+            self.raw_response.extend_from_slice(chunk);
+        }
+    }
+
+    /// Log the response, using the streamed chunks when `body` is None
+    pub fn finish(self, status: u16, body: Option<&[u8]>) {
+        if !self.enabled {
+            return;
+        }
+        let raw = String::from_utf8_lossy(body.unwrap_or(&self.raw_response)).into_owned();
+        let body = serde_json::from_str(&raw).unwrap_or(Value::String(raw));
+        self.write(json!({
+            "type": "response",
+            "request_start_ms": self.request_start_ms,
+            "status": status,
+            "first_token_ms": self.first_token_ms,
+            "completion_ms": self.started.elapsed().as_millis(),
+            "body": redact(&body),
+        }));
+    }
+
+    fn write(&self, entry: Value) {
+        let text = serde_json::to_string_pretty(&entry).unwrap_or_default() + "\n";
+        if let Ok(path) = log_file() {
+            let _ = append_capped(&path, &text, MAX_LOG_BYTES);
+        }
+    }
+}
+
+/// Get the path to the log (~/.local/share/mermaid/debug_requests.log)
+fn log_file() -> Result<PathBuf> {
+    let home = std::env::var("HOME")?;
+    let data_dir = PathBuf::from(home)
+        .join(".local")
+        .join("share")
+        .join("mermaid");
+    fs::create_dir_all(&data_dir)?;
+    Ok(data_dir.join("debug_requests.log"))
+}
+
+/// Append to the log, first dropping its oldest half if it would exceed `max_bytes`
+fn append_capped(path: &Path, text: &str, max_bytes: u64) -> Result<()> {
+    let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    if size + text.len() as u64 > max_bytes {
+        let content = fs::read(path)?;
+        let cut = content.len().saturating_sub((max_bytes / 2) as usize);
+        // Start the kept part on a line boundary
+        let start = content[cut..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(content.len(), |i| cut + i + 1);
+        fs::write(path, &content[start..])?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    file.write_all(text.as_bytes())?;
+    Ok(())
+}
+
+/// Copy of a JSON value with credentials replaced by `[REDACTED]`
+fn redact(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let lower = key.to_lowercase();
+                    if SENSITIVE_KEYS.contains(&lower.as_str()) || lower.ends_with("_key") {
+                        (key.clone(), json!(REDACTED))
+                    } else {
+                        (key.clone(), redact(value))
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        // Provider keys pasted into messages, e.g. `sk-...`
+        Value::String(s) if looks_like_api_key(s) => json!(REDACTED),
+        _ => value.clone(),
+    }
+}
+
+fn looks_like_api_key(s: &str) -> bool {
+    s.starts_with("sk-") && s.len() >= 20 && !s.contains(char::is_whitespace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_redact_and_cap() {
+        let body = json!({
+            "model": "openai/gpt-4o",
+            "max_tokens": 100,
+            "api_key": "abc",
+            "metadata": { "openai_key": "abc" },
+            "messages": [{ "role": "user", "content": "sk-1234567890abcdefghij" }],
+        });
+        let redacted = redact(&body);
+        assert_eq!(redacted["max_tokens"], 100);
+        assert_eq!(redacted["api_key"], REDACTED);
+        assert_eq!(redacted["metadata"]["openai_key"], REDACTED);
+        assert_eq!(redacted["messages"][0]["content"], REDACTED);
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("debug.log");
+        for i in 0..10 {
+            append_capped(&path, &format!("entry {}\n", i), 40).unwrap();
+        }
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.len() <= 40);
+        assert!(content.ends_with("entry 9\n"));
+        assert!(!content.contains("entry 0"));
+    }
+
+    #[test]
+    fn test_chunks_split_inside_a_character() {
+        let mut log = RequestLog {
+            enabled: true,
+            started: Instant::now(),
+            request_start_ms: 0,
+            first_token_ms: None,
+            raw_response: Vec::new(),
+        };
+        let bytes = "café".as_bytes();
+        log.record_chunk(&bytes[..4]);
+        log.record_chunk(&bytes[4..]);
+        assert_eq!(String::from_utf8_lossy(&log.raw_response), "café");
+    }
+}
@@ -3,6 +3,7 @@ use anyhow::Result;
 use super::traits::Model;
 use super::unified::UnifiedModel;
 use crate::app::Config;
+use crate::ollama;
 
 /// Factory for creating model instances using the unified LLM interface
 pub struct ModelFactory;
@@ -22,10 +23,42 @@ impl ModelFactory {
 
         // With LiteLLM proxy, we just pass the model ID directly
         // LiteLLM handles all provider-specific authentication and routing
-        let model = UnifiedModel::new(model_id, master_key).await?;
+        let model = UnifiedModel::new(model_id, master_key)
+            .await?
+            .with_context_window_override(
+                config.and_then(|c| c.default_model.context_window_override),
+            );
         Ok(Box::new(model))
     }
 
+    /// Create a model from a loose name such as "llama3" or "gpt4"
+    /// See `resolve_model_id` for how the name is matched
+    pub async fn create_fuzzy(query: &str, config: &Config) -> Result<Box<dyn Model>> {
+        let model_id = Self::resolve_model_id(query, config).await;
+        Self::create(&model_id, Some(config)).await
+    }
+
+    /// Turn a loose model name into a `provider/model` ID: a configured alias
+    /// wins, then the closest installed Ollama model, then the name as given
+    /// (assumed to be an Ollama model when it has no provider)
+    pub async fn resolve_model_id(query: &str, config: &Config) -> String {
+        if let Some(model_id) = config.default_model.model_aliases.get(query) {
+            return model_id.clone();
+        }
+
+        // Other providers' models aren't listed by Ollama
+        let name = match query.split_once('/') {
+            Some(("ollama", name)) => name,
+            Some(_) => return query.to_string(),
+            None => query,
+        };
+        let installed = ollama::list_models_async().await.unwrap_or_default();
+        match fuzzy_match(name, &installed) {
+            Some(model) => format!("ollama/{}", model),
+            None => format!("ollama/{}", name),
+        }
+    }
+
     /// List available models from LiteLLM proxy
     pub async fn list_available() -> Result<Vec<String>> {
         use reqwest::Client;
@@ -86,3 +119,54 @@ impl ModelFactory {
         }
     }
 }
+
+/// The candidate that best matches `query`: an exact name (ignoring a
+/// `:latest` tag), then a prefix, then a substring, then the letters of the
+/// query in order; ties go to the shortest name
+fn fuzzy_match<'a>(query: &str, candidates: &'a [String]) -> Option<&'a String> {
+    let query = query.to_lowercase();
+    let score = |candidate: &str| -> Option<u8> {
+        let candidate = candidate.to_lowercase();
+        if candidate == query || candidate.strip_suffix(":latest") == Some(query.as_str()) {
+            Some(0)
+        } else if candidate.starts_with(&query) {
+            Some(1)
+        } else if candidate.contains(&query) {
+            Some(2)
+        } else if is_subsequence(&query, &candidate) {
+            Some(3)
+        } else {
+            None
+        }
+    };
+    candidates
+        .iter()
+        .filter_map(|candidate| score(candidate).map(|s| (s, candidate.len(), candidate)))
+        .min()
+        .map(|(_, _, candidate)| candidate)
+}
+
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().all(|c| haystack.any(|h| h == c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_match() {
+        let installed: Vec<String> = ["llama3:latest", "llama3:70b", "deepseek-coder:33b"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let matched = |query| fuzzy_match(query, &installed).map(String::as_str);
+
+        assert_eq!(matched("llama3"), Some("llama3:latest"));
+        assert_eq!(matched("llama3:7"), Some("llama3:70b"));
+        assert_eq!(matched("coder"), Some("deepseek-coder:33b"));
+        assert_eq!(matched("dscoder"), Some("deepseek-coder:33b"));
+        assert_eq!(matched("mistral"), None);
+    }
+}
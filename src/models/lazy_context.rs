@@ -1,9 +1,15 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use tokio::sync::RwLock;
+use tokio::sync::{Notify, RwLock};
+
+use crate::models::FileContent;
+use crate::utils::estimate_tokens;
+
+/// Number of files loaded concurrently per background batch
+const BACKGROUND_BATCH_SIZE: usize = 10;
 
 /// A lazily-loaded project context that loads files on demand
 #[derive(Debug, Clone)]
@@ -15,13 +21,21 @@ pub struct LazyProjectContext {
     /// List of all file paths (loaded immediately)
     pub file_paths: Arc<Vec<PathBuf>>,
     /// Lazily loaded file contents
-    pub files: Arc<RwLock<HashMap<String, String>>>,
+    pub files: Arc<RwLock<HashMap<String, FileContent>>>,
     /// Running token count (updated as files are loaded)
     pub token_count: Arc<AtomicUsize>,
     /// Files that have been requested for loading
     pub loading_queue: Arc<Mutex<Vec<PathBuf>>>,
     /// Cache manager for persistent caching
     pub cache: Option<Arc<crate::cache::CacheManager>>,
+    /// Number of files processed by the background loader
+    pub load_progress: Arc<AtomicUsize>,
+    /// Signalled once the background loader has processed every file
+    loaded: Arc<Notify>,
+    /// Remove comments from source files as they load
+    strip_comments: bool,
+    /// Estimated tokens saved so far by stripping comments
+    stripped_tokens: Arc<AtomicUsize>,
 }
 
 impl LazyProjectContext {
@@ -37,11 +51,35 @@ impl LazyProjectContext {
             token_count: Arc::new(AtomicUsize::new(0)),
             loading_queue: Arc::new(Mutex::new(Vec::new())),
             cache,
+            load_progress: Arc::new(AtomicUsize::new(0)),
+            loaded: Arc::new(Notify::new()),
+            strip_comments: false,
+            stripped_tokens: Arc::new(AtomicUsize::new(0)),
         }
     }
 
+    /// Strip comments from source files as they load (see `context::strip_comments`)
+    pub fn with_comment_stripping(mut self, enabled: bool) -> Self {
+        self.strip_comments = enabled;
+        self
+    }
+
+    /// Order files so those with a priority extension are loaded first
+    pub fn with_priority_extensions(mut self, extensions: &[String]) -> Self {
+        let mut paths = self.file_paths.as_ref().clone();
+        paths.sort_by_key(|path| {
+            let ext = path
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            !extensions.contains(&ext)
+        });
+        self.file_paths = Arc::new(paths);
+        self
+    }
+
     /// Get a file's content, loading it if necessary
-    pub async fn get_file(&self, path: &str) -> Result<Option<String>> {
+    pub async fn get_file(&self, path: &str) -> Result<Option<FileContent>> {
         // Check if already loaded
         {
             let files = self.files.read().await;
@@ -59,11 +97,30 @@ impl LazyProjectContext {
 
         // Load the file
         if full_path.exists() {
-            let content = tokio::fs::read_to_string(&full_path).await?;
+            // Binary files are rejected by content, as in a full load
+            let bytes = tokio::fs::read(&full_path).await?;
+            if crate::context::is_binary(&bytes) {
+                anyhow::bail!("Skipping binary file: {}", full_path.display());
+            }
+            let raw = String::from_utf8(bytes)
+                .with_context(|| format!("File is not valid UTF-8: {}", full_path.display()))?;
+            let stripped = self
+                .strip_comments
+                .then(|| crate::context::strip_comments(&full_path, &raw))
+                .flatten();
+            let content = match stripped {
+                Some(stripped) => {
+                    let saved = estimate_tokens(&raw).saturating_sub(estimate_tokens(&stripped));
+                    self.stripped_tokens.fetch_add(saved, Ordering::Relaxed);
+                    crate::context::stripped_file_content(&full_path, stripped)
+                },
+                None => crate::context::file_content(&full_path, raw),
+            };
 
             // Count tokens
             if let Some(ref cache) = self.cache {
-                if let Ok(tokens) = cache.get_or_compute_tokens(&full_path, &content, "cl100k_base")
+                if let Ok(tokens) =
+                    cache.get_or_compute_tokens(&full_path, content.text(), "cl100k_base")
                 {
                     self.token_count.fetch_add(tokens, Ordering::Relaxed);
                 }
@@ -79,6 +136,11 @@ impl LazyProjectContext {
         }
     }
 
+    /// Estimated tokens saved so far by `with_comment_stripping`
+    pub fn stripped_comment_tokens(&self) -> usize {
+        self.stripped_tokens.load(Ordering::Relaxed)
+    }
+
     /// Load a batch of files in the background
     pub async fn load_files_batch(&self, paths: Vec<String>) -> Result<()> {
         use futures::future::join_all;
@@ -98,12 +160,7 @@ impl LazyProjectContext {
     pub fn get_file_list(&self) -> Vec<String> {
         self.file_paths
             .iter()
-            .filter_map(|p| {
-                p.strip_prefix(&self.root_path)
-                    .ok()
-                    .and_then(|p| p.to_str())
-                    .map(|s| s.to_string())
-            })
+            .filter_map(|p| self.relative_path(p).map(|s| s.to_string()))
             .collect()
     }
 
@@ -117,9 +174,58 @@ impl LazyProjectContext {
         self.file_paths.len()
     }
 
-    /// Check if all files are loaded
-    pub async fn is_fully_loaded(&self) -> bool {
-        self.loaded_file_count().await >= self.total_file_count()
+    /// Check if the background loader has processed every file
+    pub fn is_fully_loaded(&self) -> bool {
+        self.load_progress.load(Ordering::Relaxed) >= self.total_file_count()
+    }
+
+    /// Wait until the background loader has processed every file
+    pub async fn await_loaded(&self) {
+        let notified = self.loaded.notified();
+        if self.is_fully_loaded() {
+            return;
+        }
+        notified.await;
+    }
+
+    /// Files in background load order: well-known project files, then the rest
+    pub fn load_order(&self) -> Vec<String> {
+        let mut order = get_priority_files(&self.root_path);
+        order.retain(|f| {
+            self.file_paths
+                .iter()
+                .any(|p| self.relative_path(p) == Some(f.as_str()))
+        });
+
+        for path in self.file_paths.iter() {
+            match self.relative_path(path) {
+                Some(rel) if order.iter().any(|f| f == rel) => {},
+                Some(rel) => order.push(rel.to_string()),
+                // Non-UTF-8 paths can't be keyed, but still count towards progress
+                None => order.push(String::new()),
+            }
+        }
+
+        order
+    }
+
+    /// Load every file in the background, making each batch available as soon as it's read
+    pub async fn load_all(&self) {
+        let order = self.load_order();
+        for chunk in order.chunks(BACKGROUND_BATCH_SIZE) {
+            let paths: Vec<String> = chunk.iter().filter(|p| !p.is_empty()).cloned().collect();
+            let _ = self.load_files_batch(paths).await;
+            self.load_progress.fetch_add(chunk.len(), Ordering::Relaxed);
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        self.loaded.notify_waiters();
+    }
+
+    /// Path relative to the project root
+    fn relative_path<'a>(&self, path: &'a Path) -> Option<&'a str> {
+        path.strip_prefix(&self.root_path)
+            .ok()
+            .and_then(|p| p.to_str())
     }
 
     /// Convert to regular ProjectContext (for compatibility)
@@ -189,3 +295,39 @@ pub fn get_priority_files(root_path: &str) -> Vec<String> {
     })
     .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priority_extensions_load_first() {
+        let context = LazyProjectContext::new(
+            "/nonexistent".to_string(),
+            vec![
+                PathBuf::from("/nonexistent/notes.txt"),
+                PathBuf::from("/nonexistent/src/main.rs"),
+                PathBuf::from("/nonexistent/data.csv"),
+                PathBuf::from("/nonexistent/src/lib.rs"),
+            ],
+        )
+        .with_priority_extensions(&["rs".to_string()]);
+
+        assert_eq!(
+            context.load_order(),
+            vec!["src/main.rs", "src/lib.rs", "notes.txt", "data.csv"]
+        );
+        assert!(!context.is_fully_loaded());
+    }
+
+    #[tokio::test]
+    async fn test_get_file_rejects_binary() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("notes.md"), "# Notes\n").unwrap();
+        std::fs::write(dir.path().join("logo.md"), b"\x89PNG\r\n\x1a\n\0\0").unwrap();
+        let context = LazyProjectContext::new(dir.path().to_string_lossy().into_owned(), vec![]);
+
+        assert!(context.get_file("notes.md").await.unwrap().is_some());
+        assert!(context.get_file("logo.md").await.is_err());
+    }
+}
@@ -2,18 +2,22 @@
 // All external access must go through this gateway
 
 // Private submodules - not directly accessible from outside
+mod debug_log;
 mod factory;
 mod lazy_context;
+mod sse;
 mod traits;
 mod types;
 mod unified;
 
 // Public re-exports - the ONLY way to access model functionality
+pub use debug_log::set_request_debugging;
 pub use factory::ModelFactory;
 pub use lazy_context::{get_priority_files, LazyProjectContext};
 pub use traits::Model;
 pub use types::{
-    ChatMessage, MessageRole, ModelCapabilities, ModelConfig, ModelResponse, ProjectContext,
-    StreamCallback, TokenUsage,
+    format_tool_call, parse_tool_call, ChatMessage, FileContent, MessageRole, ModelCapabilities,
+    ModelConfig, ModelResponse, ProjectContext, ResponseFormat, StreamCallback, TokenUsage,
+    ToolDefinition, TOOL_CALL_PREFIX,
 };
-pub use unified::create_from_string;
+pub use unified::{create_from_string, network_stats};
@@ -0,0 +1,136 @@
+/// A complete server-sent event
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SseEvent {
+    /// Event type from the `event:` field (the spec's default is "message")
+    pub event: Option<String>,
+    /// `data:` lines joined with newlines
+    pub data: String,
+    /// Last event ID seen on the stream
+    pub id: Option<String>,
+    /// Reconnection time in milliseconds from the `retry:` field
+    pub retry: Option<u64>,
+}
+
+/// Incremental SSE parser that buffers partial lines and events across chunks
+#[derive(Debug, Default)]
+pub struct SseBuffer {
+    /// Bytes of an incomplete line (kept as bytes so split UTF-8 characters survive)
+    partial_line: Vec<u8>,
+    /// Fields of the event currently being read
+    current: SseEvent,
+    has_data: bool,
+    last_id: Option<String>,
+}
+
+impl SseBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a chunk of the response body, returning every event it completed
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.partial_line.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        while let Some(newline) = self.partial_line.iter().position(|&b| b == b'\n') {
+            let mut line: Vec<u8> = self.partial_line.drain(..=newline).collect();
+            line.pop(); // '\n'
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+
+            let line = String::from_utf8_lossy(&line);
+            if let Some(event) = self.process_line(&line) {
+                events.push(event);
+            }
+        }
+
+        events
+    }
+
+    /// Handle one line; a blank line dispatches the pending event
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+
+        // Lines starting with a colon are comments (often used as keep-alives)
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+
+        match field {
+            "data" => {
+                if self.has_data {
+                    self.current.data.push('\n');
+                }
+                self.current.data.push_str(value);
+                self.has_data = true;
+            },
+            "event" => self.current.event = Some(value.to_string()),
+            // IDs containing NULL are ignored per the spec
+            "id" if !value.contains('\0') => self.last_id = Some(value.to_string()),
+            "retry" => {
+                if let Ok(retry) = value.parse() {
+                    self.current.retry = Some(retry);
+                }
+            },
+            _ => {},
+        }
+
+        None
+    }
+
+    /// Emit the pending event if it carried any data
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let mut event = std::mem::take(&mut self.current);
+        let has_data = std::mem::replace(&mut self.has_data, false);
+        if !has_data {
+            return None;
+        }
+
+        event.id = self.last_id.clone();
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_split_across_chunks() {
+        let mut buffer = SseBuffer::new();
+        let stream = "id: 1\ndata: {\"a\":\ndata: 1}\n\n: keep-alive\n\nevent: done\nretry: 3000\ndata: [DONE]\r\n\r\n";
+
+        // Split at every possible position, including mid-field and mid-"\r\n"
+        for split in 0..stream.len() {
+            let (first, second) = stream.as_bytes().split_at(split);
+            let mut events = buffer.push(first);
+            events.extend(buffer.push(second));
+
+            assert_eq!(events.len(), 2, "split at {}", split);
+            assert_eq!(events[0].data, "{\"a\":\n1}");
+            assert_eq!(events[0].id.as_deref(), Some("1"));
+            assert_eq!(events[1].event.as_deref(), Some("done"));
+            assert_eq!(events[1].retry, Some(3000));
+            assert_eq!(events[1].data, "[DONE]");
+        }
+    }
+
+    #[test]
+    fn test_split_utf8_character() {
+        let mut buffer = SseBuffer::new();
+        let bytes = "data: héllo\n\n".as_bytes();
+        let (first, second) = bytes.split_at(8); // inside 'é'
+
+        assert!(buffer.push(first).is_empty());
+        let events = buffer.push(second);
+        assert_eq!(events[0].data, "héllo");
+    }
+}
@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::context::{summarize_dependencies, DEPENDENCY_MANIFESTS};
+
 /// Represents a chat message
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
@@ -11,10 +13,93 @@ pub struct ChatMessage {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "StoredRole")]
 pub enum MessageRole {
     User,
     Assistant,
     System,
+    /// Result of a function call requested by the assistant, answering the call with this ID
+    /// (empty for results that don't answer a call, such as web search feedback)
+    Tool {
+        tool_call_id: String,
+    },
+}
+
+impl MessageRole {
+    /// Role of a result that doesn't answer a particular call
+    pub fn tool() -> Self {
+        MessageRole::Tool {
+            tool_call_id: String::new(),
+        }
+    }
+
+    pub fn is_tool(&self) -> bool {
+        matches!(self, MessageRole::Tool { .. })
+    }
+}
+
+/// How roles are saved, including the bare `"Tool"` of conversations saved before
+/// tool results carried their call ID
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredRole {
+    Current(CurrentRole),
+    Legacy(LegacyRole),
+}
+
+#[derive(Deserialize)]
+enum CurrentRole {
+    User,
+    Assistant,
+    System,
+    Tool { tool_call_id: String },
+}
+
+#[derive(Deserialize)]
+enum LegacyRole {
+    Tool,
+}
+
+impl From<StoredRole> for MessageRole {
+    fn from(role: StoredRole) -> Self {
+        match role {
+            StoredRole::Current(CurrentRole::User) => MessageRole::User,
+            StoredRole::Current(CurrentRole::Assistant) => MessageRole::Assistant,
+            StoredRole::Current(CurrentRole::System) => MessageRole::System,
+            StoredRole::Current(CurrentRole::Tool { tool_call_id }) => {
+                MessageRole::Tool { tool_call_id }
+            },
+            StoredRole::Legacy(LegacyRole::Tool) => MessageRole::tool(),
+        }
+    }
+}
+
+/// Content of a file in the project context
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileContent {
+    /// The file as it is on disk
+    Raw(String),
+    /// A generated summary that stands in for a verbose spec file
+    SpecSummary(String),
+    /// Source with its comments removed, so it mustn't be written back as is
+    CommentsStripped(String),
+}
+
+impl FileContent {
+    /// The text sent to the model
+    pub fn text(&self) -> &str {
+        match self {
+            FileContent::Raw(text)
+            | FileContent::SpecSummary(text)
+            | FileContent::CommentsStripped(text) => text,
+        }
+    }
+}
+
+impl From<String> for FileContent {
+    fn from(content: String) -> Self {
+        FileContent::Raw(content)
+    }
 }
 
 /// Represents the context of the current project
@@ -23,13 +108,17 @@ pub struct ProjectContext {
     /// Root directory of the project
     pub root_path: String,
     /// Map of file paths to their contents
-    pub files: HashMap<String, String>,
+    pub files: HashMap<String, FileContent>,
     /// Project type (e.g., "rust", "python", "javascript")
     pub project_type: Option<String>,
     /// Total token count of the context
     pub token_count: usize,
     /// Files to explicitly include in context
     pub included_files: Vec<String>,
+    /// `name = version` pairs from the root dependency manifests
+    pub dependencies_summary: Option<String>,
+    /// Ranked symbols of the most relevant files, for the current request
+    pub repo_map: Option<String>,
 }
 
 impl ProjectContext {
@@ -40,12 +129,30 @@ impl ProjectContext {
             project_type: None,
             token_count: 0,
             included_files: Vec::new(),
+            dependencies_summary: None,
+            repo_map: None,
         }
     }
 
     /// Add a file to the context
-    pub fn add_file(&mut self, path: String, content: String) {
-        self.files.insert(path, content);
+    pub fn add_file(&mut self, path: String, content: impl Into<FileContent>) {
+        let manifest = DEPENDENCY_MANIFESTS.contains(&path.as_str());
+        self.files.insert(path, content.into());
+        if manifest {
+            self.update_dependencies_summary();
+        }
+    }
+
+    /// Rebuild the dependency summary from the loaded root manifests
+    fn update_dependencies_summary(&mut self) {
+        let sections: Vec<String> = DEPENDENCY_MANIFESTS
+            .iter()
+            .filter_map(|manifest| {
+                let content = self.files.get(*manifest)?;
+                summarize_dependencies(manifest, content.text())
+            })
+            .collect();
+        self.dependencies_summary = (!sections.is_empty()).then(|| sections.join("\n"));
     }
 
     /// Get a formatted string of the project context for the model
@@ -57,6 +164,9 @@ impl ProjectContext {
         }
 
         context.push_str(&format!("Project root: {}\n", self.root_path));
+        if let Some(dependencies) = &self.dependencies_summary {
+            context.push_str(&format!("Dependencies:\n{}\n", dependencies));
+        }
         context.push_str(&format!("Files in context: {}\n\n", self.files.len()));
 
         // Add file tree structure
@@ -66,13 +176,26 @@ impl ProjectContext {
         }
         context.push('\n');
 
+        if let Some(repo_map) = &self.repo_map {
+            context.push_str(&format!("Repository map:\n{}\n\n", repo_map));
+        }
+
         // Add explicitly included files
         if !self.included_files.is_empty() {
             context.push_str("Relevant file contents:\n");
             for file_path in &self.included_files {
                 if let Some(content) = self.files.get(file_path) {
-                    context.push_str(&format!("\n=== {} ===\n", file_path));
-                    context.push_str(content);
+                    match content {
+                        FileContent::Raw(_) => {
+                            context.push_str(&format!("\n=== {} ===\n", file_path))
+                        },
+                        FileContent::SpecSummary(_) => {
+                            context.push_str(&format!("\n=== {} (summary) ===\n", file_path))
+                        },
+                        FileContent::CommentsStripped(_) => context
+                            .push_str(&format!("\n=== {} (comments stripped) ===\n", file_path)),
+                    }
+                    context.push_str(content.text());
                     context.push_str("\n=== end ===\n");
                 }
             }
@@ -91,6 +214,122 @@ pub struct ModelConfig {
     pub frequency_penalty: Option<f32>,
     pub presence_penalty: Option<f32>,
     pub system_prompt: Option<String>,
+    pub response_format: Option<ResponseFormat>,
+    pub tools: Option<Vec<ToolDefinition>>,
+}
+
+/// A function the model may call instead of (or as well as) replying
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    /// JSON Schema for the function's arguments
+    pub parameters: serde_json::Value,
+}
+
+impl ToolDefinition {
+    /// OpenAI-compatible entry for the `tools` request field
+    pub fn to_request_value(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "function",
+            "function": {
+                "name": self.name,
+                "description": self.description,
+                "parameters": self.parameters,
+            },
+        })
+    }
+}
+
+/// Prefix of the marker line a function call is written as in response text
+pub const TOOL_CALL_PREFIX: &str = "[TOOL_CALL: ";
+
+/// Format a function call as a single `[TOOL_CALL: name]:json_args` line
+pub fn format_tool_call(name: &str, arguments: &str) -> String {
+    // Re-serialize so pretty-printed arguments stay on one line
+    let arguments = serde_json::from_str::<serde_json::Value>(arguments)
+        .map(|v| v.to_string())
+        .unwrap_or_else(|_| arguments.replace('\n', " "));
+    format!("{}{}]:{}", TOOL_CALL_PREFIX, name, arguments)
+}
+
+/// Parse a `[TOOL_CALL: name]:json_args` line into (name, arguments)
+pub fn parse_tool_call(line: &str) -> Option<(&str, &str)> {
+    let rest = line.trim().strip_prefix(TOOL_CALL_PREFIX)?;
+    let (name, arguments) = rest.split_once("]:")?;
+    Some((name.trim(), arguments.trim()))
+}
+
+/// Output format requested from the model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ResponseFormat {
+    /// Any valid JSON object
+    Json,
+    /// JSON matching the given JSON Schema
+    JsonSchema(serde_json::Value),
+    /// Plain text (the provider default)
+    Text,
+}
+
+impl ResponseFormat {
+    /// OpenAI-compatible `response_format` request field
+    pub fn to_request_value(&self) -> serde_json::Value {
+        match self {
+            ResponseFormat::Json => serde_json::json!({ "type": "json_object" }),
+            ResponseFormat::JsonSchema(schema) => serde_json::json!({
+                "type": "json_schema",
+                "json_schema": { "name": "response", "schema": schema },
+            }),
+            ResponseFormat::Text => serde_json::json!({ "type": "text" }),
+        }
+    }
+}
+
+impl ModelConfig {
+    /// Only the sampling parameters, as kept in a preset
+    pub fn parameters(&self) -> ModelConfig {
+        ModelConfig {
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            top_p: self.top_p,
+            frequency_penalty: self.frequency_penalty,
+            presence_penalty: self.presence_penalty,
+            system_prompt: None,
+            response_format: None,
+            tools: None,
+        }
+    }
+
+    /// Override parameters with the ones a preset sets
+    pub fn apply_preset(&mut self, preset: &ModelConfig) {
+        self.temperature = preset.temperature.or(self.temperature);
+        self.max_tokens = preset.max_tokens.or(self.max_tokens);
+        self.top_p = preset.top_p.or(self.top_p);
+        self.frequency_penalty = preset.frequency_penalty.or(self.frequency_penalty);
+        self.presence_penalty = preset.presence_penalty.or(self.presence_penalty);
+    }
+
+    /// Parameters that are set, e.g. "temperature=0.2, top_p=0.7"
+    pub fn describe_parameters(&self) -> String {
+        let params = [
+            ("temperature", self.temperature.map(|v| v.to_string())),
+            ("max_tokens", self.max_tokens.map(|v| v.to_string())),
+            ("top_p", self.top_p.map(|v| v.to_string())),
+            (
+                "frequency_penalty",
+                self.frequency_penalty.map(|v| v.to_string()),
+            ),
+            (
+                "presence_penalty",
+                self.presence_penalty.map(|v| v.to_string()),
+            ),
+        ];
+        params
+            .iter()
+            .filter_map(|(name, value)| value.as_ref().map(|v| format!("{}={}", name, v)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 }
 
 impl Default for ModelConfig {
@@ -151,6 +390,34 @@ To see git diff:
 [GIT_DIFF]
 ```
 
+To fold the current changes into the last commit (leave the message empty to keep it):
+```
+[GIT_COMMIT_AMEND: Fix typo in README]
+```
+
+### HTTP Requests
+
+To test an API endpoint (the body is optional):
+```
+[HTTP_REQUEST: POST http://localhost:8080/users]
+{"name": "test"}
+[/HTTP_REQUEST]
+```
+
+### Environment Variables
+
+To set a variable for later commands (add `persist=true` to also save it to `.env`):
+```
+[SET_ENV: DATABASE_URL=postgres://localhost/test]
+```
+
+### Web Search
+
+To look up documentation, error messages or library APIs (when a search provider is configured):
+```
+[WEB_SEARCH: tokio select macro]
+```
+
 ## Guidelines
 
 1. When asked to create or modify files, ALWAYS use the [FILE_WRITE:] action block
@@ -161,6 +428,8 @@ To see git diff:
 
 Remember: You're not just showing code examples - you can actually create, modify, and execute files!"#.to_string()
             ),
+            response_format: None,
+            tools: None,
         }
     }
 }
@@ -1,28 +1,105 @@
 use anyhow::{Context as _, Result};
 use async_trait::async_trait;
 use futures::StreamExt;
-use reqwest::Client;
+use once_cell::sync::Lazy;
+use reqwest::{Client, RequestBuilder, Response};
 use serde::Deserialize;
 use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
 
+use super::debug_log::RequestLog;
+use super::sse::SseBuffer;
 use super::traits::Model;
 use super::types::{
-    ChatMessage, MessageRole, ModelCapabilities, ModelConfig, ModelResponse, ProjectContext,
-    StreamCallback,
+    format_tool_call, parse_tool_call, ChatMessage, MessageRole, ModelCapabilities, ModelConfig,
+    ModelResponse, ProjectContext, StreamCallback,
 };
 use crate::constants::{
     CLAUDE_25_CONTEXT, CLAUDE_3_OPUS_CONTEXT, DEFAULT_LITELLM_PROXY_URL, GEMINI_15_PRO_CONTEXT,
     GPT35_CONTEXT, GPT4_32K_CONTEXT, GPT4_TURBO_CONTEXT, GROQ_DEFAULT_CONTEXT, GROQ_LLAMA_CONTEXT,
     HTTP_REQUEST_TIMEOUT_SECS, OLLAMA_DEFAULT_CONTEXT,
 };
+use crate::diagnostics::SharedNetworkStats;
+use crate::utils::{count_file_tokens, MermaidError};
+
+/// Traffic counters for every request made to the proxy
+static NETWORK_STATS: Lazy<SharedNetworkStats> = Lazy::new(SharedNetworkStats::default);
+
+/// Get the shared proxy traffic counters
+pub fn network_stats() -> SharedNetworkStats {
+    NETWORK_STATS.clone()
+}
+
+/// HTTP client that records request traffic and latency
+#[derive(Clone)]
+struct MonitoredClient {
+    client: Client,
+    stats: SharedNetworkStats,
+}
+
+impl MonitoredClient {
+    fn new(client: Client, stats: SharedNetworkStats) -> Self {
+        Self { client, stats }
+    }
+
+    fn post(&self, url: &str) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    fn get(&self, url: &str) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    /// Send a request, recording its size and the latency until headers arrive
+    async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        let request = request.build()?;
+        let body_len = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map_or(0, |bytes| bytes.len()) as u64;
+        self.update(|stats| {
+            stats.bytes_sent_total += body_len;
+            stats.requests_in_flight = stats.requests_in_flight.saturating_add(1);
+        });
+
+        let start = Instant::now();
+        let result = self.client.execute(request).await;
+        let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+        self.update(|stats| {
+            stats.requests_in_flight = stats.requests_in_flight.saturating_sub(1);
+            stats.record_latency(latency_ms);
+        });
+        result
+    }
+
+    /// Record bytes read from a response body
+    fn record_received(&self, bytes: usize) {
+        self.update(|stats| stats.bytes_received_total += bytes as u64);
+    }
+
+    fn update(&self, f: impl FnOnce(&mut crate::diagnostics::NetworkStats)) {
+        if let Ok(mut stats) = self.stats.lock() {
+            f(&mut stats);
+        }
+    }
+}
 
 /// Unified model implementation using LiteLLM Proxy
 /// This drastically simplifies our code - ALL providers go through the same interface
+#[derive(Clone)]
 pub struct UnifiedModel {
-    client: Client,
+    client: MonitoredClient,
     proxy_url: String,
     model_name: String,
     master_key: Option<String>,
+    /// Capabilities reported by the proxy, fetched on first use
+    capabilities: Arc<OnceLock<ModelCapabilities>>,
+    capabilities_requested: Arc<AtomicBool>,
+    context_window_override: Option<usize>,
 }
 
 impl UnifiedModel {
@@ -39,15 +116,69 @@ impl UnifiedModel {
             .or(config_master_key);
 
         Ok(Self {
-            client: Client::builder()
-                .timeout(std::time::Duration::from_secs(HTTP_REQUEST_TIMEOUT_SECS))
-                .build()?,
+            client: MonitoredClient::new(
+                Client::builder()
+                    .timeout(std::time::Duration::from_secs(HTTP_REQUEST_TIMEOUT_SECS))
+                    .build()?,
+                network_stats(),
+            ),
             proxy_url,
             model_name: model_name.to_string(),
             master_key,
+            capabilities: Arc::new(OnceLock::new()),
+            capabilities_requested: Arc::new(AtomicBool::new(false)),
+            context_window_override: None,
         })
     }
 
+    /// Use the given context window instead of the detected one
+    pub fn with_context_window_override(mut self, context_window: Option<usize>) -> Self {
+        self.context_window_override = context_window;
+        self
+    }
+
+    /// Fetch capabilities from the proxy's `/model/info` endpoint and cache them
+    pub async fn fetch_capabilities(&self) -> Result<ModelCapabilities> {
+        if let Some(cached) = self.capabilities.get() {
+            return Ok(cached.clone());
+        }
+
+        let url = format!("{}/model/info", self.proxy_url);
+        let mut request = self.client.get(&url).query(&[("model", &self.model_name)]);
+        if let Some(key) = &self.master_key {
+            request = request.header("Authorization", format!("Bearer {}", key));
+        }
+
+        let response = self.client.send(request).await?;
+        if !response.status().is_success() {
+            anyhow::bail!("LiteLLM proxy error: {}", response.text().await?);
+        }
+
+        let body = response.bytes().await?;
+        self.client.record_received(body.len());
+        let info: ModelInfoResponse = serde_json::from_slice(&body)?;
+        let capabilities = info
+            .to_capabilities(&self.model_name, self.get_capabilities())
+            .context("Model not found in LiteLLM model info")?;
+
+        Ok(self.capabilities.get_or_init(|| capabilities).clone())
+    }
+
+    /// Start a one-off background fetch of the proxy's capabilities
+    fn request_capabilities(&self) {
+        if self.capabilities_requested.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+
+        let model = self.clone();
+        runtime.spawn(async move {
+            let _ = model.fetch_capabilities().await;
+        });
+    }
+
     /// Get capabilities based on model name
     /// LiteLLM handles all the provider-specific details
     fn get_capabilities(&self) -> ModelCapabilities {
@@ -116,6 +247,62 @@ impl UnifiedModel {
             || self.model_name.starts_with("local/")
             || self.model_name.starts_with("llamafile/")
     }
+
+    /// Turn a failed chat response into a structured error where one applies
+    async fn proxy_error(
+        &self,
+        status: reqwest::StatusCode,
+        error_text: &str,
+        request_body: &serde_json::Value,
+    ) -> anyhow::Error {
+        let lower = error_text.to_lowercase();
+        if status == reqwest::StatusCode::NOT_FOUND
+            || lower.contains("invalid model")
+            || lower.contains("model not found")
+        {
+            return MermaidError::ModelNotFound {
+                model_id: self.model_name.clone(),
+                available: self.available_models().await,
+            }
+            .into();
+        }
+        if lower.contains("contextwindowexceeded") || lower.contains("context_length_exceeded") {
+            return MermaidError::ContextTooLarge {
+                token_count: count_file_tokens(
+                    &request_body["messages"].to_string(),
+                    &self.model_name,
+                ),
+                limit: self.capabilities().max_context_length,
+            }
+            .into();
+        }
+        anyhow::anyhow!("LiteLLM proxy error: {}", error_text)
+    }
+
+    /// Model IDs the proxy serves, or none if it can't be asked
+    async fn available_models(&self) -> Vec<String> {
+        #[derive(Deserialize)]
+        struct ModelsResponse {
+            data: Vec<ModelId>,
+        }
+        #[derive(Deserialize)]
+        struct ModelId {
+            id: String,
+        }
+
+        let mut request = self.client.get(&format!("{}/v1/models", self.proxy_url));
+        if let Some(key) = &self.master_key {
+            request = request.header("Authorization", format!("Bearer {}", key));
+        }
+        match self.client.send(request).await {
+            Ok(response) if response.status().is_success() => response
+                .json::<ModelsResponse>()
+                .await
+                .map(|models| models.data.into_iter().map(|m| m.id).collect())
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        }
+    }
 }
 
 #[async_trait]
@@ -148,17 +335,7 @@ impl Model for UnifiedModel {
         }
 
         // Convert ChatMessage array to JSON format
-        for msg in messages {
-            let role = match msg.role {
-                MessageRole::User => "user",
-                MessageRole::Assistant => "assistant",
-                MessageRole::System => "system",
-            };
-            json_messages.push(json!({
-                "role": role,
-                "content": msg.content
-            }));
-        }
+        json_messages.extend(build_api_messages(messages));
 
         // Prepare request body (OpenAI format - LiteLLM handles translation)
         let mut request_body = json!({
@@ -167,6 +344,11 @@ impl Model for UnifiedModel {
             "stream": stream_callback.is_some(),
         });
 
+        // Ask for a final usage chunk so streamed responses can be metered
+        if stream_callback.is_some() {
+            request_body["stream_options"] = json!({ "include_usage": true });
+        }
+
         // Add optional parameters from config
         if let Some(temp) = config.temperature {
             request_body["temperature"] = json!(temp);
@@ -177,9 +359,21 @@ impl Model for UnifiedModel {
         if let Some(top_p) = config.top_p {
             request_body["top_p"] = json!(top_p);
         }
+        if let Some(format) = &config.response_format {
+            request_body["response_format"] = format.to_request_value();
+        }
+        // Models without native function calling use the text action blocks instead
+        let tools = config.tools.as_ref().filter(|t| !t.is_empty());
+        if let Some(tools) = tools.filter(|_| self.capabilities().supports_functions) {
+            request_body["tools"] = json!(tools
+                .iter()
+                .map(|t| t.to_request_value())
+                .collect::<Vec<_>>());
+        }
 
         // Make request to LiteLLM proxy
         let url = format!("{}/v1/chat/completions", self.proxy_url);
+        let mut debug = RequestLog::start(&url, self.master_key.is_some(), &request_body);
 
         if let Some(callback) = stream_callback {
             // Streaming response
@@ -190,48 +384,85 @@ impl Model for UnifiedModel {
                 request = request.header("Authorization", format!("Bearer {}", key));
             }
 
-            let response = request
-                .send()
-                .await
-                .with_context(|| format!("Failed to connect to LiteLLM proxy at {}. Is the proxy running? Try: ./start_litellm.sh", self.proxy_url))?;
+            let response =
+                self.client
+                    .send(request)
+                    .await
+                    .context(MermaidError::ProxyNotRunning {
+                        proxy_url: self.proxy_url.clone(),
+                    })?;
 
-            if !response.status().is_success() {
+            let status = response.status();
+            if !status.is_success() {
                 let error_text = response.text().await?;
-                anyhow::bail!("LiteLLM proxy error: {}", error_text);
+                debug.finish(status.as_u16(), Some(error_text.as_bytes()));
+                return Err(self.proxy_error(status, &error_text, &request_body).await);
             }
 
             let mut stream = response.bytes_stream();
+            let mut sse = SseBuffer::new();
             let mut full_response = String::new();
+            let mut usage = None;
+            // Function calls stream in fragments keyed by index: (name, arguments)
+            let mut tool_calls: BTreeMap<usize, (String, String)> = BTreeMap::new();
 
-            while let Some(chunk) = stream.next().await {
+            'stream: while let Some(chunk) = stream.next().await {
                 let chunk = chunk?;
-                let text = String::from_utf8_lossy(&chunk);
-
-                // Parse SSE format
-                for line in text.lines() {
-                    if line.starts_with("data: ") {
-                        let data = &line[6..];
-                        if data == "[DONE]" {
-                            break;
-                        }
+                self.client.record_received(chunk.len());
+                debug.record_chunk(&chunk);
+
+                // Only complete SSE events are parsed; partial ones wait for the next chunk
+                for event in sse.push(&chunk) {
+                    if event.data == "[DONE]" {
+                        break 'stream;
+                    }
 
-                        if let Ok(json_chunk) = serde_json::from_str::<StreamChunk>(data) {
-                            if let Some(delta) = json_chunk
-                                .choices
-                                .get(0)
-                                .and_then(|c| c.delta.content.as_ref())
-                            {
-                                full_response.push_str(delta);
-                                callback(delta);
+                    if let Ok(json_chunk) = serde_json::from_str::<StreamChunk>(&event.data) {
+                        if let Some(u) = json_chunk.usage {
+                            usage = Some(u);
+                        }
+                        let Some(choice) = json_chunk.choices.first() else {
+                            continue;
+                        };
+                        if let Some(delta) = choice.delta.content.as_ref() {
+                            debug.first_token();
+                            full_response.push_str(delta);
+                            callback(delta);
+                        }
+                        for call in &choice.delta.tool_calls {
+                            let entry = tool_calls.entry(call.index).or_default();
+                            if let Some(name) = &call.function.name {
+                                entry.0.push_str(name);
+                            }
+                            if let Some(arguments) = &call.function.arguments {
+                                entry.1.push_str(arguments);
                             }
                         }
                     }
                 }
             }
 
+            debug.finish(status.as_u16(), None);
+
+            // Hand complete function calls to the caller as marker lines
+            for (name, arguments) in tool_calls.values() {
+                let separator = if full_response.is_empty() || full_response.ends_with('\n') {
+                    ""
+                } else {
+                    "\n"
+                };
+                let marker = format!("{}{}\n", separator, format_tool_call(name, arguments));
+                full_response.push_str(&marker);
+                callback(&marker);
+            }
+
             Ok(ModelResponse {
                 content: String::new(), // Content already sent via callback, don't duplicate
-                usage: None,            // Usage stats not available in streaming
+                usage: usage.map(|u| super::types::TokenUsage {
+                    prompt_tokens: u.prompt_tokens,
+                    completion_tokens: u.completion_tokens,
+                    total_tokens: u.total_tokens,
+                }),
                 model_name: self.model_name.clone(),
             })
         } else {
@@ -243,20 +474,45 @@ impl Model for UnifiedModel {
                 request = request.header("Authorization", format!("Bearer {}", key));
             }
 
-            let response = request
-                .send()
-                .await
-                .with_context(|| format!("Failed to connect to LiteLLM proxy at {}. Is the proxy running? Try: ./start_litellm.sh", self.proxy_url))?;
+            let response =
+                self.client
+                    .send(request)
+                    .await
+                    .context(MermaidError::ProxyNotRunning {
+                        proxy_url: self.proxy_url.clone(),
+                    })?;
 
-            if !response.status().is_success() {
+            let status = response.status();
+            if !status.is_success() {
                 let error_text = response.text().await?;
-                anyhow::bail!("LiteLLM proxy error: {}", error_text);
+                debug.finish(status.as_u16(), Some(error_text.as_bytes()));
+                return Err(self.proxy_error(status, &error_text, &request_body).await);
             }
 
-            let response_json: ChatCompletionResponse = response.json().await?;
+            let body = response.bytes().await?;
+            self.client.record_received(body.len());
+            debug.finish(status.as_u16(), Some(&body));
+            let response_json: ChatCompletionResponse = serde_json::from_slice(&body)?;
+
+            let message = &response_json
+                .choices
+                .first()
+                .context("LiteLLM proxy returned no choices")?
+                .message;
+            let mut content = message.content.clone().unwrap_or_default();
+            for call in &message.tool_calls {
+                if !content.is_empty() && !content.ends_with('\n') {
+                    content.push('\n');
+                }
+                content.push_str(&format_tool_call(
+                    &call.function.name,
+                    &call.function.arguments,
+                ));
+                content.push('\n');
+            }
 
             Ok(ModelResponse {
-                content: response_json.choices[0].message.content.clone(),
+                content,
                 usage: response_json.usage.map(|u| super::types::TokenUsage {
                     prompt_tokens: u.prompt_tokens,
                     completion_tokens: u.completion_tokens,
@@ -276,7 +532,22 @@ impl Model for UnifiedModel {
     }
 
     fn capabilities(&self) -> ModelCapabilities {
-        self.get_capabilities()
+        // Local models don't report anything useful, so keep the known defaults for them;
+        // others use the built-in values until the proxy's answer arrives
+        let mut capabilities = match self.capabilities.get() {
+            Some(cached) => cached.clone(),
+            None => {
+                if !self.is_local_provider() {
+                    self.request_capabilities();
+                }
+                self.get_capabilities()
+            },
+        };
+
+        if let Some(context_window) = self.context_window_override {
+            capabilities.max_context_length = context_window;
+        }
+        capabilities
     }
 
     async fn validate_connection(&self) -> Result<bool> {
@@ -321,8 +592,154 @@ pub async fn create_from_string(model_string: &str) -> Result<UnifiedModel> {
     UnifiedModel::new(model_string, None).await
 }
 
+/// Convert chat history to OpenAI-format messages
+///
+/// Function calls are stored as `[TOOL_CALL: ...]` lines in assistant messages, with their
+/// results in the following `Tool` messages. Calls that have a result become `tool_calls`
+/// paired with `tool` messages by the results' `tool_call_id`s; anything unpaired is sent as plain text,
+/// since the API rejects calls without results (and results without calls).
+fn build_api_messages(messages: &[ChatMessage]) -> Vec<serde_json::Value> {
+    let mut json_messages = Vec::new();
+    let mut i = 0;
+
+    while i < messages.len() {
+        let msg = &messages[i];
+        let results: Vec<&ChatMessage> = messages[i + 1..]
+            .iter()
+            .take_while(|m| m.role.is_tool())
+            .collect();
+
+        match msg.role {
+            MessageRole::Assistant if !results.is_empty() => {
+                let mut calls = Vec::new();
+                let mut text = Vec::new();
+                for line in msg.content.lines() {
+                    match parse_tool_call(line) {
+                        Some(call) if calls.len() < results.len() => calls.push(call),
+                        _ => text.push(line),
+                    }
+                }
+
+                // Results saved without a call ID get one from their position
+                let ids: Vec<String> = results
+                    .iter()
+                    .take(calls.len())
+                    .enumerate()
+                    .map(|(n, result)| match &result.role {
+                        MessageRole::Tool { tool_call_id } if !tool_call_id.is_empty() => {
+                            tool_call_id.clone()
+                        },
+                        _ => format!("call_{}_{}", i, n),
+                    })
+                    .collect();
+                let text = text.join("\n");
+                let mut assistant = json!({
+                    "role": "assistant",
+                    "content": if text.trim().is_empty() { None } else { Some(text) },
+                });
+                if !calls.is_empty() {
+                    assistant["tool_calls"] = json!(calls
+                        .iter()
+                        .zip(&ids)
+                        .map(|((name, arguments), id)| json!({
+                            "id": id,
+                            "type": "function",
+                            "function": { "name": name, "arguments": arguments },
+                        }))
+                        .collect::<Vec<_>>());
+                }
+                json_messages.push(assistant);
+
+                for (n, result) in results.iter().enumerate() {
+                    json_messages.push(match ids.get(n) {
+                        Some(id) => json!({
+                            "role": "tool",
+                            "tool_call_id": id,
+                            "content": result.content,
+                        }),
+                        None => unpaired_tool_result(result),
+                    });
+                }
+                i += 1 + results.len();
+                continue;
+            },
+            MessageRole::Tool { .. } => json_messages.push(unpaired_tool_result(msg)),
+            _ => {
+                let role = match msg.role {
+                    MessageRole::User => "user",
+                    MessageRole::Assistant => "assistant",
+                    MessageRole::System | MessageRole::Tool { .. } => "system",
+                };
+                json_messages.push(json!({
+                    "role": role,
+                    "content": msg.content
+                }));
+            },
+        }
+        i += 1;
+    }
+
+    json_messages
+}
+
+/// A tool result with no matching call, sent as a system message
+fn unpaired_tool_result(msg: &ChatMessage) -> serde_json::Value {
+    json!({
+        "role": "system",
+        "content": format!("Function result:\n{}", msg.content),
+    })
+}
+
 // Response structures for LiteLLM proxy (OpenAI format)
 
+#[derive(Debug, Deserialize)]
+struct ModelInfoResponse {
+    data: Vec<ModelInfoEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelInfoEntry {
+    model_name: String,
+    #[serde(default)]
+    model_info: ModelInfoDetails,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ModelInfoDetails {
+    max_input_tokens: Option<usize>,
+    max_tokens: Option<usize>,
+    supports_function_calling: Option<bool>,
+    supports_vision: Option<bool>,
+}
+
+impl ModelInfoResponse {
+    /// Capabilities for the given model, filling unknown fields from the fallback
+    fn to_capabilities(
+        &self,
+        model_name: &str,
+        fallback: ModelCapabilities,
+    ) -> Option<ModelCapabilities> {
+        let entry = self
+            .data
+            .iter()
+            .find(|e| e.model_name == model_name)
+            .or_else(|| (self.data.len() == 1).then(|| &self.data[0]))?;
+        let info = &entry.model_info;
+
+        Some(ModelCapabilities {
+            max_context_length: info
+                .max_input_tokens
+                .or(info.max_tokens)
+                .unwrap_or(fallback.max_context_length),
+            supports_streaming: fallback.supports_streaming,
+            supports_functions: info
+                .supports_function_calling
+                .unwrap_or(fallback.supports_functions),
+            supports_vision: info.supports_vision.unwrap_or(fallback.supports_vision),
+        })
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct ChatCompletionResponse {
     choices: Vec<Choice>,
@@ -336,7 +753,22 @@ struct Choice {
 
 #[derive(Debug, Deserialize)]
 struct Message {
-    content: String,
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ToolCall {
+    function: FunctionCall,
+}
+
+#[derive(Debug, Deserialize)]
+struct FunctionCall {
+    name: String,
+    #[serde(default)]
+    arguments: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -348,7 +780,9 @@ struct Usage {
 
 #[derive(Debug, Deserialize)]
 struct StreamChunk {
+    #[serde(default)]
     choices: Vec<StreamChoice>,
+    usage: Option<Usage>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -359,4 +793,99 @@ struct StreamChoice {
 #[derive(Debug, Deserialize)]
 struct Delta {
     content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCallDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ToolCallDelta {
+    #[serde(default)]
+    index: usize,
+    #[serde(default)]
+    function: FunctionCallDelta,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FunctionCallDelta {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_info_to_capabilities() {
+        let info: ModelInfoResponse = serde_json::from_str(
+            r#"{"data":[
+                {"model_name":"openai/gpt-4o","model_info":{"max_input_tokens":128000,"supports_vision":true}},
+                {"model_name":"ollama/qwen","model_info":{}}
+            ]}"#,
+        )
+        .unwrap();
+
+        let capabilities = info
+            .to_capabilities("openai/gpt-4o", ModelCapabilities::default())
+            .unwrap();
+        assert_eq!(capabilities.max_context_length, 128000);
+        assert!(capabilities.supports_vision);
+        assert!(!capabilities.supports_functions);
+
+        let fallback = info
+            .to_capabilities("ollama/qwen", ModelCapabilities::default())
+            .unwrap();
+        assert_eq!(fallback.max_context_length, 4096);
+        assert!(info
+            .to_capabilities("anthropic/claude", ModelCapabilities::default())
+            .is_none());
+    }
+
+    #[test]
+    fn test_tool_calls_paired_with_results() {
+        let message = |role, content: &str| ChatMessage {
+            role,
+            content: content.to_string(),
+            timestamp: chrono::Local::now(),
+        };
+        let messages = vec![
+            message(MessageRole::User, "What's in main.rs?"),
+            message(
+                MessageRole::Assistant,
+                "Let me look.\n[TOOL_CALL: read_file]:{\"path\":\"main.rs\"}\n[TOOL_CALL: git_status]:{}",
+            ),
+            message(
+                MessageRole::Tool {
+                    tool_call_id: "call_1_0".to_string(),
+                },
+                "fn main() {}",
+            ),
+            message(MessageRole::User, "Thanks"),
+        ];
+
+        let api = build_api_messages(&messages);
+        assert_eq!(api.len(), 4);
+
+        // Only the call with a result becomes a tool call; the other stays as text
+        let calls = api[1]["tool_calls"].as_array().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0]["function"]["name"], "read_file");
+        assert_eq!(
+            api[1]["content"],
+            "Let me look.\n[TOOL_CALL: git_status]:{}"
+        );
+        assert_eq!(calls[0]["id"], "call_1_0");
+        assert_eq!(api[2]["role"], "tool");
+        assert_eq!(api[2]["tool_call_id"], "call_1_0");
+
+        // Conversations saved before results had call IDs still load
+        let legacy: ChatMessage = serde_json::from_str(
+            r#"{"role":"Tool","content":"ok","timestamp":"2024-05-01T10:00:00+00:00"}"#,
+        )
+        .unwrap();
+        assert_eq!(legacy.role, MessageRole::tool());
+        let saved = serde_json::to_string(&messages[2]).unwrap();
+        let loaded: ChatMessage = serde_json::from_str(&saved).unwrap();
+        assert_eq!(loaded.role, messages[2].role);
+    }
 }
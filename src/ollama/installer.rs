@@ -1,29 +1,152 @@
 use super::detector;
 use super::guide;
-use anyhow::Result;
+use crate::constants::DEFAULT_OLLAMA_PORT;
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Instant;
 
-/// Install an Ollama model with progress display
-pub async fn install_model(model: &str) -> Result<()> {
-    use std::process::{Command, Stdio};
+/// Width of the terminal progress bar in characters
+const PROGRESS_BAR_WIDTH: usize = 30;
 
-    println!("[DOWNLOADING] Pulling {} model...", model);
+/// One progress update from a model pull
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PullProgress {
+    pub status: String,
+    #[serde(default)]
+    pub total: u64,
+    #[serde(default)]
+    pub completed: u64,
+    /// Download speed from consecutive `completed` values
+    #[serde(default)]
+    pub bytes_per_sec: f64,
+}
 
-    let mut child = Command::new("ollama")
-        .arg("pull")
-        .arg(model)
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .spawn()?;
+impl PullProgress {
+    /// Percentage of the current layer downloaded, if the size is known
+    pub fn percent(&self) -> Option<f32> {
+        (self.total > 0).then(|| (self.completed as f32 / self.total as f32 * 100.0).min(100.0))
+    }
 
-    let status = child.wait()?;
+    /// Download speed in MB/s
+    pub fn mb_per_sec(&self) -> f64 {
+        self.bytes_per_sec / (1024.0 * 1024.0)
+    }
 
-    if !status.success() {
-        anyhow::bail!("Failed to install {} model", model);
+    /// Render as `status [#####-----] 45% 12.3 MB/s`, or just the status while sizes are unknown
+    pub fn to_bar(&self, width: usize) -> String {
+        match self.percent() {
+            Some(percent) => {
+                let filled = (percent / 100.0 * width as f32).round() as usize;
+                format!(
+                    "{} [{}{}] {:.0}% {:.1} MB/s",
+                    self.status,
+                    "#".repeat(filled),
+                    "-".repeat(width.saturating_sub(filled)),
+                    percent,
+                    self.mb_per_sec()
+                )
+            },
+            None => self.status.clone(),
+        }
+    }
+}
+
+/// Callback invoked for each pull progress update
+pub type PullProgressCallback = Arc<dyn Fn(&PullProgress) + Send + Sync>;
+
+/// Ollama API base URL from OLLAMA_HOST, defaulting to the local server
+fn ollama_base_url() -> String {
+    match std::env::var("OLLAMA_HOST") {
+        Ok(host) if host.starts_with("http") => host.trim_end_matches('/').to_string(),
+        Ok(host) if !host.is_empty() => format!("http://{}", host.trim_end_matches('/')),
+        _ => format!("http://localhost:{}", DEFAULT_OLLAMA_PORT),
+    }
+}
+
+/// Pull a model, reporting progress from Ollama's streaming JSON output
+///
+/// The `ollama pull` CLI only draws a terminal progress bar, so the JSON lines
+/// (`{"status":"...","total":N,"completed":N}`) are read from the pull API instead.
+pub async fn pull_model_with_progress(model: &str, progress: PullProgressCallback) -> Result<()> {
+    let url = format!("{}/api/pull", ollama_base_url());
+    let response = reqwest::Client::new()
+        .post(&url)
+        .json(&serde_json::json!({ "model": model, "stream": true }))
+        .send()
+        .await
+        .with_context(|| format!("Failed to connect to Ollama at {}", url))?;
+
+    if !response.status().is_success() {
+        anyhow::bail!(
+            "Failed to install {} model: {}",
+            model,
+            response.text().await.unwrap_or_default()
+        );
+    }
+
+    let mut stream = response.bytes_stream();
+    let mut buffer = String::new();
+    let mut last_sample: Option<(String, u64, Instant)> = None;
+    let mut speed = 0.0;
+
+    while let Some(chunk) = stream.next().await {
+        buffer.push_str(&String::from_utf8_lossy(&chunk?));
+
+        // Only complete lines are parsed; a partial line waits for the next chunk
+        while let Some(newline) = buffer.find('\n') {
+            let line: String = buffer.drain(..=newline).collect();
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let value: serde_json::Value = serde_json::from_str(line)?;
+            if let Some(error) = value.get("error").and_then(|e| e.as_str()) {
+                anyhow::bail!("Failed to install {} model: {}", model, error);
+            }
+            let mut update: PullProgress = serde_json::from_value(value)?;
+
+            // Speed is measured per layer; a new status means a new download
+            let now = Instant::now();
+            if let Some((status, completed, time)) = &last_sample {
+                let elapsed = now.duration_since(*time).as_secs_f64();
+                if *status == update.status && update.completed >= *completed && elapsed > 0.0 {
+                    speed = (update.completed - completed) as f64 / elapsed;
+                } else if *status != update.status {
+                    speed = 0.0;
+                }
+            }
+            update.bytes_per_sec = speed;
+            last_sample = Some((update.status.clone(), update.completed, now));
+
+            progress(&update);
+        }
     }
 
     Ok(())
 }
 
+/// Install an Ollama model with progress display
+pub async fn install_model(model: &str) -> Result<()> {
+    println!("[DOWNLOADING] Pulling {} model...", model);
+
+    let result = pull_model_with_progress(
+        model,
+        Arc::new(|update: &PullProgress| {
+            // Redraw the same terminal line for each update
+            print!("\r\x1b[2K{}", update.to_bar(PROGRESS_BAR_WIDTH));
+            let _ = std::io::stdout().flush();
+        }),
+    )
+    .await;
+    println!();
+
+    result
+}
+
 /// Ensure Ollama model is available, auto-installing if needed
 pub async fn ensure_model(model_name: &str, no_auto_install: bool) -> Result<()> {
     // Skip if not using Ollama
@@ -64,3 +187,21 @@ pub async fn ensure_model(model_name: &str, no_auto_install: bool) -> Result<()>
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pull_progress_bar() {
+        let progress: PullProgress =
+            serde_json::from_str(r#"{"status":"pulling abc","total":200,"completed":50}"#).unwrap();
+        assert_eq!(progress.percent(), Some(25.0));
+        assert_eq!(progress.to_bar(4), "pulling abc [#---] 25% 0.0 MB/s");
+
+        let manifest: PullProgress =
+            serde_json::from_str(r#"{"status":"pulling manifest"}"#).unwrap();
+        assert_eq!(manifest.percent(), None);
+        assert_eq!(manifest.to_bar(4), "pulling manifest");
+    }
+}
@@ -3,6 +3,8 @@ mod detector;
 mod guide;
 mod installer;
 
-pub use detector::{is_installed, list_models};
+pub use detector::{is_installed, list_models, list_models_async};
 pub use guide::detect_and_guide;
-pub use installer::{ensure_model, install_model};
+pub use installer::{
+    ensure_model, install_model, pull_model_with_progress, PullProgress, PullProgressCallback,
+};
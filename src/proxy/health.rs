@@ -1,3 +1,12 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::start_proxy;
+use crate::app::LiteLLMConfig;
+use crate::constants::PROXY_MAX_RESTART_ATTEMPTS;
+use crate::utils::log_warn;
+
 /// Check if LiteLLM proxy is running
 pub async fn is_proxy_running() -> bool {
     let proxy_url =
@@ -24,3 +33,104 @@ pub async fn is_proxy_running() -> bool {
 
     false
 }
+
+/// Health of the LiteLLM proxy as seen by the watchdog
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyStatus {
+    Running,
+    Restarting,
+    Down,
+}
+
+/// Background task that restarts the proxy when it stops responding
+#[derive(Debug, Clone)]
+pub struct ProxyWatchdog {
+    check_interval: Duration,
+    auto_restart: bool,
+    proxy_status: Arc<Mutex<ProxyStatus>>,
+    /// Consecutive failed restart attempts
+    proxy_restart_count: Arc<AtomicU32>,
+}
+
+impl ProxyWatchdog {
+    /// Create a watchdog using the configured check interval and restart policy
+    pub fn new(config: &LiteLLMConfig) -> Self {
+        Self {
+            check_interval: Duration::from_secs(config.proxy_health_check_interval_secs.max(1)),
+            auto_restart: config.auto_restart,
+            proxy_status: Arc::new(Mutex::new(ProxyStatus::Running)),
+            proxy_restart_count: Arc::new(AtomicU32::new(0)),
+        }
+    }
+
+    /// Current proxy status
+    pub fn status(&self) -> ProxyStatus {
+        self.proxy_status
+            .lock()
+            .map(|s| *s)
+            .unwrap_or(ProxyStatus::Running)
+    }
+
+    /// Number of consecutive failed restart attempts
+    pub fn restart_count(&self) -> u32 {
+        self.proxy_restart_count.load(Ordering::Relaxed)
+    }
+
+    /// Start checking the proxy in the background
+    pub fn spawn(&self) -> tokio::task::JoinHandle<()> {
+        let watchdog = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(watchdog.check_interval);
+            // The first tick completes immediately; the proxy was just checked at startup
+            interval.tick().await;
+
+            loop {
+                interval.tick().await;
+                watchdog.check().await;
+            }
+        })
+    }
+
+    /// Check the proxy once, restarting it if allowed
+    async fn check(&self) {
+        if is_proxy_running().await {
+            self.proxy_restart_count.store(0, Ordering::Relaxed);
+            self.set_status(ProxyStatus::Running);
+            return;
+        }
+
+        // Give up after too many consecutive failures, but keep watching in case
+        // the user brings the proxy back manually
+        if !self.auto_restart || self.restart_count() >= PROXY_MAX_RESTART_ATTEMPTS {
+            self.set_status(ProxyStatus::Down);
+            return;
+        }
+
+        self.set_status(ProxyStatus::Restarting);
+        match start_proxy().await {
+            Ok(()) => {
+                self.proxy_restart_count.store(0, Ordering::Relaxed);
+                self.set_status(ProxyStatus::Running);
+            },
+            Err(e) => {
+                let failures = self.proxy_restart_count.fetch_add(1, Ordering::Relaxed) + 1;
+                log_warn(
+                    "PROXY",
+                    format!(
+                        "Restart attempt {}/{} failed: {}",
+                        failures, PROXY_MAX_RESTART_ATTEMPTS, e
+                    ),
+                );
+                if failures >= PROXY_MAX_RESTART_ATTEMPTS {
+                    self.set_status(ProxyStatus::Down);
+                }
+            },
+        }
+    }
+
+    fn set_status(&self, status: ProxyStatus) {
+        if let Ok(mut current) = self.proxy_status.lock() {
+            *current = status;
+        }
+    }
+}
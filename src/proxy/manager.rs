@@ -1,7 +1,7 @@
 use anyhow::Result;
 use tokio::process::Command;
 
-use super::{get_compose_dir, is_container_runtime_available, is_proxy_running};
+use super::{detect_runtime, get_compose_dir, is_proxy_running, PROXY_CONTAINER_NAME};
 use crate::constants::{
     PROXY_MAX_STARTUP_ATTEMPTS, PROXY_POLL_INTERVAL_MS, PROXY_STARTUP_WAIT_SECS,
 };
@@ -9,10 +9,10 @@ use crate::utils::{log_error, log_info, log_warn};
 
 /// Start the LiteLLM proxy
 pub async fn start_proxy() -> Result<()> {
-    // Detect container runtime - prefer podman-compose
-    let runtime = is_container_runtime_available().ok_or_else(|| {
+    // Detect container runtime - Podman, then Docker, then nerdctl
+    let runtime = detect_runtime().ok_or_else(|| {
         anyhow::anyhow!(
-            "ERROR: Neither Podman nor Docker found\n   \
+            "ERROR: No container runtime found (Podman, Docker or nerdctl)\n   \
                 Install Podman: sudo apt-get install podman podman-compose\n   \
                 Or install Docker: https://docs.docker.com/engine/install/"
         )
@@ -26,22 +26,13 @@ pub async fn start_proxy() -> Result<()> {
         format!("Starting LiteLLM proxy with {}...", runtime),
     );
 
-    // Build the command based on runtime type
-    let output = if runtime == "podman" || runtime == "docker" {
-        // Use compose subcommand for standalone podman/docker
-        Command::new(runtime)
-            .args(&["compose", "up", "-d", "litellm"])
-            .current_dir(&compose_dir)
-            .output()
-            .await?
-    } else {
-        // Use podman-compose or docker-compose directly
-        Command::new(runtime)
-            .args(&["up", "-d", "litellm"])
-            .current_dir(&compose_dir)
-            .output()
-            .await?
-    };
+    let (program, compose_args) = runtime.compose_command();
+    let output = Command::new(program)
+        .args(compose_args)
+        .args(["up", "-d", "litellm"])
+        .current_dir(&compose_dir)
+        .output()
+        .await?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -73,34 +64,33 @@ pub async fn start_proxy() -> Result<()> {
     }
 
     anyhow::bail!(
-        "LiteLLM proxy failed to start properly. Check logs with: {} logs litellm",
-        runtime
+        "LiteLLM proxy failed to start properly. Check logs with: {} logs {}",
+        runtime,
+        PROXY_CONTAINER_NAME
     )
 }
 
 /// Stop the LiteLLM proxy
 pub async fn stop_proxy() -> Result<()> {
-    let runtime = is_container_runtime_available()
-        .ok_or_else(|| anyhow::anyhow!("No container runtime found (Podman or Docker)"))?;
+    let runtime = detect_runtime()
+        .ok_or_else(|| anyhow::anyhow!("No container runtime found (Podman, Docker or nerdctl)"))?;
+
+    // Started by another runtime, or already stopped
+    if !runtime.is_container_running(PROXY_CONTAINER_NAME) {
+        return Ok(());
+    }
 
     let compose_dir = get_compose_dir()?;
 
     log_info("STOP", "Stopping LiteLLM proxy...");
 
-    // Build the command based on runtime type
-    let output = if runtime == "podman" || runtime == "docker" {
-        Command::new(runtime)
-            .args(&["compose", "stop", "litellm"])
-            .current_dir(&compose_dir)
-            .output()
-            .await?
-    } else {
-        Command::new(runtime)
-            .args(&["stop", "litellm"])
-            .current_dir(&compose_dir)
-            .output()
-            .await?
-    };
+    let (program, compose_args) = runtime.compose_command();
+    let output = Command::new(program)
+        .args(compose_args)
+        .args(["stop", "litellm"])
+        .current_dir(&compose_dir)
+        .output()
+        .await?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
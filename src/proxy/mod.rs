@@ -3,6 +3,9 @@ mod health;
 mod manager;
 mod podman;
 
-pub use health::is_proxy_running;
+pub use health::{is_proxy_running, ProxyStatus, ProxyWatchdog};
 pub use manager::{ensure_proxy, start_proxy, stop_proxy};
-pub use podman::{count_mermaid_processes, get_compose_dir, is_container_runtime_available};
+pub use podman::{
+    count_mermaid_processes, detect_runtime, get_compose_dir, ContainerRuntime,
+    PROXY_CONTAINER_NAME,
+};
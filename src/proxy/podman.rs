@@ -1,27 +1,104 @@
 use anyhow::Result;
+use std::fmt;
 use std::path::PathBuf;
+use std::process::Command;
+
+use crate::app::load_config;
+use crate::utils::log_warn;
+
+/// Name of the proxy container in docker-compose.yml
+pub const PROXY_CONTAINER_NAME: &str = "mermaid-litellm";
 
 /// Check if a command is available on the system
 fn is_command_available(cmd: &str) -> bool {
     which::which(cmd).is_ok()
 }
 
-/// Check if container runtime is available and return its name
-/// Prefers Podman over Docker
-pub fn is_container_runtime_available() -> Option<&'static str> {
-    if is_command_available("podman-compose") {
-        Some("podman-compose")
-    } else if is_command_available("docker-compose") {
-        Some("docker-compose")
-    } else if is_command_available("podman") {
-        // Fallback to standalone podman with compose plugin
-        Some("podman")
-    } else if is_command_available("docker") {
-        // Fallback to standalone docker with compose plugin
-        Some("docker")
-    } else {
-        None
+/// Container runtime used to run the LiteLLM proxy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerRuntime {
+    Podman,
+    Docker,
+    Nerdctl,
+}
+
+impl ContainerRuntime {
+    /// Detection order: Podman first, then Docker, then nerdctl
+    pub const ALL: [ContainerRuntime; 3] = [Self::Podman, Self::Docker, Self::Nerdctl];
+
+    /// The runtime's CLI
+    pub fn command(&self) -> &'static str {
+        match self {
+            Self::Podman => "podman",
+            Self::Docker => "docker",
+            Self::Nerdctl => "nerdctl",
+        }
+    }
+
+    /// Parse a runtime name as written in `litellm.container_runtime`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|runtime| runtime.command().eq_ignore_ascii_case(name.trim()))
+    }
+
+    fn is_available(&self) -> bool {
+        is_command_available(self.command())
+    }
+
+    /// Program and leading arguments for compose commands, preferring the
+    /// standalone podman-compose/docker-compose tools over the compose plugin
+    pub fn compose_command(&self) -> (&'static str, &'static [&'static str]) {
+        match self {
+            Self::Podman if is_command_available("podman-compose") => ("podman-compose", &[]),
+            Self::Docker if is_command_available("docker-compose") => ("docker-compose", &[]),
+            _ => (self.command(), &["compose"]),
+        }
+    }
+
+    /// Whether a container with this name is running under the runtime
+    pub fn is_container_running(&self, name: &str) -> bool {
+        Command::new(self.command())
+            .args(["ps", "--format", "{{.Names}}"])
+            .output()
+            .map(|output| {
+                String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .any(|line| line.trim() == name)
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl fmt::Display for ContainerRuntime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.command())
+    }
+}
+
+/// The container runtime to use: `litellm.container_runtime` when set and
+/// installed, otherwise the first installed of Podman, Docker and nerdctl
+pub fn detect_runtime() -> Option<ContainerRuntime> {
+    let forced = load_config().ok().and_then(|c| c.litellm.container_runtime);
+    if let Some(name) = forced {
+        match ContainerRuntime::from_name(&name) {
+            Some(runtime) if runtime.is_available() => return Some(runtime),
+            Some(runtime) => log_warn(
+                "WARNING",
+                format!("Configured container runtime {} is not installed", runtime),
+            ),
+            None => log_warn(
+                "WARNING",
+                format!(
+                    "Unknown container runtime '{}' (expected podman, docker or nerdctl)",
+                    name
+                ),
+            ),
+        }
     }
+    ContainerRuntime::ALL
+        .into_iter()
+        .find(ContainerRuntime::is_available)
 }
 
 /// Get the directory where docker-compose.yml is located
@@ -46,8 +123,6 @@ pub fn get_compose_dir() -> Result<PathBuf> {
 
 /// Check if other mermaid processes are running
 pub fn count_mermaid_processes() -> usize {
-    use std::process::Command;
-
     let output = Command::new("pgrep").arg("-c").arg("mermaid").output();
 
     match output {
@@ -60,3 +135,23 @@ pub fn count_mermaid_processes() -> usize {
         _ => 1, // Assume just us if pgrep fails
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runtime_names() {
+        assert_eq!(
+            ContainerRuntime::from_name("Docker"),
+            Some(ContainerRuntime::Docker)
+        );
+        assert_eq!(
+            ContainerRuntime::from_name(" nerdctl "),
+            Some(ContainerRuntime::Nerdctl)
+        );
+        assert_eq!(ContainerRuntime::from_name("lxc"), None);
+        assert_eq!(ContainerRuntime::ALL[0], ContainerRuntime::Podman);
+        assert_eq!(ContainerRuntime::Podman.to_string(), "podman");
+    }
+}
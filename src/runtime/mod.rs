@@ -2,5 +2,8 @@ mod non_interactive;
 /// Runtime orchestrator module - Gateway
 mod orchestrator;
 
-pub use non_interactive::{NonInteractiveResult, NonInteractiveRunner};
+pub use non_interactive::{
+    batch_prompts, context_messages, BatchResult, BatchSummary, NonInteractiveResult,
+    NonInteractiveRunner,
+};
 pub use orchestrator::Orchestrator;
@@ -1,18 +1,28 @@
 use anyhow::Result;
 use colored::Colorize;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Semaphore};
 
 use crate::{
-    agents::{execute_action, parse_actions, ActionResult as AgentActionResult, AgentAction},
+    agents::{
+        allow_workspace_root, builtin_tools, execute_action, parse_actions,
+        ActionResult as AgentActionResult, AgentAction,
+    },
     app::Config,
     cli::OutputFormat,
     context::ContextLoader,
-    models::{ChatMessage, MessageRole, Model, ModelConfig, ModelFactory, ProjectContext},
+    models::{
+        ChatMessage, MessageRole, Model, ModelConfig, ModelFactory, ProjectContext, ResponseFormat,
+    },
+    session::{ConversationHistory, UsageLog},
 };
 
+/// Most follow-up turns a run gives the model for function results
+const MAX_FUNCTION_ROUNDS: usize = 10;
+
 /// Result of a non-interactive run
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NonInteractiveResult {
@@ -38,6 +48,9 @@ pub struct ActionResult {
     pub success: bool,
     /// Output or error message
     pub output: Option<String>,
+    /// Time the action took to run in milliseconds (0 when not executed)
+    #[serde(default)]
+    pub duration_ms: u128,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -52,40 +65,180 @@ pub struct ExecutionMetadata {
     pub actions_executed: bool,
 }
 
+/// Results of a `--prompts-file` run, in prompt order
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchResult {
+    pub results: Vec<NonInteractiveResult>,
+    pub summary: BatchSummary,
+}
+
+/// Counts and totals over every prompt of a batch
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchSummary {
+    pub total: usize,
+    /// Prompts that finished without errors
+    pub succeeded: usize,
+    pub failed: usize,
+    pub total_tokens: usize,
+    /// Wall-clock time for the whole batch in milliseconds
+    pub duration_ms: u128,
+}
+
+impl NonInteractiveResult {
+    /// 1 if anything failed, 2 if a dry run proposed actions, otherwise 0
+    pub fn exit_code(&self, dry_run: bool) -> i32 {
+        if !self.errors.is_empty() {
+            1
+        } else if dry_run && !self.actions.is_empty() {
+            2
+        } else {
+            0
+        }
+    }
+}
+
+impl BatchResult {
+    /// The most severe exit code of any prompt, with failures over proposed actions
+    pub fn exit_code(&self, dry_run: bool) -> i32 {
+        let codes = self.results.iter().map(|result| result.exit_code(dry_run));
+        codes.fold(0, |worst, code| match (worst, code) {
+            (1, _) | (_, 1) => 1,
+            (worst, code) => worst.max(code),
+        })
+    }
+}
+
+impl BatchSummary {
+    fn new(results: &[NonInteractiveResult], duration_ms: u128) -> Self {
+        let failed = results.iter().filter(|r| !r.errors.is_empty()).count();
+        Self {
+            total: results.len(),
+            succeeded: results.len() - failed,
+            failed,
+            total_tokens: results.iter().filter_map(|r| r.metadata.tokens_used).sum(),
+            duration_ms,
+        }
+    }
+}
+
+/// Parse a model response as JSON, tolerating a surrounding markdown code fence
+fn parse_json_response(response: &str) -> serde_json::Result<serde_json::Value> {
+    let trimmed = response.trim();
+    let unfenced = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|s| s.strip_suffix("```"))
+        .unwrap_or(trimmed);
+    serde_json::from_str(unfenced.trim())
+}
+
 /// Non-interactive runner for executing single prompts
 pub struct NonInteractiveRunner {
+    model_id: String,
     model: Arc<Mutex<Box<dyn Model>>>,
+    /// Extra instances so parallel batch prompts don't queue on one model
+    extra_models: Vec<Arc<Mutex<Box<dyn Model>>>>,
     context: ProjectContext,
     config: Config,
-    no_execute: bool,
+    dry_run: bool,
     max_tokens: Option<usize>,
+    parse_as_json: bool,
+    /// Earlier turns sent before each prompt, e.g. from `--context-file`
+    initial_messages: Vec<ChatMessage>,
+    /// One lock per file that actions write, so parallel prompts take turns on it
+    file_locks: std::sync::Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>,
 }
 
 impl NonInteractiveRunner {
     /// Create a new non-interactive runner
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         model_id: String,
         project_path: PathBuf,
         config: Config,
-        no_execute: bool,
+        dry_run: bool,
         max_tokens: Option<usize>,
+        parse_as_json: bool,
+        extra_roots: Vec<PathBuf>,
+        initial_messages: Vec<ChatMessage>,
     ) -> Result<Self> {
         // Create model instance
         let model = ModelFactory::create(&model_id, Some(&config)).await?;
 
         // Load project context
-        let loader = ContextLoader::new()?;
+        let loader = ContextLoader::new()?.with_additional_roots(extra_roots);
         let context = loader.load_context(&project_path)?;
+        // File actions may reach the project even when it isn't the current directory
+        if let Ok(root) = project_path.canonicalize() {
+            allow_workspace_root(root);
+        }
 
         Ok(Self {
+            model_id,
             model: Arc::new(Mutex::new(model)),
+            extra_models: Vec::new(),
             context,
             config,
-            no_execute,
+            dry_run,
             max_tokens,
+            parse_as_json,
+            initial_messages,
+            file_locks: Default::default(),
         })
     }
 
+    /// Lock for the file an action changes, or None if it doesn't change one
+    fn file_lock(&self, action: &AgentAction) -> Option<Arc<Mutex<()>>> {
+        let path = match action.resolve().ok()? {
+            AgentAction::WriteFile { path, .. }
+            | AgentAction::DeleteFile { path }
+            | AgentAction::LintFile { path, .. } => path,
+            _ => return None,
+        };
+        let key = PathBuf::from(path.trim_start_matches("./"));
+        let mut locks = self.file_locks.lock().unwrap();
+        Some(locks.entry(key).or_default().clone())
+    }
+
+    /// Create enough model instances to run `parallel` prompts at once
+    pub async fn with_parallelism(mut self, parallel: usize) -> Result<Self> {
+        while self.extra_models.len() + 1 < parallel {
+            let model = ModelFactory::create(&self.model_id, Some(&self.config)).await?;
+            self.extra_models.push(Arc::new(Mutex::new(model)));
+        }
+        Ok(self)
+    }
+
+    /// Execute prompts with at most `parallel` in flight, keeping their order
+    pub async fn execute_batch(&self, prompts: Vec<String>, parallel: usize) -> BatchResult {
+        let start_time = std::time::Instant::now();
+        let permits = Semaphore::new(parallel.max(1));
+        let runs = prompts.into_iter().map(|prompt| {
+            let permits = &permits;
+            async move {
+                let _permit = permits.acquire().await;
+                let fallback = prompt.clone();
+                self.execute(prompt)
+                    .await
+                    .unwrap_or_else(|e| NonInteractiveResult {
+                        prompt: fallback,
+                        response: String::new(),
+                        actions: Vec::new(),
+                        errors: vec![e.to_string()],
+                        metadata: ExecutionMetadata {
+                            model: self.model_id.clone(),
+                            tokens_used: None,
+                            duration_ms: 0,
+                            actions_executed: false,
+                        },
+                    })
+            }
+        });
+        let results = futures::future::join_all(runs).await;
+        let summary = BatchSummary::new(&results, start_time.elapsed().as_millis());
+        BatchResult { results, summary }
+    }
+
     /// Execute a single prompt and return the result
     pub async fn execute(&self, prompt: String) -> Result<NonInteractiveResult> {
         let start_time = std::time::Instant::now();
@@ -132,7 +285,9 @@ impl NonInteractiveRunner {
             timestamp: chrono::Local::now(),
         };
 
-        let messages = vec![system_message, user_message];
+        let mut messages = vec![system_message];
+        messages.extend(self.initial_messages.iter().cloned());
+        messages.push(user_message);
 
         // Create model config
         let model_config = ModelConfig {
@@ -142,54 +297,79 @@ impl NonInteractiveRunner {
             frequency_penalty: None,
             presence_penalty: None,
             system_prompt: None,
+            response_format: self.parse_as_json.then_some(ResponseFormat::Json),
+            tools: Some(builtin_tools()),
         };
 
-        // Send prompt to model
+        // Send prompt to model, feeding function results back until it stops calling them
         let mut full_response = String::new();
-        let tokens_used;
-
-        // Create a callback to capture the response
-        let response_text = Arc::new(std::sync::Mutex::new(String::new()));
-        let response_clone = Arc::clone(&response_text);
-        let callback = Arc::new(move |chunk: &str| {
-            let mut resp = response_clone.lock().unwrap();
-            resp.push_str(chunk);
-        });
-
-        // Call the model
-        let model_name;
-        let result = {
-            let mut model = self.model.lock().await;
-            model_name = model.name().to_string();
-            model
-                .chat(&messages, &self.context, &model_config, Some(callback))
-                .await
-        };
-
-        match result {
-            Ok(response) => {
-                // Try to get content from the callback first
-                let callback_content = response_text.lock().unwrap().clone();
-                if !callback_content.is_empty() {
-                    full_response = callback_content;
-                } else {
-                    full_response = response.content;
-                }
-                tokens_used = response.usage.map(|u| u.total_tokens).unwrap_or(0);
-            },
-            Err(e) => {
-                errors.push(format!("Model error: {}", e));
-                full_response = response_text.lock().unwrap().clone();
-                tokens_used = 0;
-            },
-        }
+        let mut tokens_used = 0;
+        let mut model_name;
+        let mut parsed_actions;
+        // Variables the model sets apply to its later commands
+        let mut session_env = HashMap::new();
+        let root = PathBuf::from(&self.context.root_path);
+        let mut rounds = 0;
+        loop {
+            // Create a callback to capture the response
+            let response_text = Arc::new(std::sync::Mutex::new(String::new()));
+            let response_clone = Arc::clone(&response_text);
+            let callback = Arc::new(move |chunk: &str| {
+                let mut resp = response_clone.lock().unwrap();
+                resp.push_str(chunk);
+            });
+
+            // Call the model
+            let result = {
+                // Take an idle instance when running in parallel
+                let mut model = match self.extra_models.iter().find_map(|m| m.try_lock().ok()) {
+                    Some(model) => model,
+                    None => self.model.lock().await,
+                };
+                model_name = model.name().to_string();
+                model
+                    .chat(&messages, &self.context, &model_config, Some(callback))
+                    .await
+            };
+
+            let round_response = match result {
+                Ok(response) => {
+                    if let Some(usage) = &response.usage {
+                        let _ = UsageLog::new(
+                            model_name.clone(),
+                            usage.prompt_tokens as u64,
+                            usage.completion_tokens as u64,
+                        )
+                        .append();
+                    }
+                    tokens_used += response.usage.map(|u| u.total_tokens).unwrap_or(0);
+                    // Try to get content from the callback first
+                    let callback_content = response_text.lock().unwrap().clone();
+                    if !callback_content.is_empty() {
+                        callback_content
+                    } else {
+                        response.content
+                    }
+                },
+                Err(e) => {
+                    errors.push(format!("Model error: {}", e));
+                    response_text.lock().unwrap().clone()
+                },
+            };
+            if !full_response.is_empty() && !round_response.is_empty() {
+                full_response.push_str("\n\n");
+            }
+            full_response.push_str(&round_response);
 
-        // Parse actions from response
-        let parsed_actions = parse_actions(&full_response);
+            // Parse actions from response
+            parsed_actions = parse_actions(&round_response);
 
-        // Execute actions if not in no-execute mode
-        if !self.no_execute && !parsed_actions.is_empty() {
-            for action in parsed_actions {
+            // Execute actions unless this is a dry run
+            if self.dry_run || parsed_actions.is_empty() {
+                break;
+            }
+            let mut function_results = Vec::new();
+            for action in std::mem::take(&mut parsed_actions) {
                 let (action_type, target) = match &action {
                     AgentAction::WriteFile { path, .. } => ("file_write", path.clone()),
                     AgentAction::ExecuteCommand { command, .. } => ("command", command.clone()),
@@ -198,35 +378,111 @@ impl NonInteractiveRunner {
                     AgentAction::DeleteFile { path } => ("delete_file", path.clone()),
                     AgentAction::GitDiff { .. } => ("git_diff", "git diff".to_string()),
                     AgentAction::GitStatus => ("git_status", "git status".to_string()),
+                    AgentAction::GitStashList => ("git_stash_list", "git stash list".to_string()),
+                    AgentAction::GitStashShow { stash_ref } => {
+                        ("git_stash_show", stash_ref.clone())
+                    },
                     AgentAction::GitCommit { message, .. } => ("git_commit", message.clone()),
-                };
-
-                let result = execute_action(&action)
-                    .await
-                    .unwrap_or(AgentActionResult::Error {
-                        error: "Failed to execute action".to_string(),
-                    });
-
-                let action_result = match result {
-                    AgentActionResult::Success { output } => ActionResult {
-                        action_type: action_type.to_string(),
-                        target,
-                        success: true,
-                        output: Some(output),
+                    AgentAction::GitPush { remote, branch, .. } => {
+                        ("git_push", format!("{}/{}", remote, branch))
+                    },
+                    AgentAction::SearchReplace { pattern, .. } => {
+                        ("search_replace", pattern.clone())
+                    },
+                    AgentAction::AstSearch { pattern, .. } => ("ast_search", pattern.clone()),
+                    AgentAction::GenerateTests { source_file, .. } => {
+                        ("generate_tests", source_file.clone())
                     },
-                    AgentActionResult::Error { error } => ActionResult {
-                        action_type: action_type.to_string(),
-                        target,
-                        success: false,
-                        output: Some(error),
+                    AgentAction::GenerateDocs { source_file, .. } => {
+                        ("generate_docs", source_file.clone())
                     },
+                    AgentAction::LintFile { path, .. } => ("lint", path.clone()),
+                    AgentAction::FormatFiles { paths } => ("format", paths.join(", ")),
+                    AgentAction::HttpRequest { url, .. } => ("http_request", url.clone()),
+                    AgentAction::SetEnvironmentVariable { key, .. } => ("set_env", key.clone()),
+                    AgentAction::WebSearch { query, .. } => ("web_search", query.clone()),
+                    AgentAction::Rollback { to_snapshot_id } => {
+                        ("rollback", to_snapshot_id.clone())
+                    },
+                    AgentAction::CallFunction { name, .. } => ("function_call", name.clone()),
+                };
+
+                let action_start = std::time::Instant::now();
+                // Pushes always need a confirmation, which can't be given here
+                let result = if matches!(action.resolve(), Ok(AgentAction::GitPush { .. })) {
+                    AgentActionResult::error("git push needs confirmation; run it interactively")
+                } else {
+                    let lock = self.file_lock(&action);
+                    let _guard = match &lock {
+                        Some(lock) => Some(lock.lock().await),
+                        None => None,
+                    };
+                    execute_action(&action, &root, &session_env, &self.config)
+                        .await
+                        .unwrap_or(AgentActionResult::error("Failed to execute action"))
+                };
+                let duration_ms = action_start.elapsed().as_millis();
+                if let (
+                    Ok(AgentAction::SetEnvironmentVariable {
+                        key,
+                        value,
+                        persist: false,
+                    }),
+                    AgentActionResult::Success { .. },
+                ) = (action.resolve(), &result)
+                {
+                    session_env.insert(key, value);
+                }
+
+                let action_result = ActionResult {
+                    action_type: action_type.to_string(),
+                    target,
+                    success: matches!(result, AgentActionResult::Success { .. }),
+                    output: Some(result.output()),
+                    duration_ms,
                 };
 
+                if matches!(action, AgentAction::CallFunction { .. }) {
+                    function_results.push(result.output());
+                }
                 actions.push(action_result);
             }
-        } else if !parsed_actions.is_empty() {
-            // Actions were found but not executed (no-execute mode)
+
+            if function_results.is_empty() || !errors.is_empty() || rounds >= MAX_FUNCTION_ROUNDS {
+                break;
+            }
+            rounds += 1;
+            let assistant = messages.len();
+            messages.push(ChatMessage {
+                role: MessageRole::Assistant,
+                content: round_response,
+                timestamp: chrono::Local::now(),
+            });
+            for (n, output) in function_results.into_iter().enumerate() {
+                messages.push(ChatMessage {
+                    role: MessageRole::Tool {
+                        tool_call_id: format!("call_{}_{}", assistant, n),
+                    },
+                    content: output,
+                    timestamp: chrono::Local::now(),
+                });
+            }
+        }
+
+        // Validate JSON responses, keeping the raw text if they don't parse
+        if self.parse_as_json && errors.is_empty() {
+            match parse_json_response(&full_response) {
+                Ok(value) => {
+                    full_response = serde_json::to_string_pretty(&value).unwrap_or(full_response);
+                },
+                Err(e) => errors.push(format!("Invalid JSON response: {}", e)),
+            }
+        }
+
+        if self.dry_run && !parsed_actions.is_empty() {
+            // Actions were found but not executed (dry-run mode)
             for action in parsed_actions {
+                let preview = dry_run_preview(&action);
                 let (action_type, target) = match action {
                     AgentAction::WriteFile { path, .. } => ("file_write", path),
                     AgentAction::ExecuteCommand { command, .. } => ("command", command),
@@ -235,20 +491,39 @@ impl NonInteractiveRunner {
                     AgentAction::DeleteFile { path } => ("delete_file", path),
                     AgentAction::GitDiff { .. } => ("git_diff", "git diff".to_string()),
                     AgentAction::GitStatus => ("git_status", "git status".to_string()),
+                    AgentAction::GitStashList => ("git_stash_list", "git stash list".to_string()),
+                    AgentAction::GitStashShow { stash_ref } => ("git_stash_show", stash_ref),
                     AgentAction::GitCommit { message, .. } => ("git_commit", message),
+                    AgentAction::GitPush { remote, branch, .. } => {
+                        ("git_push", format!("{}/{}", remote, branch))
+                    },
+                    AgentAction::SearchReplace { pattern, .. } => ("search_replace", pattern),
+                    AgentAction::AstSearch { pattern, .. } => ("ast_search", pattern),
+                    AgentAction::GenerateTests { source_file, .. } => {
+                        ("generate_tests", source_file)
+                    },
+                    AgentAction::GenerateDocs { source_file, .. } => ("generate_docs", source_file),
+                    AgentAction::LintFile { path, .. } => ("lint", path),
+                    AgentAction::FormatFiles { paths } => ("format", paths.join(", ")),
+                    AgentAction::HttpRequest { url, .. } => ("http_request", url),
+                    AgentAction::SetEnvironmentVariable { key, .. } => ("set_env", key),
+                    AgentAction::WebSearch { query, .. } => ("web_search", query),
+                    AgentAction::Rollback { to_snapshot_id } => ("rollback", to_snapshot_id),
+                    AgentAction::CallFunction { name, .. } => ("function_call", name),
                 };
 
                 actions.push(ActionResult {
                     action_type: action_type.to_string(),
                     target,
                     success: false,
-                    output: Some("Not executed (--no-execute mode)".to_string()),
+                    output: Some(preview),
+                    duration_ms: 0,
                 });
             }
         }
 
         let duration_ms = start_time.elapsed().as_millis();
-        let actions_executed = !self.no_execute && !actions.is_empty();
+        let actions_executed = !self.dry_run && !actions.is_empty();
 
         Ok(NonInteractiveResult {
             prompt,
@@ -270,11 +545,26 @@ impl NonInteractiveRunner {
             OutputFormat::Json => serde_json::to_string_pretty(result).unwrap_or_else(|e| {
                 format!("{{\"error\": \"Failed to serialize result: {}\"}}", e)
             }),
+            OutputFormat::Ndjson => serde_json::to_string(result).unwrap_or_else(|e| {
+                format!("{{\"error\": \"Failed to serialize result: {}\"}}", e)
+            }),
+            OutputFormat::Csv => std::iter::once(CSV_HEADER.to_string())
+                .chain(csv_rows(result))
+                .collect::<Vec<_>>()
+                .join("\n"),
             OutputFormat::Text => {
                 let mut output = String::new();
                 output.push_str(&result.response);
 
-                if !result.actions.is_empty() {
+                if self.dry_run && !result.actions.is_empty() {
+                    output.push_str("\n\n--- Would execute ---\n");
+                    for action in &result.actions {
+                        output.push_str(&format!("• {} - {}\n", action.action_type, action.target));
+                        for line in action.output.as_deref().unwrap_or_default().lines() {
+                            output.push_str(&format!("    {}\n", line));
+                        }
+                    }
+                } else if !result.actions.is_empty() {
                     output.push_str("\n\n--- Actions ---\n");
                     for action in &result.actions {
                         output.push_str(&format!(
@@ -305,7 +595,18 @@ impl NonInteractiveRunner {
                 output.push_str(&result.response);
                 output.push_str("\n\n");
 
-                if !result.actions.is_empty() {
+                if self.dry_run && !result.actions.is_empty() {
+                    output.push_str("## Would Execute\n\n");
+                    for action in &result.actions {
+                        output.push_str(&format!(
+                            "- **{}**: `{}`\n\n```\n{}\n```\n",
+                            action.action_type,
+                            action.target,
+                            action.output.as_deref().unwrap_or_default()
+                        ));
+                    }
+                    output.push('\n');
+                } else if !result.actions.is_empty() {
                     output.push_str("## Actions Executed\n\n");
                     for action in &result.actions {
                         let status = if action.success { "SUCCESS" } else { "FAILED" };
@@ -340,4 +641,310 @@ impl NonInteractiveRunner {
             },
         }
     }
+
+    /// Format a batch: JSON as `{ results, summary }`, NDJSON as one line per
+    /// result followed by a summary line, text and markdown result by result
+    pub fn format_batch(&self, batch: &BatchResult, format: OutputFormat) -> String {
+        let summary = &batch.summary;
+        match format {
+            OutputFormat::Json => serde_json::to_string_pretty(batch).unwrap_or_else(|e| {
+                format!("{{\"error\": \"Failed to serialize result: {}\"}}", e)
+            }),
+            OutputFormat::Ndjson => batch
+                .results
+                .iter()
+                .map(|result| self.format_result(result, OutputFormat::Ndjson))
+                .chain(std::iter::once(
+                    serde_json::json!({ "summary": summary }).to_string(),
+                ))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            // One header for the whole batch
+            OutputFormat::Csv => std::iter::once(CSV_HEADER.to_string())
+                .chain(batch.results.iter().flat_map(csv_rows))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            format => {
+                let mut output = String::new();
+                for (i, result) in batch.results.iter().enumerate() {
+                    output.push_str(&format!(
+                        "=== [{}/{}] {} ===\n",
+                        i + 1,
+                        summary.total,
+                        result.prompt.lines().last().unwrap_or_default()
+                    ));
+                    output.push_str(&self.format_result(result, format.clone()));
+                    output.push_str("\n\n");
+                }
+                output.push_str(&format!(
+                    "--- Summary ---\n{} prompts: {} succeeded, {} failed | Tokens: {} | Duration: {}ms\n",
+                    summary.total,
+                    summary.succeeded,
+                    summary.failed,
+                    summary.total_tokens,
+                    summary.duration_ms
+                ));
+                output
+            },
+        }
+    }
+}
+
+const CSV_HEADER: &str = "action_type,target,success,output,duration_ms";
+
+/// One CSV row per action, then the response as an `action_type="response"` row
+fn csv_rows(result: &NonInteractiveResult) -> Vec<String> {
+    let row = |action_type: &str, target: &str, success: bool, output: &str, duration_ms: u128| {
+        format!(
+            "{},{},{},{},{}",
+            csv_field(action_type),
+            csv_field(target),
+            success,
+            csv_field(output),
+            duration_ms
+        )
+    };
+    result
+        .actions
+        .iter()
+        .map(|action| {
+            row(
+                &action.action_type,
+                &action.target,
+                action.success,
+                action.output.as_deref().unwrap_or_default(),
+                action.duration_ms,
+            )
+        })
+        .chain(std::iter::once(row(
+            "response",
+            &result.metadata.model,
+            result.errors.is_empty(),
+            &result.response,
+            result.metadata.duration_ms,
+        )))
+        .collect()
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// What an action would do, shown in place of its output by `--dry-run`
+fn dry_run_preview(action: &AgentAction) -> String {
+    match action {
+        AgentAction::WriteFile { path, content } => {
+            format!("Write {} ({} bytes):\n{}", path, content.len(), content)
+        },
+        AgentAction::ExecuteCommand {
+            command,
+            working_dir: Some(dir),
+        } => format!("$ {}  (in {})", command, dir),
+        AgentAction::ExecuteCommand { command, .. } => format!("$ {}", command),
+        AgentAction::DeleteFile { path } => format!("Delete {}", path),
+        AgentAction::HttpRequest {
+            method, url, body, ..
+        } => match body {
+            Some(body) => format!("{} {}\n{}", method, url, body),
+            None => format!("{} {}", method, url),
+        },
+        AgentAction::GitCommit {
+            message,
+            files,
+            amend,
+        } => format!(
+            "{} {}: {}",
+            if *amend {
+                "Amend last commit with"
+            } else {
+                "Commit"
+            },
+            files.join(", "),
+            message
+        ),
+        _ => format!("Would run {}", action.type_name()),
+    }
+}
+
+/// Messages of a saved conversation to replay as context
+/// System messages are UI notices in saved conversations, so they're left out
+pub fn context_messages(conversation: &ConversationHistory) -> Vec<ChatMessage> {
+    conversation
+        .messages
+        .iter()
+        .filter(|message| message.role != MessageRole::System)
+        .cloned()
+        .collect()
+}
+
+/// Combine the `-p` prompt with each non-empty line of a prompts file
+pub fn batch_prompts(prompt: &str, lines: &str) -> Vec<String> {
+    lines
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            if prompt.trim().is_empty() {
+                line.to_string()
+            } else {
+                format!("{}\n\n{}", prompt, line)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json_response() {
+        assert_eq!(
+            parse_json_response("```json\n{\"ok\": true}\n```").unwrap(),
+            serde_json::json!({ "ok": true })
+        );
+        assert!(parse_json_response("Sure! Here is the JSON: {").is_err());
+    }
+
+    #[test]
+    fn test_batch_prompts_and_summary() {
+        let prompts = batch_prompts("Review this file", "src/a.rs\n\n  src/b.rs\n");
+        assert_eq!(
+            prompts,
+            vec![
+                "Review this file\n\nsrc/a.rs",
+                "Review this file\n\nsrc/b.rs"
+            ]
+        );
+        assert_eq!(batch_prompts("", "one\ntwo"), vec!["one", "two"]);
+
+        let result = |tokens, errors: Vec<String>| NonInteractiveResult {
+            prompt: String::new(),
+            response: String::new(),
+            actions: Vec::new(),
+            errors,
+            metadata: ExecutionMetadata {
+                model: "ollama/tinyllama".to_string(),
+                tokens_used: Some(tokens),
+                duration_ms: 10,
+                actions_executed: false,
+            },
+        };
+        let summary = BatchSummary::new(
+            &[
+                result(100, Vec::new()),
+                result(50, vec!["Model error".to_string()]),
+            ],
+            25,
+        );
+        assert_eq!(
+            (summary.total, summary.succeeded, summary.failed),
+            (2, 1, 1)
+        );
+        assert_eq!(summary.total_tokens, 150);
+    }
+
+    #[test]
+    fn test_context_messages() {
+        let message = |role, content: &str| ChatMessage {
+            role,
+            content: content.to_string(),
+            timestamp: chrono::Local::now(),
+        };
+        let mut conversation =
+            ConversationHistory::new("/tmp".to_string(), "ollama/tinyllama".to_string());
+        conversation.add_messages(&[
+            message(MessageRole::User, "step 1"),
+            message(MessageRole::System, "[OK] File written"),
+            message(MessageRole::Assistant, "done"),
+        ]);
+        let contents: Vec<String> = context_messages(&conversation)
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(contents, vec!["step 1", "done"]);
+    }
+
+    #[test]
+    fn test_dry_run_preview_and_exit_code() {
+        let write = AgentAction::WriteFile {
+            path: "src/lib.rs".to_string(),
+            content: "pub fn f() {}".to_string(),
+        };
+        assert_eq!(
+            dry_run_preview(&write),
+            "Write src/lib.rs (13 bytes):\npub fn f() {}"
+        );
+        let command = AgentAction::ExecuteCommand {
+            command: "cargo test".to_string(),
+            working_dir: None,
+        };
+        assert_eq!(dry_run_preview(&command), "$ cargo test");
+
+        let result = |actions: usize, errors: usize| NonInteractiveResult {
+            prompt: String::new(),
+            response: String::new(),
+            actions: (0..actions)
+                .map(|_| ActionResult {
+                    action_type: "command".to_string(),
+                    target: "cargo test".to_string(),
+                    success: false,
+                    output: None,
+                    duration_ms: 0,
+                })
+                .collect(),
+            errors: vec!["Model error".to_string(); errors],
+            metadata: ExecutionMetadata {
+                model: "ollama/tinyllama".to_string(),
+                tokens_used: None,
+                duration_ms: 0,
+                actions_executed: false,
+            },
+        };
+        assert_eq!(result(0, 0).exit_code(true), 0);
+        assert_eq!(result(1, 0).exit_code(true), 2);
+        assert_eq!(result(1, 0).exit_code(false), 0);
+        assert_eq!(result(1, 1).exit_code(true), 1);
+
+        let batch = |results| BatchResult {
+            summary: BatchSummary::new(&[], 0),
+            results,
+        };
+        assert_eq!(batch(vec![result(1, 0), result(0, 0)]).exit_code(true), 2);
+        assert_eq!(batch(vec![result(1, 0), result(0, 1)]).exit_code(true), 1);
+    }
+
+    #[test]
+    fn test_csv_rows() {
+        let result = NonInteractiveResult {
+            prompt: "Fix it".to_string(),
+            response: "Done, see \"main.rs\"\nThanks".to_string(),
+            actions: vec![ActionResult {
+                action_type: "command".to_string(),
+                target: "cargo test, quietly".to_string(),
+                success: true,
+                output: Some("ok".to_string()),
+                duration_ms: 42,
+            }],
+            errors: Vec::new(),
+            metadata: ExecutionMetadata {
+                model: "ollama/tinyllama".to_string(),
+                tokens_used: Some(10),
+                duration_ms: 900,
+                actions_executed: true,
+            },
+        };
+        assert_eq!(
+            csv_rows(&result),
+            vec![
+                "command,\"cargo test, quietly\",true,ok,42",
+                "response,ollama/tinyllama,true,\"Done, see \"\"main.rs\"\"\nThanks\",900",
+            ]
+        );
+    }
 }
@@ -1,19 +1,29 @@
 use anyhow::Result;
 use colored::Colorize;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
 
 use crate::{
+    agents::allow_workspace_root,
     app::{load_config, Config},
+    cache::CacheManager,
     cli::{handle_command, Cli},
     context::ContextLoader,
     models::{ModelFactory, ProjectContext},
     ollama::ensure_model as ensure_ollama_model,
-    proxy::{count_mermaid_processes, ensure_proxy, is_proxy_running, stop_proxy},
+    proxy::{
+        count_mermaid_processes, detect_runtime, ensure_proxy, is_proxy_running, stop_proxy,
+        ProxyWatchdog,
+    },
     session::{select_conversation, ConversationManager, SessionState},
-    tui::{run_ui, App},
+    tui::{run_ui, App, OperationMode},
     utils::{log_error, log_info, log_progress, log_warn},
 };
 
+/// Number of scanned files between progress reports
+const PROGRESS_REPORT_INTERVAL: usize = 250;
+
 /// Main runtime orchestrator
 pub struct Orchestrator {
     cli: Cli,
@@ -105,6 +115,10 @@ impl Orchestrator {
         // Ensure LiteLLM proxy is running (unless --no-auto-proxy is set)
         current_step += 1;
         log_progress(current_step, total_steps, "Checking LiteLLM proxy");
+        match detect_runtime() {
+            Some(runtime) => log_info("RUNTIME", format!("Container runtime: {}", runtime)),
+            None => log_info("RUNTIME", "No container runtime found"),
+        }
         if !is_proxy_running().await {
             ensure_proxy(self.cli.no_auto_proxy).await?;
             self.proxy_started_by_us = !self.cli.no_auto_proxy;
@@ -130,8 +144,35 @@ impl Orchestrator {
             },
         };
 
-        // Set up project context
-        let project_path = self.cli.path.clone().unwrap_or_else(|| PathBuf::from("."));
+        // Set up project context, resuming the last directory when remembering the session
+        let resumed_dir = self
+            .session
+            .last_working_dir
+            .clone()
+            .filter(|dir| self.config.mode.remember_mode && dir.is_dir());
+        let project_path = match (self.cli.path.clone(), resumed_dir) {
+            (Some(path), _) => path,
+            (None, Some(dir)) => {
+                log_info("RESUME", format!("Resuming in {}", dir.display()));
+                dir
+            },
+            (None, None) => PathBuf::from("."),
+        };
+
+        // Parse files changed since the last run while the rest of startup proceeds
+        let prewarm = self.session.last_run.map(|last_run| {
+            let root = project_path.clone();
+            tokio::task::spawn_blocking(move || CacheManager::new()?.prewarm(&root, last_run))
+        });
+        self.session.last_run = Some(SystemTime::now());
+        self.session.last_working_dir = project_path.canonicalize().ok();
+        // File actions may reach the project even when it isn't the current directory
+        if let Ok(root) = project_path.canonicalize() {
+            allow_workspace_root(root);
+        }
+        if let Err(e) = self.session.save() {
+            log_warn("WARNING", format!("Failed to save session: {}", e));
+        }
 
         // Load project structure quickly (no file contents)
         current_step += 1;
@@ -141,30 +182,44 @@ impl Orchestrator {
         // Create app instance with model and lazy context (converts to regular context)
         current_step += 1;
         log_progress(current_step, total_steps, "Starting UI");
-        let context = lazy_context.to_project_context().await;
-        let mut app = App::new(model, context);
+        match prewarm {
+            Some(handle) if handle.is_finished() => match handle.await {
+                Ok(Ok(count)) => log_info("CACHE", format!("Prewarmed {} changed files", count)),
+                Ok(Err(e)) => log_warn("CACHE", format!("Cache prewarm failed: {}", e)),
+                Err(e) => log_warn("CACHE", format!("Cache prewarm failed: {}", e)),
+            },
+            Some(_) => log_info("CACHE", "Prewarming changed files in the background..."),
+            None => {},
+        }
+        let mut context = lazy_context.to_project_context().await;
+        self.load_additional_roots(&mut context)?;
+        let mut app = App::new(model, context)
+            .with_config(self.config.clone())
+            .with_lazy_context(lazy_context.clone());
+
+        // Watch the proxy for the rest of the session, restarting it unless disabled
+        let mut proxy_config = self.config.litellm.clone();
+        proxy_config.auto_restart &= !self.cli.no_auto_proxy;
+        let watchdog = ProxyWatchdog::new(&proxy_config);
+        watchdog.spawn();
+        app = app.with_proxy_watchdog(watchdog);
+        app.override_budget = self.cli.override_budget;
+        app.extra_roots = self.cli.extra_roots.clone();
+        app.refresh_stashes();
+        app.note_env_file();
+        if let Some(mode) = self.restored_mode() {
+            app.operation_mode = mode;
+        }
 
         // Start loading files in background after UI is visible
-        let lazy_context_bg = lazy_context.clone();
-        let project_path_bg = project_path.clone();
         tokio::spawn(async move {
-            // Load priority files first (README, config, etc.)
-            let priority = crate::models::get_priority_files(&project_path_bg.to_string_lossy());
-            if !priority.is_empty() {
-                let _ = lazy_context_bg.load_files_batch(priority).await;
-            }
-
-            // Then load remaining files progressively
-            let all_files = lazy_context_bg.get_file_list();
-            for chunk in all_files.chunks(10) {
-                let _ = lazy_context_bg.load_files_batch(chunk.to_vec()).await;
-                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-            }
+            lazy_context.load_all().await;
         });
 
         // Handle --resume or --continue flags
         if self.cli.resume || self.cli.continue_conversation {
-            let conversation_manager = ConversationManager::new(&project_path)?;
+            let conversation_manager =
+                ConversationManager::with_backend(&project_path, &self.config.storage_backend)?;
             let conversations = conversation_manager.list_conversations()?;
 
             if self.cli.continue_conversation {
@@ -206,12 +261,30 @@ impl Orchestrator {
         result
     }
 
+    /// Operation mode saved last session, if `mode.remember_mode` allows restoring it
+    fn restored_mode(&self) -> Option<OperationMode> {
+        let mode_config = &self.config.mode;
+        if !mode_config.remember_mode {
+            return None;
+        }
+        self.session
+            .last_mode
+            .as_deref()
+            .and_then(OperationMode::from_str)
+            .filter(|mode| mode.persistable(mode_config.allow_persist_bypass))
+    }
+
     /// Load project structure quickly (no file contents)
     fn load_project_structure(
         &self,
         project_path: &PathBuf,
     ) -> Result<crate::models::LazyProjectContext> {
-        let loader = ContextLoader::new()?;
+        // Report scanning progress periodically so large projects don't look stalled
+        let loader = ContextLoader::new()?.with_progress_callback(Arc::new(|processed, total| {
+            if processed % PROGRESS_REPORT_INTERVAL == 0 || processed == total {
+                log_progress(processed, total, "files scanned");
+            }
+        }));
 
         log_info(
             "FILES",
@@ -231,6 +304,19 @@ impl Orchestrator {
         Ok(context)
     }
 
+    /// Merge files from `--root` and `context.additional_roots` into the context
+    fn load_additional_roots(&self, context: &mut ProjectContext) -> Result<()> {
+        let loader = ContextLoader::new()?.with_additional_roots(self.cli.extra_roots.clone());
+        let added = loader.merge_additional_roots(context)?;
+        if added > 0 {
+            log_info(
+                "FILES",
+                format!("Loaded {} files from additional roots", added),
+            );
+        }
+        Ok(())
+    }
+
     /// Load project context (keeping for compatibility)
     fn load_project_context(&self, project_path: &PathBuf) -> Result<ProjectContext> {
         let loader = ContextLoader::new()?;
@@ -250,6 +336,13 @@ impl Orchestrator {
                 context.token_count
             ),
         );
+        let stripped = loader.stripped_comment_tokens();
+        if stripped > 0 {
+            log_info(
+                "STATS",
+                format!("Stripping comments saved ~{} tokens", stripped),
+            );
+        }
 
         Ok(context)
     }
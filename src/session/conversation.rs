@@ -1,9 +1,14 @@
+use super::IndexManager;
 use crate::models::{ChatMessage, MessageRole};
 use anyhow::Result;
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
 use std::fs;
+use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 
 /// A complete conversation history
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,6 +21,21 @@ pub struct ConversationHistory {
     pub created_at: DateTime<Local>,
     pub updated_at: DateTime<Local>,
     pub total_tokens: Option<usize>,
+    /// IDs of the conversations this one was merged from
+    #[serde(default)]
+    pub merged_from: Vec<String>,
+    /// Whether the title was set explicitly (e.g. generated) rather than derived
+    #[serde(default)]
+    pub custom_title: bool,
+    /// Pinned conversations are never trashed by bulk cleanup
+    #[serde(default)]
+    pub pinned: bool,
+    /// System prompt that replaces the configured default
+    #[serde(default)]
+    pub custom_system_prompt: Option<String>,
+    /// Model-generated summary of what the conversation accomplished
+    #[serde(default)]
+    pub summary: Option<String>,
 }
 
 impl ConversationHistory {
@@ -32,6 +52,11 @@ impl ConversationHistory {
             created_at: now,
             updated_at: now,
             total_tokens: None,
+            merged_from: Vec::new(),
+            custom_title: false,
+            pinned: false,
+            custom_system_prompt: None,
+            summary: None,
         }
     }
 
@@ -42,8 +67,22 @@ impl ConversationHistory {
         self.update_title();
     }
 
+    /// Merge each run of consecutive system messages into one, dropping repeats
+    pub fn deduplicate_system_messages(&mut self) {
+        merge_system_messages(&mut self.messages);
+    }
+
+    /// Set an explicit title that later messages won't overwrite
+    pub fn set_title(&mut self, title: impl Into<String>) {
+        self.title = title.into();
+        self.custom_title = true;
+    }
+
     /// Update the title based on the first user message
     fn update_title(&mut self) {
+        if self.custom_title {
+            return;
+        }
         if let Some(first_user_msg) = self.messages.iter().find(|m| m.role == MessageRole::User) {
             // Take first 60 chars of first user message as title
             let preview = if first_user_msg.content.len() > 60 {
@@ -73,64 +112,248 @@ impl ConversationHistory {
     }
 }
 
-/// Manages conversation persistence for a project
-pub struct ConversationManager {
-    #[allow(dead_code)]
-    project_dir: PathBuf,
+/// Merge each run of consecutive system messages into one, dropping repeats
+/// The merged message keeps the timestamp of the run's last message
+pub fn merge_system_messages(messages: &mut Vec<ChatMessage>) {
+    let mut merged: Vec<ChatMessage> = Vec::with_capacity(messages.len());
+    // Contents already in the trailing system run
+    let mut run: Vec<String> = Vec::new();
+    for message in messages.drain(..) {
+        if message.role != MessageRole::System {
+            run.clear();
+            merged.push(message);
+            continue;
+        }
+        match merged.last_mut() {
+            Some(last) if !run.is_empty() => {
+                if !run.contains(&message.content) {
+                    last.content.push_str("\n\n");
+                    last.content.push_str(&message.content);
+                    run.push(message.content);
+                }
+                last.timestamp = message.timestamp;
+            },
+            _ => {
+                run.push(message.content.clone());
+                merged.push(message);
+            },
+        }
+    }
+    *messages = merged;
+}
+
+/// A saved conversation whose content matched a search query
+#[derive(Debug, Clone)]
+pub struct ConversationMatch {
+    pub conversation_id: String,
+    pub title: String,
+    pub matching_message_index: usize,
+    pub snippet: String,
+}
+
+/// Characters of context to keep on each side of a search hit
+const SNIPPET_CONTEXT_CHARS: usize = 40;
+
+/// Build a single-line snippet around the match at `byte_pos`, moved back to the
+/// start of its character when it falls inside one
+fn build_snippet(content: &str, byte_pos: usize, match_len: usize) -> String {
+    let mut byte_pos = byte_pos.min(content.len());
+    while !content.is_char_boundary(byte_pos) {
+        byte_pos -= 1;
+    }
+    let before: Vec<char> = content[..byte_pos].chars().collect();
+    let start = before.len().saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let prefix: String = before[start..].iter().collect();
+    let rest: String = content[byte_pos..]
+        .chars()
+        .take(match_len + SNIPPET_CONTEXT_CHARS)
+        .collect();
+
+    let mut snippet = format!("{}{}", prefix, rest).replace('\n', " ");
+    if start > 0 {
+        snippet.insert_str(0, "...");
+    }
+    if byte_pos + rest.len() < content.len() {
+        snippet.push_str("...");
+    }
+    snippet
+}
+
+/// Snippet around the first query word found in the message, or its start
+fn match_snippet(content: &str, query: &str) -> String {
+    let lowered = content.to_lowercase();
+    let hit = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .find_map(|word| {
+            let word = word.to_lowercase();
+            lowered.find(&word).map(|pos| (pos, word.chars().count()))
+        });
+    // Lowercasing can change byte lengths, so only use the original when it's safe
+    let text = if lowered.len() == content.len() {
+        content
+    } else {
+        &lowered
+    };
+    let (pos, len) = hit.unwrap_or((0, 0));
+    build_snippet(text, pos, len)
+}
+
+/// Move a file, falling back to copy + remove across filesystems
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+/// Refuse IDs that aren't plain file names, so `../x` can't reach outside the store
+pub(super) fn check_id(id: &str) -> Result<()> {
+    anyhow::ensure!(
+        !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'),
+        "Invalid conversation ID: {}",
+        id
+    );
+    Ok(())
+}
+
+/// Where conversations are kept
+pub trait ConversationStorage: Send + Sync {
+    /// Save a conversation, replacing an earlier save of it
+    fn save(&self, conversation: &ConversationHistory) -> Result<()>;
+
+    /// Load a specific conversation by ID
+    fn load(&self, id: &str) -> Result<ConversationHistory>;
+
+    /// All saved conversations, newest first
+    fn list(&self) -> Result<Vec<ConversationHistory>>;
+
+    /// Move a conversation to the project's trash (see `ConversationManager::trash_dir`)
+    fn delete(&self, id: &str) -> Result<()>;
+
+    /// Conversations with a message containing every word of the query, newest first
+    fn search(&self, query: &str) -> Result<Vec<ConversationMatch>>;
+
+    /// Rebuild any search index from scratch, returning how many conversations it covers
+    fn rebuild_index(&self) -> Result<usize> {
+        Ok(self.list()?.len())
+    }
+
+    /// Where conversations are stored, for display
+    fn location(&self) -> String;
+}
+
+/// One JSON file per conversation in `.mermaid/conversations`, searched through the
+/// project's `IndexManager`; deleted conversations go to the project's trash
+pub struct FileSystemStorage {
     conversations_dir: PathBuf,
+    index_path: PathBuf,
+    trash_dir: PathBuf,
 }
 
-impl ConversationManager {
-    /// Create a new conversation manager for a project directory
-    pub fn new(project_dir: impl AsRef<Path>) -> Result<Self> {
-        let project_dir = project_dir.as_ref().to_path_buf();
+impl FileSystemStorage {
+    /// Store the project's conversations in `.mermaid/conversations`
+    pub fn new(project_dir: &Path, index_path: PathBuf) -> Result<Self> {
+        Self::open(
+            project_dir,
+            index_path,
+            ConversationManager::trash_dir(project_dir)?,
+        )
+    }
+
+    /// Store the project's conversations in `.mermaid/conversations`, trashing
+    /// deleted conversations into `trash_dir`
+    pub fn open(project_dir: &Path, index_path: PathBuf, trash_dir: PathBuf) -> Result<Self> {
         let conversations_dir = project_dir.join(".mermaid").join("conversations");
 
         // Create conversations directory if it doesn't exist
         fs::create_dir_all(&conversations_dir)?;
 
         Ok(Self {
-            project_dir,
             conversations_dir,
+            index_path,
+            trash_dir,
         })
     }
 
-    /// Save a conversation to disk
-    pub fn save_conversation(&self, conversation: &ConversationHistory) -> Result<()> {
-        let filename = format!("{}.json", conversation.id);
-        let path = self.conversations_dir.join(filename);
+    /// IDs of the saved conversations, read from the file names
+    fn conversation_ids(&self) -> Vec<String> {
+        fs::read_dir(&self.conversations_dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                    .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
 
-        let json = serde_json::to_string_pretty(conversation)?;
-        fs::write(path, json)?;
+    fn path(&self, id: &str) -> Result<PathBuf> {
+        check_id(id)?;
+        Ok(self.conversations_dir.join(format!("{}.json", id)))
+    }
+
+    /// Changes whenever the conversation's file does, from its modification time and size
+    fn version(&self, id: &str) -> Option<u64> {
+        let metadata = fs::metadata(self.path(id).ok()?).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        let mut hasher = DefaultHasher::new();
+        (modified.as_nanos(), metadata.len()).hash(&mut hasher);
+        Some(hasher.finish())
+    }
 
+    /// Bring the index up to date with the conversation files: new and changed ones
+    /// are (re-)indexed and deleted ones dropped, so saves never touch the index and
+    /// a write lost to another instance is redone on the next search
+    fn refresh_index(&self, index: &mut IndexManager) -> Result<()> {
+        let ids: HashSet<String> = self.conversation_ids().into_iter().collect();
+        let mut updated = false;
+        for id in index.indexed_ids() {
+            if !ids.contains(&id) {
+                index.remove_conversation(&id);
+                updated = true;
+            }
+        }
+        for id in &ids {
+            let Some(version) = self.version(id) else {
+                continue;
+            };
+            if index.is_current(id, version) {
+                continue;
+            }
+            if let Ok(conversation) = self.load(id) {
+                index.index_conversation(&conversation, version);
+                updated = true;
+            }
+        }
+        if updated {
+            index.save()?;
+        }
         Ok(())
     }
+}
 
-    /// Load a specific conversation by ID
-    pub fn load_conversation(&self, id: &str) -> Result<ConversationHistory> {
-        let filename = format!("{}.json", id);
-        let path = self.conversations_dir.join(filename);
+impl ConversationStorage for FileSystemStorage {
+    fn save(&self, conversation: &ConversationHistory) -> Result<()> {
+        let json = serde_json::to_string_pretty(conversation)?;
+        fs::write(self.path(&conversation.id)?, json)?;
+        Ok(())
+    }
 
-        let json = fs::read_to_string(path)?;
+    fn load(&self, id: &str) -> Result<ConversationHistory> {
+        let json = fs::read_to_string(self.path(id)?)?;
         let conversation: ConversationHistory = serde_json::from_str(&json)?;
 
         Ok(conversation)
     }
 
-    /// Load the most recent conversation
-    pub fn load_last_conversation(&self) -> Result<Option<ConversationHistory>> {
-        let conversations = self.list_conversations()?;
-
-        if conversations.is_empty() {
-            return Ok(None);
-        }
-
-        // Conversations are already sorted by modification time (newest first)
-        Ok(Some(conversations.into_iter().next().unwrap()))
-    }
-
-    /// List all conversations in the project
-    pub fn list_conversations(&self) -> Result<Vec<ConversationHistory>> {
+    fn list(&self) -> Result<Vec<ConversationHistory>> {
         let mut conversations = Vec::new();
 
         // Read all JSON files in the conversations directory
@@ -154,20 +377,458 @@ impl ConversationManager {
         Ok(conversations)
     }
 
-    /// Delete a conversation
+    fn delete(&self, id: &str) -> Result<()> {
+        let path = self.path(id)?;
+        if !path.exists() {
+            anyhow::bail!("Conversation not found: {}", id);
+        }
+
+        fs::create_dir_all(&self.trash_dir)?;
+        move_file(&path, &self.trash_dir.join(format!("{}.json", id)))
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<ConversationMatch>> {
+        let mut index = IndexManager::open(&self.index_path);
+        self.refresh_index(&mut index)?;
+
+        // Hits are sorted, so the first one of each conversation is its earliest message
+        let mut seen = HashSet::new();
+        let mut found = Vec::new();
+        for (id, index) in index.lookup(query) {
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+            let Ok(conversation) = self.load(&id) else {
+                continue;
+            };
+            if conversation.messages.get(index).is_some() {
+                found.push((conversation, index));
+            }
+        }
+        Ok(sorted_matches(found, query))
+    }
+
+    /// Re-index every conversation in this project, starting from an empty index
+    fn rebuild_index(&self) -> Result<usize> {
+        let mut index = IndexManager::open(&self.index_path);
+        index.clear();
+        self.refresh_index(&mut index)?;
+        index.save()?;
+        Ok(index.indexed_ids().len())
+    }
+
+    fn location(&self) -> String {
+        self.conversations_dir.to_string_lossy().into_owned()
+    }
+}
+
+/// Search results for (conversation, matching message index) pairs, newest conversation first
+pub(super) fn sorted_matches(
+    mut found: Vec<(ConversationHistory, usize)>,
+    query: &str,
+) -> Vec<ConversationMatch> {
+    found.sort_by_key(|(conversation, _)| std::cmp::Reverse(conversation.updated_at));
+    found
+        .into_iter()
+        .map(|(conversation, index)| ConversationMatch {
+            snippet: match_snippet(&conversation.messages[index].content, query),
+            conversation_id: conversation.id,
+            title: conversation.title,
+            matching_message_index: index,
+        })
+        .collect()
+}
+
+/// Manages conversation persistence for a project
+pub struct ConversationManager {
+    #[allow(dead_code)]
+    project_dir: PathBuf,
+    storage: Box<dyn ConversationStorage>,
+}
+
+impl ConversationManager {
+    /// Create a new conversation manager for a project directory
+    pub fn new(project_dir: impl AsRef<Path>) -> Result<Self> {
+        let index_path = IndexManager::default_path(project_dir.as_ref())?;
+        Self::with_index(project_dir, index_path)
+    }
+
+    /// Create a conversation manager that keeps its search index at `index_path`
+    pub fn with_index(project_dir: impl AsRef<Path>, index_path: PathBuf) -> Result<Self> {
+        let project_dir = project_dir.as_ref().to_path_buf();
+        let storage = FileSystemStorage::new(&project_dir, index_path)?;
+        Ok(Self::with_storage(project_dir, Box::new(storage)))
+    }
+
+    /// Create a conversation manager for the configured `storage_backend`
+    /// ("filesystem" or "sqlite")
+    pub fn with_backend(project_dir: impl AsRef<Path>, backend: &str) -> Result<Self> {
+        match backend {
+            "filesystem" => Self::new(project_dir),
+            #[cfg(feature = "sqlite-storage")]
+            "sqlite" => {
+                let project_dir = project_dir.as_ref().to_path_buf();
+                let storage = super::sqlite::SqliteStorage::new(&project_dir)?;
+                Ok(Self::with_storage(project_dir, Box::new(storage)))
+            },
+            #[cfg(not(feature = "sqlite-storage"))]
+            "sqlite" => anyhow::bail!(
+                "storage_backend = \"sqlite\" needs mermaid built with the sqlite-storage feature"
+            ),
+            other => anyhow::bail!(
+                "Unknown storage_backend: {} (expected \"filesystem\" or \"sqlite\")",
+                other
+            ),
+        }
+    }
+
+    /// Create a conversation manager that keeps conversations in `storage`
+    pub fn with_storage(project_dir: PathBuf, storage: Box<dyn ConversationStorage>) -> Self {
+        Self {
+            project_dir,
+            storage,
+        }
+    }
+
+    /// Save a conversation
+    pub fn save_conversation(&self, conversation: &ConversationHistory) -> Result<()> {
+        self.storage.save(conversation)
+    }
+
+    /// Load a specific conversation by ID
+    pub fn load_conversation(&self, id: &str) -> Result<ConversationHistory> {
+        self.storage.load(id)
+    }
+
+    /// Load the most recent conversation
+    pub fn load_last_conversation(&self) -> Result<Option<ConversationHistory>> {
+        // Conversations are sorted by modification time (newest first)
+        Ok(self.list_conversations()?.into_iter().next())
+    }
+
+    /// List all conversations in the project
+    pub fn list_conversations(&self) -> Result<Vec<ConversationHistory>> {
+        self.storage.list()
+    }
+
+    /// Merge two saved conversations into a new one, interleaving messages by timestamp
+    /// The originals are left untouched
+    pub fn merge_conversations(&self, id1: &str, id2: &str) -> Result<ConversationHistory> {
+        let first = self.load_conversation(id1)?;
+        let second = self.load_conversation(id2)?;
+
+        let mut merged =
+            ConversationHistory::new(first.project_path.clone(), first.model_name.clone());
+        // IDs only go down to the second, so pick one no saved conversation already uses
+        let base_id = merged.id.clone();
+        let mut suffix = 1;
+        while self.load_conversation(&merged.id).is_ok() {
+            merged.id = format!("{}_merged_{}", base_id, suffix);
+            suffix += 1;
+        }
+
+        let mut messages: Vec<ChatMessage> = first
+            .messages
+            .iter()
+            .chain(second.messages.iter())
+            .cloned()
+            .collect();
+        messages.sort_by_key(|m| m.timestamp);
+        merged.messages = messages;
+        merged.custom_system_prompt = first
+            .custom_system_prompt
+            .clone()
+            .or_else(|| second.custom_system_prompt.clone());
+
+        merged.set_title(format!("Merge of: {} + {}", first.title, second.title));
+        merged.created_at = first.created_at.min(second.created_at);
+        merged.total_tokens = match (first.total_tokens, second.total_tokens) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+        };
+        merged.merged_from = vec![first.id, second.id];
+
+        self.save_conversation(&merged)?;
+        Ok(merged)
+    }
+
+    /// Find saved conversations with a message containing every word of the query
+    /// Reports the first matching message of each conversation, newest conversation first
+    pub fn search_conversations(&self, query: &str) -> Result<Vec<ConversationMatch>> {
+        self.storage.search(query)
+    }
+
+    /// Rebuild the search index of every conversation in this project
+    pub fn rebuild_index(&self) -> Result<usize> {
+        self.storage.rebuild_index()
+    }
+
+    /// Delete a conversation by moving it to the trash
     pub fn delete_conversation(&self, id: &str) -> Result<()> {
-        let filename = format!("{}.json", id);
-        let path = self.conversations_dir.join(filename);
+        self.storage.delete(id)
+    }
 
-        if path.exists() {
-            fs::remove_file(path)?;
+    /// Restore a conversation from a project's trash, using that project's `backend`
+    pub fn restore_conversation(
+        project_dir: &Path,
+        id: &str,
+        backend: &str,
+    ) -> Result<ConversationHistory> {
+        Self::with_backend(project_dir, backend)?
+            .restore_from_trash(&Self::trash_dir(project_dir)?, id)
+    }
+
+    /// Move a conversation from `trash_dir` back into this manager's storage
+    pub fn restore_from_trash(&self, trash_dir: &Path, id: &str) -> Result<ConversationHistory> {
+        check_id(id)?;
+        let trashed = trash_dir.join(format!("{}.json", id));
+        if !trashed.exists() {
+            anyhow::bail!("No trashed conversation with id {} in this project", id);
         }
 
-        Ok(())
+        let conversation: ConversationHistory =
+            serde_json::from_str(&fs::read_to_string(&trashed)?)?;
+        if self.load_conversation(id).is_ok() {
+            anyhow::bail!(
+                "Conversation {} already exists in {}",
+                id,
+                self.project_dir.display()
+            );
+        }
+
+        self.save_conversation(&conversation)?;
+        fs::remove_file(&trashed)?;
+        Ok(conversation)
     }
 
-    /// Get the conversations directory path
-    pub fn conversations_dir(&self) -> &Path {
-        &self.conversations_dir
+    /// List unpinned conversations not updated in the last `days` days
+    pub fn conversations_older_than(&self, days: i64) -> Result<Vec<ConversationHistory>> {
+        let cutoff = Local::now() - chrono::Duration::days(days);
+        Ok(self
+            .list_conversations()?
+            .into_iter()
+            .filter(|c| !c.pinned && c.updated_at < cutoff)
+            .collect())
+    }
+
+    /// Get a project's trash directory (~/.local/share/mermaid/trash/<project hash>)
+    pub fn trash_dir(project_dir: &Path) -> Result<PathBuf> {
+        let home = std::env::var("HOME")?;
+        let trash_dir = PathBuf::from(home)
+            .join(".local")
+            .join("share")
+            .join("mermaid")
+            .join("trash")
+            .join(super::index::project_key(project_dir));
+        Ok(trash_dir)
+    }
+
+    /// Where conversations are stored, for display
+    pub fn storage_location(&self) -> String {
+        self.storage.location()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_deduplicate_system_messages() {
+        let message = |role, content: &str| ChatMessage {
+            role,
+            content: content.to_string(),
+            timestamp: Local::now(),
+        };
+        let mut conversation = ConversationHistory::new("/tmp".to_string(), "test".to_string());
+        conversation.add_messages(&[
+            message(MessageRole::System, "Read a.rs"),
+            message(MessageRole::System, "Read a.rs"),
+            message(MessageRole::System, "Read b.rs"),
+            message(MessageRole::User, "hi"),
+            message(MessageRole::System, "Read a.rs"),
+            message(MessageRole::Assistant, "hello"),
+        ]);
+        conversation.deduplicate_system_messages();
+
+        let contents: Vec<&str> = conversation
+            .messages
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(
+            contents,
+            vec!["Read a.rs\n\nRead b.rs", "hi", "Read a.rs", "hello"]
+        );
+    }
+
+    #[test]
+    fn test_search_conversations() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager =
+            ConversationManager::with_index(temp_dir.path(), temp_dir.path().join("index.bin"))
+                .unwrap();
+
+        let mut conversation = ConversationHistory::new(
+            temp_dir.path().to_string_lossy().to_string(),
+            "ollama/test".to_string(),
+        );
+        conversation.add_messages(&[
+            ChatMessage {
+                role: MessageRole::User,
+                content: "How do I configure the LiteLLM proxy?".to_string(),
+                timestamp: Local::now(),
+            },
+            ChatMessage {
+                role: MessageRole::Assistant,
+                content: "Edit litellm_config.yaml and restart.".to_string(),
+                timestamp: Local::now(),
+            },
+        ]);
+        manager.save_conversation(&conversation).unwrap();
+
+        let matches = manager.search_conversations("litellm").unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].conversation_id, conversation.id);
+        assert_eq!(matches[0].matching_message_index, 0);
+        assert!(matches[0].snippet.contains("LiteLLM"));
+
+        let matches = manager.search_conversations("restart").unwrap();
+        assert_eq!(matches[0].matching_message_index, 1);
+
+        assert!(manager
+            .search_conversations("kubernetes")
+            .unwrap()
+            .is_empty());
+
+        // Later saves and deletes are picked up by the next search
+        conversation.add_messages(&[ChatMessage {
+            role: MessageRole::User,
+            content: "What about kubernetes?".to_string(),
+            timestamp: Local::now(),
+        }]);
+        manager.save_conversation(&conversation).unwrap();
+        assert_eq!(manager.search_conversations("kubernetes").unwrap().len(), 1);
+        fs::remove_file(
+            temp_dir
+                .path()
+                .join(".mermaid/conversations")
+                .join(format!("{}.json", conversation.id)),
+        )
+        .unwrap();
+        assert!(manager.search_conversations("litellm").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_snippet_offset_inside_a_character() {
+        // Byte 1 is inside the two-byte "é"
+        assert_eq!(build_snippet("émoji", 1, 2), "émoji");
+    }
+
+    #[test]
+    fn test_trash_is_per_project() {
+        let project = TempDir::new().unwrap();
+        let other = TempDir::new().unwrap();
+        assert_ne!(
+            ConversationManager::trash_dir(project.path()).unwrap(),
+            ConversationManager::trash_dir(other.path()).unwrap()
+        );
+
+        // Keep the trash out of the real data directory
+        let trash_root = TempDir::new().unwrap();
+        let manager_for = |dir: &Path| {
+            let trash = trash_root
+                .path()
+                .join(super::super::index::project_key(dir));
+            let storage = FileSystemStorage::open(dir, dir.join("index.bin"), trash.clone());
+            let manager =
+                ConversationManager::with_storage(dir.to_path_buf(), Box::new(storage.unwrap()));
+            (manager, trash)
+        };
+        let (manager, trash) = manager_for(project.path());
+        let (other_manager, other_trash) = manager_for(other.path());
+
+        let conversation = ConversationHistory::new(
+            project.path().to_string_lossy().into_owned(),
+            "test".to_string(),
+        );
+        manager.save_conversation(&conversation).unwrap();
+        manager.delete_conversation(&conversation.id).unwrap();
+        assert!(trash.join(format!("{}.json", conversation.id)).exists());
+
+        assert!(other_manager
+            .restore_from_trash(&other_trash, &conversation.id)
+            .is_err());
+        manager
+            .restore_from_trash(&trash, &conversation.id)
+            .unwrap();
+        assert!(manager.load_conversation(&conversation.id).is_ok());
+
+        // IDs can't climb out of the conversations directory or the trash
+        fs::write(project.path().join("outside.json"), "{}").unwrap();
+        assert!(manager.delete_conversation("../../outside").is_err());
+        assert!(manager.load_conversation("../x").is_err());
+        assert!(manager.restore_from_trash(&trash, "../x").is_err());
+        assert!(project.path().join("outside.json").exists());
+    }
+
+    #[test]
+    fn test_storage_backends() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = ConversationManager::with_storage(
+            temp_dir.path().to_path_buf(),
+            Box::new(
+                FileSystemStorage::new(temp_dir.path(), temp_dir.path().join("index.bin")).unwrap(),
+            ),
+        );
+        assert!(manager.storage_location().ends_with("conversations"));
+        assert!(manager.load_last_conversation().unwrap().is_none());
+
+        assert!(ConversationManager::with_backend(temp_dir.path(), "s3").is_err());
+        assert_eq!(
+            ConversationManager::with_backend(temp_dir.path(), "sqlite").is_ok(),
+            cfg!(feature = "sqlite-storage")
+        );
+    }
+
+    #[test]
+    fn test_merge_conversations() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager =
+            ConversationManager::with_index(temp_dir.path(), temp_dir.path().join("index.bin"))
+                .unwrap();
+        let base = Local::now();
+        let message = |content: &str, offset: i64| ChatMessage {
+            role: MessageRole::User,
+            content: content.to_string(),
+            timestamp: base + chrono::Duration::seconds(offset),
+        };
+
+        let mut first = ConversationHistory::new("/tmp".to_string(), "ollama/test".to_string());
+        first.id = "first".to_string();
+        first.add_messages(&[message("one", 0), message("three", 2)]);
+        manager.save_conversation(&first).unwrap();
+
+        let mut second = ConversationHistory::new("/tmp".to_string(), "ollama/test".to_string());
+        second.id = "second".to_string();
+        second.add_messages(&[message("two", 1), message("four", 3)]);
+        manager.save_conversation(&second).unwrap();
+
+        let merged = manager.merge_conversations("first", "second").unwrap();
+        let contents: Vec<&str> = merged.messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["one", "two", "three", "four"]);
+        assert_eq!(merged.title, "Merge of: one + two");
+        assert_eq!(merged.merged_from, vec!["first", "second"]);
+
+        // Originals are preserved alongside the merged conversation
+        assert!(manager.load_conversation("first").is_ok());
+        assert!(manager.load_conversation("second").is_ok());
+        assert!(manager.load_conversation(&merged.id).is_ok());
+
+        // A second merge in the same second gets its own ID
+        let again = manager.merge_conversations("first", "second").unwrap();
+        assert_ne!(again.id, merged.id);
+        assert!(manager.load_conversation(&merged.id).is_ok());
     }
 }
@@ -0,0 +1,220 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::ConversationHistory;
+
+/// Suffixes removed by the stemmer, longest first
+const SUFFIXES: &[&str] = &[
+    "ations", "ation", "ings", "ing", "ies", "es", "ed", "ly", "s",
+];
+
+/// Stems never get shorter than this
+const MIN_STEM_CHARS: usize = 3;
+
+/// Posting lists plus the conversations they were built from
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SearchIndex {
+    /// Normalized word -> (conversation ID, message index) of every occurrence
+    words: HashMap<String, Vec<(String, usize)>>,
+    /// Version (file modification time) of each indexed conversation, including
+    /// ones without any words
+    conversations: HashMap<String, u64>,
+}
+
+/// Inverted index over one project's saved conversations
+pub struct IndexManager {
+    path: PathBuf,
+    index: SearchIndex,
+}
+
+impl IndexManager {
+    /// Get the default index location of a project
+    /// (~/.local/share/mermaid/index/<project hash>.bin)
+    pub fn default_path(project_dir: &Path) -> Result<PathBuf> {
+        let home = std::env::var("HOME")?;
+        Ok(PathBuf::from(home)
+            .join(".local")
+            .join("share")
+            .join("mermaid")
+            .join("index")
+            .join(format!("{}.bin", project_key(project_dir))))
+    }
+
+    /// Load the index at `path`, starting empty if it doesn't exist or can't be read
+    pub fn open(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let index = fs::read(&path)
+            .ok()
+            .and_then(|bytes| bincode::deserialize(&bytes).ok())
+            .unwrap_or_default();
+        Self { path, index }
+    }
+
+    /// Write the index back to disk
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Write then rename so a concurrent reader never sees half an index
+        let tmp = self.path.with_extension("bin.tmp");
+        fs::write(&tmp, bincode::serialize(&self.index)?)?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to write index {}", self.path.display()))
+    }
+
+    /// Index every message of a conversation at `version`, replacing what was
+    /// indexed for it before
+    pub fn index_conversation(&mut self, conversation: &ConversationHistory, version: u64) {
+        self.remove_conversation(&conversation.id);
+        for (index, message) in conversation.messages.iter().enumerate() {
+            let words: BTreeSet<String> = tokenize(&message.content).collect();
+            for word in words {
+                self.index
+                    .words
+                    .entry(word)
+                    .or_default()
+                    .push((conversation.id.clone(), index));
+            }
+        }
+        self.index
+            .conversations
+            .insert(conversation.id.clone(), version);
+    }
+
+    /// Drop every posting of a conversation
+    pub fn remove_conversation(&mut self, id: &str) {
+        if self.index.conversations.remove(id).is_none() {
+            return;
+        }
+        self.index.words.retain(|_, postings| {
+            postings.retain(|(conversation_id, _)| conversation_id != id);
+            !postings.is_empty()
+        });
+    }
+
+    /// Forget everything, e.g. before a rebuild
+    pub fn clear(&mut self) {
+        self.index = SearchIndex::default();
+    }
+
+    /// Whether a conversation has been indexed at `version`
+    pub fn is_current(&self, id: &str, version: u64) -> bool {
+        self.index.conversations.get(id) == Some(&version)
+    }
+
+    /// IDs of the indexed conversations
+    pub fn indexed_ids(&self) -> Vec<String> {
+        self.index.conversations.keys().cloned().collect()
+    }
+
+    /// Messages containing every word of the query, sorted by conversation and index
+    pub fn lookup(&self, query: &str) -> Vec<(String, usize)> {
+        let mut hits: Option<BTreeSet<(String, usize)>> = None;
+        for word in tokenize(query) {
+            let postings: BTreeSet<(String, usize)> = self
+                .index
+                .words
+                .get(&word)
+                .map(|postings| postings.iter().cloned().collect())
+                .unwrap_or_default();
+            hits = Some(match hits {
+                Some(hits) => hits.intersection(&postings).cloned().collect(),
+                None => postings,
+            });
+        }
+        hits.map(|hits| hits.into_iter().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Short stable name for a project directory
+pub(super) fn project_key(project_dir: &Path) -> String {
+    let dir = project_dir
+        .canonicalize()
+        .unwrap_or_else(|_| project_dir.to_path_buf());
+    let digest = Sha256::digest(dir.to_string_lossy().as_bytes());
+    digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Split text into normalized words
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(normalize)
+}
+
+/// Lowercase a word and strip a common English suffix
+pub fn normalize(word: &str) -> String {
+    let word = word.to_lowercase();
+    for suffix in SUFFIXES {
+        if let Some(stem) = word.strip_suffix(suffix) {
+            if stem.chars().count() >= MIN_STEM_CHARS {
+                return stem.to_string();
+            }
+        }
+    }
+    word
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ChatMessage, MessageRole};
+    use chrono::Local;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_index_lookup_and_persistence() {
+        assert_eq!(normalize("Running"), "runn");
+        assert_eq!(normalize("configured"), "configur");
+        assert_eq!(normalize("is"), "is");
+        assert_eq!(normalize("configures"), normalize("configured"));
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("index.bin");
+        let mut conversation = ConversationHistory::new("/tmp".to_string(), "test".to_string());
+        conversation.id = "conv".to_string();
+        conversation.add_messages(&[
+            ChatMessage {
+                role: MessageRole::User,
+                content: "Which tests are failing?".to_string(),
+                timestamp: Local::now(),
+            },
+            ChatMessage {
+                role: MessageRole::Assistant,
+                content: "The parser test failed on nested blocks.".to_string(),
+                timestamp: Local::now(),
+            },
+        ]);
+
+        let mut index = IndexManager::open(&path);
+        index.index_conversation(&conversation, 1);
+        index.save().unwrap();
+
+        let mut index = IndexManager::open(&path);
+        assert!(index.is_current("conv", 1));
+        assert!(!index.is_current("conv", 2));
+        assert_eq!(
+            index.lookup("test"),
+            vec![("conv".to_string(), 0), ("conv".to_string(), 1)]
+        );
+        assert_eq!(index.lookup("PARSER tests"), vec![("conv".to_string(), 1)]);
+        assert!(index.lookup("parser kubernetes").is_empty());
+        assert!(index.lookup("").is_empty());
+
+        index.remove_conversation("conv");
+        assert!(index.indexed_ids().is_empty());
+
+        // Each project gets its own index
+        let other = TempDir::new().unwrap();
+        assert_ne!(
+            IndexManager::default_path(dir.path()).unwrap(),
+            IndexManager::default_path(other.path()).unwrap()
+        );
+        assert!(index.lookup("test").is_empty());
+    }
+}
@@ -1,8 +1,19 @@
 /// Session management module - Gateway
 mod conversation;
+mod index;
 mod selector;
+#[cfg(feature = "sqlite-storage")]
+mod sqlite;
 mod state;
+mod usage;
 
-pub use conversation::{ConversationHistory, ConversationManager};
+pub use conversation::{
+    merge_system_messages, ConversationHistory, ConversationManager, ConversationMatch,
+    ConversationStorage, FileSystemStorage,
+};
+pub use index::IndexManager;
 pub use selector::select_conversation;
+#[cfg(feature = "sqlite-storage")]
+pub use sqlite::SqliteStorage;
 pub use state::SessionState;
+pub use usage::{BudgetStatus, UsageLog, UsageReport};
@@ -0,0 +1,164 @@
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::conversation::{check_id, sorted_matches, ConversationStorage};
+use super::index::tokenize;
+use super::{ConversationHistory, ConversationManager, ConversationMatch};
+
+/// Conversations of a project in `.mermaid/conversations.db`, one JSON row each
+pub struct SqliteStorage {
+    path: PathBuf,
+    connection: Mutex<Connection>,
+    trash_dir: PathBuf,
+}
+
+impl SqliteStorage {
+    /// Open (or create) the project's conversation database
+    pub fn new(project_dir: &Path) -> Result<Self> {
+        let dir = project_dir.join(".mermaid");
+        fs::create_dir_all(&dir)?;
+        Self::open(
+            dir.join("conversations.db"),
+            ConversationManager::trash_dir(project_dir)?,
+        )
+    }
+
+    /// Open (or create) a conversation database at `path`, trashing deleted
+    /// conversations into `trash_dir`
+    pub fn open(path: PathBuf, trash_dir: PathBuf) -> Result<Self> {
+        let connection = Connection::open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS conversations (
+                id TEXT PRIMARY KEY,
+                updated_at TEXT NOT NULL,
+                data TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(Self {
+            path,
+            connection: Mutex::new(connection),
+            trash_dir,
+        })
+    }
+}
+
+impl ConversationStorage for SqliteStorage {
+    fn save(&self, conversation: &ConversationHistory) -> Result<()> {
+        self.connection.lock().execute(
+            "INSERT OR REPLACE INTO conversations (id, updated_at, data) VALUES (?1, ?2, ?3)",
+            params![
+                conversation.id,
+                conversation.updated_at.to_rfc3339(),
+                serde_json::to_string(conversation)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn load(&self, id: &str) -> Result<ConversationHistory> {
+        let data: Option<String> = self
+            .connection
+            .lock()
+            .query_row(
+                "SELECT data FROM conversations WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match data {
+            Some(data) => Ok(serde_json::from_str(&data)?),
+            None => anyhow::bail!("Conversation not found: {}", id),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<ConversationHistory>> {
+        let connection = self.connection.lock();
+        let mut statement = connection.prepare("SELECT data FROM conversations")?;
+        let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+        let mut conversations: Vec<ConversationHistory> = rows
+            .flatten()
+            .filter_map(|data| serde_json::from_str(&data).ok())
+            .collect();
+
+        // Newest first
+        conversations.sort_by_key(|conversation| std::cmp::Reverse(conversation.updated_at));
+        Ok(conversations)
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        check_id(id)?;
+        let conversation = self.load(id)?;
+        fs::create_dir_all(&self.trash_dir)?;
+        let trashed = self.trash_dir.join(format!("{}.json", id));
+        fs::write(trashed, serde_json::to_string_pretty(&conversation)?)?;
+        self.connection
+            .lock()
+            .execute("DELETE FROM conversations WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Scans the messages directly; a project's conversations are few enough
+    fn search(&self, query: &str) -> Result<Vec<ConversationMatch>> {
+        let words: Vec<String> = tokenize(query).collect();
+        if words.is_empty() {
+            return Ok(Vec::new());
+        }
+        let found = self
+            .list()?
+            .into_iter()
+            .filter_map(|conversation| {
+                let index = conversation.messages.iter().position(|message| {
+                    let message_words: Vec<String> = tokenize(&message.content).collect();
+                    words.iter().all(|word| message_words.contains(word))
+                })?;
+                Some((conversation, index))
+            })
+            .collect();
+        Ok(sorted_matches(found, query))
+    }
+
+    fn location(&self) -> String {
+        self.path.to_string_lossy().into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ChatMessage, MessageRole};
+    use chrono::Local;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sqlite_storage() {
+        let dir = TempDir::new().unwrap();
+        let storage = SqliteStorage::open(
+            dir.path().join("conversations.db"),
+            dir.path().join("trash"),
+        )
+        .unwrap();
+
+        let mut conversation = ConversationHistory::new("/tmp".to_string(), "test".to_string());
+        conversation.id = "conv".to_string();
+        conversation.add_messages(&[ChatMessage {
+            role: MessageRole::User,
+            content: "Why are the parser tests failing?".to_string(),
+            timestamp: Local::now(),
+        }]);
+        storage.save(&conversation).unwrap();
+        storage.save(&conversation).unwrap();
+
+        assert_eq!(storage.list().unwrap().len(), 1);
+        assert_eq!(storage.load("conv").unwrap().title, conversation.title);
+        let matches = storage.search("parser test").unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].matching_message_index, 0);
+        assert!(storage.search("kubernetes").unwrap().is_empty());
+        assert!(storage.load("missing").is_err());
+    }
+}
@@ -2,21 +2,30 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
+use std::time::SystemTime;
 
 /// Session state that persists between runs
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionState {
     pub last_used_model: Option<String>,
-    pub last_project_path: Option<String>,
-    pub operation_mode: Option<String>,
+    /// Project directory of the last run, resumed by a bare `mermaid` with `mode.remember_mode`
+    #[serde(default, alias = "last_project_path")]
+    pub last_working_dir: Option<PathBuf>,
+    /// Operation mode when it last changed, restored with `mode.remember_mode`
+    #[serde(default, alias = "operation_mode")]
+    pub last_mode: Option<String>,
+    /// When Mermaid last started, used to prewarm the cache for files changed since
+    #[serde(default)]
+    pub last_run: Option<SystemTime>,
 }
 
 impl Default for SessionState {
     fn default() -> Self {
         Self {
             last_used_model: None,
-            last_project_path: None,
-            operation_mode: None,
+            last_working_dir: None,
+            last_mode: None,
+            last_run: None,
         }
     }
 }
@@ -59,3 +68,21 @@ impl SessionState {
         self.last_used_model.as_deref()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_older_session_keys() {
+        let state: SessionState = toml::from_str(
+            "last_used_model = \"ollama/qwen\"\n\
+             last_project_path = \"/work/app\"\n\
+             operation_mode = \"accept_edits\"\n",
+        )
+        .unwrap();
+        assert_eq!(state.last_working_dir, Some(PathBuf::from("/work/app")));
+        assert_eq!(state.last_mode.as_deref(), Some("accept_edits"));
+        assert!(toml::to_string(&state).unwrap().contains("last_mode"));
+    }
+}
@@ -0,0 +1,224 @@
+use anyhow::Result;
+use chrono::{Datelike, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::app::BudgetLimits;
+use crate::diagnostics::estimate_cost;
+
+/// Token usage for a single model on a single day
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageLog {
+    pub date: NaiveDate,
+    pub model: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl UsageLog {
+    /// Create a usage entry for today
+    pub fn new(model: impl Into<String>, prompt_tokens: u64, completion_tokens: u64) -> Self {
+        Self {
+            date: Local::now().date_naive(),
+            model: model.into(),
+            prompt_tokens,
+            completion_tokens,
+        }
+    }
+
+    /// Total tokens in this entry
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    /// Estimated cost of this entry in USD
+    pub fn cost(&self) -> f64 {
+        estimate_cost(&self.model, self.prompt_tokens, self.completion_tokens)
+    }
+
+    /// Append this entry to the usage log as a JSON line
+    pub fn append(&self) -> Result<()> {
+        let path = UsageReport::usage_file()?;
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Reader for the persisted usage log
+pub struct UsageReport;
+
+impl UsageReport {
+    /// Get the path to the usage log (~/.local/share/mermaid/usage.json)
+    fn usage_file() -> Result<PathBuf> {
+        let home = std::env::var("HOME")?;
+        let data_dir = PathBuf::from(home)
+            .join(".local")
+            .join("share")
+            .join("mermaid");
+        fs::create_dir_all(&data_dir)?;
+        Ok(data_dir.join("usage.json"))
+    }
+
+    /// Load all usage entries, skipping malformed lines
+    pub fn load() -> Vec<UsageLog> {
+        let Ok(path) = Self::usage_file() else {
+            return Vec::new();
+        };
+        let Ok(content) = fs::read_to_string(path) else {
+            return Vec::new();
+        };
+
+        content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+
+    /// Merge entries into daily per-model counters covering the last `days` days
+    pub fn summarize(entries: &[UsageLog], days: i64) -> Vec<UsageLog> {
+        let cutoff = Local::now().date_naive() - chrono::Duration::days(days.max(1) - 1);
+        let mut daily: Vec<UsageLog> = Vec::new();
+
+        for entry in entries.iter().filter(|e| e.date >= cutoff) {
+            match daily
+                .iter_mut()
+                .find(|d| d.date == entry.date && d.model == entry.model)
+            {
+                Some(existing) => {
+                    existing.prompt_tokens += entry.prompt_tokens;
+                    existing.completion_tokens += entry.completion_tokens;
+                },
+                None => daily.push(entry.clone()),
+            }
+        }
+
+        daily.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| a.model.cmp(&b.model)));
+        daily
+    }
+
+    /// Today's per-model counters
+    pub fn today() -> Vec<UsageLog> {
+        Self::summarize(&Self::load(), 1)
+    }
+
+    /// Spend in USD today and in the current calendar month
+    pub fn spend(entries: &[UsageLog]) -> (f64, f64) {
+        let today = Local::now().date_naive();
+        let month_start = today.with_day(1).unwrap_or(today);
+        entries
+            .iter()
+            .filter(|e| e.date >= month_start)
+            .fold((0.0, 0.0), |(day, month), e| {
+                let cost = e.cost();
+                let day = if e.date == today { day + cost } else { day };
+                (day, month + cost)
+            })
+    }
+
+    /// Compare recorded spend plus the next request's estimated cost with the limits
+    pub fn check_budget(
+        limits: &BudgetLimits,
+        entries: &[UsageLog],
+        next_cost: f64,
+    ) -> BudgetStatus {
+        let (today, month) = Self::spend(entries);
+        let budgets = [
+            ("daily", limits.daily_usd, today),
+            ("monthly", limits.monthly_usd, month),
+        ];
+
+        let mut warning = None;
+        for (period, limit, spent) in budgets {
+            let Some(limit) = limit else {
+                continue;
+            };
+            let projected = spent + next_cost;
+            if spent >= limit {
+                return BudgetStatus::Exceeded(format!(
+                    "{} budget of ${:.2} reached (${:.2} spent)",
+                    period, limit, spent
+                ));
+            } else if projected > limit {
+                warning.get_or_insert(format!(
+                    "This request may exceed the {} budget (${:.2} of ${:.2} spent)",
+                    period, spent, limit
+                ));
+            } else if projected >= limit * f64::from(limits.warn_at_percent) / 100.0 {
+                warning.get_or_insert(format!(
+                    "{:.0}% of the {} budget used (${:.2} of ${:.2})",
+                    projected / limit * 100.0,
+                    period,
+                    projected,
+                    limit
+                ));
+            }
+        }
+        warning.map_or(BudgetStatus::WithinBudget, BudgetStatus::Warning)
+    }
+}
+
+/// Where spending stands against the configured budget
+#[derive(Debug, Clone, PartialEq)]
+pub enum BudgetStatus {
+    WithinBudget,
+    /// Close to a limit, or the next request would pass one
+    Warning(String),
+    /// A limit has been reached; further requests are blocked
+    Exceeded(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_merges_same_day_and_model() {
+        let today = Local::now().date_naive();
+        let old = today - chrono::Duration::days(30);
+        let entries = vec![
+            UsageLog::new("ollama/qwen", 100, 20),
+            UsageLog::new("ollama/qwen", 50, 10),
+            UsageLog::new("openai/gpt-4o", 10, 5),
+            UsageLog {
+                date: old,
+                model: "ollama/qwen".to_string(),
+                prompt_tokens: 999,
+                completion_tokens: 999,
+            },
+        ];
+
+        let summary = UsageReport::summarize(&entries, 7);
+        assert_eq!(summary.len(), 2);
+
+        let qwen = summary.iter().find(|u| u.model == "ollama/qwen").unwrap();
+        assert_eq!(qwen.prompt_tokens, 150);
+        assert_eq!(qwen.completion_tokens, 30);
+        assert_eq!(qwen.total_tokens(), 180);
+    }
+
+    #[test]
+    fn test_check_budget() {
+        // 100k prompt tokens of gpt-4o at $0.0025 per 1k = $0.25
+        let entries = vec![UsageLog::new("openai/gpt-4o", 100_000, 0)];
+        let limits = |daily| BudgetLimits {
+            daily_usd: Some(daily),
+            ..BudgetLimits::default()
+        };
+
+        assert_eq!(
+            UsageReport::check_budget(&limits(1.0), &entries, 0.01),
+            BudgetStatus::WithinBudget
+        );
+        assert!(matches!(
+            UsageReport::check_budget(&limits(0.3), &entries, 0.01),
+            BudgetStatus::Warning(_)
+        ));
+        assert!(matches!(
+            UsageReport::check_budget(&limits(0.25), &entries, 0.0),
+            BudgetStatus::Exceeded(_)
+        ));
+    }
+}
@@ -1,10 +1,32 @@
+use super::markdown::extract_code_blocks;
 use super::mode::OperationMode;
-use crate::agents::{AgentAction, ModeAwareExecutor};
-use crate::diagnostics::{DiagnosticsMode, HardwareMonitor, HardwareStats};
-use crate::models::{ChatMessage, MessageRole, Model, ProjectContext};
-use crate::session::{ConversationHistory, ConversationManager};
+use super::render::{chat_inner_width, LayoutAreas};
+use super::widgets::SidebarState;
+use crate::agents::{
+    builtin_tools, has_conflict_markers, head_branch, list_stashes, resolve_cd, sanitize_for_model,
+    AgentAction, ModeAwareExecutor, StashEntry,
+};
+use crate::app::{substitute_prompt_variables, Config};
+use crate::context::{is_env_file, RepoMap};
+use crate::diagnostics::{
+    estimate_cost, DailyUsage, DiagnosticsMode, HardwareMonitor, HardwareStats, ModelInfo,
+};
+use crate::models::{
+    network_stats, parse_tool_call, ChatMessage, FileContent, LazyProjectContext, MessageRole,
+    Model, ModelConfig, ProjectContext,
+};
+use crate::ollama::PullProgress;
+use crate::proxy::ProxyWatchdog;
+use crate::session::{
+    merge_system_messages, BudgetStatus, ConversationHistory, ConversationManager, UsageReport,
+};
+use crate::utils::{count_file_tokens, Tokenizer};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 /// Application state
 pub struct App {
@@ -12,7 +34,7 @@ pub struct App {
     pub messages: Vec<ChatMessage>,
     /// User input buffer
     pub input: String,
-    /// Cursor position in the input string
+    /// Cursor position in the input string, as a byte index on a grapheme boundary
     pub cursor_position: usize,
     /// Is the app running?
     pub running: bool,
@@ -36,6 +58,8 @@ pub struct App {
     pub working_dir: String,
     /// Model name for display
     pub model_name: String,
+    /// Token counter for the current model, replaced along with `model_name`
+    tokenizer: Tokenizer,
     /// Status message
     pub status_message: Option<String>,
     /// Current operation mode (Normal, AcceptEdits, PlanMode, BypassAll)
@@ -46,14 +70,24 @@ pub struct App {
     pub pending_action: Option<AgentAction>,
     /// Executor for pending action
     pub pending_executor: Option<ModeAwareExecutor>,
+    /// Snapshot taken before the last batch of actions, restored by :rollback
+    pub last_snapshot_id: Option<String>,
+    /// Environment variables set this session, passed to every command
+    pub session_env: HashMap<String, String>,
+    /// Description and start time of the action currently executing
+    pub running_action: Option<(String, std::time::Instant)>,
     /// Track if FILE_READ feedback is pending
     pub pending_file_read: bool,
+    /// Follow-up turns sent with function results since the user's last message
+    pub tool_rounds: usize,
     /// Status text to show during file reading
     pub reading_file_status: Option<String>,
     /// Current confirmation state
     pub confirmation_state: Option<ConfirmationState>,
     /// Track if user is manually scrolling (not at bottom)
     pub is_user_scrolling: bool,
+    /// Terminal (width, height), updated on resize
+    pub terminal_size: (u16, u16),
     /// Track last time status was set for timeout
     pub status_timestamp: Option<std::time::Instant>,
     /// Abort handle for canceling generation
@@ -68,26 +102,78 @@ pub struct App {
     pub hardware_stats: Option<HardwareStats>,
     /// Diagnostics display mode
     pub diagnostics_mode: DiagnosticsMode,
+    /// Token usage accumulated today
+    pub usage_today: DailyUsage,
+    /// Loaded application configuration
+    pub config: Config,
+    /// Repository map, kept up to date from file watcher events once built
+    pub repo_map: Option<Arc<Mutex<RepoMap>>>,
+    /// PageRank importance per context file path (relative to the working directory)
+    pub file_importance: HashMap<String, f64>,
+    /// Context being reloaded in the background after the working directory changed
+    pub context_reload: Option<tokio::task::JoinHandle<anyhow::Result<ProjectContext>>>,
+    /// Project files still being loaded in the background
+    pub lazy_context: Option<LazyProjectContext>,
+    /// Background proxy health checker
+    pub proxy_watchdog: Option<ProxyWatchdog>,
+    /// Latest progress of a model download started with :pull
+    pub pull_progress: Option<PullProgress>,
+    /// System prompt that replaces the configured default for this conversation
+    pub custom_system_prompt: Option<String>,
+    /// Parameter preset applied to model requests, chosen with :preset load
+    pub active_preset: Option<String>,
+    /// Estimated USD cost of the last message sent to a paid model
+    pub cost_estimate: Option<f64>,
+    /// Keep sending after a budget limit is reached (--override-budget)
+    pub override_budget: bool,
+    /// Directories from `--root` loaded alongside the project, kept for refreshes
+    pub extra_roots: Vec<std::path::PathBuf>,
+    /// Checked-out git branch, shown in the header
+    pub current_branch: Option<String>,
+    /// Project-relative paths of files containing merge conflict markers
+    pub conflicted_files: Vec<String>,
+    /// Stashes shown in the sidebar when `ui.git_status` is on
+    pub stashes: Vec<StashEntry>,
+    /// Horizontal scroll offset applied to code blocks in the chat
+    pub code_block_scroll_x: u16,
+    /// Frames drawn so far, for animating the generation spinner
+    pub spinner_tick: u64,
+    /// Collapse state of code blocks, keyed by (message index, block index)
+    pub collapsed_blocks: HashMap<(usize, usize), bool>,
+    /// What became of each assistant message's actions, keyed by message index
+    pub action_outcomes: HashMap<usize, Vec<(String, ActionOutcome)>>,
+    /// Selected file in the sidebar
+    pub sidebar_state: SidebarState,
+    /// Widget areas from the last frame, shared with the mouse handler
+    pub layout_areas: Arc<std::sync::Mutex<LayoutAreas>>,
+    /// Sidebar file index and time of the last click (for double-click detection)
+    pub last_sidebar_click: Option<(usize, std::time::Instant)>,
+    /// Code block highlighted for copying, as (message index, block index)
+    pub focused_code_block: Option<(usize, usize)>,
+    /// Changes found by the last refresh that changed anything, shown by :context-diff
+    pub last_context_diff: Option<ContextDiff>,
+    /// Whether the chat already says a redacted `.env` is in the context
+    pub env_file_noted: bool,
 }
 
 impl App {
     /// Create a new app instance
     pub fn new(model: Box<dyn Model>, context: ProjectContext) -> Self {
         let model_name = model.name().to_string();
-        let working_dir = std::env::current_dir()
-            .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or_else(|_| ".".to_string());
+        let working_dir = project_dir(&context);
 
-        // Initialize conversation manager for the current directory
+        // Initialize conversation manager for the project directory
         let conversation_manager = ConversationManager::new(&working_dir).ok();
         let current_conversation = conversation_manager
             .as_ref()
             .map(|_| ConversationHistory::new(working_dir.clone(), model_name.clone()));
 
         // Initialize hardware monitor
-        let hardware_monitor = Some(Arc::new(Mutex::new(HardwareMonitor::new())));
+        let mut monitor = HardwareMonitor::new();
+        monitor.set_network_stats(network_stats());
+        let hardware_monitor = Some(Arc::new(Mutex::new(monitor)));
 
-        Self {
+        let mut app = Self {
             messages: Vec::new(),
             input: String::new(),
             cursor_position: 0,
@@ -101,16 +187,22 @@ impl App {
             show_sidebar: true,
             sidebar_expanded: false,
             working_dir,
+            tokenizer: Tokenizer::new(&model_name),
             model_name,
             status_message: None,
             operation_mode: OperationMode::default(), // Starts in Normal mode
             bypass_confirmed: false,
             pending_action: None,
             pending_executor: None,
+            last_snapshot_id: None,
+            session_env: HashMap::new(),
+            running_action: None,
             pending_file_read: false,
+            tool_rounds: 0,
             reading_file_status: None,
             confirmation_state: None,
             is_user_scrolling: false,
+            terminal_size: (0, 0),
             status_timestamp: None,
             generation_abort: None,
             conversation_manager,
@@ -118,7 +210,199 @@ impl App {
             hardware_monitor,
             hardware_stats: None,
             diagnostics_mode: DiagnosticsMode::Compact,
+            usage_today: DailyUsage::default(),
+            config: Config::default(),
+            repo_map: None,
+            file_importance: HashMap::new(),
+            context_reload: None,
+            lazy_context: None,
+            proxy_watchdog: None,
+            pull_progress: None,
+            custom_system_prompt: None,
+            active_preset: None,
+            cost_estimate: None,
+            override_budget: false,
+            extra_roots: Vec::new(),
+            current_branch: head_branch().ok(),
+            conflicted_files: Vec::new(),
+            stashes: Vec::new(),
+            code_block_scroll_x: 0,
+            spinner_tick: 0,
+            collapsed_blocks: HashMap::new(),
+            action_outcomes: HashMap::new(),
+            sidebar_state: SidebarState::new(),
+            layout_areas: Arc::new(std::sync::Mutex::new(LayoutAreas::default())),
+            last_sidebar_click: None,
+            focused_code_block: None,
+            last_context_diff: None,
+            env_file_noted: false,
+        };
+        app.refresh_usage_today();
+        app
+    }
+
+    /// Use the given configuration instead of the defaults
+    pub fn with_config(mut self, config: Config) -> Self {
+        if self.conversation_manager.is_some() && config.storage_backend != "filesystem" {
+            match ConversationManager::with_backend(&self.working_dir, &config.storage_backend) {
+                Ok(manager) => self.conversation_manager = Some(manager),
+                Err(e) => {
+                    self.set_status(format!("[WARNING] {}; saving conversations as files", e))
+                },
+            }
+        }
+        self.config = config;
+        self
+    }
+
+    /// Pick up files from a context that is loading in the background
+    pub fn with_lazy_context(mut self, lazy_context: LazyProjectContext) -> Self {
+        self.lazy_context = Some(lazy_context);
+        self
+    }
+
+    /// Report proxy health from the given watchdog
+    pub fn with_proxy_watchdog(mut self, watchdog: ProxyWatchdog) -> Self {
+        self.proxy_watchdog = Some(watchdog);
+        self
+    }
+
+    /// Copy files the background loader has read so far into the context
+    pub async fn sync_lazy_context(&mut self) {
+        let Some(ref lazy) = self.lazy_context else {
+            return;
+        };
+
+        let files = lazy.files.read().await;
+        for (path, content) in files.iter() {
+            if !self.context.files.contains_key(path) {
+                self.context.token_count += count_file_tokens(content.text(), &self.model_name);
+                self.context.add_file(path.clone(), content.clone());
+            }
+        }
+        drop(files);
+
+        if lazy.is_fully_loaded() {
+            let stripped = lazy.stripped_comment_tokens();
+            self.lazy_context = None;
+            if stripped > 0 {
+                self.set_status(format!("Stripping comments saved ~{} tokens", stripped));
+            }
+        }
+        self.note_env_file();
+    }
+
+    /// Remember a variable the model set for this session, so later commands get it
+    pub fn apply_session_env(&mut self, action: &AgentAction) {
+        if let Ok(AgentAction::SetEnvironmentVariable {
+            key,
+            value,
+            persist: false,
+        }) = action.resolve()
+        {
+            self.session_env.insert(key, value);
+        }
+    }
+
+    /// The absolute project directory, which `cd` can't leave
+    pub fn project_root(&self) -> PathBuf {
+        PathBuf::from(project_dir(&self.context))
+    }
+
+    /// Follow a standalone `cd` the model ran, so later commands and context loads use it
+    /// Returns whether the working directory changed
+    pub fn apply_directory_change(&mut self, action: &AgentAction) -> bool {
+        let Ok(AgentAction::ExecuteCommand {
+            command,
+            working_dir,
+        }) = action.resolve()
+        else {
+            return false;
+        };
+        let base = working_dir.unwrap_or_else(|| self.working_dir.clone());
+        match resolve_cd(
+            &command,
+            Path::new(&base),
+            Path::new(&self.context.root_path),
+        ) {
+            Some(dir) => {
+                let dir = dir.to_string_lossy().to_string();
+                let changed = dir != self.working_dir;
+                self.working_dir = dir;
+                changed
+            },
+            None => false,
+        }
+    }
+
+    /// Record how an action of the last assistant message ended, and send a function
+    /// call's result back to the model as a tool message
+    /// Results answer the last assistant message's calls in order, so the call ID is
+    /// `call_<assistant message index>_<result number>`
+    pub fn add_function_result(
+        &mut self,
+        action: &AgentAction,
+        outcome: ActionOutcome,
+        result: &str,
+    ) {
+        let assistant = self
+            .messages
+            .iter()
+            .rposition(|m| m.role == MessageRole::Assistant)
+            .unwrap_or(0);
+        if !matches!(action, AgentAction::CallFunction { .. }) {
+            self.action_outcomes
+                .entry(assistant)
+                .or_default()
+                .push((action_key(action), outcome));
+            return;
         }
+        let answered = self.messages[assistant..]
+            .iter()
+            .filter(|m| m.role.is_tool())
+            .count();
+        let tool_call_id = format!("call_{}_{}", assistant, answered);
+        self.add_message(MessageRole::Tool { tool_call_id }, result.to_string());
+    }
+
+    /// Why a write can't go ahead: the model only saw the file with its comments
+    /// stripped, so its version would delete them
+    pub fn stripped_write_refusal(&self, action: &AgentAction) -> Option<String> {
+        let Ok(AgentAction::WriteFile { path, .. }) = action.resolve() else {
+            return None;
+        };
+        let stripped = matches!(
+            self.context.files.get(path.trim_start_matches("./")),
+            Some(FileContent::CommentsStripped(_))
+        );
+        stripped.then(|| {
+            format!(
+                "Refused to write {}: it was loaded with its comments stripped, so the write \
+                 would delete them. Read it with [FILE_READ: {}] first.",
+                path, path
+            )
+        })
+    }
+
+    /// Swap a stripped file's context copy for the full text once the model has read it
+    pub fn note_file_read(&mut self, action: &AgentAction, contents: &str) {
+        let Ok(AgentAction::ReadFile { path }) = action.resolve() else {
+            return;
+        };
+        let key = path.trim_start_matches("./");
+        let Some(FileContent::CommentsStripped(stripped)) = self.context.files.get(key) else {
+            return;
+        };
+        let previous = count_file_tokens(stripped, &self.model_name);
+        self.context.token_count = self.context.token_count.saturating_sub(previous)
+            + count_file_tokens(contents, &self.model_name);
+        self.context
+            .add_file(key.to_string(), FileContent::Raw(contents.to_string()));
+    }
+
+    /// Whether the last response made function calls and every one has a result
+    pub fn function_calls_answered(&self) -> bool {
+        function_calls_answered(&self.messages)
     }
 
     /// Add a message to the chat
@@ -146,11 +430,177 @@ impl App {
         self.cursor_position = 0;
     }
 
+    /// Insert a character at the cursor and move past it
+    pub fn input_insert_char(&mut self, c: char) {
+        self.input.insert(self.cursor_position, c);
+        self.cursor_position += c.len_utf8();
+    }
+
+    /// Delete the grapheme before the cursor (Backspace)
+    pub fn input_delete_before_cursor(&mut self) {
+        let start = prev_grapheme_boundary(&self.input, self.cursor_position);
+        self.input.drain(start..self.cursor_position);
+        self.cursor_position = start;
+    }
+
+    /// Delete the grapheme under the cursor (Delete)
+    pub fn input_delete_at_cursor(&mut self) {
+        let end = next_grapheme_boundary(&self.input, self.cursor_position);
+        self.input.drain(self.cursor_position..end);
+    }
+
+    /// Move the cursor one grapheme left
+    pub fn input_cursor_left(&mut self) {
+        self.cursor_position = prev_grapheme_boundary(&self.input, self.cursor_position);
+    }
+
+    /// Move the cursor one grapheme right
+    pub fn input_cursor_right(&mut self) {
+        self.cursor_position = next_grapheme_boundary(&self.input, self.cursor_position);
+    }
+
     /// Toggle sidebar visibility
     pub fn toggle_sidebar(&mut self) {
         self.show_sidebar = !self.show_sidebar;
     }
 
+    /// Re-read the checked-out branch, returning it if it changed
+    pub fn refresh_branch(&mut self) -> Option<String> {
+        let branch = head_branch().ok();
+        if branch == self.current_branch {
+            return None;
+        }
+        self.current_branch = branch.clone();
+        branch
+    }
+
+    /// Re-scan changed files for conflict markers, forgetting deleted ones
+    pub fn update_conflicts(&mut self, changed: &[PathBuf], deleted: &[PathBuf]) {
+        let root = PathBuf::from(&self.working_dir);
+        let relative = |path: &PathBuf| {
+            path.strip_prefix(&root)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .trim_start_matches("./")
+                .to_string()
+        };
+        for path in deleted {
+            let path = relative(path);
+            self.conflicted_files.retain(|p| *p != path);
+        }
+        let in_git_dir = |path: &PathBuf| path.components().any(|c| c.as_os_str() == ".git");
+        for path in changed.iter().filter(|path| !in_git_dir(path)) {
+            let Ok(content) = std::fs::read(path) else {
+                continue;
+            };
+            let path = relative(path);
+            let conflicted = has_conflict_markers(&content);
+            let known = self.conflicted_files.contains(&path);
+            if conflicted && !known {
+                self.conflicted_files.push(path);
+            } else if !conflicted && known {
+                self.conflicted_files.retain(|p| *p != path);
+            }
+        }
+        self.conflicted_files.sort();
+    }
+
+    /// Files added, removed and modified between two loads of the context
+    pub fn compute_context_diff(old: &ProjectContext, new: &ProjectContext) -> ContextDiff {
+        let mut diff = ContextDiff::default();
+        for (path, content) in &new.files {
+            match old.files.get(path) {
+                None => diff.added.push(path.clone()),
+                Some(previous) if previous != content => diff.modified.push(path.clone()),
+                Some(_) => {},
+            }
+        }
+        diff.removed = old
+            .files
+            .keys()
+            .filter(|path| !new.files.contains_key(*path))
+            .cloned()
+            .collect();
+        diff.added.sort();
+        diff.removed.sort();
+        diff.modified.sort();
+        diff
+    }
+
+    /// Files added, removed and modified by an in-place update of `context`, given the
+    /// entries the update replaced (None for paths it added)
+    pub fn compute_update_diff(
+        previous: &[(String, Option<FileContent>)],
+        context: &ProjectContext,
+    ) -> ContextDiff {
+        let mut diff = ContextDiff::default();
+        let mut seen = std::collections::HashSet::new();
+        // A path touched twice in one batch is compared from its first, oldest entry
+        for (path, before) in previous.iter().filter(|(path, _)| seen.insert(path)) {
+            match (before, context.files.get(path)) {
+                (None, Some(_)) => diff.added.push(path.clone()),
+                (Some(_), None) => diff.removed.push(path.clone()),
+                (Some(before), Some(now)) if before != now => diff.modified.push(path.clone()),
+                _ => {},
+            }
+        }
+        diff.added.sort();
+        diff.removed.sort();
+        diff.modified.sort();
+        diff
+    }
+
+    /// Swap in a reloaded context and note what changed in the chat
+    pub fn apply_refreshed_context(&mut self, new_context: ProjectContext) -> ContextDiff {
+        let diff = Self::compute_context_diff(&self.context, &new_context);
+        // Update the context while preserving conversation history
+        self.context.files = new_context.files;
+        self.context.token_count = new_context.token_count;
+        self.report_context_diff(diff)
+    }
+
+    /// Note what an in-place update of the context changed in the chat
+    pub fn apply_context_updates(
+        &mut self,
+        previous: &[(String, Option<FileContent>)],
+    ) -> ContextDiff {
+        let diff = Self::compute_update_diff(previous, &self.context);
+        self.report_context_diff(diff)
+    }
+
+    /// Show a non-empty diff in the chat and keep it for :context-diff
+    fn report_context_diff(&mut self, diff: ContextDiff) -> ContextDiff {
+        if !diff.is_empty() {
+            self.add_message(
+                MessageRole::System,
+                format!("{} (:context-diff for details)", diff.summary()),
+            );
+            self.last_context_diff = Some(diff.clone());
+        }
+        self.note_env_file();
+        diff
+    }
+
+    /// Say once that a `.env` file is in the context, with its secrets redacted
+    pub fn note_env_file(&mut self) {
+        if !self.env_file_noted && self.context.files.keys().any(|p| is_env_file(Path::new(p))) {
+            self.env_file_noted = true;
+            self.add_message(
+                MessageRole::System,
+                "NOTE: .env included with sensitive values redacted".to_string(),
+            );
+        }
+    }
+
+    /// Reload the sidebar's stash list (empty outside a git repository)
+    pub fn refresh_stashes(&mut self) {
+        self.stashes = if self.config.ui.git_status {
+            list_stashes().unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+    }
+
     /// Set status message
     pub fn set_status(&mut self, message: impl Into<String>) {
         self.status_message = Some(message.into());
@@ -162,14 +612,17 @@ impl App {
     }
 
     /// Calculate the maximum scroll offset (bottom of content)
-    pub fn calculate_max_scroll(&self, viewport_height: u16) -> u16 {
-        let mut total_lines = 0u16;
+    /// Lines wider than the chat pane (`chat_width`, inside its borders) count once per
+    /// row they wrap to
+    pub fn calculate_max_scroll(&self, viewport_height: u16, chat_width: u16) -> u16 {
+        let width = chat_width as usize;
+        let mut total_lines = 0usize;
 
         for msg in &self.messages {
             // Role line: [You] or [Mermaid]
             total_lines += 1;
             // Content lines (can be many for code blocks)
-            total_lines += msg.content.lines().count() as u16;
+            total_lines += rendered_rows(&msg.content, width);
             // Assistant messages have completion indicator (3 lines)
             if matches!(msg.role, MessageRole::Assistant) {
                 total_lines += 3;
@@ -179,28 +632,115 @@ impl App {
         }
 
         // Add lines for current response if generating
-        if self.is_generating && !self.current_response.is_empty() {
+        if self.is_generating {
             total_lines += 1; // Role line
-            total_lines += self.current_response.lines().count() as u16;
-            total_lines += 1; // Typing indicator
+            total_lines += rendered_rows(&self.current_response, width);
+            total_lines += 1; // Spinner
         }
 
         // Max scroll is total lines minus viewport height
+        let total_lines = total_lines.min(u16::MAX as usize) as u16;
         total_lines.saturating_sub(viewport_height)
     }
 
+    /// Width inside the chat pane's borders, next to the sidebar when it's shown
+    pub fn chat_width(&self) -> u16 {
+        chat_inner_width(self.terminal_size.0, self.show_sidebar)
+    }
+
+    /// Height of the chat viewport: the terminal minus 3 header + 3 input + 1 status + 1 margin
+    pub fn viewport_height(&self) -> u16 {
+        self.terminal_size.1.saturating_sub(8)
+    }
+
+    /// Record a new terminal size and keep the scroll position valid for it
+    pub fn handle_resize(&mut self, width: u16, height: u16) {
+        self.terminal_size = (width, height);
+        let viewport_height = self.viewport_height();
+        if self.is_user_scrolling {
+            self.scroll_offset = self
+                .scroll_offset
+                .min(self.calculate_max_scroll(viewport_height, self.chat_width()));
+        } else {
+            self.auto_scroll_to_bottom(viewport_height);
+        }
+    }
+
     /// Auto-scroll to bottom of chat
     pub fn auto_scroll_to_bottom(&mut self, viewport_height: u16) {
         if !self.is_user_scrolling {
-            self.scroll_offset = self.calculate_max_scroll(viewport_height);
+            self.scroll_offset = self.calculate_max_scroll(viewport_height, self.chat_width());
+        }
+    }
+
+    /// Move the message selection by `delta`, expanding the newly focused message's code blocks
+    pub fn select_message(&mut self, delta: isize) {
+        if self.messages.is_empty() {
+            return;
         }
+        let last = self.messages.len() - 1;
+        let selected = match self.selected_message {
+            Some(i) => i.saturating_add_signed(delta).min(last),
+            None if delta < 0 => last,
+            None => 0,
+        };
+        self.selected_message = Some(selected);
+        self.collapsed_blocks.retain(|(msg, _), _| *msg != selected);
+        self.focused_code_block = (!extract_code_blocks(&self.messages[selected].content)
+            .is_empty())
+        .then_some((selected, 0));
+    }
+
+    /// Content of the focused code block, or of the most recent one if none is focused
+    pub fn code_block_to_copy(&self) -> Option<String> {
+        if let Some((msg, block)) = self.focused_code_block {
+            return self
+                .messages
+                .get(msg)
+                .and_then(|m| extract_code_blocks(&m.content).into_iter().nth(block));
+        }
+        self.messages
+            .iter()
+            .rev()
+            .filter(|m| m.role == MessageRole::Assistant)
+            .find_map(|m| extract_code_blocks(&m.content).pop())
+    }
+
+    /// Toggle the first code block of the selected message
+    pub fn toggle_selected_code_block(&mut self) {
+        let Some(msg) = self.selected_message.filter(|_| self.selected_has_code()) else {
+            return;
+        };
+        let collapsed = self.collapsed_blocks.entry((msg, 0)).or_insert(false);
+        *collapsed = !*collapsed;
+    }
+
+    /// Whether the selected message has a code block to collapse
+    pub fn selected_has_code(&self) -> bool {
+        self.selected_message
+            .and_then(|msg| self.messages.get(msg))
+            .is_some_and(|m| m.content.contains("```"))
+    }
+
+    /// Indices of the collapsed code blocks in a message
+    pub fn collapsed_blocks_in(&self, msg: usize) -> Vec<usize> {
+        self.collapsed_blocks
+            .iter()
+            .filter(|((m, _), collapsed)| *m == msg && **collapsed)
+            .map(|((_, block), _)| *block)
+            .collect()
+    }
+
+    /// Scroll code blocks horizontally by `delta` columns
+    pub fn scroll_code_blocks(&mut self, delta: i16) {
+        self.code_block_scroll_x = self.code_block_scroll_x.saturating_add_signed(delta);
     }
 
     /// Scroll chat view up
     pub fn scroll_up(&mut self, amount: u16) {
         // Calculate max scroll: total lines minus viewport height
         let viewport_height = 20; // This should be passed in, but keeping for compatibility
-        let max_scroll = self.calculate_max_scroll(viewport_height);
+        let max_scroll = self.calculate_max_scroll(viewport_height, self.chat_width());
 
         self.scroll_offset = self.scroll_offset.saturating_add(amount).min(max_scroll);
 
@@ -217,7 +757,7 @@ impl App {
 
         // If user scrolls close to bottom, resume auto-scrolling
         let viewport_height = 20; // Should be passed in
-        let max_scroll = self.calculate_max_scroll(viewport_height);
+        let max_scroll = self.calculate_max_scroll(viewport_height, self.chat_width());
         let threshold = 3;
         if self.scroll_offset >= max_scroll.saturating_sub(threshold) {
             self.is_user_scrolling = false;
@@ -263,15 +803,167 @@ impl App {
     }
 
     /// Build message history for sending to the model
-    /// Includes only user and assistant messages (not system messages from the UI)
+    /// Includes user, assistant and tool messages (not system messages from the UI),
+    /// with the action blocks of assistant messages replaced by placeholders
     pub fn build_message_history(&self) -> Vec<ChatMessage> {
-        self.messages
-            .iter()
-            .filter(|msg| msg.role == MessageRole::User || msg.role == MessageRole::Assistant)
-            .cloned()
+        self.custom_system_message()
+            .into_iter()
+            .chain(
+                self.messages
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, msg)| {
+                        matches!(
+                            msg.role,
+                            MessageRole::User | MessageRole::Assistant | MessageRole::Tool { .. }
+                        )
+                    })
+                    .map(|(index, msg)| match msg.role {
+                        // Action blocks that already ran shouldn't be run again
+                        MessageRole::Assistant => ChatMessage {
+                            content: sanitize_for_model(&msg.content, |action| {
+                                self.action_outcome(index, action)
+                            }),
+                            ..msg.clone()
+                        },
+                        _ => msg.clone(),
+                    }),
+            )
             .collect()
     }
 
+    /// What became of an action of the message at `index`; unknown for messages
+    /// from a loaded conversation, "not run" for ones left behind a confirmation
+    fn action_outcome(&self, index: usize, action: &AgentAction) -> Option<&'static str> {
+        let outcomes = self.action_outcomes.get(&index)?;
+        let key = action_key(action);
+        Some(
+            outcomes
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map_or("not run", |(_, outcome)| outcome.label()),
+        )
+    }
+
+    /// The custom system prompt as a chat message, if one is set
+    fn custom_system_message(&self) -> Option<ChatMessage> {
+        self.custom_system_prompt
+            .as_ref()
+            .map(|prompt| ChatMessage {
+                role: MessageRole::System,
+                content: self.render_prompt(prompt),
+                timestamp: chrono::Local::now(),
+            })
+    }
+
+    /// Model configuration for a chat request, leaving out the default
+    /// system prompt when a custom one is part of the message history
+    pub fn model_config(&self) -> ModelConfig {
+        let mut config = ModelConfig::default();
+        if self.custom_system_prompt.is_some() {
+            config.system_prompt = None;
+        } else if let Some(prompt) = &self.config.default_model.system_prompt {
+            config.system_prompt = Some(self.render_prompt(prompt));
+        }
+        if let Some(preset) = self
+            .active_preset
+            .as_ref()
+            .and_then(|name| self.config.presets.get(name))
+        {
+            config.apply_preset(preset);
+        }
+        config.tools = Some(builtin_tools());
+        config
+    }
+
+    /// Built-in prompt variables merged with the ones from the config
+    pub fn prompt_variables(&self) -> HashMap<String, String> {
+        let mut vars = HashMap::from([
+            (
+                "PROJECT_TYPE".to_string(),
+                self.context
+                    .project_type
+                    .clone()
+                    .unwrap_or_else(|| "unknown".to_string()),
+            ),
+            ("WORKING_DIR".to_string(), self.working_dir.clone()),
+            ("MODEL_NAME".to_string(), self.model_name.clone()),
+            (
+                "DATE".to_string(),
+                chrono::Local::now().format("%Y-%m-%d").to_string(),
+            ),
+        ]);
+        vars.extend(self.config.prompt_variables.clone());
+        vars
+    }
+
+    /// Substitute prompt variables into a system prompt template
+    fn render_prompt(&self, template: &str) -> String {
+        substitute_prompt_variables(template, &self.prompt_variables())
+    }
+
+    /// The system prompt currently in effect
+    pub fn effective_system_prompt(&self) -> String {
+        self.custom_system_prompt
+            .clone()
+            .or_else(|| self.config.default_model.system_prompt.clone())
+            .unwrap_or_default()
+    }
+
+    /// Replace the system prompt for this conversation
+    pub fn set_custom_system_prompt(&mut self, prompt: Option<String>) {
+        if let Some(ref mut conv) = self.current_conversation {
+            conv.custom_system_prompt = prompt.clone();
+        }
+        self.custom_system_prompt = prompt;
+    }
+
+    /// Switch to another model's name and token counting
+    pub fn set_model_name(&mut self, model_name: String) {
+        self.tokenizer = Tokenizer::new(&model_name);
+        self.model_name = model_name;
+    }
+
+    /// Estimated USD prompt cost of sending `messages` with the system prompt and context
+    /// None for free (local) models or when the estimate is turned off
+    pub fn estimate_request_cost(&self, messages: &[ChatMessage]) -> Option<f64> {
+        if !self.config.ui.show_cost_estimate {
+            return None;
+        }
+        let mut outgoing: Vec<(String, String)> = messages
+            .iter()
+            .map(|msg| {
+                let role = match msg.role {
+                    MessageRole::User => "user",
+                    MessageRole::Assistant => "assistant",
+                    MessageRole::System => "system",
+                    MessageRole::Tool { .. } => "tool",
+                };
+                (role.to_string(), msg.content.clone())
+            })
+            .collect();
+        let system_prompt = self.model_config().system_prompt.unwrap_or_default();
+        outgoing.push((
+            "system".to_string(),
+            format!("{}\n{}", system_prompt, self.context.to_prompt_context()),
+        ));
+
+        let tokens = self
+            .tokenizer
+            .count_chat_tokens(&outgoing)
+            .unwrap_or_else(|_| outgoing.iter().map(|(_, c)| c.len() / 4).sum());
+        let cost = estimate_cost(&self.model_name, tokens as u64, 0);
+        (cost > 0.0).then_some(cost)
+    }
+
+    /// Check the configured budget, counting `next_cost` for the request about to be sent
+    pub fn budget_status(&self, next_cost: f64) -> BudgetStatus {
+        match &self.config.budget_limits {
+            Some(limits) => UsageReport::check_budget(limits, &UsageReport::load(), next_cost),
+            None => BudgetStatus::WithinBudget,
+        }
+    }
+
     /// Build message history with token management
     /// Ensures the conversation doesn't exceed the model's context window
     pub fn build_managed_message_history(
@@ -279,18 +971,25 @@ impl App {
         max_context_tokens: usize,
         reserve_tokens: usize,
     ) -> Vec<ChatMessage> {
-        use crate::utils::Tokenizer;
-
-        let tokenizer = Tokenizer::new(&self.model_name);
+        let tokenizer = &self.tokenizer;
         let available_tokens = max_context_tokens.saturating_sub(reserve_tokens);
 
-        // Get all relevant messages
-        let all_messages: Vec<ChatMessage> = self
-            .messages
+        // Get all relevant messages, with feedback runs merged and only the
+        // most recent system messages kept
+        let mut all_messages = self.messages.clone();
+        merge_system_messages(&mut all_messages);
+        let mut system_to_drop = all_messages
             .iter()
-            .filter(|msg| msg.role == MessageRole::User || msg.role == MessageRole::Assistant)
-            .cloned()
-            .collect();
+            .filter(|msg| msg.role == MessageRole::System)
+            .count()
+            .saturating_sub(self.config.max_system_messages_in_history);
+        all_messages.retain(|msg| {
+            if msg.role == MessageRole::System && system_to_drop > 0 {
+                system_to_drop -= 1;
+                return false;
+            }
+            true
+        });
 
         // If no messages, return empty
         if all_messages.is_empty() {
@@ -305,6 +1004,7 @@ impl App {
                     MessageRole::User => "user",
                     MessageRole::Assistant => "assistant",
                     MessageRole::System => "system",
+                    MessageRole::Tool { .. } => "tool",
                 };
                 (role.to_string(), msg.content.clone())
             })
@@ -334,6 +1034,7 @@ impl App {
                     MessageRole::User => "user",
                     MessageRole::Assistant => "assistant",
                     MessageRole::System => "system",
+                    MessageRole::Tool { .. } => "tool",
                 }
                 .to_string(),
                 msg.content.clone(),
@@ -364,6 +1065,11 @@ impl App {
     pub fn load_conversation(&mut self, conversation: ConversationHistory) {
         // Load messages from the conversation
         self.messages = conversation.messages.clone();
+        self.selected_message = None;
+        self.focused_code_block = None;
+        self.collapsed_blocks.clear();
+        self.action_outcomes.clear();
+        self.custom_system_prompt = conversation.custom_system_prompt.clone();
         self.current_conversation = Some(conversation);
         self.set_status("Conversation loaded");
     }
@@ -403,24 +1109,180 @@ impl App {
         self.set_status(format!("Diagnostics: {:?}", self.diagnostics_mode));
     }
 
-    /// Update hardware stats
-    pub fn update_hardware_stats(&mut self, stats: HardwareStats) {
+    /// Update hardware stats, describing the current model when the monitor doesn't
+    pub fn update_hardware_stats(&mut self, mut stats: HardwareStats) {
+        if stats.model_info.is_none() {
+            let context_length = self
+                .config
+                .default_model
+                .context_window_override
+                .unwrap_or_else(|| self.tokenizer.get_max_tokens());
+            stats.model_info = Some(ModelInfo {
+                name: self.model_name.clone(),
+                size_on_disk_gb: None,
+                loaded_memory_gb: None,
+                context_length,
+                context_used: self.context.token_count,
+            });
+        }
         self.hardware_stats = Some(stats);
     }
+
+    /// Check whether the current conversation should get a generated title
+    /// (only right after the first assistant response)
+    pub fn needs_auto_title(&self) -> bool {
+        self.config.mode.auto_title
+            && self
+                .current_conversation
+                .as_ref()
+                .is_some_and(|c| !c.custom_title)
+            && self
+                .messages
+                .iter()
+                .filter(|m| m.role == MessageRole::Assistant)
+                .count()
+                == 1
+    }
+
+    /// Set the title of the current conversation
+    pub fn set_conversation_title(&mut self, title: String) {
+        if let Some(ref mut conv) = self.current_conversation {
+            conv.set_title(title);
+        }
+    }
+
+    /// Store a generated summary on the current conversation
+    pub fn set_conversation_summary(&mut self, summary: String) {
+        if let Some(ref mut conv) = self.current_conversation {
+            conv.summary = Some(summary);
+        }
+    }
+
+    /// Replace file importance scores from a ranked repository map
+    pub fn set_file_importance(&mut self, ranked: Vec<(PathBuf, f64)>) {
+        let root = PathBuf::from(&self.working_dir);
+        self.file_importance = ranked
+            .into_iter()
+            .map(|(path, score)| {
+                let relative = path.strip_prefix(&root).unwrap_or(&path);
+                (relative.to_string_lossy().to_string(), score)
+            })
+            .collect();
+    }
+
+    /// Reload today's token usage from the usage log
+    pub fn refresh_usage_today(&mut self) {
+        self.usage_today =
+            UsageReport::today()
+                .iter()
+                .fold(DailyUsage::default(), |mut total, entry| {
+                    total.prompt_tokens += entry.prompt_tokens;
+                    total.completion_tokens += entry.completion_tokens;
+                    total.estimated_cost +=
+                        estimate_cost(&entry.model, entry.prompt_tokens, entry.completion_tokens);
+                    total
+                });
+    }
 }
 
 // AppState removed - we're always in "chat" mode now
 
+/// Start of the grapheme ending at byte index `idx`, or 0
+fn prev_grapheme_boundary(s: &str, idx: usize) -> usize {
+    s[..idx]
+        .grapheme_indices(true)
+        .next_back()
+        .map_or(0, |(start, _)| start)
+}
+
+/// End of the grapheme starting at byte index `idx`, or the end of the string
+fn next_grapheme_boundary(s: &str, idx: usize) -> usize {
+    s[idx..]
+        .graphemes(true)
+        .next()
+        .map_or(s.len(), |grapheme| idx + grapheme.len())
+}
+
+/// How an action from a response ended, named in its place in the history sent to the model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionOutcome {
+    Executed,
+    Failed,
+    /// The user said no at the confirmation
+    Declined,
+    /// Blocked before running, e.g. a write over a comment-stripped file
+    Refused,
+}
+
+impl ActionOutcome {
+    /// Word used in the history placeholder
+    pub fn label(self) -> &'static str {
+        match self {
+            ActionOutcome::Executed => "executed",
+            ActionOutcome::Failed => "failed",
+            ActionOutcome::Declined => "declined",
+            ActionOutcome::Refused => "refused",
+        }
+    }
+}
+
+/// Identifies an action among those of one response
+fn action_key(action: &AgentAction) -> String {
+    format!("{} {}", action.type_name(), action.target())
+}
+
+/// Files that changed between two loads of the project context
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContextDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+}
+
+impl ContextDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    /// Compact summary, e.g. `[Context updated: +2 files, ~3 modified, -1 removed]`
+    pub fn summary(&self) -> String {
+        format!(
+            "[Context updated: +{} files, ~{} modified, -{} removed]",
+            self.added.len(),
+            self.modified.len(),
+            self.removed.len()
+        )
+    }
+
+    /// Every changed path on its own line, marked with +, ~ or -
+    pub fn details(&self) -> String {
+        let mut lines = Vec::new();
+        lines.extend(self.added.iter().map(|path| format!("+ {}", path)));
+        lines.extend(self.modified.iter().map(|path| format!("~ {}", path)));
+        lines.extend(self.removed.iter().map(|path| format!("- {}", path)));
+        lines.join("\n")
+    }
+}
+
 /// State for action confirmation
 #[derive(Debug, Clone)]
 pub struct ConfirmationState {
-    pub action: AgentAction,
+    pub kind: ConfirmationKind,
     pub action_description: String,
     pub preview_lines: Vec<String>,  // First few lines for preview
     pub file_info: Option<FileInfo>, // Size, path, overwrite status
     pub allow_always: bool,          // Can user select "always approve"?
 }
 
+/// What the user is asked to confirm
+#[derive(Debug, Clone)]
+pub enum ConfirmationKind {
+    /// An agent action, run by the pending executor
+    Action(AgentAction),
+    /// Conversations to move to the trash
+    TrashConversations(Vec<String>),
+}
+
 #[derive(Debug, Clone)]
 pub struct FileInfo {
     pub path: String,
@@ -428,3 +1290,179 @@ pub struct FileInfo {
     pub exists: bool,
     pub language: Option<String>,
 }
+
+/// Rows the text takes in the chat at `width` columns; an empty line still takes one
+/// Code block lines scroll sideways instead of wrapping, so they always take one
+fn rendered_rows(text: &str, width: usize) -> usize {
+    let mut in_code = false;
+    text.lines()
+        .map(|line| {
+            if line.trim_start().starts_with("```") {
+                in_code = !in_code;
+                1
+            } else if in_code {
+                1
+            } else {
+                wrapped_rows(line, width.max(1))
+            }
+        })
+        .sum()
+}
+
+/// Rows a line takes word-wrapped at `width` display columns, as the chat paragraph
+/// wraps it: words move to the next row whole unless they're wider than a row
+fn wrapped_rows(line: &str, width: usize) -> usize {
+    let mut rows = 1;
+    let mut column = 0;
+    for word in line.split_inclusive(' ') {
+        let text = word.trim_end_matches(' ');
+        let text_width = text.width();
+        if column > 0 && column + text_width > width {
+            rows += 1;
+            column = 0;
+        }
+        column += text_width;
+        while column > width {
+            rows += 1;
+            column -= width;
+        }
+        column = (column + word.len() - text.len()).min(width);
+    }
+    rows
+}
+
+/// Whether the last assistant message has function calls, all answered by the tool
+/// messages after it
+fn function_calls_answered(messages: &[ChatMessage]) -> bool {
+    let Some(assistant) = messages
+        .iter()
+        .rposition(|m| m.role == MessageRole::Assistant)
+    else {
+        return false;
+    };
+    let calls = messages[assistant]
+        .content
+        .lines()
+        .filter(|line| parse_tool_call(line).is_some())
+        .count();
+    let results = messages[assistant + 1..]
+        .iter()
+        .filter(|m| m.role.is_tool())
+        .count();
+    calls > 0 && results >= calls
+}
+
+/// The absolute project directory the context was loaded from, which may not be
+/// the process's current directory (e.g. a resumed `last_working_dir`)
+fn project_dir(context: &ProjectContext) -> String {
+    std::fs::canonicalize(&context.root_path)
+        .or_else(|_| std::env::current_dir())
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| ".".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_dir_follows_the_context_root() {
+        let project = tempfile::TempDir::new().unwrap();
+        let root = project.path().canonicalize().unwrap();
+        assert_ne!(Some(&root), std::env::current_dir().ok().as_ref());
+
+        let context = ProjectContext::new(project.path().to_string_lossy().to_string());
+        assert_eq!(PathBuf::from(project_dir(&context)), root);
+    }
+
+    #[test]
+    fn test_function_calls_answered() {
+        let message = |role, content: &str| ChatMessage {
+            role,
+            content: content.to_string(),
+            timestamp: chrono::Local::now(),
+        };
+        let calls = "Checking\n[TOOL_CALL: read_file]:{\"path\":\"a\"}\n[TOOL_CALL: git_status]:{}";
+        let mut messages = vec![
+            message(MessageRole::User, "hi"),
+            message(MessageRole::Assistant, calls),
+            message(MessageRole::tool(), "contents"),
+        ];
+        assert!(!function_calls_answered(&messages));
+        messages.push(message(MessageRole::tool(), "clean"));
+        assert!(function_calls_answered(&messages));
+
+        messages.push(message(MessageRole::Assistant, "Done"));
+        assert!(!function_calls_answered(&messages));
+    }
+
+    #[test]
+    fn test_rendered_rows_counts_wrapped_lines() {
+        assert_eq!(rendered_rows("", 76), 0);
+        assert_eq!(rendered_rows("short\n\nline", 76), 3);
+        assert_eq!(rendered_rows(&"x".repeat(76), 76), 1);
+        assert_eq!(rendered_rows(&"x".repeat(77), 76), 2);
+        assert_eq!(rendered_rows(&format!("{}\nend", "x".repeat(200)), 76), 4);
+        assert_eq!(rendered_rows("abc", 0), 3);
+
+        // Words wrap whole, wide characters take two columns, code lines never wrap
+        assert_eq!(rendered_rows("aaaa bbbb cccc", 10), 2);
+        assert_eq!(rendered_rows(&"漢".repeat(6), 10), 2);
+        assert_eq!(
+            rendered_rows(&format!("```\n{}\n```", "x".repeat(200)), 76),
+            3
+        );
+    }
+
+    #[test]
+    fn test_grapheme_boundaries() {
+        // "e" + combining acute, a CJK character and a skin-toned emoji
+        let input = "ae\u{301}中👋🏽!";
+        let crossed: Vec<usize> = std::iter::successors(Some(0), |&idx| {
+            (idx < input.len()).then(|| next_grapheme_boundary(input, idx))
+        })
+        .collect();
+        assert_eq!(crossed, vec![0, 1, 4, 7, 15, 16]);
+
+        assert_eq!(prev_grapheme_boundary(input, 15), 7);
+        assert_eq!(prev_grapheme_boundary(input, 4), 1);
+        assert_eq!(prev_grapheme_boundary(input, 0), 0);
+        assert_eq!(next_grapheme_boundary(input, input.len()), input.len());
+    }
+
+    #[test]
+    fn test_compute_context_diff() {
+        let context = |files: &[(&str, &str)]| {
+            let mut context = ProjectContext::new(".".to_string());
+            for (path, content) in files {
+                context.add_file(path.to_string(), FileContent::Raw(content.to_string()));
+            }
+            context
+        };
+        let old = context(&[("a.rs", "a"), ("b.rs", "b"), ("c.rs", "c")]);
+        let new = context(&[("a.rs", "a"), ("b.rs", "b2"), ("d.rs", "d"), ("e.rs", "e")]);
+
+        let diff = App::compute_context_diff(&old, &new);
+        assert_eq!(diff.added, vec!["d.rs", "e.rs"]);
+        assert_eq!(diff.modified, vec!["b.rs"]);
+        assert_eq!(diff.removed, vec!["c.rs"]);
+        assert_eq!(
+            diff.summary(),
+            "[Context updated: +2 files, ~1 modified, -1 removed]"
+        );
+        assert_eq!(diff.details(), "+ d.rs\n+ e.rs\n~ b.rs\n- c.rs");
+        assert!(App::compute_context_diff(&new, &new).is_empty());
+
+        // An in-place update compares each path with the entry it replaced
+        let previous = vec![
+            ("b.rs".to_string(), Some(FileContent::Raw("b".to_string()))),
+            ("c.rs".to_string(), Some(FileContent::Raw("c".to_string()))),
+            ("d.rs".to_string(), None),
+            ("d.rs".to_string(), Some(FileContent::Raw("d0".to_string()))),
+        ];
+        let diff = App::compute_update_diff(&previous, &new);
+        assert_eq!(diff.added, vec!["d.rs"]);
+        assert_eq!(diff.modified, vec!["b.rs"]);
+        assert_eq!(diff.removed, vec!["c.rs"]);
+    }
+}
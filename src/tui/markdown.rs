@@ -1,9 +1,121 @@
 use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-/// Parse markdown and convert to styled ratatui Lines
-pub fn parse_markdown(input: &str) -> Vec<Line<'static>> {
+/// Width of the `{:>4} │ ` line number gutter
+const LINE_NUMBER_GUTTER_WIDTH: usize = 7;
+
+/// Left border drawn beside the lines of the focused code block
+const FOCUS_BORDER: &str = "▎";
+
+/// How code block lines are laid out in the chat view
+#[derive(Debug, Clone, Default)]
+pub struct CodeView {
+    /// Columns scrolled off the left edge
+    pub scroll_x: u16,
+    /// Visible columns; code lines are clipped instead of wrapped when set
+    pub width: Option<usize>,
+    /// Color of the line number gutter, shown when set
+    pub line_numbers: Option<Color>,
+    /// Indices of the code blocks (within one message) shown as a summary line
+    pub collapsed: Vec<usize>,
+    /// Index of the code block (within one message) highlighted for copying
+    pub focused: Option<usize>,
+}
+
+impl CodeView {
+    /// Clip a code line to the visible horizontal window, measured in display columns
+    pub fn clip(&self, line: &str, focused: bool) -> String {
+        let visible = line.chars().skip(self.scroll_x as usize);
+        let gutter = self.gutter_width() + usize::from(focused) * FOCUS_BORDER.width();
+        let Some(width) = self.width.map(|w| w.saturating_sub(gutter)) else {
+            return visible.collect();
+        };
+        let mut used = 0;
+        visible
+            .take_while(|c| {
+                used += c.width().unwrap_or(0);
+                used <= width
+            })
+            .collect()
+    }
+
+    /// Columns taken by the line number gutter
+    fn gutter_width(&self) -> usize {
+        if self.line_numbers.is_some() {
+            LINE_NUMBER_GUTTER_WIDTH
+        } else {
+            0
+        }
+    }
+
+    /// Render one code line (numbered from 1 within its block)
+    pub fn code_line(
+        &self,
+        line_num: usize,
+        line: &str,
+        style: Style,
+        focused: bool,
+    ) -> Line<'static> {
+        let mut spans = Vec::new();
+        if focused {
+            spans.push(Span::styled(
+                FOCUS_BORDER,
+                Style::default().fg(Color::White),
+            ));
+        }
+        if let Some(color) = self.line_numbers {
+            spans.push(Span::styled(
+                format!("{:>4} │ ", line_num),
+                Style::default().fg(color),
+            ));
+        }
+        spans.push(Span::styled(self.clip(line, focused), style));
+        Line::from(spans)
+    }
+
+    /// Style of the ``` fences, lighter for the focused block
+    fn fence_style(&self, block: usize) -> Style {
+        if self.focused == Some(block) {
+            Style::default().fg(Color::White)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        }
+    }
+
+    /// Span showing the horizontal scroll offset, if scrolled
+    pub fn indicator(&self) -> Option<Span<'static>> {
+        (self.scroll_x > 0).then(|| {
+            Span::styled(
+                format!("  ←{}→", self.scroll_x),
+                Style::default().fg(Color::DarkGray),
+            )
+        })
+    }
+}
+
+/// Contents of the fenced and indented code blocks in a markdown text, in order
+pub fn extract_code_blocks(input: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Option<String> = None;
+    for event in Parser::new_ext(input, Options::empty()) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => current = Some(String::new()),
+            Event::Text(text) => {
+                if let Some(block) = current.as_mut() {
+                    block.push_str(&text);
+                }
+            },
+            Event::End(TagEnd::CodeBlock) => blocks.extend(current.take()),
+            _ => {},
+        }
+    }
+    blocks
+}
+
+/// Parse markdown and convert to styled ratatui Lines, laying out code blocks per `code_view`
+pub fn parse_markdown(input: &str, code_view: &CodeView) -> Vec<Line<'static>> {
     let mut options = Options::empty();
     options.insert(Options::ENABLE_STRIKETHROUGH);
     options.insert(Options::ENABLE_TABLES);
@@ -14,6 +126,8 @@ pub fn parse_markdown(input: &str) -> Vec<Line<'static>> {
     let mut style_stack = vec![Style::default()];
     let mut in_code_block = false;
     let mut code_block_content = String::new();
+    let mut code_block_lang = String::new();
+    let mut code_block_index = 0;
     let mut list_depth: usize = 0;
 
     for event in parser {
@@ -76,16 +190,16 @@ pub fn parse_markdown(input: &str) -> Vec<Line<'static>> {
                             CodeBlockKind::Fenced(lang) => lang.to_string(),
                             CodeBlockKind::Indented => "".to_string(),
                         };
-                        if !lang.is_empty() {
-                            lines.push(Line::from(vec![
-                                Span::styled("```", Style::default().fg(Color::DarkGray)),
-                                Span::styled(lang, Style::default().fg(Color::Magenta)),
-                            ]));
-                        } else {
-                            lines.push(Line::from(vec![Span::styled(
-                                "```",
-                                Style::default().fg(Color::DarkGray),
-                            )]));
+                        code_block_lang = lang.clone();
+                        // Collapsed blocks are replaced by a summary line at the end
+                        if !code_view.collapsed.contains(&code_block_index) {
+                            let mut fence =
+                                vec![Span::styled("```", code_view.fence_style(code_block_index))];
+                            if !lang.is_empty() {
+                                fence.push(Span::styled(lang, Style::default().fg(Color::Magenta)));
+                            }
+                            fence.extend(code_view.indicator());
+                            lines.push(Line::from(fence));
                         }
                         Style::default().fg(Color::Gray)
                     },
@@ -138,17 +252,37 @@ pub fn parse_markdown(input: &str) -> Vec<Line<'static>> {
                     },
                     TagEnd::CodeBlock => {
                         in_code_block = false;
+                        let collapsed = code_view.collapsed.contains(&code_block_index);
+                        let focused = code_view.focused == Some(code_block_index);
+                        let fence_style = code_view.fence_style(code_block_index);
+                        code_block_index += 1;
+                        if collapsed {
+                            let lang = if code_block_lang.is_empty() {
+                                "code"
+                            } else {
+                                &code_block_lang
+                            };
+                            lines.push(Line::from(Span::styled(
+                                format!(
+                                    "  [+] {} block ({} lines)",
+                                    lang,
+                                    code_block_content.lines().count()
+                                ),
+                                Style::default().fg(Color::Gray),
+                            )));
+                            code_block_content.clear();
+                            continue;
+                        }
                         // Render code block content
-                        for line in code_block_content.lines() {
-                            lines.push(Line::from(vec![Span::styled(
-                                line.to_string(),
+                        for (i, line) in code_block_content.lines().enumerate() {
+                            lines.push(code_view.code_line(
+                                i + 1,
+                                line,
                                 Style::default().fg(Color::Gray),
-                            )]));
+                                focused,
+                            ));
                         }
-                        lines.push(Line::from(vec![Span::styled(
-                            "```",
-                            Style::default().fg(Color::DarkGray),
-                        )]));
+                        lines.push(Line::from(vec![Span::styled("```", fence_style)]));
                         code_block_content.clear();
                     },
                     TagEnd::List(_) => {
@@ -198,3 +332,77 @@ pub fn parse_markdown(input: &str) -> Vec<Line<'static>> {
 
     lines
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_lines_are_clipped_not_wrapped() {
+        let view = CodeView {
+            scroll_x: 3,
+            width: Some(6),
+            ..Default::default()
+        };
+        let lines = parse_markdown("```rust\nfn main() { println!(); }\n```\n", &view);
+
+        let text: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        assert_eq!(text, vec!["```rust  ←3→", "main()", "```"]);
+    }
+
+    #[test]
+    fn test_clip_counts_display_columns() {
+        let view = CodeView {
+            width: Some(6),
+            ..Default::default()
+        };
+        // The border takes one column and each CJK character two
+        assert_eq!(view.clip("日本語です", true), "日本");
+        assert_eq!(view.clip("日本語です", false), "日本語");
+    }
+
+    #[test]
+    fn test_line_numbers_restart_per_block() {
+        let view = CodeView {
+            line_numbers: Some(Color::DarkGray),
+            ..Default::default()
+        };
+        let lines = parse_markdown("```\na\nb\n```\n\n```\nc\n```\n", &view);
+
+        let text: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        assert_eq!(
+            text,
+            vec![
+                "```",
+                "   1 │ a",
+                "   2 │ b",
+                "```",
+                "```",
+                "   1 │ c",
+                "```"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collapsed_block_is_summarized() {
+        let view = CodeView {
+            collapsed: vec![1],
+            ..Default::default()
+        };
+        let input = "```\na\n```\n\n```python\nx = 1\ny = 2\n```\n\nafter";
+        let lines = parse_markdown(input, &view);
+
+        let text: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        assert_eq!(
+            text,
+            vec!["```", "a", "```", "  [+] python block (2 lines)", "after"]
+        );
+    }
+
+    #[test]
+    fn test_extract_code_blocks() {
+        let input = "intro\n```rust\nfn a() {}\n```\ntext `inline`\n```\nb\nc\n```\n";
+        assert_eq!(extract_code_blocks(input), vec!["fn a() {}\n", "b\nc\n"]);
+    }
+}
@@ -11,6 +11,6 @@ mod ui;
 mod widgets;
 
 // Public re-exports - the ONLY way to access TUI functionality
-pub use app::{App, ConfirmationState, FileInfo};
+pub use app::{ActionOutcome, App, ConfirmationKind, ConfirmationState, ContextDiff, FileInfo};
 pub use mode::OperationMode;
 pub use ui::run_ui;
@@ -137,6 +137,12 @@ impl OperationMode {
         }
     }
 
+    /// Whether the mode may be saved and restored next session
+    /// Bypass All needs `allow_bypass`, since it would auto-accept everything on startup
+    pub fn persistable(&self, allow_bypass: bool) -> bool {
+        *self != Self::BypassAll || allow_bypass
+    }
+
     /// Get the warning level for this mode
     pub fn warning_level(&self) -> WarningLevel {
         match self {
@@ -225,6 +231,10 @@ mod tests {
         assert!(OperationMode::BypassAll.auto_accept_files());
         assert!(OperationMode::BypassAll.auto_accept_commands());
         assert!(OperationMode::BypassAll.auto_accept_git());
+
+        assert!(OperationMode::PlanMode.persistable(false));
+        assert!(!OperationMode::BypassAll.persistable(false));
+        assert!(OperationMode::BypassAll.persistable(true));
     }
 
     #[test]
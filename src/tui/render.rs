@@ -1,17 +1,58 @@
+use chrono::{DateTime, Local};
 use once_cell::sync::Lazy;
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Position, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
+use std::fmt::Write;
+use std::sync::atomic::Ordering;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
+use crate::agents::STDERR_MARKER;
 use crate::diagnostics::{render_diagnostics_panel, DiagnosticsMode};
-use crate::models::MessageRole;
+use crate::models::{parse_tool_call, ChatMessage, MessageRole};
+use crate::proxy::ProxyStatus;
 use crate::tui::app::App;
-use crate::tui::markdown::parse_markdown;
+use crate::tui::markdown::{parse_markdown, CodeView};
+use crate::tui::theme::Theme;
+
+/// Start of the line under an error message that suggests a fix
+pub(crate) const HINT_PREFIX: &str = "Hint: ";
+
+/// Braille spinner shown while a response is generated
+const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// Spinner for terminals without Unicode
+const ASCII_SPINNER_FRAMES: &[&str] = &["|", "/", "-", "\\"];
+
+/// Whether the locale says the terminal can draw Unicode
+static UNICODE_TERMINAL: Lazy<bool> = Lazy::new(|| {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty()))
+        .map(|locale| {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+        // Windows terminals don't set a locale but handle Unicode
+        .unwrap_or(cfg!(windows))
+});
+
+/// The spinner frame to draw on frame `tick`
+fn spinner_frame(tick: u64, unicode: bool) -> &'static str {
+    let frames = if unicode {
+        SPINNER_FRAMES
+    } else {
+        ASCII_SPINNER_FRAMES
+    };
+    frames[(tick % frames.len() as u64) as usize]
+}
 
 /// Cache for layout calculations to improve performance
 struct LayoutCache {
@@ -59,25 +100,113 @@ impl LayoutCache {
             }
         }
 
-        let layout = if show_sidebar {
-            Layout::default()
-                .direction(Direction::Horizontal)
-                .spacing(-1)  // Overlapping borders between sidebar and main content
-                .constraints([Constraint::Percentage(25), Constraint::Percentage(75)])
-                .split(area)
-                .to_vec()
-        } else {
-            vec![Rect::default(), area]
-        };
-
+        let layout = content_layout(show_sidebar, area);
         self.content_layout = Some((show_sidebar, area, layout.clone()));
         layout
     }
 }
 
+/// Sidebar and chat areas of the content row; the sidebar is empty while hidden
+fn content_layout(show_sidebar: bool, area: Rect) -> Vec<Rect> {
+    if show_sidebar {
+        Layout::default()
+            .direction(Direction::Horizontal)
+            .spacing(-1)  // Overlapping borders between sidebar and main content
+            .constraints([Constraint::Percentage(25), Constraint::Percentage(75)])
+            .split(area)
+            .to_vec()
+    } else {
+        vec![Rect::default(), area]
+    }
+}
+
+/// Width inside the chat pane's borders on a terminal `terminal_width` columns wide
+pub fn chat_inner_width(terminal_width: u16, show_sidebar: bool) -> u16 {
+    content_layout(show_sidebar, Rect::new(0, 0, terminal_width, 1))[1]
+        .width
+        .saturating_sub(2)
+}
+
 // Global layout cache
 static LAYOUT_CACHE: Lazy<Mutex<LayoutCache>> = Lazy::new(|| Mutex::new(LayoutCache::new()));
 
+/// How long each status bar hint is shown
+const HINT_ROTATION: Duration = Duration::from_secs(10);
+
+/// Shortcuts shown in the status bar while idle
+static IDLE_HINTS: &[&str] = &[
+    "Shift+Tab: cycle modes",
+    "Tab: toggle sidebar",
+    "F2: toggle diagnostics",
+    "Alt+C: copy code block",
+    "Alt+Up/Down: select message",
+    "Ctrl+L: redraw screen",
+    ":help: full command list",
+];
+
+/// Shortcuts shown while a response is streaming
+static GENERATING_HINTS: &[&str] = &["Esc: stop generation", "F2: toggle diagnostics"];
+
+/// Shortcuts shown while an action waits for confirmation
+static CONFIRMATION_HINTS: &[&str] = &["Alt+Y: approve", "Alt+N: skip", "Alt+A: always allow"];
+
+/// Cycles the status bar through the hints that apply to the current state
+struct HintRotator {
+    started: Instant,
+}
+
+impl HintRotator {
+    fn hint(&self, app: &App) -> &'static str {
+        let hints = if app.confirmation_state.is_some() {
+            CONFIRMATION_HINTS
+        } else if app.is_generating {
+            GENERATING_HINTS
+        } else {
+            IDLE_HINTS
+        };
+        hint_at(hints, self.started.elapsed())
+    }
+}
+
+/// The hint to show after `elapsed`, moving on every `HINT_ROTATION`
+fn hint_at(hints: &[&'static str], elapsed: Duration) -> &'static str {
+    let step = (elapsed.as_secs() / HINT_ROTATION.as_secs()) as usize;
+    hints[step % hints.len()]
+}
+
+static HINT_ROTATOR: Lazy<HintRotator> = Lazy::new(|| HintRotator {
+    started: Instant::now(),
+});
+
+/// Widget areas from the last rendered frame, used to map mouse clicks
+#[derive(Debug, Clone, Default)]
+pub struct LayoutAreas {
+    /// Sidebar area (empty while hidden)
+    pub sidebar: Rect,
+    /// Screen row of the first visible sidebar item
+    pub sidebar_list_top: u16,
+    /// Index of the first visible sidebar item after scrolling
+    pub sidebar_offset: usize,
+    /// Info rows listed above the files
+    pub sidebar_header_rows: usize,
+    /// Files in the order they're listed in the sidebar
+    pub sidebar_files: Vec<String>,
+}
+
+impl LayoutAreas {
+    /// The sidebar file (index and path) at a screen position
+    pub fn sidebar_file_at(&self, column: u16, row: u16) -> Option<(usize, String)> {
+        if !self.sidebar.contains(Position::new(column, row)) || row < self.sidebar_list_top {
+            return None;
+        }
+        let item = (row - self.sidebar_list_top) as usize + self.sidebar_offset;
+        let index = item.checked_sub(self.sidebar_header_rows)?;
+        self.sidebar_files
+            .get(index)
+            .map(|path| (index, path.clone()))
+    }
+}
+
 /// Render the main UI
 pub fn render_ui(frame: &mut Frame, app: &App) {
     // Calculate input area height based on content
@@ -118,6 +247,8 @@ pub fn render_ui(frame: &mut Frame, app: &App) {
     // Render sidebar if visible
     if app.show_sidebar {
         render_sidebar(frame, content_chunks[0], app);
+    } else if let Ok(mut areas) = app.layout_areas.lock() {
+        areas.sidebar = Rect::default();
     }
 
     // Render chat area
@@ -132,14 +263,14 @@ pub fn render_ui(frame: &mut Frame, app: &App) {
     // Render diagnostics panel if in detailed mode
     if app.diagnostics_mode == DiagnosticsMode::Detailed {
         if let Some(ref stats) = app.hardware_stats {
-            render_diagnostics_panel(frame, frame.area(), stats);
+            render_diagnostics_panel(frame, frame.area(), stats, &app.usage_today);
         }
     }
 }
 
 /// Render the header
 fn render_header(frame: &mut Frame, area: Rect, app: &App) {
-    let header_text = vec![Line::from(vec![
+    let mut header_text = vec![Line::from(vec![
         Span::styled("[MERMAID] ", Style::default().fg(Color::Cyan)),
         Span::styled(
             "Mermaid",
@@ -152,6 +283,17 @@ fn render_header(frame: &mut Frame, area: Rect, app: &App) {
         Span::raw(" | "),
         Span::styled(&app.working_dir, Style::default().fg(Color::Gray)),
     ])];
+    if let Some(branch) = &app.current_branch {
+        header_text[0].spans.push(Span::styled(
+            format!(" ({})", branch),
+            Style::default().fg(Color::Magenta),
+        ));
+    }
+    if app.custom_system_prompt.is_some() {
+        header_text[0]
+            .spans
+            .push(Span::styled(" | 📋", Style::default().fg(Color::Yellow)));
+    }
 
     let header = Paragraph::new(header_text)
         .block(
@@ -190,6 +332,7 @@ fn render_sidebar(frame: &mut Frame, area: Rect, app: &App) {
     ])));
 
     items.push(ListItem::new(""));
+    let header_rows = items.len();
 
     // Add file list (truncated or expanded)
     let max_files = if app.sidebar_expanded {
@@ -198,15 +341,42 @@ fn render_sidebar(frame: &mut Frame, area: Rect, app: &App) {
         20
     };
 
-    for (path, _) in app.context.files.iter().take(max_files) {
-        let icon = if path.ends_with('/') {
-            "[DIR]"
+    // Most important files first, the rest alphabetically
+    let mut files: Vec<(&String, Option<f64>)> = app
+        .context
+        .files
+        .keys()
+        .map(|path| (path, app.file_importance.get(path).copied()))
+        .collect();
+    files.sort_by(|a, b| {
+        b.1.unwrap_or(-1.0)
+            .partial_cmp(&a.1.unwrap_or(-1.0))
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.0.cmp(b.0))
+    });
+    let max_importance = files
+        .iter()
+        .filter_map(|(_, score)| *score)
+        .fold(0.0_f64, f64::max);
+
+    let files: Vec<(&String, Option<f64>)> = files.into_iter().take(max_files).collect();
+    for (path, importance) in &files {
+        let conflicted = app.conflicted_files.contains(path);
+        let (icon, icon_style) = if conflicted {
+            ("⚡", Style::default().fg(Color::Red))
+        } else if path.ends_with('/') {
+            ("[DIR]", Style::default())
         } else {
-            "[FILE]"
+            ("[FILE]", Style::default())
+        };
+        let heat = match importance {
+            Some(score) if max_importance > 0.0 => importance_bar(*score / max_importance),
+            _ => ' ',
         };
         items.push(ListItem::new(Line::from(vec![
-            Span::raw(format!("{} ", icon)),
-            Span::raw(path),
+            Span::styled(format!("{} ", heat), Style::default().fg(Color::Magenta)),
+            Span::styled(format!("{} ", icon), icon_style),
+            Span::raw(path.as_str()),
         ])));
     }
 
@@ -220,46 +390,150 @@ fn render_sidebar(frame: &mut Frame, area: Rect, app: &App) {
         )])));
     }
 
+    // Rows after the files don't map to sidebar clicks
+    if app.config.ui.git_status && !app.stashes.is_empty() {
+        items.push(ListItem::new(""));
+        items.push(ListItem::new(Line::from(vec![
+            Span::raw("[STASH] "),
+            Span::styled(
+                format!("Stashes: {}", app.stashes.len()),
+                Style::default().fg(Color::Yellow),
+            ),
+        ])));
+        for stash in &app.stashes {
+            items.push(ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("  {} ", stash.stash_ref),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::styled(stash.message.clone(), Style::default().fg(Color::DarkGray)),
+            ])));
+        }
+    }
+
+    let block = Block::default()
+        .title(format!("Files [{}] ", app.working_dir))
+        .borders(Borders::RIGHT)
+        .border_style(Style::default().fg(Color::DarkGray));
+    let list_top = block.inner(area).y;
     let list = List::new(items)
-        .block(
-            Block::default()
-                .title(format!("Files [{}] ", app.working_dir))
-                .borders(Borders::RIGHT)
-                .border_style(Style::default().fg(Color::DarkGray)),
-        )
-        .style(Style::default().fg(Color::White));
+        .block(block)
+        .style(Style::default().fg(Color::White))
+        .highlight_style(Style::default().bg(Color::Rgb(50, 50, 50)));
+
+    let selected = app.sidebar_state.selected_file;
+    let mut state = ListState::default()
+        .with_selected((selected < files.len()).then_some(header_rows + selected));
+    frame.render_stateful_widget(list, area, &mut state);
+
+    if let Ok(mut areas) = app.layout_areas.lock() {
+        *areas = LayoutAreas {
+            sidebar: area,
+            sidebar_list_top: list_top,
+            sidebar_offset: state.offset(),
+            sidebar_header_rows: header_rows,
+            sidebar_files: files.iter().map(|(path, _)| path.to_string()).collect(),
+        };
+    }
+}
 
-    frame.render_widget(list, area);
+/// Map a normalized importance score (0.0-1.0) to a block character
+fn importance_bar(normalized: f64) -> char {
+    const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let level = (normalized.clamp(0.0, 1.0) * (LEVELS.len() - 1) as f64).round() as usize;
+    LEVELS[level]
 }
 
 /// Render the chat area
 fn render_chat(frame: &mut Frame, area: Rect, app: &App) {
     let mut lines = Vec::new();
 
+    // Code lines scroll horizontally instead of wrapping (inside the borders)
+    let code_view = CodeView {
+        scroll_x: app.code_block_scroll_x,
+        width: Some(area.width.saturating_sub(2) as usize),
+        line_numbers: app.config.ui.show_code_line_numbers.then(|| {
+            Theme::from_name(&app.config.ui.theme)
+                .colors
+                .code_comment
+                .to_color()
+        }),
+        collapsed: Vec::new(),
+        focused: None,
+    };
+
     let message_count = app.messages.len();
     for (idx, msg) in app.messages.iter().enumerate() {
         let _is_last_message = idx == message_count - 1;
         // Add role indicator
         let (role_span, role_color) = match msg.role {
-            MessageRole::User => ("You", Color::Blue),
-            MessageRole::Assistant => ("Mermaid", Color::Green),
-            MessageRole::System => ("System", Color::Yellow),
+            MessageRole::User => ("You".to_string(), Color::Blue),
+            MessageRole::Assistant => ("Mermaid".to_string(), Color::Green),
+            MessageRole::System => ("System".to_string(), Color::Yellow),
+            MessageRole::Tool { .. } => (
+                format!(
+                    "🔧 {}",
+                    tool_call_name(&app.messages, idx).unwrap_or("Tool")
+                ),
+                Color::Magenta,
+            ),
         };
 
-        lines.push(Line::from(vec![Span::styled(
-            format!("[{}] ", role_span),
-            Style::default().fg(role_color).add_modifier(Modifier::BOLD),
-        )]));
+        let mut role_style = Style::default().fg(role_color).add_modifier(Modifier::BOLD);
+        if app.selected_message == Some(idx) {
+            role_style = role_style.add_modifier(Modifier::REVERSED);
+        }
+        let label = format!("[{}] ", role_span);
+        let mut header = vec![Span::styled(label.clone(), role_style)];
+        if app.config.ui.show_timestamps {
+            let timestamp = format_timestamp(&msg.timestamp, &app.config.ui.timestamp_format);
+            let width = area.width.saturating_sub(2) as usize;
+            let padding = width.saturating_sub(label.chars().count() + timestamp.chars().count());
+            header.push(Span::raw(" ".repeat(padding)));
+            header.push(Span::styled(
+                timestamp,
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        lines.push(Line::from(header));
 
         // Parse markdown for assistant messages, plain text for user messages
         if matches!(msg.role, MessageRole::Assistant) {
             // Use markdown parsing for assistant messages
-            let parsed_lines = parse_markdown(&msg.content);
+            let message_view = CodeView {
+                collapsed: app.collapsed_blocks_in(idx),
+                focused: app
+                    .focused_code_block
+                    .and_then(|(msg, block)| (msg == idx).then_some(block)),
+                ..code_view.clone()
+            };
+            let parsed_lines = parse_markdown(&msg.content, &message_view);
             lines.extend(parsed_lines);
         } else {
-            // Plain text for user messages
+            // Plain text for user messages; a command's stderr is shown in red
+            let mut in_stderr = false;
             for line in msg.content.lines() {
-                lines.push(Line::from(line.to_string()));
+                if msg.role.is_tool() && line == STDERR_MARKER {
+                    in_stderr = true;
+                }
+                match line.strip_prefix(HINT_PREFIX) {
+                    _ if in_stderr => lines.push(Line::from(Span::styled(
+                        line.to_string(),
+                        Style::default().fg(Color::Red),
+                    ))),
+                    Some(hint) if matches!(msg.role, MessageRole::System) => {
+                        lines.push(Line::from(vec![
+                            Span::styled("  → ", Style::default().fg(Color::Cyan)),
+                            Span::styled(
+                                hint.to_string(),
+                                Style::default()
+                                    .fg(Color::Cyan)
+                                    .add_modifier(Modifier::ITALIC),
+                            ),
+                        ]));
+                    },
+                    _ => lines.push(Line::from(line.to_string())),
+                }
             }
         }
 
@@ -282,7 +556,11 @@ fn render_chat(frame: &mut Frame, area: Rect, app: &App) {
                 // Add completion message with checkmark
                 lines.push(Line::from(vec![
                     Span::styled(
-                        "  [OK] ",
+                        if *UNICODE_TERMINAL {
+                            "  ✓ "
+                        } else {
+                            "  [OK] "
+                        },
                         Style::default()
                             .fg(Color::Green)
                             .add_modifier(Modifier::BOLD),
@@ -393,39 +671,42 @@ fn render_chat(frame: &mut Frame, area: Rect, app: &App) {
                     )),
                 ]));
             }
+        }
 
-            // Preview if available
-            if !confirmation.preview_lines.is_empty() {
-                lines.push(Line::from("╠".to_string() + &"─".repeat(width - 2) + "╣"));
+        // Preview if available
+        if !confirmation.preview_lines.is_empty() {
+            lines.push(Line::from("╠".to_string() + &"─".repeat(width - 2) + "╣"));
+            lines.push(Line::from(vec![
+                Span::raw("║ Preview:"),
+                Span::raw(format!("{}║", " ".repeat(width.saturating_sub(10)))),
+            ]));
+            for line in confirmation.preview_lines.iter().take(3) {
+                let preview_line = format!("   {}", line);
+                let truncated = if preview_line.len() > width - 2 {
+                    format!(
+                        "{}...",
+                        preview_line.chars().take(width - 5).collect::<String>()
+                    )
+                } else {
+                    preview_line
+                };
                 lines.push(Line::from(vec![
-                    Span::raw("║ Preview:"),
-                    Span::raw(format!("{}║", " ".repeat(width.saturating_sub(10)))),
+                    Span::raw("║"),
+                    Span::styled(
+                        truncated.clone(),
+                        Style::default().fg(Color::Rgb(150, 150, 150)),
+                    ),
+                    Span::raw(format!(
+                        "{}║",
+                        " ".repeat(width.saturating_sub(truncated.len() + 1))
+                    )),
+                ]));
+            }
+            if confirmation.preview_lines.len() > 3 {
+                lines.push(Line::from(vec![
+                    Span::raw("║   ..."),
+                    Span::raw(format!("{}║", " ".repeat(width.saturating_sub(7)))),
                 ]));
-                for line in confirmation.preview_lines.iter().take(3) {
-                    let preview_line = format!("   {}", line);
-                    let truncated = if preview_line.len() > width - 2 {
-                        format!("{}...", &preview_line[..width - 5])
-                    } else {
-                        preview_line
-                    };
-                    lines.push(Line::from(vec![
-                        Span::raw("║"),
-                        Span::styled(
-                            truncated.clone(),
-                            Style::default().fg(Color::Rgb(150, 150, 150)),
-                        ),
-                        Span::raw(format!(
-                            "{}║",
-                            " ".repeat(width.saturating_sub(truncated.len() + 1))
-                        )),
-                    ]));
-                }
-                if confirmation.preview_lines.len() > 3 {
-                    lines.push(Line::from(vec![
-                        Span::raw("║   ..."),
-                        Span::raw(format!("{}║", " ".repeat(width.saturating_sub(7)))),
-                    ]));
-                }
             }
         }
 
@@ -481,8 +762,8 @@ fn render_chat(frame: &mut Frame, area: Rect, app: &App) {
         }
     }
 
-    // Add current response if generating
-    if app.is_generating && !app.current_response.is_empty() {
+    // Add current response if generating, with a spinner until it's complete
+    if app.is_generating {
         lines.push(Line::from(vec![Span::styled(
             "[Mermaid] ",
             Style::default()
@@ -490,16 +771,30 @@ fn render_chat(frame: &mut Frame, area: Rect, app: &App) {
                 .add_modifier(Modifier::BOLD),
         )]));
 
+        let mut in_code_block = false;
+        let mut code_line_num = 0;
         for line in app.current_response.lines() {
-            lines.push(Line::from(line.to_string()));
+            if line.trim_start().starts_with("```") {
+                in_code_block = !in_code_block;
+                code_line_num = 0;
+                let mut fence = vec![Span::raw(line.to_string())];
+                if in_code_block {
+                    fence.extend(code_view.indicator());
+                }
+                lines.push(Line::from(fence));
+            } else if in_code_block {
+                code_line_num += 1;
+                lines.push(code_view.code_line(code_line_num, line, Style::default(), false));
+            } else {
+                lines.push(Line::from(line.to_string()));
+            }
         }
 
-        // Add typing indicator
         lines.push(Line::from(vec![Span::styled(
-            "▋",
+            spinner_frame(app.spinner_tick, *UNICODE_TERMINAL),
             Style::default()
                 .fg(Color::Green)
-                .add_modifier(Modifier::SLOW_BLINK),
+                .add_modifier(Modifier::BOLD),
         )]));
     }
 
@@ -520,6 +815,38 @@ fn render_chat(frame: &mut Frame, area: Rect, app: &App) {
     frame.render_widget(paragraph, area);
 }
 
+/// Name of the function a tool message answers: results follow their assistant message
+/// in the order of its `[TOOL_CALL: ...]` lines
+fn tool_call_name(messages: &[ChatMessage], index: usize) -> Option<&str> {
+    let assistant = messages[..index]
+        .iter()
+        .rposition(|m| m.role == MessageRole::Assistant)?;
+    let answered = messages[assistant + 1..index]
+        .iter()
+        .filter(|m| m.role.is_tool())
+        .count();
+    messages[assistant]
+        .content
+        .lines()
+        .filter_map(parse_tool_call)
+        .nth(answered)
+        .map(|(name, _)| name)
+}
+
+/// Format a message timestamp, adding the date for messages older than a day
+fn format_timestamp(timestamp: &DateTime<Local>, format: &str) -> String {
+    // Invalid format strings make `to_string` panic, so fall back to the default
+    let mut time = String::new();
+    if write!(time, "{}", timestamp.format(format)).is_err() {
+        time = timestamp.format("%H:%M").to_string();
+    }
+    if Local::now().signed_duration_since(*timestamp) > chrono::Duration::hours(24) {
+        format!("{} {}", timestamp.format("%Y-%m-%d"), time)
+    } else {
+        time
+    }
+}
+
 /// Render the input area
 fn render_input(frame: &mut Frame, area: Rect, app: &App) {
     // Check if we should show command hints
@@ -566,8 +893,11 @@ fn render_input(frame: &mut Frame, area: Rect, app: &App) {
                 .constraints([Constraint::Length(hints_height), Constraint::Min(3)])
                 .split(area);
 
-            // Render command hints in the hints area
+            // Render command hints in the hints area, fitted to the current width
             if !filtered_commands.is_empty() {
+                let inner_width = chunks[0].width.saturating_sub(2) as usize;
+                let command_width = 20.min(inner_width / 2);
+                let description_width = inner_width.saturating_sub(command_width + 2);
                 let mut hint_lines = vec![Line::from(vec![Span::styled(
                     " Available Commands:",
                     Style::default()
@@ -578,12 +908,15 @@ fn render_input(frame: &mut Frame, area: Rect, app: &App) {
                 for (cmd, desc) in filtered_commands.iter().take(6) {
                     hint_lines.push(Line::from(vec![
                         Span::styled(
-                            format!("  {:<20}", cmd),
+                            format!("  {:<width$}", cmd, width = command_width),
                             Style::default()
                                 .fg(Color::Yellow)
                                 .add_modifier(Modifier::BOLD),
                         ),
-                        Span::styled(*desc, Style::default().fg(Color::Gray)),
+                        Span::styled(
+                            desc.chars().take(description_width).collect::<String>(),
+                            Style::default().fg(Color::Gray),
+                        ),
                     ]));
                 }
 
@@ -591,7 +924,11 @@ fn render_input(frame: &mut Frame, area: Rect, app: &App) {
                     Block::default()
                         .borders(Borders::ALL)
                         .border_style(Style::default().fg(Color::DarkGray))
-                        .title(" Commands (up/down to navigate, Enter to execute) "),
+                        .title(if inner_width >= 50 {
+                            " Commands (up/down to navigate, Enter to execute) "
+                        } else {
+                            " Commands "
+                        }),
                 );
 
                 frame.render_widget(hints_block, chunks[0]);
@@ -635,26 +972,8 @@ fn render_input(frame: &mut Frame, area: Rect, app: &App) {
     // Calculate cursor position for wrapped text
     {
         let inner_width = input_area.width.saturating_sub(2) as usize; // Account for borders
-        let cursor_pos = app.cursor_position.min(app.input.len());
-
-        // Calculate which line and column the cursor is on
-        let mut current_line = 0;
-        let mut current_col = 0;
-        let mut char_count = 0;
-
-        for ch in app.input.chars() {
-            if char_count == cursor_pos {
-                break;
-            }
-
-            if ch == '\n' || current_col >= inner_width {
-                current_line += 1;
-                current_col = if ch == '\n' { 0 } else { 1 };
-            } else {
-                current_col += 1;
-            }
-            char_count += 1;
-        }
+        let (current_line, current_col) =
+            input_cursor_cell(&app.input, app.cursor_position, inner_width);
 
         // Set cursor position
         let cursor_x = input_area.x + 1 + (current_col as u16);
@@ -716,6 +1035,69 @@ fn render_status_bar(frame: &mut Frame, area: Rect, app: &App) {
         }
     }
 
+    // Show proxy problems detected by the watchdog
+    if let Some(ref watchdog) = app.proxy_watchdog {
+        match watchdog.status() {
+            ProxyStatus::Running => {},
+            ProxyStatus::Restarting => {
+                spans.push(Span::styled(
+                    "Restarting LiteLLM proxy...",
+                    Style::default().fg(Color::Yellow),
+                ));
+                spans.push(Span::raw(" | "));
+            },
+            ProxyStatus::Down => {
+                spans.push(Span::styled(
+                    "[ERROR] LiteLLM proxy down - start it with ./start_litellm.sh",
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                ));
+                spans.push(Span::raw(" | "));
+            },
+        }
+    }
+
+    // Show what the last message is estimated to cost
+    if let Some(cost) = app.cost_estimate {
+        spans.push(Span::styled(
+            format!("~${:.4} (est.)", cost),
+            Style::default().fg(Color::Yellow),
+        ));
+        spans.push(Span::raw(" | "));
+    }
+
+    // Show the executing action and how long it has been running
+    if let Some((description, started)) = &app.running_action {
+        spans.push(Span::styled(
+            format!("🕐 {} ({}s)", description, started.elapsed().as_secs()),
+            Style::default().fg(Color::Yellow),
+        ));
+        spans.push(Span::raw(" | "));
+    }
+
+    // Show model download progress
+    if let Some(ref pull) = app.pull_progress {
+        spans.push(Span::styled(
+            format!("Pulling: {}", pull.to_bar(20)),
+            Style::default().fg(Color::Cyan),
+        ));
+        spans.push(Span::raw(" | "));
+    }
+
+    // Show background context loading progress
+    if let Some(ref lazy) = app.lazy_context {
+        if !lazy.is_fully_loaded() {
+            spans.push(Span::styled(
+                format!(
+                    "Loading: {}/{} files",
+                    lazy.load_progress.load(Ordering::Relaxed),
+                    lazy.total_file_count()
+                ),
+                Style::default().fg(Color::Yellow),
+            ));
+            spans.push(Span::raw(" | "));
+        }
+    }
+
     // Add warning message if in dangerous mode
     let warning_level = app.operation_mode.warning_level();
     if let Some(warning) = warning_level.message() {
@@ -730,7 +1112,7 @@ fn render_status_bar(frame: &mut Frame, area: Rect, app: &App) {
     spans.push(Span::raw(status_text));
     spans.push(Span::raw(" | "));
     spans.push(Span::styled(
-        "Shift+Tab: cycle modes",
+        HINT_ROTATOR.hint(app),
         Style::default().fg(Color::DarkGray),
     ));
     spans.push(Span::raw(" | "));
@@ -749,3 +1131,96 @@ fn render_status_bar(frame: &mut Frame, area: Rect, app: &App) {
 
     frame.render_widget(status_bar, area);
 }
+
+/// Line and display column of the cursor in input wrapped at `width` columns;
+/// wide characters take two columns and move to the next line whole
+fn input_cursor_cell(input: &str, cursor: usize, width: usize) -> (usize, usize) {
+    let mut line = 0;
+    let mut col = 0;
+    for grapheme in input[..cursor.min(input.len())].graphemes(true) {
+        let grapheme_width = grapheme.width();
+        if grapheme == "\n" {
+            line += 1;
+            col = 0;
+        } else if col + grapheme_width > width {
+            line += 1;
+            col = grapheme_width;
+        } else {
+            col += grapheme_width;
+        }
+    }
+    (line, col)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_call_name() {
+        let message = |role, content: &str| ChatMessage {
+            role,
+            content: content.to_string(),
+            timestamp: Local::now(),
+        };
+        let messages = vec![
+            message(MessageRole::User, "What changed?"),
+            message(
+                MessageRole::Assistant,
+                "[TOOL_CALL: git_status]:{}\n[TOOL_CALL: git_diff]:{}",
+            ),
+            message(MessageRole::tool(), "M src/main.rs"),
+            message(MessageRole::tool(), "+fn main() {}"),
+            message(MessageRole::tool(), "extra"),
+        ];
+        assert_eq!(tool_call_name(&messages, 2), Some("git_status"));
+        assert_eq!(tool_call_name(&messages, 3), Some("git_diff"));
+        assert_eq!(tool_call_name(&messages, 4), None);
+    }
+
+    #[test]
+    fn test_input_cursor_cell_counts_display_width() {
+        assert_eq!(input_cursor_cell("abc", 3, 10), (0, 3));
+        // Each CJK character takes two columns
+        assert_eq!(input_cursor_cell("日本語", 9, 10), (0, 6));
+        assert_eq!(input_cursor_cell("日本語", 9, 5), (1, 2));
+        assert_eq!(input_cursor_cell("ab\ncd", 4, 10), (1, 1));
+    }
+
+    #[test]
+    fn test_spinner_frame_cycles() {
+        assert_eq!(spinner_frame(0, true), "⠋");
+        assert_eq!(spinner_frame(11, true), "⠙");
+        assert_eq!(spinner_frame(3, false), "\\");
+        assert_eq!(spinner_frame(6, false), "-");
+    }
+
+    #[test]
+    fn test_hint_at_rotates_every_interval() {
+        assert_eq!(hint_at(IDLE_HINTS, Duration::ZERO), IDLE_HINTS[0]);
+        assert_eq!(hint_at(IDLE_HINTS, Duration::from_secs(9)), IDLE_HINTS[0]);
+        assert_eq!(hint_at(IDLE_HINTS, Duration::from_secs(10)), IDLE_HINTS[1]);
+        let full_cycle = HINT_ROTATION * IDLE_HINTS.len() as u32;
+        assert_eq!(hint_at(IDLE_HINTS, full_cycle), IDLE_HINTS[0]);
+        assert_eq!(
+            hint_at(CONFIRMATION_HINTS, Duration::from_secs(25)),
+            "Alt+A: always allow"
+        );
+    }
+
+    #[test]
+    fn test_sidebar_file_at_accounts_for_header_and_offset() {
+        let areas = LayoutAreas {
+            sidebar: Rect::new(0, 3, 20, 10),
+            sidebar_list_top: 4,
+            sidebar_offset: 1,
+            sidebar_header_rows: 3,
+            sidebar_files: vec!["a.rs".to_string(), "b.rs".to_string()],
+        };
+
+        assert_eq!(areas.sidebar_file_at(5, 5), None); // Header row
+        assert_eq!(areas.sidebar_file_at(5, 6), Some((0, "a.rs".to_string())));
+        assert_eq!(areas.sidebar_file_at(5, 7), Some((1, "b.rs".to_string())));
+        assert_eq!(areas.sidebar_file_at(25, 6), None); // Outside the sidebar
+    }
+}
@@ -80,6 +80,15 @@ impl ColorValue {
 }
 
 impl Theme {
+    /// Look up a built-in theme by its config name, falling back to dark
+    pub fn from_name(name: &str) -> Self {
+        match name.to_lowercase().replace(['-', '_'], " ").as_str() {
+            "light" => Self::light(),
+            "high contrast" => Self::high_contrast(),
+            _ => Self::dark(),
+        }
+    }
+
     /// Create the default dark theme
     pub fn dark() -> Self {
         Self {
@@ -1,24 +1,47 @@
 use anyhow::Result;
 use crossterm::{
     event::{
-        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers, MouseEventKind,
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers, MouseButton,
+        MouseEventKind,
     },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ratatui::{backend::CrosstermBackend, Terminal};
+use ratatui::{backend::CrosstermBackend, widgets::Clear, Terminal};
+use std::collections::HashMap;
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::mpsc;
 
 use crate::agents;
 use crate::agents::ModeAwareExecutor;
-use crate::context::ContextLoader;
-use crate::models::{MessageRole, ModelConfig, StreamCallback};
-use crate::tui::render::render_ui;
-use crate::tui::{App, ConfirmationState, FileInfo};
-use crate::utils::{count_file_tokens, FileSystemWatcher};
+use crate::context::{generate_repo_map, ContextLoader, RepoMap, RepoRanker};
+use crate::diagnostics::estimate_cost;
+use crate::models::{
+    ChatMessage, MessageRole, ModelConfig, ModelResponse, ProjectContext, StreamCallback,
+};
+use crate::ollama::{pull_model_with_progress, PullProgress, PullProgressCallback};
+use crate::session::{BudgetStatus, UsageLog, UsageReport};
+use crate::tui::render::{render_ui, HINT_PREFIX};
+use crate::tui::{ActionOutcome, App, ConfirmationKind, ConfirmationState, FileInfo};
+use crate::utils::{
+    copy_to_clipboard, count_file_tokens, recovery_hint_for, CopyTarget, FileEvent,
+    FileSystemWatcher,
+};
+
+/// Prompt used to generate a conversation title after the first exchange
+const TITLE_PROMPT: &str = "In 5 words or less, describe this conversation:";
+
+/// Prompt used by :summarize
+const SUMMARY_PROMPT: &str =
+    "Summarize this conversation in 2-3 sentences, focusing on what was accomplished:";
+
+/// Maximum time between two clicks on the same sidebar row to count as a double-click
+const DOUBLE_CLICK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(300);
+
+/// Columns scrolled per Alt+Left/Right press in code blocks
+const CODE_SCROLL_STEP: i16 = 4;
 
 /// Run the terminal UI
 pub async fn run_ui(mut app: App) -> Result<()> {
@@ -78,8 +101,9 @@ async fn run_app(
     tx: mpsc::Sender<String>,
     rx: &mut mpsc::Receiver<String>,
 ) -> Result<()> {
-    // Initialize file watcher for the current directory
-    let watcher = FileSystemWatcher::new(Path::new("."))?;
+    // Initialize file watcher for the working directory, following the model's `cd`s
+    let mut watcher = FileSystemWatcher::new(Path::new(&app.working_dir))?;
+    let mut watched_dir = app.working_dir.clone();
     let mut last_refresh = std::time::Instant::now();
 
     // Start hardware monitoring if available
@@ -103,11 +127,41 @@ async fn run_app(
             }
         });
     }
+    // Build the repository map in the background to rank files for the sidebar
+    if app.repo_map.is_none() {
+        if let Ok(repo_map) = RepoMap::new(None) {
+            let repo_map = Arc::new(tokio::sync::Mutex::new(repo_map));
+            app.repo_map = Some(repo_map.clone());
+            let root = std::path::PathBuf::from(&app.working_dir);
+            let importance_tx = tx.clone();
+            tokio::spawn(async move {
+                let mut repo_map = repo_map.lock().await;
+                if repo_map.build_from_directory(&root).await.is_ok()
+                    && repo_map.generate_map(&[], &[], None).await.is_ok()
+                {
+                    if let Ok(json) = serde_json::to_string(&repo_map.get_ranked_files()) {
+                        let _ = importance_tx.send(format!("[IMPORTANCE]:{}", json)).await;
+                    }
+                }
+            });
+        }
+    }
+
+    let size = terminal.size()?;
+    app.handle_resize(size.width, size.height);
+    let mut saved_mode = app.operation_mode;
+
     loop {
         // Get viewport height for proper scrolling
-        let viewport_height = terminal.size()?.height.saturating_sub(8); // 3 header + 3 input + 1 status + 1 margin
+        let viewport_height = app.viewport_height();
+
+        if app.operation_mode != saved_mode {
+            saved_mode = app.operation_mode;
+            remember_mode(app);
+        }
 
         // Draw UI
+        app.spinner_tick = app.spinner_tick.wrapping_add(1);
         terminal.draw(|f| render_ui(f, app))?;
 
         // Handle input events
@@ -123,12 +177,16 @@ async fn run_app(
                         MouseEventKind::ScrollDown => {
                             app.scroll_up(3); // Scroll down moves view up
                                               // Check if scrolled to bottom
-                            let max_scroll = app.calculate_max_scroll(viewport_height);
+                            let max_scroll =
+                                app.calculate_max_scroll(viewport_height, app.chat_width());
                             if app.scroll_offset >= max_scroll.saturating_sub(3) {
                                 app.is_user_scrolling = false;
                                 app.scroll_offset = max_scroll;
                             }
                         },
+                        MouseEventKind::Down(MouseButton::Left) => {
+                            handle_sidebar_click(app, mouse.column, mouse.row, &tx).await;
+                        },
                         _ => {},
                     }
                 },
@@ -144,72 +202,115 @@ async fn run_app(
                                         confirmation.action_description
                                     ));
 
-                                    // Get the executor from pending_executor
+                                    let action = match confirmation.kind {
+                                        ConfirmationKind::Action(action) => action,
+                                        ConfirmationKind::TrashConversations(ids) => {
+                                            trash_conversations(app, &ids);
+                                            continue;
+                                        },
+                                    };
                                     if let Some(mut executor) = app.pending_executor.take() {
-                                        let action_clone = confirmation.action.clone();
+                                        let action_clone = action.clone();
 
                                         // Execute the action
-                                        match executor.execute(confirmation.action).await {
-                                            Ok(agents::ActionResult::Success { output }) => {
+                                        match executor.execute_confirmed(action).await {
+                                            Ok(result @ agents::ActionResult::Success { .. }) => {
                                                 handle_action_success(
                                                     app,
                                                     &action_clone,
-                                                    output,
+                                                    result.output(),
                                                     &tx,
                                                 )
                                                 .await;
                                             },
-                                            Ok(agents::ActionResult::Error { error }) => {
+                                            Ok(agents::ActionResult::Error { error, .. }) => {
+                                                app.add_function_result(
+                                                    &action_clone,
+                                                    ActionOutcome::Failed,
+                                                    &error,
+                                                );
                                                 app.set_status(format!(
                                                     "[FAILED] Action failed: {}",
                                                     error
                                                 ));
                                             },
                                             Err(e) => {
+                                                app.add_function_result(
+                                                    &action_clone,
+                                                    ActionOutcome::Failed,
+                                                    &format!("Error: {}", e),
+                                                );
                                                 app.set_status(format!("[ERROR] Error: {}", e));
                                             },
                                         }
                                     }
                                     app.pending_action = None;
+                                    continue_after_function_calls(app, &tx);
                                 }
                             },
                             KeyCode::Char('n') | KeyCode::Char('N') => {
                                 // Skip action
-                                if let Some(_) = app.confirmation_state.take() {
+                                if let Some(confirmation) = app.confirmation_state.take() {
+                                    let ConfirmationKind::Action(action) = confirmation.kind else {
+                                        app.set_status("Kept the conversations");
+                                        continue;
+                                    };
+                                    app.add_function_result(
+                                        &action,
+                                        ActionOutcome::Declined,
+                                        "The user declined this function call",
+                                    );
                                     app.set_status("Action skipped");
                                     app.pending_action = None;
                                     app.pending_executor = None;
+                                    continue_after_function_calls(app, &tx);
                                 }
                             },
                             KeyCode::Char('a') | KeyCode::Char('A') => {
                                 // Always approve - persistent preferences not yet implemented
                                 if let Some(confirmation) = app.confirmation_state.take() {
+                                    let ConfirmationKind::Action(action) = confirmation.kind else {
+                                        // Trashing is never always-approved; keep asking
+                                        app.confirmation_state = Some(confirmation);
+                                        continue;
+                                    };
                                     app.set_status("Always approving similar actions");
                                     // For now, just approve this one
                                     if let Some(mut executor) = app.pending_executor.take() {
-                                        let action_clone = confirmation.action.clone();
-                                        match executor.execute(confirmation.action).await {
-                                            Ok(agents::ActionResult::Success { output }) => {
+                                        let action_clone = action.clone();
+                                        match executor.execute_confirmed(action).await {
+                                            Ok(result @ agents::ActionResult::Success { .. }) => {
                                                 handle_action_success(
                                                     app,
                                                     &action_clone,
-                                                    output,
+                                                    result.output(),
                                                     &tx,
                                                 )
                                                 .await;
                                             },
-                                            Ok(agents::ActionResult::Error { error }) => {
+                                            Ok(agents::ActionResult::Error { error, .. }) => {
+                                                app.add_function_result(
+                                                    &action_clone,
+                                                    ActionOutcome::Failed,
+                                                    &error,
+                                                );
                                                 app.set_status(format!(
                                                     "[FAILED] Action failed: {}",
                                                     error
                                                 ));
                                             },
                                             Err(e) => {
+                                                app.add_function_result(
+                                                    &action_clone,
+                                                    ActionOutcome::Failed,
+                                                    &format!("Error: {}", e),
+                                                );
                                                 app.set_status(format!("[ERROR] Error: {}", e));
                                             },
                                         }
                                     }
                                     app.pending_action = None;
+                                    continue_after_function_calls(app, &tx);
                                 }
                             },
                             KeyCode::Char('p') | KeyCode::Char('P') => {
@@ -221,6 +322,42 @@ async fn run_app(
                         continue; // Skip normal key handling when confirmation is active
                     }
 
+                    // Ctrl+L clears and redraws the screen like a shell's `clear`;
+                    // messages are kept
+                    if key.code == KeyCode::Char('l') && key.modifiers == KeyModifiers::CONTROL {
+                        terminal.clear()?;
+                        terminal.draw(|f| f.render_widget(Clear, f.area()))?;
+                        continue;
+                    }
+
+                    // Alt+Left/Right scroll code blocks horizontally, Alt+Up/Down select messages,
+                    // Alt+C copies the focused (or latest) code block
+                    if key.modifiers == KeyModifiers::ALT {
+                        match key.code {
+                            KeyCode::Up => {
+                                app.select_message(-1);
+                                continue;
+                            },
+                            KeyCode::Down => {
+                                app.select_message(1);
+                                continue;
+                            },
+                            KeyCode::Left => {
+                                app.scroll_code_blocks(-CODE_SCROLL_STEP);
+                                continue;
+                            },
+                            KeyCode::Right => {
+                                app.scroll_code_blocks(CODE_SCROLL_STEP);
+                                continue;
+                            },
+                            KeyCode::Char('c') | KeyCode::Char('C') => {
+                                copy_code_block(app);
+                                continue;
+                            },
+                            _ => {},
+                        }
+                    }
+
                     // Simplified key handling - no modes
                     match key.code {
                         KeyCode::Esc => {
@@ -251,6 +388,9 @@ async fn run_app(
                                 app.input.clear();
                                 app.cursor_position = 0; // Reset cursor when clearing
                                 app.set_status("Input cleared");
+                            } else {
+                                app.selected_message = None;
+                                app.focused_code_block = None;
                             }
                         },
                         KeyCode::Enter => {
@@ -259,20 +399,33 @@ async fn run_app(
                                 if app.input.starts_with(':') {
                                     // Execute command
                                     let command = app.input.trim_start_matches(':').to_string();
-                                    handle_command(app, &command).await?;
+                                    // Cleared first so commands can prefill the input
                                     app.clear_input();
+                                    handle_command(app, &command, &tx).await?;
+                                } else if let Some(error) = budget_block(app) {
+                                    app.set_status(error);
                                 } else {
                                     // Clear any stuck status messages when sending new message
                                     app.pending_file_read = false;
                                     app.reading_file_status = None;
 
+                                    // Use whatever files have loaded so far
+                                    app.sync_lazy_context().await;
+
                                     // Send message
                                     let input = app.input.clone();
+                                    app.tool_rounds = 0;
                                     app.add_message(MessageRole::User, input.clone());
                                     app.clear_input();
 
                                     // Build message history including the new message
                                     let messages = app.build_message_history();
+                                    app.cost_estimate = app.estimate_request_cost(&messages);
+                                    if let BudgetStatus::Warning(warning) =
+                                        app.budget_status(app.cost_estimate.unwrap_or(0.0))
+                                    {
+                                        app.set_status(format!("[WARNING] {}", warning));
+                                    }
 
                                     // Auto-scroll to show the new user message
                                     app.auto_scroll_to_bottom(viewport_height);
@@ -282,11 +435,15 @@ async fn run_app(
                                     // Process message asynchronously
                                     let model = app.model.clone();
                                     let context = app.context.clone();
+                                    let repo_map = app.repo_map.clone();
+                                    let config = app.model_config();
                                     let tx_clone = tx.clone();
                                     let tx_done = tx.clone();
 
                                     let handle = tokio::spawn(async move {
-                                        let config = ModelConfig::default();
+                                        let context =
+                                            with_repo_map(repo_map, Some(input), context, &tx_done)
+                                                .await;
                                         let callback: StreamCallback = Arc::new(move |chunk| {
                                             let _ = tx_clone.try_send(chunk.to_string());
                                         });
@@ -296,13 +453,13 @@ async fn run_app(
                                             .chat(&messages, &context, &config, Some(callback))
                                             .await
                                         {
-                                            Ok(_) => {
+                                            Ok(response) => {
+                                                record_usage(&response);
                                                 // Response is complete - content already streamed via callback
                                                 let _ = tx_done.send("[DONE]:".to_string()).await;
                                             },
                                             Err(e) => {
-                                                let _ =
-                                                    tx_done.send(format!("[ERROR]:{}", e)).await;
+                                                let _ = tx_done.send(error_chunk(&e)).await;
                                             },
                                         }
                                     });
@@ -310,38 +467,21 @@ async fn run_app(
                                 }
                             }
                         },
-                        KeyCode::Char(c) => {
-                            // Insert character at cursor position
-                            app.input.insert(app.cursor_position, c);
-                            app.cursor_position += 1;
-                        },
-                        KeyCode::Backspace => {
-                            if app.cursor_position > 0 {
-                                app.cursor_position -= 1;
-                                app.input.remove(app.cursor_position);
-                            }
-                        },
-                        KeyCode::Delete => {
-                            if app.cursor_position < app.input.len() {
-                                app.input.remove(app.cursor_position);
-                            }
-                        },
-                        KeyCode::Left => {
-                            if app.cursor_position > 0 {
-                                app.cursor_position -= 1;
-                            }
-                        },
-                        KeyCode::Right => {
-                            if app.cursor_position < app.input.len() {
-                                app.cursor_position += 1;
-                            }
-                        },
-                        KeyCode::Home => {
-                            app.cursor_position = 0;
-                        },
-                        KeyCode::End => {
-                            app.cursor_position = app.input.len();
+                        // Otherwise 'c' is typed, so messages can still start with it
+                        KeyCode::Char('c')
+                            if key.modifiers.is_empty()
+                                && app.input.is_empty()
+                                && app.selected_has_code() =>
+                        {
+                            app.toggle_selected_code_block();
                         },
+                        KeyCode::Char(c) => app.input_insert_char(c),
+                        KeyCode::Backspace => app.input_delete_before_cursor(),
+                        KeyCode::Delete => app.input_delete_at_cursor(),
+                        KeyCode::Left => app.input_cursor_left(),
+                        KeyCode::Right => app.input_cursor_right(),
+                        KeyCode::Home => app.cursor_position = 0,
+                        KeyCode::End => app.cursor_position = app.input.len(),
                         // Navigation keys always available
                         KeyCode::Up => app.scroll_down(1),
                         KeyCode::Down => app.scroll_up(1),
@@ -392,7 +532,8 @@ async fn run_app(
                         }
                     }
                 },
-                _ => {}, // Ignore other events (FocusGained, FocusLost, Paste, Resize)
+                Event::Resize(width, height) => app.handle_resize(width, height),
+                _ => {}, // Ignore other events (FocusGained, FocusLost, Paste)
             }
         } // Close the if event::poll(...) block
 
@@ -406,6 +547,7 @@ async fn run_app(
 
                     // Generation complete
                     app.is_generating = false;
+                    app.refresh_usage_today();
 
                     // Clear feedback flags if this was a feedback response
                     if is_feedback_complete {
@@ -424,13 +566,21 @@ async fn run_app(
                         let response_text = app.current_response.clone();
                         app.add_message(MessageRole::Assistant, response_text.clone());
 
+                        if app.needs_auto_title() {
+                            spawn_title_generation(app, tx.clone());
+                        }
+
                         // Parse and execute any actions from the response
                         let actions = agents::parse_actions(&response_text);
 
                         // Check if any actions will trigger feedback loops
-                        let has_feedback_actions = actions
-                            .iter()
-                            .any(|a| matches!(a, agents::AgentAction::ReadFile { .. }));
+                        let has_feedback_actions = actions.iter().any(|a| {
+                            matches!(
+                                a,
+                                agents::AgentAction::ReadFile { .. }
+                                    | agents::AgentAction::WebSearch { .. }
+                            )
+                        });
 
                         if has_feedback_actions {
                             app.pending_file_read = true;
@@ -441,37 +591,59 @@ async fn run_app(
                             }
                         }
 
+                        take_snapshot(app, &actions);
+
                         // Create mode-aware executor
-                        let mut executor = ModeAwareExecutor::new(app.operation_mode.clone());
+                        let mut executor = ModeAwareExecutor::new(app.operation_mode)
+                            .with_session_env(app.session_env.clone())
+                            .with_working_dir(app.working_dir.clone())
+                            .with_root(app.project_root())
+                            .with_config(app.config.clone());
+
+                        // Waves of independent reads run concurrently; results are handled in order
+                        let waves = agents::plan_waves(&actions);
+                        let batch = actions.clone();
+                        let mut parallel_results = HashMap::new();
+
+                        for (index, action) in actions.into_iter().enumerate() {
+                            if let Some(wave) = waves.iter().find(|w| w.len() > 1 && w[0] == index)
+                            {
+                                let results =
+                                    run_parallel_wave(terminal, app, &executor, &batch, wave)
+                                        .await?;
+                                parallel_results.extend(results);
+                            }
+
+                            // Tests and docs are generated in the background, then confirmed as a write
+                            if let Ok(
+                                resolved @ (agents::AgentAction::GenerateTests { .. }
+                                | agents::AgentAction::GenerateDocs { .. }),
+                            ) = action.resolve()
+                            {
+                                spawn_file_generation(app, resolved, tx.clone());
+                                continue;
+                            }
+
+                            // The model's copy of a stripped file would lose its comments
+                            if let Some(refusal) = app.stripped_write_refusal(&action) {
+                                app.add_function_result(&action, ActionOutcome::Refused, &refusal);
+                                app.add_message(MessageRole::System, refusal.clone());
+                                app.set_status(format!("[FAILED] {}", refusal));
+                                continue;
+                            }
 
-                        for action in actions {
                             // Check if action needs confirmation
                             if executor.needs_confirmation(&action) {
                                 // Create confirmation state for inline display
                                 let action_desc = executor.describe_action(&action);
 
-                                // Extract preview and file info for WriteFile actions
-                                let (preview_lines, file_info) = match &action {
-                                    agents::AgentAction::WriteFile { path, content } => {
-                                        let lines: Vec<String> = content
-                                            .lines()
-                                            .take(5)
-                                            .map(|s| s.to_string())
-                                            .collect();
-                                        let info = FileInfo {
-                                            path: path.clone(),
-                                            size: content.len(),
-                                            exists: Path::new(path).exists(),
-                                            language: detect_language(path),
-                                        };
-                                        (lines, Some(info))
-                                    },
-                                    _ => (vec![], None),
-                                };
+                                // Extract preview and file info for the dialog
+                                let (preview_lines, file_info) =
+                                    confirmation_preview(&app.project_root(), &action).await;
 
                                 // Set confirmation state
                                 app.confirmation_state = Some(ConfirmationState {
-                                    action: action.clone(),
+                                    kind: ConfirmationKind::Action(action.clone()),
                                     action_description: action_desc,
                                     preview_lines,
                                     file_info,
@@ -489,9 +661,29 @@ async fn run_app(
                                 // Clone action to check type after execution
                                 let action_clone = action.clone();
 
-                                // Execute action directly
-                                match executor.execute(action).await {
-                                    Ok(agents::ActionResult::Success { output }) => {
+                                // Execute action directly, unless its wave already ran
+                                let result = execute_with_clock(
+                                    terminal,
+                                    app,
+                                    &mut executor,
+                                    action,
+                                    parallel_results.remove(&index),
+                                )
+                                .await?;
+                                match result {
+                                    Ok(result @ agents::ActionResult::Success { .. }) => {
+                                        let output = result.output();
+                                        app.add_function_result(
+                                            &action_clone,
+                                            ActionOutcome::Executed,
+                                            &output,
+                                        );
+                                        app.note_file_read(&action_clone, &output);
+                                        app.apply_session_env(&action_clone);
+                                        if app.apply_directory_change(&action_clone) {
+                                            reload_context(app);
+                                        }
+
                                         // Handle ReadFile specially - show contents in chat
                                         match &action_clone {
                                             agents::AgentAction::ReadFile { path } => {
@@ -517,11 +709,16 @@ async fn run_app(
                                                 // Send feedback to model
                                                 let model = app.model.clone();
                                                 let context = app.context.clone();
+                                                let repo_map = app.repo_map.clone();
+                                                let config = app.model_config();
                                                 let tx_clone = tx.clone();
                                                 let tx_done = tx.clone();
 
                                                 tokio::spawn(async move {
-                                                    let config = ModelConfig::default();
+                                                    let context = with_repo_map(
+                                                        repo_map, None, context, &tx_done,
+                                                    )
+                                                    .await;
                                                     let callback: StreamCallback =
                                                         Arc::new(move |chunk| {
                                                             let _ = tx_clone
@@ -538,7 +735,8 @@ async fn run_app(
                                                         )
                                                         .await
                                                     {
-                                                        Ok(_) => {
+                                                        Ok(response) => {
+                                                            record_usage(&response);
                                                             // Clear feedback flags after completion
                                                             let _ = tx_done
                                                                 .send(
@@ -548,13 +746,16 @@ async fn run_app(
                                                                 .await;
                                                         },
                                                         Err(e) => {
-                                                            let _ = tx_done
-                                                                .send(format!("[ERROR]:{}", e))
-                                                                .await;
+                                                            let _ =
+                                                                tx_done.send(error_chunk(&e)).await;
                                                         },
                                                     }
                                                 });
                                             },
+                                            agents::AgentAction::WebSearch { query, .. } => {
+                                                app.set_status(format!("[OK] Searched: {}", query));
+                                                send_search_feedback(app, &output, &tx);
+                                            },
                                             agents::AgentAction::WriteFile { path, content } => {
                                                 app.set_status(format!("[OK] {}", output));
                                                 app.context.add_file(path.clone(), content.clone());
@@ -570,7 +771,7 @@ async fn run_app(
                                                 {
                                                     // Use proper tokenizer for accurate count
                                                     let tokens = count_file_tokens(
-                                                        &content,
+                                                        content.text(),
                                                         &app.model_name,
                                                     );
                                                     app.context.token_count = app
@@ -584,13 +785,23 @@ async fn run_app(
                                             },
                                         }
                                     },
-                                    Ok(agents::ActionResult::Error { error }) => {
+                                    Ok(agents::ActionResult::Error { error, .. }) => {
+                                        app.add_function_result(
+                                            &action_clone,
+                                            ActionOutcome::Failed,
+                                            &error,
+                                        );
                                         app.set_status(format!(
                                             "[FAILED] Action failed: {}",
                                             error
                                         ));
                                     },
                                     Err(e) => {
+                                        app.add_function_result(
+                                            &action_clone,
+                                            ActionOutcome::Failed,
+                                            &format!("Error: {}", e),
+                                        );
                                         app.set_status(format!("[ERROR] Error: {}", e));
                                     },
                                 }
@@ -598,19 +809,49 @@ async fn run_app(
                         }
                     }
                     app.current_response.clear();
+                    continue_after_function_calls(app, &tx);
                 } else if chunk.starts_with("[ERROR]:") {
                     // Error occurred
                     app.is_generating = false;
                     let error = chunk.strip_prefix("[ERROR]:").unwrap_or(&chunk);
                     app.add_message(MessageRole::System, format!("Error: {}", error));
                     app.current_response.clear();
+                } else if let Some(title) = chunk.strip_prefix("[TITLE]:") {
+                    app.set_conversation_title(title.to_string());
+                } else if let Some(summary) = chunk.strip_prefix("[SUMMARY]:") {
+                    show_summary(app, summary.to_string());
+                } else if let Some(error) = chunk.strip_prefix("[SUMMARY_FAILED]:") {
+                    app.set_status(format!("[FAILED] Failed to summarize: {}", error));
+                } else if let Some(json_str) = chunk.strip_prefix("[GENERATED_FILE]:") {
+                    if let Ok(action) = serde_json::from_str(json_str) {
+                        confirm_generated_file(app, action).await;
+                    }
+                } else if let Some(error) = chunk.strip_prefix("[GENERATION_FAILED]:") {
+                    app.set_status(format!("[FAILED] Generation failed: {}", error));
+                } else if let Some(json_str) = chunk.strip_prefix("[LINT]:") {
+                    if let Ok(report) = serde_json::from_str(json_str) {
+                        show_lint_report(app, report);
+                    }
+                } else if let Some(error) = chunk.strip_prefix("[LINT_FAILED]:") {
+                    app.set_status(format!("[FAILED] Lint failed: {}", error));
+                } else if let Some(summary) = chunk.strip_prefix("[FORMATTED]:") {
+                    show_format_summary(app, summary);
+                } else if let Some(json_str) = chunk.strip_prefix("[IMPORTANCE]:") {
+                    if let Ok(ranked) = serde_json::from_str(json_str) {
+                        app.set_file_importance(ranked);
+                    }
+                } else if let Some(json_str) = chunk.strip_prefix("[PULL]:") {
+                    app.pull_progress = serde_json::from_str(json_str).ok();
+                } else if let Some(message) = chunk.strip_prefix("[PULL_DONE]:") {
+                    app.pull_progress = None;
+                    app.set_status(message.to_string());
                 } else if chunk.starts_with("[HARDWARE_STATS]:") {
                     // Hardware stats update
                     if let Some(json_str) = chunk.strip_prefix("[HARDWARE_STATS]:") {
                         if let Ok(stats) =
                             serde_json::from_str::<crate::diagnostics::HardwareStats>(json_str)
                         {
-                            app.hardware_stats = Some(stats);
+                            app.update_hardware_stats(stats);
                         }
                     }
                 } else {
@@ -634,10 +875,50 @@ async fn run_app(
                     if let Ok(stats) =
                         serde_json::from_str::<crate::diagnostics::HardwareStats>(json_str)
                     {
-                        app.hardware_stats = Some(stats);
+                        app.update_hardware_stats(stats);
                     }
                 }
                 break; // Process one update per loop iteration
+            } else if let Some(title) = chunk.strip_prefix("[TITLE]:") {
+                app.set_conversation_title(title.to_string());
+                break;
+            } else if let Some(summary) = chunk.strip_prefix("[SUMMARY]:") {
+                show_summary(app, summary.to_string());
+                break;
+            } else if let Some(error) = chunk.strip_prefix("[SUMMARY_FAILED]:") {
+                app.set_status(format!("[FAILED] Failed to summarize: {}", error));
+                break;
+            } else if let Some(json_str) = chunk.strip_prefix("[GENERATED_FILE]:") {
+                if let Ok(action) = serde_json::from_str(json_str) {
+                    confirm_generated_file(app, action).await;
+                }
+                break;
+            } else if let Some(error) = chunk.strip_prefix("[GENERATION_FAILED]:") {
+                app.set_status(format!("[FAILED] Generation failed: {}", error));
+                break;
+            } else if let Some(json_str) = chunk.strip_prefix("[LINT]:") {
+                if let Ok(report) = serde_json::from_str(json_str) {
+                    show_lint_report(app, report);
+                }
+                break;
+            } else if let Some(error) = chunk.strip_prefix("[LINT_FAILED]:") {
+                app.set_status(format!("[FAILED] Lint failed: {}", error));
+                break;
+            } else if let Some(summary) = chunk.strip_prefix("[FORMATTED]:") {
+                show_format_summary(app, summary);
+                break;
+            } else if let Some(json_str) = chunk.strip_prefix("[IMPORTANCE]:") {
+                if let Ok(ranked) = serde_json::from_str(json_str) {
+                    app.set_file_importance(ranked);
+                }
+                break;
+            } else if let Some(json_str) = chunk.strip_prefix("[PULL]:") {
+                app.pull_progress = serde_json::from_str(json_str).ok();
+                break;
+            } else if let Some(message) = chunk.strip_prefix("[PULL_DONE]:") {
+                app.pull_progress = None;
+                app.set_status(message.to_string());
+                break;
             } else if !app.is_generating {
                 // If we're not generating and it's not a hardware stats message,
                 // put it back for later processing when generation starts
@@ -646,16 +927,72 @@ async fn run_app(
             }
         }
 
+        finish_context_reload(app).await;
+
         // Check for external file system changes (throttled to once per second)
         if last_refresh.elapsed() >= std::time::Duration::from_secs(1) {
+            if watched_dir != app.working_dir {
+                match FileSystemWatcher::new(Path::new(&app.working_dir)) {
+                    Ok(new_watcher) => watcher = new_watcher,
+                    Err(e) => app.set_status(format!("[FAILED] Failed to watch files: {}", e)),
+                }
+                watched_dir = app.working_dir.clone();
+            }
             let events = watcher.check_events();
             if !events.is_empty() {
-                // Reload the context to pick up external changes
+                let mut changed = Vec::new();
+                let mut deleted = Vec::new();
+                for event in &events {
+                    match event {
+                        FileEvent::Created(path) | FileEvent::Modified(path) => {
+                            changed.push(path.clone())
+                        },
+                        FileEvent::Deleted(path) => deleted.push(path.clone()),
+                        FileEvent::Renamed { from, to } => {
+                            deleted.push(from.clone());
+                            changed.push(to.clone());
+                        },
+                        FileEvent::HeadChanged => {},
+                    }
+                }
+                app.update_conflicts(&changed, &deleted);
+
+                // Keep the repository map in sync without a full rebuild
+                if let Some(repo_map) = app.repo_map.clone() {
+                    let (changed, deleted) = (changed.clone(), deleted.clone());
+                    tokio::spawn(async move {
+                        let mut repo_map = repo_map.lock().await;
+                        let _ = repo_map.update_incremental(&changed, &deleted).await;
+                    });
+                }
+
+                // A checkout swaps the files under us, so say why the context changed
+                let head_changed = events.iter().any(|e| matches!(e, FileEvent::HeadChanged));
+                if head_changed {
+                    if let Some(branch) = app.refresh_branch() {
+                        app.add_message(
+                            MessageRole::System,
+                            format!("[Branch changed to: {}. Context refreshed.]", branch),
+                        );
+                        app.refresh_stashes();
+                    }
+                }
+
+                // Pick up external changes: a checkout reloads everything, otherwise
+                // only the changed files are read
                 if let Ok(loader) = ContextLoader::new() {
-                    if let Ok(new_context) = loader.load(Path::new(".")) {
-                        // Update the context while preserving conversation history
-                        app.context.files = new_context.files;
-                        app.context.token_count = new_context.token_count;
+                    let loader = loader.with_additional_roots(app.extra_roots.clone());
+                    let root = PathBuf::from(&app.working_dir);
+                    let refreshed = if head_changed {
+                        loader
+                            .load(&root)
+                            .map(|new_context| app.apply_refreshed_context(new_context))
+                    } else {
+                        loader
+                            .update_context(&root, &mut app.context, &changed, &deleted)
+                            .map(|previous| app.apply_context_updates(&previous))
+                    };
+                    if refreshed.is_ok() {
                         app.set_status("[OK] Files refreshed from disk");
                     }
                 }
@@ -682,6 +1019,14 @@ async fn run_app(
     Ok(())
 }
 
+/// `[ERROR]:` chunk for a failed request, with the recovery hint on its own line
+fn error_chunk(error: &anyhow::Error) -> String {
+    match recovery_hint_for(error) {
+        Some(hint) => format!("[ERROR]:{}\n{}{}", error, HINT_PREFIX, hint),
+        None => format!("[ERROR]:{}", error),
+    }
+}
+
 /// Extract the model's reading intent from the text before [FILE_READ]
 fn extract_reading_intent(text: &str) -> Option<String> {
     // Find the FILE_READ action block
@@ -720,7 +1065,7 @@ fn extract_reading_intent(text: &str) -> Option<String> {
     }
 }
 
-async fn handle_command(app: &mut App, command: &str) -> Result<()> {
+async fn handle_command(app: &mut App, command: &str, tx: &mpsc::Sender<String>) -> Result<()> {
     let parts: Vec<&str> = command.split_whitespace().collect();
 
     match parts.get(0).map(|s| *s) {
@@ -730,21 +1075,16 @@ async fn handle_command(app: &mut App, command: &str) -> Result<()> {
         },
         Some("clear") => {
             app.messages.clear();
+            app.selected_message = None;
+            app.focused_code_block = None;
+            app.collapsed_blocks.clear();
+            app.action_outcomes.clear();
             app.set_status("Chat cleared");
         },
         Some("model") => {
-            if let Some(model_name) = parts.get(1) {
-                // Parse the model name (could be provider/model or just model)
-                let model_id = if model_name.contains('/') {
-                    model_name.to_string()
-                } else {
-                    // Assume ollama if no provider specified
-                    format!("ollama/{}", model_name)
-                };
-
-                app.set_status(format!("Switching to model: {}...", model_id));
-
-                // Try to create the new model
+            if parts.get(1) == Some(&"list") {
+                show_model_list(app).await;
+            } else if let Some(model_name) = parts.get(1) {
                 use crate::app::load_config;
                 use crate::models::ModelFactory;
 
@@ -756,6 +1096,13 @@ async fn handle_command(app: &mut App, command: &str) -> Result<()> {
                     },
                 };
 
+                // Resolve aliases and partial Ollama names (could be provider/model or just model)
+                let model_id = ModelFactory::resolve_model_id(model_name, &config).await;
+
+                app.set_status(format!("Switching to model: {}...", model_id));
+
+                // Try to create the new model
+
                 // Create new model asynchronously
                 let model_id_clone = model_id.clone();
                 let new_model = tokio::task::spawn(async move {
@@ -766,7 +1113,7 @@ async fn handle_command(app: &mut App, command: &str) -> Result<()> {
                     Ok(Ok(model)) => {
                         // Update the model and model name
                         *app.model.lock().await = model;
-                        app.model_name = model_id.clone();
+                        app.set_model_name(model_id.clone());
                         app.set_status(format!("Switched to model: {}", model_id));
 
                         // Save the model preference to session
@@ -792,25 +1139,68 @@ async fn handle_command(app: &mut App, command: &str) -> Result<()> {
         Some("refresh") | Some("r") => {
             // Manually refresh file context from disk
             match ContextLoader::new() {
-                Ok(loader) => match loader.load(Path::new(".")) {
-                    Ok(new_context) => {
-                        app.context.files = new_context.files;
-                        app.context.token_count = new_context.token_count;
-                        app.set_status(format!(
-                            "[OK] Refreshed: {} files, ~{} tokens",
-                            app.context.files.len(),
-                            app.context.token_count
-                        ));
-                    },
-                    Err(e) => {
-                        app.set_status(format!("[FAILED] Failed to refresh: {}", e));
-                    },
+                Ok(loader) => {
+                    let loader = loader.with_additional_roots(app.extra_roots.clone());
+                    match loader.load(Path::new(".")) {
+                        Ok(new_context) => {
+                            app.apply_refreshed_context(new_context);
+                            let mut status = format!(
+                                "[OK] Refreshed: {} files, ~{} tokens",
+                                app.context.files.len(),
+                                app.context.token_count
+                            );
+                            let stripped = loader.stripped_comment_tokens();
+                            if stripped > 0 {
+                                status.push_str(&format!(
+                                    " ({} saved by stripping comments)",
+                                    stripped
+                                ));
+                            }
+                            app.set_status(status);
+                        },
+                        Err(e) => {
+                            app.set_status(format!("[FAILED] Failed to refresh: {}", e));
+                        },
+                    }
                 },
                 Err(e) => {
                     app.set_status(format!("[FAILED] Failed to create loader: {}", e));
                 },
             }
         },
+        Some("context-diff") => match &app.last_context_diff {
+            Some(diff) => {
+                let message = format!("{}\n{}", diff.summary(), diff.details());
+                app.add_message(MessageRole::System, message);
+            },
+            None => app.set_status("No context changes since the session started"),
+        },
+        Some("conflicts") => show_conflicts(app),
+        Some("stash-list") => show_stash_list(app),
+        Some("stash-show") => match parts.get(1) {
+            Some(stash_ref) => {
+                let action = agents::AgentAction::GitStashShow {
+                    stash_ref: stash_ref.to_string(),
+                };
+                match agents::execute_action(
+                    &action,
+                    &app.project_root(),
+                    &app.session_env,
+                    &app.config,
+                )
+                .await?
+                {
+                    agents::ActionResult::Success { stdout: output, .. } => app.add_message(
+                        MessageRole::System,
+                        format!("{}:\n```diff\n{}\n```", stash_ref, output.trim_end()),
+                    ),
+                    agents::ActionResult::Error { error, .. } => {
+                        app.set_status(format!("[FAILED] {}", error))
+                    },
+                }
+            },
+            None => app.set_status("Usage: :stash-show <ref>, e.g. :stash-show stash@{0}"),
+        },
         Some("save") => {
             // Save conversation with optional name
             let name = parts.get(1).map(|s| s.to_string());
@@ -876,7 +1266,10 @@ async fn handle_command(app: &mut App, command: &str) -> Result<()> {
                         } else {
                             let list = conversations
                                 .iter()
-                                .map(|c| c.summary())
+                                .map(|c| match &c.summary {
+                                    Some(summary) => format!("{}\n    {}", c.summary(), summary),
+                                    None => c.summary(),
+                                })
                                 .collect::<Vec<_>>()
                                 .join("\n");
                             app.add_message(
@@ -891,19 +1284,514 @@ async fn handle_command(app: &mut App, command: &str) -> Result<()> {
                 }
             }
         },
+        Some("merge") => {
+            // Combine two saved conversations into a new one
+            match (parts.get(1), parts.get(2)) {
+                (Some(id1), Some(id2)) => {
+                    if let Some(ref manager) = app.conversation_manager {
+                        match manager.merge_conversations(id1, id2) {
+                            Ok(merged) => {
+                                app.set_status(format!(
+                                    "Merged into {} - use :load {} to open it",
+                                    merged.id, merged.id
+                                ));
+                            },
+                            Err(e) => {
+                                app.set_status(format!("Failed to merge: {}", e));
+                            },
+                        }
+                    }
+                },
+                _ => app.set_status("Usage: :merge <id1> <id2>"),
+            }
+        },
+        Some("delete") => {
+            // Ask for confirmation before trashing a conversation
+            match (parts.get(1), &app.conversation_manager) {
+                (Some(id), Some(manager)) => match manager.load_conversation(id) {
+                    Ok(conv) => {
+                        request_conversation_trash(
+                            app,
+                            format!("Delete conversation {}", id),
+                            vec![conv],
+                        );
+                    },
+                    Err(_) => app.set_status(format!("Conversation not found: {}", id)),
+                },
+                (None, _) => app.set_status("Usage: :delete <id>"),
+                _ => {},
+            }
+        },
+        Some("cleanup") => {
+            // Trash unpinned conversations older than N days (default 30)
+            let days = parts
+                .get(1)
+                .map(|s| s.trim_start_matches("days="))
+                .and_then(|s| s.parse::<i64>().ok())
+                .unwrap_or(30);
+
+            if let Some(ref manager) = app.conversation_manager {
+                match manager.conversations_older_than(days) {
+                    Ok(old) if old.is_empty() => {
+                        app.set_status(format!("No conversations older than {} days", days));
+                    },
+                    Ok(old) => {
+                        request_conversation_trash(
+                            app,
+                            format!("Trash {} conversations older than {} days", old.len(), days),
+                            old,
+                        );
+                    },
+                    Err(e) => {
+                        app.set_status(format!("Failed to list conversations: {}", e));
+                    },
+                }
+            }
+        },
+        Some("pin") => {
+            // Toggle whether the current conversation is protected from cleanup
+            if let Some(ref mut conv) = app.current_conversation {
+                conv.pinned = !conv.pinned;
+                let pinned = conv.pinned;
+                app.set_status(if pinned {
+                    "Conversation pinned"
+                } else {
+                    "Conversation unpinned"
+                });
+            }
+        },
+        Some("search-sessions") => {
+            // Full-text search across saved conversations
+            let query = parts[1..].join(" ");
+            if query.is_empty() {
+                app.set_status("Usage: :search-sessions <query>");
+            } else if let Some(ref manager) = app.conversation_manager {
+                match manager.search_conversations(&query) {
+                    Ok(matches) if matches.is_empty() => {
+                        app.set_status(format!("No conversations match '{}'", query));
+                    },
+                    Ok(matches) => {
+                        let list = matches
+                            .iter()
+                            .map(|m| {
+                                format!(
+                                    "{} | {} (message {})\n    {}",
+                                    m.conversation_id,
+                                    m.title,
+                                    m.matching_message_index + 1,
+                                    m.snippet
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        app.add_message(
+                            MessageRole::System,
+                            format!(
+                                "Conversations matching '{}':\n{}\n\nUse :load <id> to open one",
+                                query, list
+                            ),
+                        );
+                    },
+                    Err(e) => {
+                        app.set_status(format!("Failed to search conversations: {}", e));
+                    },
+                }
+            }
+        },
+        Some("repomap") => {
+            // Show the ranked repository map, optionally with a token budget
+            let budget = parts
+                .iter()
+                .position(|p| *p == "--tokens")
+                .and_then(|i| parts.get(i + 1))
+                .and_then(|n| n.parse::<usize>().ok())
+                .unwrap_or(1024);
+
+            app.set_status("Building repository map...");
+            match generate_repo_map(Path::new(&app.working_dir), &[], budget).await {
+                Ok(map) if map.trim().is_empty() => {
+                    app.set_status("No supported source files found for the repository map");
+                },
+                Ok(map) => {
+                    app.add_message(
+                        MessageRole::System,
+                        format!("Repository map (~{} tokens):\n{}", budget, map),
+                    );
+                    app.clear_status();
+                },
+                Err(e) => {
+                    app.set_status(format!("[FAILED] Failed to build repository map: {}", e));
+                },
+            }
+        },
+        Some("repomap-dot") => {
+            // Export the dependency graph as Graphviz DOT (pipe to `dot -Tsvg`)
+            match ensure_repo_map(app).await {
+                Ok(repo_map) => {
+                    let mut repo_map = repo_map.lock().await;
+                    // Ranking assigns the importance scores used as node labels
+                    let _ = repo_map.generate_map(&[], &[], None).await;
+                    let ranked = repo_map.get_ranked_files();
+                    let dot = repo_map.to_dot();
+                    drop(repo_map);
+                    app.set_file_importance(ranked);
+                    app.add_message(MessageRole::System, dot);
+                },
+                Err(e) => {
+                    app.set_status(format!("[FAILED] Failed to build repository map: {}", e));
+                },
+            }
+        },
+        Some("pull") => {
+            let Some(model_name) = parts.get(1) else {
+                app.set_status("Usage: :pull <model>");
+                return Ok(());
+            };
+            if app.pull_progress.is_some() {
+                app.set_status("A model download is already in progress");
+                return Ok(());
+            }
+
+            // Download in the background, reporting progress in the status bar
+            let model = model_name.trim_start_matches("ollama/").to_string();
+            app.pull_progress = Some(PullProgress {
+                status: format!("pulling {}", model),
+                ..Default::default()
+            });
+
+            let tx = tx.clone();
+            let progress_tx = tx.clone();
+            tokio::spawn(async move {
+                let callback: PullProgressCallback = Arc::new(move |update: &PullProgress| {
+                    if let Ok(json) = serde_json::to_string(update) {
+                        let _ = progress_tx.try_send(format!("[PULL]:{}", json));
+                    }
+                });
+
+                let message = match pull_model_with_progress(&model, callback).await {
+                    Ok(()) => format!("[OK] Pulled {} - switch with :model {}", model, model),
+                    Err(e) => format!("[FAILED] {}", e),
+                };
+                let _ = tx.send(format!("[PULL_DONE]:{}", message)).await;
+            });
+        },
+        Some("sysprompt") => {
+            // Keep the prompt text as typed, including newlines
+            let prompt = command
+                .split_once(char::is_whitespace)
+                .map(|(_, rest)| rest.trim())
+                .unwrap_or_default();
+            if prompt.is_empty() {
+                app.set_status("Usage: :sysprompt <text>");
+            } else {
+                app.set_custom_system_prompt(Some(prompt.to_string()));
+                app.set_status("Custom system prompt set");
+            }
+        },
+        Some("sysprompt-edit") => {
+            app.input = format!(":sysprompt {}", app.effective_system_prompt());
+            app.cursor_position = app.input.len();
+            app.set_status("Edit the system prompt and press Enter");
+        },
+        Some("sysprompt-reset") => {
+            app.set_custom_system_prompt(None);
+            app.set_status("System prompt reset to the config default");
+        },
+        Some("summarize") => {
+            if app.build_message_history().is_empty() {
+                app.set_status("Nothing to summarize yet");
+            } else {
+                app.set_status("Summarizing conversation...");
+                spawn_summary_generation(app, tx.clone());
+            }
+        },
+        Some("summary") => {
+            match app
+                .current_conversation
+                .as_ref()
+                .and_then(|c| c.summary.clone())
+            {
+                Some(summary) => app.add_message(
+                    MessageRole::System,
+                    format!("Conversation summary:\n{}", summary),
+                ),
+                None => app.set_status("No summary yet - generate one with :summarize"),
+            }
+        },
+        Some("gen-tests") => match parts.get(1) {
+            Some(file) if Path::new(file).is_file() => {
+                let action = agents::AgentAction::GenerateTests {
+                    source_file: file.to_string(),
+                    test_framework: parts.get(2).map(|f| f.to_string()),
+                };
+                spawn_file_generation(app, action, tx.clone());
+            },
+            Some(file) => app.set_status(format!("[ERROR] File not found: {}", file)),
+            None => app.set_status("Usage: :gen-tests <file> [framework]"),
+        },
+        Some("gen-docs") => match parts.get(1) {
+            Some(file) if Path::new(file).is_file() => {
+                let format = match parts.get(2) {
+                    Some(name) => agents::DocFormat::from_name(name),
+                    None => Ok(agents::DocFormat::for_file(file)),
+                };
+                match format {
+                    Ok(format) => {
+                        let action = agents::AgentAction::GenerateDocs {
+                            source_file: file.to_string(),
+                            format,
+                        };
+                        spawn_file_generation(app, action, tx.clone());
+                    },
+                    Err(e) => app.set_status(format!("[ERROR] {}", e)),
+                }
+            },
+            Some(file) => app.set_status(format!("[ERROR] File not found: {}", file)),
+            None => app.set_status("Usage: :gen-docs <file> [rustdoc|jsdoc|sphinx|markdown]"),
+        },
+        Some("lint") => {
+            let fix = parts.contains(&"--fix");
+            let path = parts[1..]
+                .iter()
+                .find(|p| !p.starts_with("--"))
+                .unwrap_or(&".")
+                .to_string();
+            spawn_lint(app, path, fix, tx.clone());
+        },
+        Some("format") => {
+            // Without a file, format every loaded context file that has a formatter
+            let paths: Vec<String> = match parts.get(1) {
+                Some(file) => vec![file.to_string()],
+                None => {
+                    let mut paths: Vec<String> = app
+                        .context
+                        .files
+                        .keys()
+                        .filter(|p| agents::can_format(p))
+                        .cloned()
+                        .collect();
+                    paths.sort();
+                    paths
+                },
+            };
+            if paths.is_empty() {
+                app.set_status("No loaded files have a formatter");
+            } else {
+                app.set_status(format!("Formatting {} file(s)...", paths.len()));
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    let summary = agents::format_files(&paths)
+                        .await
+                        .unwrap_or_else(|e| format!("Format failed: {}", e));
+                    let _ = tx.send(format!("[FORMATTED]:{}", summary)).await;
+                });
+            }
+        },
+        Some("preset") => match (parts.get(1).copied(), parts.get(2)) {
+            (Some("save"), Some(name)) => {
+                let preset = app.model_config().parameters();
+                match crate::app::save_preset(name, &preset) {
+                    Ok(()) => {
+                        app.set_status(format!(
+                            "[OK] Saved preset {}: {}",
+                            name,
+                            preset.describe_parameters()
+                        ));
+                        app.config.presets.insert(name.to_string(), preset);
+                    },
+                    Err(e) => app.set_status(format!("[ERROR] Failed to save preset: {}", e)),
+                }
+            },
+            (Some("load"), Some(name)) => match app.config.presets.get(*name) {
+                Some(preset) => {
+                    app.set_status(format!(
+                        "[OK] Using preset {}: {}",
+                        name,
+                        preset.describe_parameters()
+                    ));
+                    app.active_preset = Some(name.to_string());
+                },
+                None => app.set_status(format!("[ERROR] Unknown preset: {}", name)),
+            },
+            (Some("list"), _) => {
+                let mut names: Vec<&String> = app.config.presets.keys().collect();
+                names.sort();
+                let lines: Vec<String> = names
+                    .into_iter()
+                    .map(|name| {
+                        let marker = if app.active_preset.as_ref() == Some(name) {
+                            " (active)"
+                        } else {
+                            ""
+                        };
+                        format!(
+                            "  {}{}: {}",
+                            name,
+                            marker,
+                            app.config.presets[name].describe_parameters()
+                        )
+                    })
+                    .collect();
+                app.add_message(
+                    MessageRole::System,
+                    format!("Model presets:\n{}", lines.join("\n")),
+                );
+            },
+            _ => app.set_status("Usage: :preset save|load <name> or :preset list"),
+        },
+        Some("audit") => {
+            // Accepts `:audit 50` and `:audit tail=50`
+            let count = parts
+                .get(1)
+                .map(|arg| arg.trim_start_matches("tail="))
+                .map_or(Ok(20), |n| n.parse::<usize>());
+            match count {
+                Ok(count) => show_audit_log(app, count),
+                Err(_) => app.set_status("Usage: :audit [tail=N]"),
+            }
+        },
+        Some("rollback") => {
+            match parts
+                .get(1)
+                .map(|id| id.to_string())
+                .or_else(|| app.last_snapshot_id.clone())
+            {
+                Some(to_snapshot_id) => {
+                    let action = agents::AgentAction::Rollback { to_snapshot_id };
+                    match agents::execute_action(
+                        &action,
+                        &app.project_root(),
+                        &app.session_env,
+                        &app.config,
+                    )
+                    .await?
+                    {
+                        agents::ActionResult::Success { stdout: output, .. } => {
+                            app.set_status(format!("[OK] {}", output))
+                        },
+                        agents::ActionResult::Error { error, .. } => {
+                            app.set_status(format!("[FAILED] Rollback failed: {}", error))
+                        },
+                    }
+                },
+                None => app.set_status("No snapshot to roll back to"),
+            }
+        },
+        Some("timestamps") => match parts.get(1).copied() {
+            Some("on") => {
+                app.config.ui.show_timestamps = true;
+                app.set_status("Timestamps shown");
+            },
+            Some("off") => {
+                app.config.ui.show_timestamps = false;
+                app.set_status("Timestamps hidden");
+            },
+            _ => app.set_status("Usage: :timestamps on|off"),
+        },
+        Some("vars") => match parts.get(1).copied() {
+            Some("set") => {
+                let (Some(name), Some(_)) = (parts.get(2), parts.get(3)) else {
+                    app.set_status("Usage: :vars set <name> <value>");
+                    return Ok(());
+                };
+                let value = parts[3..].join(" ");
+                app.config
+                    .prompt_variables
+                    .insert(name.to_string(), value.clone());
+                app.set_status(format!("{{{{{}}}}} = {}", name, value));
+            },
+            Some("list") | None => {
+                let mut vars: Vec<_> = app.prompt_variables().into_iter().collect();
+                vars.sort();
+                let lines = vars
+                    .iter()
+                    .map(|(name, value)| format!("  {{{{{}}}}} = {}", name, value))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                app.add_message(MessageRole::System, format!("Prompt variables:\n{}", lines));
+            },
+            Some(other) => {
+                app.set_status(format!("Unknown vars subcommand: {}", other));
+            },
+        },
+        Some("usage") => {
+            // Summarize token usage for the last N days (default 7)
+            let days = parts
+                .get(1)
+                .map(|s| s.trim_start_matches("days="))
+                .and_then(|s| s.parse::<i64>().ok())
+                .unwrap_or(7);
+
+            let summary = UsageReport::summarize(&UsageReport::load(), days);
+            if summary.is_empty() {
+                app.set_status(format!("No token usage recorded in the last {} days", days));
+            } else {
+                let mut total_tokens = 0;
+                let mut total_cost = 0.0;
+                let lines = summary
+                    .iter()
+                    .map(|u| {
+                        let cost = estimate_cost(&u.model, u.prompt_tokens, u.completion_tokens);
+                        total_tokens += u.total_tokens();
+                        total_cost += cost;
+                        format!(
+                            "  {} {} - {} in / {} out (${:.4})",
+                            u.date, u.model, u.prompt_tokens, u.completion_tokens, cost
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                app.add_message(
+                    MessageRole::System,
+                    format!(
+                        "Token usage (last {} days):\n{}\n\nTotal: {} tokens, est. ${:.4}",
+                        days, lines, total_tokens, total_cost
+                    ),
+                );
+            }
+        },
         Some("help") | Some("h") => {
             app.add_message(
                 MessageRole::System,
                 "Commands:\n\
                  :quit/:q - Quit the application\n\
                  :clear - Clear chat history\n\
-                 :model [name] - Switch model or show current\n\
+                 :model [name] - Switch model (aliases and partial names work) or show current\n\
+                 :model list - Show model aliases and installed Ollama models\n\
+                 :preset save|load <name> - Save or apply model parameters (:preset list)\n\
+                 :pull <model> - Download an Ollama model\n\
+                 :sysprompt <text> - Set the system prompt for this conversation\n\
+                 :sysprompt-edit - Edit the current system prompt\n\
+                 :sysprompt-reset - Revert to the configured system prompt\n\
+                 :timestamps on|off - Show or hide message timestamps\n\
+                 :vars [list] - Show system prompt variables\n\
+                 :vars set <name> <value> - Set a variable for this session\n\
                  :sidebar/:sb - Toggle file sidebar\n\
                  :refresh/:r - Refresh file context from disk\n\
+                 :context-diff - Show the files the last refresh added, changed or removed\n\
+                 :stash-list - List git stashes\n\
+                 :stash-show <ref> - Show the changes in a stash\n\
+                 :conflicts - Show files with merge conflicts and ask for a resolution\n\
                  :save [name] - Save current conversation\n\
                  :load [name] - Load a conversation\n\
                  :list - List saved conversations\n\
+                 :summarize - Summarize this conversation\n\
+                 :summary - Show the conversation summary\n\
+                 :gen-tests <file> [framework] - Generate unit tests for a file\n\
+                 :gen-docs <file> [format] - Add doc comments to a file\n\
+                 :lint [--fix] [path] - Run the project's linter\n\
+                 :format [file] - Format a file, or all loaded files\n\
+                 :rollback [id] - Restore files from before the last batch of actions\n\
+                 :audit [tail=20] - Show the last executed actions from the audit log\n\
+                 :search-sessions <query> - Search saved conversations\n\
+                 :merge <id1> <id2> - Merge two conversations into a new one\n\
+                 :delete <id> - Move a conversation to the trash\n\
+                 :cleanup [days] - Trash conversations older than N days (default 30)\n\
+                 :pin - Toggle pin on the current conversation\n\
                  :stats/:diag - Toggle hardware diagnostics\n\
+                 :usage [days] - Show token usage (default 7 days)\n\
+                 :repomap [--tokens N] - Show the ranked repository map\n\
+                 :repomap-dot - Export the dependency graph as Graphviz DOT\n\
                  :help/:h - Show this help\n\
                  \n\
                  Keys:\n\
@@ -911,7 +1799,13 @@ async fn handle_command(app: &mut App, command: &str) -> Result<()> {
                  Esc - Return to normal mode / Close diagnostics\n\
                  : - Enter command mode\n\
                  Tab - Toggle sidebar\n\
+                 Alt+Left/Right - Scroll code blocks horizontally\n\
+                 Alt+Up/Down - Select a message\n\
+                 Click/double-click - Select/read a sidebar file\n\
+                 c - Collapse/expand the selected message's code block (empty input, message has code)\n\
+                 Alt+C - Copy the selected (or latest) code block\n\
                  F2 - Toggle hardware diagnostics\n\
+                 Ctrl+L - Clear and redraw the screen\n\
                  Ctrl+C - Quit"
                     .to_string(),
             );
@@ -924,6 +1818,774 @@ async fn handle_command(app: &mut App, command: &str) -> Result<()> {
     Ok(())
 }
 
+/// Generate a short title for the current conversation in the background
+/// Falls back to the start of the first user message if the model is slow or unavailable
+fn spawn_title_generation(app: &App, tx: mpsc::Sender<String>) {
+    let first_user = app.messages.iter().find(|m| m.role == MessageRole::User);
+    let first_assistant = app
+        .messages
+        .iter()
+        .find(|m| m.role == MessageRole::Assistant);
+    let (Some(user), Some(assistant)) = (first_user, first_assistant) else {
+        return;
+    };
+
+    let fallback: String = user.content.chars().take(40).collect();
+    let messages = vec![
+        user.clone(),
+        assistant.clone(),
+        ChatMessage {
+            role: MessageRole::User,
+            content: TITLE_PROMPT.to_string(),
+            timestamp: chrono::Local::now(),
+        },
+    ];
+    let model = app.model.clone();
+    let context = ProjectContext::new(app.context.root_path.clone());
+
+    tokio::spawn(async move {
+        let config = ModelConfig {
+            max_tokens: Some(10),
+            system_prompt: None,
+            ..ModelConfig::default()
+        };
+        let generate = async {
+            let mut model = model.lock().await;
+            model.chat(&messages, &context, &config, None).await
+        };
+
+        let title = match tokio::time::timeout(std::time::Duration::from_secs(3), generate).await {
+            Ok(Ok(response)) => {
+                record_usage(&response);
+                response
+                    .content
+                    .lines()
+                    .next()
+                    .map(|line| line.trim().trim_matches('"').trim().to_string())
+                    .filter(|line| !line.is_empty())
+            },
+            _ => None,
+        };
+
+        let _ = tx
+            .send(format!("[TITLE]:{}", title.unwrap_or(fallback)))
+            .await;
+    });
+}
+
+/// Preview lines and file details shown when confirming an action in the project at `root`
+async fn confirmation_preview(
+    root: &Path,
+    action: &agents::AgentAction,
+) -> (Vec<String>, Option<FileInfo>) {
+    match action {
+        agents::AgentAction::WriteFile { path, content } => {
+            let lines: Vec<String> = content.lines().take(5).map(|s| s.to_string()).collect();
+            let info = FileInfo {
+                path: path.clone(),
+                size: content.len(),
+                exists: Path::new(path).exists(),
+                language: detect_language(path),
+            };
+            (lines, Some(info))
+        },
+        agents::AgentAction::SearchReplace {
+            pattern,
+            replacement,
+            path_filter,
+            regex,
+        } => {
+            // List the files that would change
+            let files =
+                agents::affected_files(root, pattern, replacement, path_filter.as_deref(), *regex)
+                    .await
+                    .unwrap_or_else(|e| vec![e.to_string()]);
+            (files, None)
+        },
+        agents::AgentAction::HttpRequest { headers, body, .. } => {
+            let lines = headers
+                .iter()
+                .map(|(name, value)| format!("{}: {}", name, value))
+                .chain(
+                    body.iter()
+                        .flat_map(|b| b.lines().take(5).map(String::from)),
+                )
+                .collect();
+            (lines, None)
+        },
+        _ => (vec![], None),
+    }
+}
+
+/// Copy the focused code block, reporting where it went
+fn copy_code_block(app: &mut App) {
+    let Some(code) = app.code_block_to_copy() else {
+        app.set_status("No code block to copy");
+        return;
+    };
+    match copy_to_clipboard(&code) {
+        Ok(CopyTarget::Clipboard) => app.set_status("Copied!"),
+        Ok(CopyTarget::File(path)) => app.set_status(format!(
+            "No clipboard available - code block written to {}",
+            path.display()
+        )),
+        Err(e) => app.set_status(format!("[FAILED] Failed to copy: {}", e)),
+    }
+}
+
+/// Select the clicked sidebar file; double-clicking it reads the file
+async fn handle_sidebar_click(app: &mut App, column: u16, row: u16, tx: &mpsc::Sender<String>) {
+    let clicked = app
+        .layout_areas
+        .lock()
+        .ok()
+        .and_then(|areas| areas.sidebar_file_at(column, row));
+    let Some((index, path)) = clicked else {
+        return;
+    };
+    app.sidebar_state.selected_file = index;
+    app.sidebar_state.table_state.select(Some(index));
+
+    let now = std::time::Instant::now();
+    let double_click = app
+        .last_sidebar_click
+        .is_some_and(|(last, at)| last == index && now.duration_since(at) <= DOUBLE_CLICK_INTERVAL);
+    if !double_click || app.is_generating {
+        app.last_sidebar_click = Some((index, now));
+        return;
+    }
+    app.last_sidebar_click = None;
+
+    let action = agents::AgentAction::ReadFile { path };
+    let mut executor = ModeAwareExecutor::new(app.operation_mode).with_config(app.config.clone());
+    match executor.execute(action.clone()).await {
+        Ok(agents::ActionResult::Success { stdout: output, .. }) => {
+            handle_action_success(app, &action, output, tx).await;
+        },
+        Ok(agents::ActionResult::Error { error, .. }) => {
+            app.set_status(format!("[FAILED] Action failed: {}", error));
+        },
+        Err(e) => {
+            app.set_status(format!("[ERROR] Error: {}", e));
+        },
+    }
+}
+
+/// Summarize the conversation in the background, reporting back over `tx`
+fn spawn_summary_generation(app: &App, tx: mpsc::Sender<String>) {
+    let mut messages = app.build_message_history();
+    messages.push(ChatMessage {
+        role: MessageRole::User,
+        content: SUMMARY_PROMPT.to_string(),
+        timestamp: chrono::Local::now(),
+    });
+    let model = app.model.clone();
+    let context = ProjectContext::new(app.context.root_path.clone());
+
+    tokio::spawn(async move {
+        let config = ModelConfig {
+            max_tokens: Some(150),
+            system_prompt: None,
+            ..ModelConfig::default()
+        };
+        let result = {
+            let mut model = model.lock().await;
+            model.chat(&messages, &context, &config, None).await
+        };
+
+        let message = match result {
+            Ok(response) => {
+                record_usage(&response);
+                format!("[SUMMARY]:{}", response.content.trim())
+            },
+            Err(e) => format!("[SUMMARY_FAILED]:{}", e),
+        };
+        let _ = tx.send(message).await;
+    });
+}
+
+/// Run a GenerateTests or GenerateDocs action in the background, reporting back over `tx`
+fn spawn_file_generation(app: &mut App, action: agents::AgentAction, tx: mpsc::Sender<String>) {
+    let executor = ModeAwareExecutor::new(app.operation_mode);
+    app.set_status(format!("{}...", executor.describe_action(&action)));
+    let model = app.model.clone();
+    let context = app.context.clone();
+
+    tokio::spawn(async move {
+        let result = {
+            let mut model = model.lock().await;
+            match action {
+                agents::AgentAction::GenerateTests {
+                    source_file,
+                    test_framework,
+                } => {
+                    agents::generate_tests(
+                        model.as_mut(),
+                        &context,
+                        &source_file,
+                        test_framework.as_deref(),
+                    )
+                    .await
+                },
+                agents::AgentAction::GenerateDocs {
+                    source_file,
+                    format,
+                } => agents::generate_docs(model.as_mut(), &context, &source_file, format).await,
+                other => Err(anyhow::anyhow!("Not a generation action: {:?}", other)),
+            }
+        };
+
+        let message = match result.and_then(|action| Ok(serde_json::to_string(&action)?)) {
+            Ok(json) => format!("[GENERATED_FILE]:{}", json),
+            Err(e) => format!("[GENERATION_FAILED]:{}", e),
+        };
+        let _ = tx.send(message).await;
+    });
+}
+
+/// Show a generated file (or its diff, for an existing file) in the chat and ask before writing it
+async fn confirm_generated_file(app: &mut App, action: agents::AgentAction) {
+    let agents::AgentAction::WriteFile { path, content } = &action else {
+        return;
+    };
+    let original = std::fs::read_to_string(path).ok();
+    let (review, preview_lines) = match original {
+        Some(original) => {
+            let diff = agents::line_diff(&original, content);
+            (format!("```diff\n{}\n```", diff.join("\n")), diff)
+        },
+        None => (
+            format!(
+                "```{}\n{}```",
+                detect_language(path).unwrap_or_default(),
+                content
+            ),
+            confirmation_preview(&app.project_root(), &action).await.0,
+        ),
+    };
+    app.add_message(
+        MessageRole::System,
+        format!("Generated {} for review:\n\n{}", path, review),
+    );
+
+    let executor = ModeAwareExecutor::new(app.operation_mode).with_config(app.config.clone());
+    app.confirmation_state = Some(ConfirmationState {
+        kind: ConfirmationKind::Action(action.clone()),
+        action_description: executor.describe_action(&action),
+        preview_lines,
+        file_info: confirmation_preview(&app.project_root(), &action).await.1,
+        allow_always: false,
+    });
+    app.pending_action = Some(action);
+    app.pending_executor = Some(executor);
+}
+
+/// Execute an action, redrawing every second so the status bar shows its elapsed time
+/// Returns `wave_result` instead when the action already ran in a parallel wave
+async fn execute_with_clock(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    app: &mut App,
+    executor: &mut ModeAwareExecutor,
+    action: agents::AgentAction,
+    wave_result: Option<Result<agents::ActionResult>>,
+) -> Result<Result<agents::ActionResult>> {
+    if let Some(result) = wave_result {
+        return Ok(result);
+    }
+    app.running_action = Some((executor.describe_action(&action), std::time::Instant::now()));
+    let execution = executor.execute(action);
+    tokio::pin!(execution);
+    let mut ticks = tokio::time::interval(std::time::Duration::from_secs(1));
+
+    let result = loop {
+        tokio::select! {
+            result = &mut execution => break Ok(result),
+            _ = ticks.tick() => {
+                if let Err(e) = terminal.draw(|f| render_ui(f, app)) {
+                    break Err(e.into());
+                }
+            },
+        }
+    };
+    app.running_action = None;
+    result
+}
+
+/// Run a wave of independent actions concurrently, keyed by batch index
+/// Runs nothing when any action in the wave needs confirmation or is
+/// destructive, so the sequential path's checks still apply
+async fn run_parallel_wave(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    app: &mut App,
+    executor: &ModeAwareExecutor,
+    batch: &[agents::AgentAction],
+    wave: &[usize],
+) -> Result<Vec<(usize, Result<agents::ActionResult>)>> {
+    let actions: Vec<_> = wave.iter().map(|&i| batch[i].clone()).collect();
+    if actions
+        .iter()
+        .any(|a| executor.needs_confirmation(a) || executor.is_destructive(a))
+    {
+        return Ok(Vec::new());
+    }
+
+    app.set_status(format!("[Parallel: {} actions]", actions.len()));
+    terminal.draw(|f| render_ui(f, app))?;
+    let results = executor.execute_parallel(&actions).await;
+    Ok(wave.iter().copied().zip(results).collect())
+}
+
+/// Snapshot the context files and any files the batch writes, unless the batch only reads
+fn take_snapshot(app: &mut App, actions: &[agents::AgentAction]) {
+    let modifies_files = actions.iter().any(|action| {
+        !matches!(
+            action.resolve(),
+            Ok(agents::AgentAction::ReadFile { .. }
+                | agents::AgentAction::GitStatus
+                | agents::AgentAction::GitDiff { .. }
+                | agents::AgentAction::GitStashList
+                | agents::AgentAction::GitStashShow { .. }
+                | agents::AgentAction::GitPush { .. }
+                | agents::AgentAction::AstSearch { .. }
+                | agents::AgentAction::WebSearch { .. }
+                | agents::AgentAction::HttpRequest { .. }
+                | agents::AgentAction::LintFile { fix: false, .. })
+        )
+    });
+    if !modifies_files {
+        return;
+    }
+
+    // Context files are relative to the project root, action paths to where `cd` left
+    // the model; snapshots live in the project that rollback runs in
+    let root = app.project_root();
+    let mut paths: Vec<PathBuf> = app.context.files.keys().map(|p| root.join(p)).collect();
+    for action in actions {
+        if let Ok(
+            agents::AgentAction::WriteFile { path, .. } | agents::AgentAction::DeleteFile { path },
+        ) = action.resolve()
+        {
+            paths.push(Path::new(&app.working_dir).join(path));
+        }
+    }
+    paths.sort();
+    paths.dedup();
+
+    let snapshot = agents::SnapshotManager::new(&root).and_then(|s| s.take(&paths));
+    match snapshot {
+        Ok(id) => app.last_snapshot_id = Some(id),
+        Err(e) => app.set_status(format!("[WARN] Could not snapshot files: {}", e)),
+    }
+}
+
+/// Run the project's linter in the background, reporting back over `tx`
+fn spawn_lint(app: &mut App, path: String, fix: bool, tx: mpsc::Sender<String>) {
+    app.set_status(format!("Linting {}...", path));
+    let project_type = app.context.project_type.clone();
+
+    tokio::spawn(async move {
+        let message = match agents::lint_file(&path, fix, project_type.as_deref())
+            .await
+            .and_then(|report| Ok(serde_json::to_string(&report)?))
+        {
+            Ok(json) => format!("[LINT]:{}", json),
+            Err(e) => format!("[LINT_FAILED]:{}", e),
+        };
+        let _ = tx.send(message).await;
+    });
+}
+
+/// Show lint issues in the chat, each annotated under its source line
+fn show_lint_report(app: &mut App, report: agents::LintReport) {
+    let mut sources: std::collections::HashMap<String, Vec<String>> =
+        std::collections::HashMap::new();
+    let mut annotations = Vec::new();
+    for issue in &report.issues {
+        let lines = sources.entry(issue.file.clone()).or_insert_with(|| {
+            std::fs::read_to_string(&issue.file)
+                .map(|s| s.lines().map(String::from).collect())
+                .unwrap_or_default()
+        });
+        let source = issue
+            .line
+            .checked_sub(1)
+            .and_then(|i| lines.get(i))
+            .map(|l| l.trim_end())
+            .unwrap_or_default();
+        let code = if issue.code.is_empty() {
+            String::new()
+        } else {
+            format!("[{}]", issue.code)
+        };
+        annotations.push(format!(
+            "{}:{}\n{:>5} | {}\n      ^ {}{}: {}",
+            issue.file, issue.line, issue.line, source, issue.severity, code, issue.message
+        ));
+    }
+
+    let mut message = if report.issues.is_empty() {
+        "Lint: no issues found".to_string()
+    } else {
+        format!(
+            "Lint: {} issue(s)\n\n```text\n{}\n```",
+            report.issues.len(),
+            annotations.join("\n\n")
+        )
+    };
+    if !report.fixed.is_empty() {
+        message.push_str(&format!("\n\nFixed {} issue(s):", report.fixed.len()));
+        for issue in &report.fixed {
+            message.push_str(&format!(
+                "\n- {}:{} {}: {}",
+                issue.file, issue.line, issue.code, issue.message
+            ));
+        }
+    }
+    app.add_message(MessageRole::System, message);
+    app.set_status(format!(
+        "Lint finished: {} issue(s), {} fixed",
+        report.issues.len(),
+        report.fixed.len()
+    ));
+}
+
+/// List configured model aliases and installed Ollama models
+async fn show_model_list(app: &mut App) {
+    let mut aliases: Vec<_> = app.config.default_model.model_aliases.iter().collect();
+    aliases.sort();
+    let installed = crate::ollama::list_models_async().await.unwrap_or_default();
+
+    let mut lines = vec![format!("Current model: {}", app.model_name)];
+    if !aliases.is_empty() {
+        lines.push("\nAliases:".to_string());
+        lines.extend(
+            aliases
+                .iter()
+                .map(|(alias, model_id)| format!("  {} -> {}", alias, model_id)),
+        );
+    }
+    lines.push("\nOllama models:".to_string());
+    if installed.is_empty() {
+        lines.push("  (none found - is Ollama running?)".to_string());
+    } else {
+        lines.extend(installed.iter().map(|model| format!("  ollama/{}", model)));
+    }
+    app.add_message(MessageRole::System, lines.join("\n"));
+}
+
+fn show_audit_log(app: &mut App, count: usize) {
+    let entries = match agents::AuditLogger::new(&app.working_dir).tail(count) {
+        Ok(entries) => entries,
+        Err(e) => {
+            app.set_status(format!("[ERROR] Failed to read audit log: {}", e));
+            return;
+        },
+    };
+    if entries.is_empty() {
+        app.set_status("No actions in the audit log yet");
+        return;
+    }
+
+    let lines: Vec<String> = entries
+        .iter()
+        .map(|entry| {
+            format!(
+                "{} [{}] {} {} - {} in {}ms{}",
+                entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                entry.mode.short_name(),
+                entry.action_type,
+                entry.target,
+                if entry.success { "ok" } else { "failed" },
+                entry.duration_ms,
+                if entry.user_confirmed {
+                    ", confirmed"
+                } else {
+                    ""
+                }
+            )
+        })
+        .collect();
+    app.add_message(
+        MessageRole::System,
+        format!(
+            "Audit log (last {}):\n```text\n{}\n```",
+            entries.len(),
+            lines.join("\n")
+        ),
+    );
+}
+
+fn show_conflicts(app: &mut App) {
+    // Also catch conflicts that were there before the session started
+    let root = std::path::Path::new(&app.context.root_path);
+    let loaded: Vec<std::path::PathBuf> = app
+        .context
+        .files
+        .keys()
+        .map(|path| root.join(path))
+        .collect();
+    app.update_conflicts(&loaded, &[]);
+    if app.conflicted_files.is_empty() {
+        app.set_status("No merge conflicts");
+        return;
+    }
+
+    let mut message = String::from("Please help resolve these merge conflicts");
+    for path in &app.conflicted_files {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        message.push_str(&format!("\n\n{}:\n```\n{}\n```", path, content.trim_end()));
+    }
+    let files = app.conflicted_files.join(", ");
+    app.add_message(MessageRole::System, message);
+    // System messages aren't sent to the model, so prefill the request
+    app.input = format!(
+        "Please help resolve the merge conflicts in {} and write back the resolved files",
+        files
+    );
+    app.cursor_position = app.input.len();
+}
+
+/// List the repository's stashes in the chat
+fn show_stash_list(app: &mut App) {
+    let stashes = match agents::list_stashes() {
+        Ok(stashes) => stashes,
+        Err(e) => {
+            app.set_status(format!("[FAILED] Failed to list stashes: {}", e));
+            return;
+        },
+    };
+    if app.config.ui.git_status {
+        app.stashes = stashes.clone();
+    }
+    if stashes.is_empty() {
+        app.set_status("No stashes");
+        return;
+    }
+
+    let lines: Vec<String> = stashes
+        .iter()
+        .map(|stash| format!("{}  {}", stash.stash_ref, stash.message))
+        .collect();
+    app.add_message(
+        MessageRole::System,
+        format!(
+            "Stashes ({}):\n```text\n{}\n```",
+            stashes.len(),
+            lines.join("\n")
+        ),
+    );
+}
+
+/// Show a formatting report in the chat
+fn show_format_summary(app: &mut App, summary: &str) {
+    let headline = summary.lines().next().unwrap_or_default().to_string();
+    app.add_message(MessageRole::System, summary.to_string());
+    app.set_status(headline);
+}
+
+/// Store a generated summary and show it in the chat
+fn show_summary(app: &mut App, summary: String) {
+    app.add_message(
+        MessageRole::System,
+        format!("Conversation summary:\n{}", summary),
+    );
+    app.set_conversation_summary(summary);
+    app.set_status("Conversation summarized");
+}
+
+/// `context` with the repository map added for a request; a new prompt (`query`)
+/// first re-ranks the map toward the files it's about and updates the sidebar,
+/// while follow-up turns reuse the ranking of the prompt they answer
+async fn with_repo_map(
+    repo_map: Option<Arc<tokio::sync::Mutex<RepoMap>>>,
+    query: Option<String>,
+    mut context: ProjectContext,
+    tx: &mpsc::Sender<String>,
+) -> ProjectContext {
+    let Some(repo_map) = repo_map else {
+        return context;
+    };
+    let mut repo_map = repo_map.lock().await;
+    if let Some(ref query) = query {
+        let weights = RepoRanker::personalize_from_query(query, &context);
+        repo_map.set_query_weights(weights).await;
+    }
+    if let Ok(map) = repo_map.generate_map(&[], &[], None).await {
+        context.repo_map = Some(map);
+        if query.is_some() {
+            if let Ok(json) = serde_json::to_string(&repo_map.get_ranked_files()) {
+                let _ = tx.send(format!("[IMPORTANCE]:{}", json)).await;
+            }
+        }
+    }
+    context
+}
+
+/// Get the app's repository map, building it from the working directory on first use
+async fn ensure_repo_map(app: &mut App) -> Result<Arc<tokio::sync::Mutex<RepoMap>>> {
+    if let Some(ref repo_map) = app.repo_map {
+        return Ok(repo_map.clone());
+    }
+
+    let mut repo_map = RepoMap::new(None)?;
+    repo_map
+        .build_from_directory(Path::new(&app.working_dir))
+        .await?;
+    repo_map.generate_map(&[], &[], None).await?;
+    app.set_file_importance(repo_map.get_ranked_files());
+    let repo_map = Arc::new(tokio::sync::Mutex::new(repo_map));
+    app.repo_map = Some(repo_map.clone());
+    Ok(repo_map)
+}
+
+/// Show an inline confirmation for moving conversations to the trash
+fn request_conversation_trash(
+    app: &mut App,
+    description: String,
+    conversations: Vec<crate::session::ConversationHistory>,
+) {
+    app.confirmation_state = Some(ConfirmationState {
+        kind: ConfirmationKind::TrashConversations(
+            conversations.iter().map(|c| c.id.clone()).collect(),
+        ),
+        action_description: description,
+        preview_lines: conversations.iter().map(|c| c.summary()).collect(),
+        file_info: None,
+        allow_always: false,
+    });
+}
+
+/// Move confirmed conversations to the trash
+fn trash_conversations(app: &mut App, ids: &[String]) {
+    let Some(ref manager) = app.conversation_manager else {
+        return;
+    };
+
+    let failed: Vec<&String> = ids
+        .iter()
+        .filter(|id| manager.delete_conversation(id).is_err())
+        .collect();
+
+    if failed.is_empty() {
+        app.set_status(format!(
+            "[OK] Moved {} conversation(s) to trash - restore with: mermaid conversations restore <id>",
+            ids.len()
+        ));
+    } else {
+        app.set_status(format!(
+            "[FAILED] Could not trash: {}",
+            failed
+                .iter()
+                .map(|s| s.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+}
+
+/// Error to show instead of sending once a budget limit has been reached
+fn budget_block(app: &App) -> Option<String> {
+    match app.budget_status(0.0) {
+        BudgetStatus::Exceeded(error) if !app.override_budget => Some(format!(
+            "[ERROR] {}. Restart with --override-budget to keep going.",
+            error
+        )),
+        _ => None,
+    }
+}
+
+/// Save the operation mode to the session when `mode.remember_mode` is on
+fn remember_mode(app: &App) {
+    let mode = &app.config.mode;
+    if !mode.remember_mode || !app.operation_mode.persistable(mode.allow_persist_bypass) {
+        return;
+    }
+    use crate::session::SessionState;
+    let mut session = SessionState::load().unwrap_or_default();
+    session.last_mode = Some(app.operation_mode.to_str().to_string());
+    let _ = session.save();
+}
+
+/// Append a response's token usage to the usage log
+fn record_usage(response: &ModelResponse) {
+    if let Some(usage) = &response.usage {
+        let _ = UsageLog::new(
+            response.model_name.clone(),
+            usage.prompt_tokens as u64,
+            usage.completion_tokens as u64,
+        )
+        .append();
+    }
+}
+
+/// Feedback loop for a web search: send the results back to the model
+fn send_search_feedback(app: &mut App, results: &str, tx: &mpsc::Sender<String>) {
+    app.is_generating = true;
+    app.current_response.clear();
+
+    let feedback_prompt = format!(
+        "{}\n\nUse these results to answer the user, citing the links you rely on.",
+        results
+    );
+    // A tool message, since system messages stay out of the model's history
+    app.add_message(MessageRole::tool(), feedback_prompt);
+    spawn_follow_up(app, tx);
+}
+
+/// Most follow-up turns the model gets for function results before it waits for the user
+const MAX_TOOL_ROUNDS: usize = 10;
+
+/// Once every function call in the last response has a result, let the model continue
+fn continue_after_function_calls(app: &mut App, tx: &mpsc::Sender<String>) {
+    if app.is_generating || app.confirmation_state.is_some() || !app.function_calls_answered() {
+        return;
+    }
+    if app.tool_rounds >= MAX_TOOL_ROUNDS {
+        app.set_status(format!(
+            "[WARNING] Stopped after {} rounds of function calls",
+            MAX_TOOL_ROUNDS
+        ));
+        return;
+    }
+    app.tool_rounds += 1;
+    app.is_generating = true;
+    app.current_response.clear();
+    spawn_follow_up(app, tx);
+}
+
+/// Stream the model's next turn for the history as it stands
+fn spawn_follow_up(app: &mut App, tx: &mpsc::Sender<String>) {
+    let messages = app.build_message_history();
+    let model = app.model.clone();
+    let context = app.context.clone();
+    let repo_map = app.repo_map.clone();
+    let config = app.model_config();
+    let tx_clone = tx.clone();
+    let tx_done = tx.clone();
+
+    let handle = tokio::spawn(async move {
+        let context = with_repo_map(repo_map, None, context, &tx_done).await;
+        let callback: StreamCallback = Arc::new(move |chunk| {
+            let _ = tx_clone.try_send(chunk.to_string());
+        });
+
+        let mut model = model.lock().await;
+        match model
+            .chat(&messages, &context, &config, Some(callback))
+            .await
+        {
+            Ok(response) => {
+                record_usage(&response);
+                let _ = tx_done.send("[DONE]:[FEEDBACK_COMPLETE]".to_string()).await;
+            },
+            Err(e) => {
+                let _ = tx_done.send(error_chunk(&e)).await;
+            },
+        }
+    });
+    app.generation_abort = Some(handle.abort_handle());
+}
+
 /// Handle successful action execution
 async fn handle_action_success(
     app: &mut App,
@@ -931,6 +2593,13 @@ async fn handle_action_success(
     output: String,
     tx: &mpsc::Sender<String>,
 ) {
+    app.add_function_result(action, ActionOutcome::Executed, &output);
+    app.note_file_read(action, &output);
+    app.apply_session_env(action);
+    if app.apply_directory_change(action) {
+        reload_context(app);
+    }
+
     match action {
         agents::AgentAction::ReadFile { path } => {
             // Feedback loop: Send file contents back to model
@@ -959,11 +2628,13 @@ async fn handle_action_success(
             // Send feedback to model
             let model = app.model.clone();
             let context = app.context.clone();
+            let repo_map = app.repo_map.clone();
+            let config = app.model_config();
             let tx_clone = tx.clone();
             let tx_done = tx.clone();
 
             tokio::spawn(async move {
-                let config = ModelConfig::default();
+                let context = with_repo_map(repo_map, None, context, &tx_done).await;
                 let callback: StreamCallback = Arc::new(move |chunk| {
                     let _ = tx_clone.try_send(chunk.to_string());
                 });
@@ -973,16 +2644,22 @@ async fn handle_action_success(
                     .chat(&messages, &context, &config, Some(callback))
                     .await
                 {
-                    Ok(_) => {
+                    Ok(response) => {
+                        record_usage(&response);
                         // Clear feedback flags after completion
                         let _ = tx_done.send("[DONE]:[FEEDBACK_COMPLETE]".to_string()).await;
                     },
                     Err(e) => {
-                        let _ = tx_done.send(format!("[ERROR]:{}", e)).await;
+                        let _ = tx_done.send(error_chunk(&e)).await;
                     },
                 }
             });
         },
+        agents::AgentAction::WebSearch { query, .. } => {
+            app.set_status(format!("[OK] Searched: {}", query));
+            app.pending_file_read = true;
+            send_search_feedback(app, &output, tx);
+        },
         agents::AgentAction::WriteFile { path, content } => {
             app.set_status(format!("[OK] {}", output));
             app.context.add_file(path.clone(), content.clone());
@@ -994,7 +2671,7 @@ async fn handle_action_success(
             app.set_status(format!("[OK] {}", output));
             if let Some(content) = app.context.files.remove(path) {
                 // Use proper tokenizer for accurate count
-                let tokens = count_file_tokens(&content, &app.model_name);
+                let tokens = count_file_tokens(content.text(), &app.model_name);
                 app.context.token_count = app.context.token_count.saturating_sub(tokens);
             }
         },
@@ -1004,6 +2681,39 @@ async fn handle_action_success(
     }
 }
 
+/// Load the context again from the working directory, e.g. after the model ran `cd`
+fn reload_context(app: &mut App) {
+    let extra_roots = app.extra_roots.clone();
+    let working_dir = app.working_dir.clone();
+    if let Some(previous) = app.context_reload.take() {
+        previous.abort();
+    }
+    app.context_reload = Some(tokio::task::spawn_blocking(move || {
+        ContextLoader::new()?
+            .with_additional_roots(extra_roots)
+            .load(Path::new(&working_dir))
+    }));
+    app.set_status(format!("Reloading context from {}...", app.working_dir));
+}
+
+/// Swap in the reloaded context once the background load finishes
+async fn finish_context_reload(app: &mut App) {
+    if !app.context_reload.as_ref().is_some_and(|h| h.is_finished()) {
+        return;
+    }
+    let Some(handle) = app.context_reload.take() else {
+        return;
+    };
+    match handle.await {
+        Ok(Ok(context)) => {
+            app.apply_refreshed_context(context);
+            app.set_status(format!("[OK] Context reloaded from {}", app.working_dir));
+        },
+        Ok(Err(e)) => app.set_status(format!("[FAILED] Failed to reload context: {}", e)),
+        Err(e) => app.set_status(format!("[FAILED] Failed to reload context: {}", e)),
+    }
+}
+
 /// Detect language from file extension
 fn detect_language(path: &str) -> Option<String> {
     let ext = Path::new(path).extension().and_then(|e| e.to_str())?;
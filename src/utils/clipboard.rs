@@ -0,0 +1,58 @@
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Where copied text ended up
+#[derive(Debug, Clone, PartialEq)]
+pub enum CopyTarget {
+    /// The system clipboard
+    Clipboard,
+    /// A temp file, used when no clipboard is reachable
+    File(PathBuf),
+}
+
+/// Clipboard commands tried in order (Wayland, X11, macOS, Windows)
+const CLIPBOARD_COMMANDS: &[(&str, &[&str])] = &[
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("pbcopy", &[]),
+    ("clip.exe", &[]),
+];
+
+/// Copy text to the system clipboard, falling back to a temp file
+pub fn copy_to_clipboard(text: &str) -> Result<CopyTarget> {
+    if CLIPBOARD_COMMANDS
+        .iter()
+        .any(|(program, args)| pipe_to(program, args, text).is_ok())
+    {
+        return Ok(CopyTarget::Clipboard);
+    }
+
+    let path = std::env::temp_dir().join(format!(
+        "mermaid-copy-{}.txt",
+        chrono::Local::now().format("%Y%m%d_%H%M%S")
+    ));
+    std::fs::write(&path, text).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(CopyTarget::File(path))
+}
+
+/// Run a clipboard command with `text` on stdin
+fn pipe_to(program: &str, args: &[&str], text: &str) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .context("Clipboard command has no stdin")?
+        .write_all(text.as_bytes())?;
+
+    let status = child.wait()?;
+    anyhow::ensure!(status.success(), "{} exited with {}", program, status);
+    Ok(())
+}
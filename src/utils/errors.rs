@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use thiserror::Error;
 
 /// Main error type for Mermaid
@@ -32,4 +33,116 @@ pub enum MermaidError {
 
     #[error("Unknown error: {0}")]
     Unknown(String),
+
+    #[error("LiteLLM proxy is not running at {proxy_url}")]
+    ProxyNotRunning { proxy_url: String },
+
+    #[error("Model not found: {model_id}{}", available_suffix(.available))]
+    ModelNotFound {
+        model_id: String,
+        available: Vec<String>,
+    },
+
+    #[error("Context too large: {token_count} tokens (limit {limit})")]
+    ContextTooLarge { token_count: usize, limit: usize },
+
+    #[error("Permission denied: {}", .path.display())]
+    FilePermissionDenied { path: PathBuf },
+
+    #[error("Parse error in {}:{line}: {message}", .file.display())]
+    ParseError {
+        file: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl MermaidError {
+    /// What the user can do about the error, for the structured variants
+    pub fn recovery_hint(&self) -> Option<&'static str> {
+        match self {
+            MermaidError::ProxyNotRunning { .. } => {
+                Some("Start the proxy with ./start_litellm.sh, or set LITELLM_PROXY_URL")
+            },
+            MermaidError::ModelNotFound { .. } => {
+                Some("Pick a listed model with :model <name>, or add it to litellm_config.yaml")
+            },
+            MermaidError::ContextTooLarge { .. } => {
+                Some("Run :summarize or :clear, or load fewer files into context")
+            },
+            MermaidError::FilePermissionDenied { .. } => {
+                Some("Check the file's permissions, or run Mermaid as a user who can access it")
+            },
+            MermaidError::ParseError { .. } => Some("Fix the syntax at the reported line"),
+            _ => None,
+        }
+    }
+}
+
+/// Recovery hint of the first `MermaidError` in an error chain
+pub fn recovery_hint_for(error: &anyhow::Error) -> Option<&'static str> {
+    // A `MermaidError` given as context isn't in the chain as itself
+    error
+        .downcast_ref::<MermaidError>()
+        .or_else(|| {
+            error
+                .chain()
+                .find_map(|cause| cause.downcast_ref::<MermaidError>())
+        })
+        .and_then(MermaidError::recovery_hint)
+}
+
+fn available_suffix(available: &[String]) -> String {
+    if available.is_empty() {
+        String::new()
+    } else {
+        format!(" (available: {})", available.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_structured_errors_and_hints() {
+        let error = MermaidError::ModelNotFound {
+            model_id: "openai/gpt-5".to_string(),
+            available: vec!["openai/gpt-4o".to_string(), "ollama/qwen".to_string()],
+        };
+        assert_eq!(
+            error.to_string(),
+            "Model not found: openai/gpt-5 (available: openai/gpt-4o, ollama/qwen)"
+        );
+
+        let error = MermaidError::ParseError {
+            file: PathBuf::from("config.toml"),
+            line: 3,
+            message: "expected `=`".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "Parse error in config.toml:3: expected `=`"
+        );
+
+        let wrapped = anyhow::Error::from(MermaidError::ProxyNotRunning {
+            proxy_url: "http://localhost:4000".to_string(),
+        })
+        .context("Chat request failed");
+        assert!(recovery_hint_for(&wrapped)
+            .unwrap()
+            .contains("start_litellm.sh"));
+
+        // How the chat client attaches it to a failed send
+        let as_context = Err::<(), _>(std::io::Error::other("connection refused"))
+            .context(MermaidError::ProxyNotRunning {
+                proxy_url: "http://localhost:4000".to_string(),
+            })
+            .unwrap_err();
+        assert!(recovery_hint_for(&as_context)
+            .unwrap()
+            .contains("start_litellm.sh"));
+        assert_eq!(MermaidError::Unknown("x".to_string()).recovery_hint(), None);
+    }
 }
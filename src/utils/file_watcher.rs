@@ -3,12 +3,18 @@ use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::{self, Receiver};
 
-/// Events that we care about for the file system
-#[derive(Debug, Clone)]
+/// Events that we care about for the file system, one per path
+#[derive(Debug, Clone, PartialEq)]
 pub enum FileEvent {
-    Created(Vec<PathBuf>),
-    Modified(Vec<PathBuf>),
-    Deleted(Vec<PathBuf>),
+    Created(PathBuf),
+    Modified(PathBuf),
+    Deleted(PathBuf),
+    Renamed {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// `.git/HEAD` was rewritten, e.g. by a branch switch
+    HeadChanged,
 }
 
 /// A file system watcher that monitors changes in a directory
@@ -41,36 +47,54 @@ impl FileSystemWatcher {
 
         // Process all available events
         while let Ok(Ok(event)) = self.rx.try_recv() {
-            match event.kind {
-                EventKind::Create(_) => {
-                    if !event.paths.is_empty() {
-                        events.push(FileEvent::Created(event.paths));
-                    }
-                },
-                EventKind::Modify(modify_kind) => {
-                    // Filter out metadata-only changes
-                    use notify::event::ModifyKind;
-                    match modify_kind {
-                        ModifyKind::Data(_) | ModifyKind::Any => {
-                            if !event.paths.is_empty() {
-                                events.push(FileEvent::Modified(event.paths));
-                            }
-                        },
-                        _ => {}, // Ignore metadata changes
-                    }
-                },
-                EventKind::Remove(_) => {
-                    if !event.paths.is_empty() {
-                        events.push(FileEvent::Deleted(event.paths));
-                    }
-                },
-                _ => {}, // Ignore other events
+            // Git replaces HEAD by renaming HEAD.lock, so any event kind counts
+            if event.paths.iter().any(|path| Self::is_git_head(path)) {
+                events.push(FileEvent::HeadChanged);
+                continue;
             }
+            events.extend(Self::classify(event));
         }
 
         events
     }
 
+    /// Turn a notify event into one `FileEvent` per affected path
+    fn classify(event: Event) -> Vec<FileEvent> {
+        use notify::event::{ModifyKind, RenameMode};
+        let paths = event.paths.into_iter();
+        match event.kind {
+            EventKind::Create(_) => paths.map(FileEvent::Created).collect(),
+            EventKind::Remove(_) => paths.map(FileEvent::Deleted).collect(),
+            EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Any) => {
+                paths.map(FileEvent::Modified).collect()
+            },
+            EventKind::Modify(ModifyKind::Name(mode)) => {
+                let mut paths = paths;
+                match (mode, paths.next(), paths.next()) {
+                    (RenameMode::Both, Some(from), Some(to)) => {
+                        vec![FileEvent::Renamed { from, to }]
+                    },
+                    (RenameMode::From, Some(from), None) => vec![FileEvent::Deleted(from)],
+                    (RenameMode::To, Some(to), None) => vec![FileEvent::Created(to)],
+                    // Backends that can't tell the halves apart; check what's on disk
+                    (_, Some(path), None) if path.exists() => vec![FileEvent::Created(path)],
+                    (_, Some(path), None) => vec![FileEvent::Deleted(path)],
+                    _ => Vec::new(),
+                }
+            },
+            _ => Vec::new(), // Ignore metadata changes and other events
+        }
+    }
+
+    /// Whether a path is a repository's `.git/HEAD`
+    pub fn is_git_head(path: &Path) -> bool {
+        path.file_name().is_some_and(|name| name == "HEAD")
+            && path
+                .parent()
+                .and_then(|parent| parent.file_name())
+                .is_some_and(|name| name == ".git")
+    }
+
     /// Check if a path should be ignored (e.g., hidden files, git files, etc.)
     pub fn should_ignore_path(path: &Path) -> bool {
         // Ignore hidden files and directories
@@ -141,6 +165,10 @@ mod tests {
         assert!(!FileSystemWatcher::should_ignore_path(Path::new(
             "config.toml"
         )));
+
+        assert!(FileSystemWatcher::is_git_head(Path::new("./.git/HEAD")));
+        assert!(!FileSystemWatcher::is_git_head(Path::new(".git/refs/HEAD")));
+        assert!(!FileSystemWatcher::is_git_head(Path::new("HEAD")));
     }
 
     #[tokio::test]
@@ -158,4 +186,44 @@ mod tests {
         let events = watcher.check_events();
         assert!(!events.is_empty(), "Should have detected file creation");
     }
+
+    #[test]
+    fn test_classify_events() {
+        use notify::event::{CreateKind, MetadataKind, ModifyKind, RemoveKind, RenameMode};
+        let event = |kind, paths: &[&str]| Event {
+            kind,
+            paths: paths.iter().map(PathBuf::from).collect(),
+            attrs: Default::default(),
+        };
+
+        assert_eq!(
+            FileSystemWatcher::classify(event(
+                EventKind::Create(CreateKind::File),
+                &["a.rs", "b.rs"]
+            )),
+            vec![
+                FileEvent::Created(PathBuf::from("a.rs")),
+                FileEvent::Created(PathBuf::from("b.rs"))
+            ]
+        );
+        assert_eq!(
+            FileSystemWatcher::classify(event(EventKind::Remove(RemoveKind::File), &["a.rs"])),
+            vec![FileEvent::Deleted(PathBuf::from("a.rs"))]
+        );
+        assert_eq!(
+            FileSystemWatcher::classify(event(
+                EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+                &["old.rs", "new.rs"]
+            )),
+            vec![FileEvent::Renamed {
+                from: PathBuf::from("old.rs"),
+                to: PathBuf::from("new.rs")
+            }]
+        );
+        assert!(FileSystemWatcher::classify(event(
+            EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any)),
+            &["a.rs"]
+        ))
+        .is_empty());
+    }
 }
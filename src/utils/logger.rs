@@ -1,44 +1,141 @@
-use std::io;
-use tracing::{debug, error, info, warn};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::panic::Location;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::{debug, error, info, warn, Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+use crate::app::LogFormat;
+
+/// Levels applied by the `log_*` helpers, which all log from this module's target
+static LEVELS: OnceLock<ModuleLevels> = OnceLock::new();
+
+/// Default level plus per-module overrides such as `proxy = "debug"`
+struct ModuleLevels {
+    default: LevelFilter,
+    modules: HashMap<String, LevelFilter>,
+}
+
+impl ModuleLevels {
+    fn enabled(&self, module: &str, level: Level) -> bool {
+        level <= self.modules.get(module).copied().unwrap_or(self.default)
+    }
+}
 
 /// Initialize the logging system
-pub fn init_logger() {
+/// Pretty output goes to stderr and only with `--verbose`; JSON output always
+/// goes to `~/.local/share/mermaid/mermaid.log` so it can't disturb the TUI
+pub fn init_logger(format: LogFormat, log_levels: &HashMap<String, String>, verbose: bool) {
     // Use RUST_LOG environment variable, default to info level
-    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+    let mut filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let default = filter.max_level_hint().unwrap_or(LevelFilter::INFO);
+
+    let mut modules = HashMap::new();
+    let mut invalid = Vec::new();
+    for (module, level) in log_levels {
+        match level.parse::<LevelFilter>() {
+            Ok(level) => {
+                modules.insert(module.clone(), level);
+                if let Ok(directive) = format!("mermaid::{}={}", module, level).parse() {
+                    filter = filter.add_directive(directive);
+                }
+            },
+            Err(_) => invalid.push(format!("{} = \"{}\"", module, level)),
+        }
+    }
+    // The helpers below check their caller's module themselves
+    if let Ok(directive) = format!("{}=trace", module_path!()).parse() {
+        filter = filter.add_directive(directive);
+    }
 
+    let pretty = (format == LogFormat::Pretty && verbose).then(|| {
+        tracing_subscriber::fmt::layer()
+            .with_writer(io::stderr) // Write to stderr to not interfere with TUI
+            .with_target(false) // Don't show module paths
+            .with_thread_ids(false)
+            .with_thread_names(false)
+            .compact() // Use compact format
+    });
+    let json = match format {
+        LogFormat::Json => match JsonLayer::open() {
+            Ok(layer) => Some(layer),
+            Err(e) => {
+                eprintln!("[WARNING] Failed to open log file: {}", e);
+                None
+            },
+        },
+        LogFormat::Pretty => None,
+    };
+    if pretty.is_none() && json.is_none() {
+        return;
+    }
+
+    let _ = LEVELS.set(ModuleLevels { default, modules });
     tracing_subscriber::registry()
         .with(filter)
-        .with(
-            tracing_subscriber::fmt::layer()
-                .with_writer(io::stderr) // Write to stderr to not interfere with TUI
-                .with_target(false) // Don't show module paths
-                .with_thread_ids(false)
-                .with_thread_names(false)
-                .compact(), // Use compact format
-        )
+        .with(pretty)
+        .with(json)
         .init();
+
+    for entry in invalid {
+        log_warn("WARNING", format!("Ignoring invalid log level: {}", entry));
+    }
+}
+
+/// Top-level module of a source file, e.g. `src/proxy/manager.rs` -> `proxy`
+fn module_of(file: &str) -> &str {
+    let path = file.strip_prefix("src/").unwrap_or(file);
+    let first = path.split(['/', '\\']).next().unwrap_or(path);
+    first.strip_suffix(".rs").unwrap_or(first)
+}
+
+#[track_caller]
+fn caller_enabled(level: Level) -> Option<&'static str> {
+    let module = module_of(Location::caller().file());
+    LEVELS
+        .get()
+        .map_or(true, |levels| levels.enabled(module, level))
+        .then_some(module)
 }
 
 /// Log an info message with emoji prefix
+#[track_caller]
 pub fn log_info(emoji: &str, message: impl std::fmt::Display) {
-    info!("{} {}", emoji, message);
+    if let Some(module) = caller_enabled(Level::INFO) {
+        info!(module, "{} {}", emoji, message);
+    }
 }
 
 /// Log a warning message with emoji prefix
+#[track_caller]
 pub fn log_warn(emoji: &str, message: impl std::fmt::Display) {
-    warn!("{} {}", emoji, message);
+    if let Some(module) = caller_enabled(Level::WARN) {
+        warn!(module, "{} {}", emoji, message);
+    }
 }
 
 /// Log an error message with emoji prefix
+#[track_caller]
 pub fn log_error(emoji: &str, message: impl std::fmt::Display) {
-    error!("{} {}", emoji, message);
+    if let Some(module) = caller_enabled(Level::ERROR) {
+        error!(module, "{} {}", emoji, message);
+    }
 }
 
 /// Log a debug message
+#[track_caller]
 pub fn log_debug(message: impl std::fmt::Display) {
-    debug!("{}", message);
+    if let Some(module) = caller_enabled(Level::DEBUG) {
+        debug!(module, "{}", message);
+    }
 }
 
 /// Status messages for the TUI (special handling)
@@ -53,3 +150,96 @@ pub fn log_progress(step: usize, total: usize, message: impl std::fmt::Display)
     let progress = format!("[{}/{}]", step, total);
     eprintln!("{} {} {}", progress, "->".to_string(), message);
 }
+
+/// Writes each event as a JSON line: `{"level","module","msg","ts"}`
+struct JsonLayer {
+    file: Mutex<File>,
+}
+
+impl JsonLayer {
+    /// Open the log file (~/.local/share/mermaid/mermaid.log) for appending
+    fn open() -> Result<Self> {
+        let home = std::env::var("HOME")?;
+        let data_dir = PathBuf::from(home)
+            .join(".local")
+            .join("share")
+            .join("mermaid");
+        fs::create_dir_all(&data_dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(data_dir.join("mermaid.log"))?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl<S: Subscriber> Layer<S> for JsonLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = JsonFields::default();
+        event.record(&mut fields);
+        let metadata = event.metadata();
+        // Events from outside the helpers are attributed by their target
+        let module = fields.module.unwrap_or_else(|| {
+            let target = metadata.target();
+            let inner = target.strip_prefix("mermaid::").unwrap_or(target);
+            inner.split("::").next().unwrap_or(inner).to_string()
+        });
+        let line = serde_json::json!({
+            "level": metadata.level().to_string().to_lowercase(),
+            "module": module,
+            "msg": fields.message,
+            "ts": chrono::Local::now().to_rfc3339(),
+        });
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+}
+
+#[derive(Default)]
+struct JsonFields {
+    message: String,
+    module: Option<String>,
+}
+
+impl Visit for JsonFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "module" => self.module = Some(value.to_string()),
+            "message" => self.message = value.to_string(),
+            _ => {},
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_levels() {
+        assert_eq!(module_of("src/proxy/manager.rs"), "proxy");
+        assert_eq!(module_of("src/main.rs"), "main");
+
+        let levels = ModuleLevels {
+            default: LevelFilter::INFO,
+            modules: HashMap::from([
+                ("proxy".to_string(), LevelFilter::DEBUG),
+                ("tui".to_string(), LevelFilter::WARN),
+            ]),
+        };
+        assert!(levels.enabled("proxy", Level::DEBUG));
+        assert!(!levels.enabled("tui", Level::INFO));
+        assert!(levels.enabled("tui", Level::WARN));
+        assert!(levels.enabled("runtime", Level::INFO));
+        assert!(!levels.enabled("runtime", Level::DEBUG));
+    }
+}
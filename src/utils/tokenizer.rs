@@ -2,26 +2,63 @@ use anyhow::Result;
 use std::collections::HashMap;
 use tiktoken_rs::{num_tokens_from_messages, ChatCompletionRequestMessage};
 
+/// Average characters per token assumed by the estimating backend
+const ESTIMATE_CHARS_PER_TOKEN: f64 = 3.5;
+
+/// A token counter for a tokenizer tiktoken doesn't know
+pub trait CountTokens: Send + Sync {
+    fn count_tokens(&self, text: &str) -> usize;
+}
+
+/// How a `Tokenizer` counts tokens
+pub enum TokenizerBackend {
+    /// A tiktoken encoding, named by the OpenAI model that uses it
+    Tiktoken(String),
+    /// Characters / 3.5, for local models whose vocabulary isn't cl100k
+    Estimate,
+    Custom(Box<dyn CountTokens>),
+}
+
 /// Token counting utility for various model families
 pub struct Tokenizer {
     model_name: String,
+    backend: TokenizerBackend,
 }
 
 impl Tokenizer {
     /// Create a new tokenizer for the given model
+    /// Ollama models get the estimate; everything else a tiktoken encoding
     pub fn new(model_name: &str) -> Self {
+        let mut tokenizer = Self::with_backend(model_name, TokenizerBackend::Estimate);
+        if !model_name.starts_with("ollama/") {
+            tokenizer.backend = TokenizerBackend::Tiktoken(tokenizer.get_base_model_name());
+        }
+        tokenizer
+    }
+
+    /// Create a tokenizer that counts with the given backend
+    pub fn with_backend(model_name: &str, backend: TokenizerBackend) -> Self {
         Self {
             model_name: model_name.to_string(),
+            backend,
         }
     }
 
+    /// The backend used to count tokens
+    pub fn backend(&self) -> &TokenizerBackend {
+        &self.backend
+    }
+
     /// Count tokens in a single text string
     pub fn count_tokens(&self, text: &str) -> Result<usize> {
-        // Extract the base model name for tokenizer selection
-        let model_for_encoding = self.get_base_model_name();
+        let model_for_encoding = match &self.backend {
+            TokenizerBackend::Tiktoken(model) => model,
+            TokenizerBackend::Estimate => return Ok(estimate_tokens(text)),
+            TokenizerBackend::Custom(counter) => return Ok(counter.count_tokens(text)),
+        };
 
         // Get the appropriate tokenizer
-        match tiktoken_rs::get_bpe_from_model(&model_for_encoding) {
+        match tiktoken_rs::get_bpe_from_model(model_for_encoding) {
             Ok(bpe) => {
                 // Count tokens using the BPE tokenizer
                 Ok(bpe.encode_with_special_tokens(text).len())
@@ -37,6 +74,16 @@ impl Tokenizer {
 
     /// Count tokens in a chat message format
     pub fn count_chat_tokens(&self, messages: &[(String, String)]) -> Result<usize> {
+        let model_for_encoding = match &self.backend {
+            TokenizerBackend::Tiktoken(model) => model,
+            // Roughly what chat formatting adds to each message
+            _ => {
+                return messages.iter().try_fold(0, |total, (role, content)| {
+                    Ok(total + self.count_tokens(role)? + self.count_tokens(content)? + 4)
+                })
+            },
+        };
+
         // Convert to tiktoken's ChatCompletionRequestMessage format
         let chat_messages: Vec<ChatCompletionRequestMessage> = messages
             .iter()
@@ -48,10 +95,8 @@ impl Tokenizer {
             })
             .collect();
 
-        let model_for_encoding = self.get_base_model_name();
-
         // Use tiktoken's chat token counter
-        match num_tokens_from_messages(&model_for_encoding, &chat_messages) {
+        match num_tokens_from_messages(model_for_encoding, &chat_messages) {
             Ok(count) => Ok(count),
             Err(_) => {
                 // Fallback to GPT-3.5 encoding
@@ -126,7 +171,8 @@ impl Tokenizer {
             ("gpt-4-turbo", "gpt-4-turbo"),
             ("gpt-4", "gpt-4"),
             ("gpt-3.5-turbo", "gpt-3.5-turbo"),
-            // Claude models - use GPT-4 encoding as approximation
+            // Claude's tokenizer isn't public; GPT-4's cl100k_base encoding is an
+            // approximation that can be off by 10-20%
             ("claude-3", "gpt-4"),
             ("claude-3-opus", "gpt-4"),
             ("claude-3-sonnet", "gpt-4"),
@@ -157,6 +203,11 @@ impl Tokenizer {
     }
 }
 
+/// Token count estimated from the text length
+pub fn estimate_tokens(text: &str) -> usize {
+    (text.len() as f64 / ESTIMATE_CHARS_PER_TOKEN) as usize
+}
+
 /// Count tokens in file contents (convenience function)
 pub fn count_file_tokens(content: &str, model_name: &str) -> usize {
     let tokenizer = Tokenizer::new(model_name);
@@ -190,6 +241,30 @@ mod tests {
         assert_eq!(tokenizer.get_base_model_name(), "gpt-3.5-turbo"); // Default
     }
 
+    #[test]
+    fn test_backends() {
+        struct Words;
+        impl CountTokens for Words {
+            fn count_tokens(&self, text: &str) -> usize {
+                text.split_whitespace().count()
+            }
+        }
+
+        let text = "fn main() { println!(\"hi\"); }";
+        let tokenizer = Tokenizer::new("ollama/qwen3-coder:30b");
+        assert!(matches!(tokenizer.backend(), TokenizerBackend::Estimate));
+        assert_eq!(tokenizer.count_tokens(text).unwrap(), 8);
+
+        let tokenizer = Tokenizer::new("anthropic/claude-3-haiku");
+        assert!(matches!(tokenizer.backend(), TokenizerBackend::Tiktoken(m) if m == "gpt-4"));
+
+        let tokenizer =
+            Tokenizer::with_backend("custom", TokenizerBackend::Custom(Box::new(Words)));
+        assert_eq!(tokenizer.count_tokens(text).unwrap(), 5);
+        let messages = vec![("user".to_string(), "two words".to_string())];
+        assert_eq!(tokenizer.count_chat_tokens(&messages).unwrap(), 7);
+    }
+
     #[test]
     fn test_max_tokens() {
         let tokenizer = Tokenizer::new("gpt-4");